@@ -1,22 +1,22 @@
-use rig::agent::Agent;
+use futures::{Stream, StreamExt};
+use rig::agent::{Agent, AgentBuilder, MultiTurnStreamItem};
 use rig::client::CompletionClient;
-use rig::completion::Prompt;
-use rig::providers::{anthropic, ollama, openai};
-use thiserror::Error;
+use rig::completion::{CompletionModel, Prompt};
+use rig::providers::{anthropic, deepseek, groq, ollama, openai, xai};
+use rig::streaming::{StreamedAssistantContent, StreamingChat};
+use serde_json::json;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::Instrument;
 
-use onyx_core::{Config, Message, Provider};
-
-#[derive(Debug, Error)]
-pub enum AgentError {
-    #[error("Configuration error: {0}")]
-    ConfigError(#[from] onyx_core::ConfigError),
-
-    #[error("Agent error: {0}")]
-    RigError(String),
-}
-
-pub type Result<T> = std::result::Result<T, AgentError>;
+use crate::context::{TokenEstimator, trim_history};
+use crate::error::{AgentError, Result};
+use crate::http::http_client;
+use crate::llama_cpp::LlamaCppClient;
+use crate::retry::RetryPolicy;
+use crate::thinking::{StreamPiece, forward_pieces};
+use crate::tools::{self, ShellApproval, ToolEvents};
+use onyx_core::{Config, HistoryStrategy, Memories, Message, MessageKind, Provider, Role, Usage};
 
 #[derive(Debug, Clone)]
 pub enum StreamEvent {
@@ -24,126 +24,500 @@ pub enum StreamEvent {
     ThinkingChunk(String),
     ThinkingEnd,
     ContentChunk(String),
-    Done,
+    Retrying { attempt: u32, delay: Duration },
+    ContextTruncated { dropped_messages: usize },
+    HistorySummarized { replaced_messages: usize, summary: String },
+    ToolCallStart { name: String, arguments: String },
+    ToolCallResult { name: String, summary: String },
+    ShellApprovalRequested(ShellApproval),
+    Progress(String),
+    Done(Option<Usage>),
     Error(String),
 }
 
-pub enum ChatAgent {
+pub struct ChatAgent {
+    backend: Backend,
+    /// The same model without tools, for summaries and titles: they run alongside the chat's
+    /// requests, and mustn't call tools or report them to its [`ToolEvents`].
+    side_backend: Backend,
+    provider: Provider,
+    model: String,
+    retry: RetryPolicy,
+    estimator: TokenEstimator,
+    system_prompt: Option<String>,
+    context_token_limit: u64,
+    history_strategy: HistoryStrategy,
+    tool_events: ToolEvents,
+}
+
+/// Upper bound on model/tool round trips for a single prompt.
+const MAX_TOOL_TURNS: usize = 8;
+
+const MAX_TITLE_WORDS: usize = 6;
+
+enum Backend {
     OpenAI(Agent<openai::responses_api::ResponsesCompletionModel>),
     Anthropic(Agent<anthropic::completion::CompletionModel>),
     Ollama(Agent<ollama::CompletionModel<reqwest::Client>>),
+    OpenAICompatible(Agent<openai::CompletionModel>),
+    Groq(Agent<groq::CompletionModel<reqwest::Client>>),
+    Xai(Agent<xai::completion::CompletionModel>),
+    DeepSeek(Agent<deepseek::CompletionModel>),
+    LlamaCpp(LlamaCppClient),
+}
+
+macro_rules! with_agent {
+    ($chat_agent:expr, $agent:ident => $body:expr, $client:ident => $client_body:expr) => {
+        match $chat_agent {
+            Backend::OpenAI($agent) => $body,
+            Backend::Anthropic($agent) => $body,
+            Backend::Ollama($agent) => $body,
+            Backend::OpenAICompatible($agent) => $body,
+            Backend::Groq($agent) => $body,
+            Backend::Xai($agent) => $body,
+            Backend::DeepSeek($agent) => $body,
+            Backend::LlamaCpp($client) => $client_body,
+        }
+    };
+}
+
+fn configure<M: CompletionModel>(
+    builder: AgentBuilder<M>,
+    config: &Config,
+    preamble: Option<&str>,
+    tool_events: Option<&ToolEvents>,
+) -> AgentBuilder<M> {
+    let provider_config = config.get_active_provider();
+
+    let mut builder = match preamble {
+        Some(prompt) => builder.preamble(prompt),
+        None => builder,
+    };
+    // Anthropic rejects any temperature alongside extended thinking.
+    if let Some(temperature) = provider_config.temperature
+        && thinking_budget(config).is_none()
+    {
+        builder = builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = provider_config.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    if let Some(tool_events) = tool_events
+        && (config.tools_enabled || config.shell_enabled)
+    {
+        builder = builder.tool_server_handle(tools::server(config, tool_events.clone()).run());
+    }
+
+    builder
+}
+
+/// The configured system prompt followed by the user's pinned memories.
+fn compose_preamble(config: &Config, memories: &Memories) -> Option<String> {
+    let parts: Vec<String> = config
+        .system_prompt()
+        .map(str::to_string)
+        .into_iter()
+        .chain(memories.as_context())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+/// Renders `messages` as a plain transcript for side requests such as summaries and titles.
+fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let speaker = match (message.kind, &message.role) {
+                (MessageKind::Summary, _) => "Earlier summary",
+                (_, Role::User) => "User",
+                (_, Role::Assistant) => "Assistant",
+                (_, Role::System) => "System",
+            };
+            format!("{}: {}\n\n", speaker, message.prompt_text())
+        })
+        .collect()
+}
+
+/// The extended thinking budget to ask for, if thinking is on for the active model.
+fn thinking_budget(config: &Config) -> Option<u64> {
+    let provider_config = config.get_active_provider();
+    provider_config.thinking_budget_tokens.filter(|_| {
+        config.active_provider == Provider::Anthropic
+            && supports_extended_thinking(&provider_config.model)
+    })
+}
+
+/// Extended thinking is only accepted by Claude 3.7 and the Claude 4 family; older models
+/// reject the `thinking` parameter outright.
+fn supports_extended_thinking(model: &str) -> bool {
+    ["claude-3-7-sonnet", "claude-sonnet-4", "claude-opus-4", "claude-haiku-4"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
 }
 
 impl ChatAgent {
     pub async fn new(config: &Config) -> Result<Self> {
         config.validate()?;
+        let tool_events = ToolEvents::default();
+        // A damaged memories file shouldn't keep the chat from starting; /remember reports it.
+        let preamble = compose_preamble(config, &Memories::load().unwrap_or_default());
 
+        Ok(Self {
+            backend: Backend::new(config, preamble.as_deref(), Some(&tool_events))?,
+            side_backend: Backend::new(config, preamble.as_deref(), None)?,
+            provider: config.active_provider.clone(),
+            model: config.get_active_provider().model.clone(),
+            retry: RetryPolicy::from_config(config),
+            estimator: TokenEstimator::for_model(
+                &config.active_provider,
+                &config.get_active_provider().model,
+            ),
+            system_prompt: preamble,
+            context_token_limit: config.context_token_limit,
+            history_strategy: config.history_strategy,
+            tool_events,
+        })
+    }
+
+    /// Groups the log lines of one request to the provider, so they say where it went.
+    fn request_span(&self, kind: &'static str) -> tracing::Span {
+        tracing::info_span!("request", kind, provider = %self.provider, model = %self.model)
+    }
+
+    /// Trims `history` to the context budget, returning the dropped messages.
+    fn fit_history(&self, message: &Message, history: &mut Vec<Message>) -> Vec<Message> {
+        trim_history(
+            history,
+            message,
+            self.system_prompt.as_deref(),
+            self.context_token_limit,
+            &self.estimator,
+        )
+    }
+
+    /// Brings `history` within the context budget using the configured strategy. With
+    /// summarization the dropped messages are condensed by a side request, which runs to
+    /// completion before the caller sends its own, and replaced by a single summary message.
+    async fn prepare_history(
+        &self,
+        message: &Message,
+        history: &mut Vec<Message>,
+        tx: &mpsc::UnboundedSender<StreamEvent>,
+    ) {
+        let dropped = self.fit_history(message, history);
+        if dropped.is_empty() {
+            return;
+        }
+
+        if self.history_strategy == HistoryStrategy::Summarize
+            && let Ok(summary) = self.summarize(&dropped).await
+        {
+            let _ = tx.send(StreamEvent::HistorySummarized {
+                replaced_messages: dropped.len(),
+                summary: summary.clone(),
+            });
+            history.insert(0, Message::summary(summary));
+
+            let dropped = self.fit_history(message, history);
+            if !dropped.is_empty() {
+                let _ = tx.send(StreamEvent::ContextTruncated { dropped_messages: dropped.len() });
+            }
+            return;
+        }
+
+        let _ = tx.send(StreamEvent::ContextTruncated { dropped_messages: dropped.len() });
+    }
+
+    async fn summarize(&self, messages: &[Message]) -> Result<String> {
+        let mut request = String::from(
+            "Summarize the following earlier part of our conversation in a few short paragraphs. \
+            Keep names, decisions, open questions and any details needed to continue. \
+            Reply with the summary only.\n\n",
+        );
+        request.push_str(&transcript(messages));
+
+        let summary = self
+            .retry
+            .run(|| self.side_backend.prompt(&request, &[]), |_, _| {})
+            .instrument(self.request_span("summary"))
+            .await?;
+        Ok(summary.trim().to_string())
+    }
+
+    /// Asks the model for a short title for the conversation in `messages`, as a side request
+    /// that leaves the chat history untouched.
+    pub async fn generate_title(&self, messages: &[Message]) -> Result<String> {
+        let request = format!(
+            "Write a title of at most {} words for the conversation below. \
+            Reply with the title only, without quotes or punctuation at the end.\n\n{}",
+            MAX_TITLE_WORDS,
+            transcript(messages)
+        );
+
+        let reply = self
+            .retry
+            .run(|| self.side_backend.prompt(&request, &[]), |_, _| {})
+            .instrument(self.request_span("title"))
+            .await?;
+        let title = reply
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default()
+            .trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '*' | '#' | '.'))
+            .split_whitespace()
+            .take(MAX_TITLE_WORDS)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(title)
+    }
+
+    pub async fn send(&self, message: Message, mut history: Vec<Message>) -> Result<Message> {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        self.prepare_history(&message, &mut history, &tx).await;
+        let prompt = message.prompt_text();
+        let response = self
+            .retry
+            .run(|| self.backend.prompt(&prompt, &history), |_, _| {})
+            .instrument(self.request_span("chat"))
+            .await?;
+        Ok(Message::assistant(response))
+    }
+
+    /// Describes `error` for the chat, pointing to `/pull` when Ollama doesn't have the model.
+    pub fn describe_error(&self, error: &AgentError) -> String {
+        match (error, &self.provider) {
+            (AgentError::ModelNotFound(_), Provider::Ollama) => format!(
+                "Ollama doesn't have {} yet — download it with /pull {}",
+                self.model, self.model
+            ),
+            _ => error.user_message(),
+        }
+    }
+
+    /// Streams a response to `message`, with `history` holding the earlier turns of the
+    /// conversation. Each retry is announced with [`StreamEvent::Retrying`] before the request
+    /// restarts from scratch, so the receiver should discard whatever the failed attempt had
+    /// produced. An attempt that failed after calling a tool isn't retried.
+    pub async fn send_stream(
+        &self,
+        message: Message,
+        mut history: Vec<Message>,
+        tx: mpsc::UnboundedSender<StreamEvent>,
+    ) -> Result<()> {
+        let request = async {
+            self.tool_events.attach(tx.clone());
+            self.prepare_history(&message, &mut history, &tx).await;
+            let prompt = message.prompt_text();
+            tracing::info!(history = history.len(), "streaming request");
+
+            // Once a tool has run, retrying would run it again and ask to approve the same
+            // commands twice, so the error is reported instead.
+            let result = self
+                .retry
+                .run_if(
+                    || {
+                        self.tool_events.clear_calls();
+                        self.backend.stream(&prompt, &history, &tx)
+                    },
+                    || !self.tool_events.any_called(),
+                    |attempt, delay| {
+                        let _ = tx.send(StreamEvent::Retrying { attempt, delay });
+                    },
+                )
+                .await;
+            if result.is_ok() {
+                tracing::info!("request finished");
+            }
+            result
+        };
+        request.instrument(self.request_span("chat")).await
+    }
+}
+
+impl Backend {
+    /// The backend for the active provider, with tools reporting to `tool_events` if given and
+    /// enabled.
+    fn new(
+        config: &Config,
+        preamble: Option<&str>,
+        tool_events: Option<&ToolEvents>,
+    ) -> Result<Self> {
         let provider_config = config.get_active_provider();
+        let http_client = http_client(provider_config)?;
 
         match config.active_provider {
             Provider::OpenAI => {
                 let api_key = provider_config.api_key.as_ref().unwrap();
-                let client = openai::Client::new(api_key);
-                let agent = client.agent(&provider_config.model).build();
+                let mut builder = openai::Client::builder(api_key).with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build();
+                let agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events)
+                        .build();
                 Ok(Self::OpenAI(agent))
             }
             Provider::Anthropic => {
                 let api_key = provider_config.api_key.as_ref().unwrap();
-                let client = anthropic::Client::new(api_key);
-                let agent = client.agent(&provider_config.model).build();
-                Ok(Self::Anthropic(agent))
+                let mut builder = anthropic::Client::builder(api_key).with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build().map_err(|e| AgentError::RigError(e.to_string()))?;
+                let mut agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events);
+                if let Some(budget) = thinking_budget(config) {
+                    agent = agent.additional_params(json!({
+                        "thinking": { "type": "enabled", "budget_tokens": budget }
+                    }));
+                }
+                Ok(Self::Anthropic(agent.build()))
             }
             Provider::Ollama => {
-                let client = ollama::Client::new();
-                let agent = client.agent(&provider_config.model).build();
+                let mut builder = ollama::Client::builder().with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build();
+                let agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events)
+                        .build();
                 Ok(Self::Ollama(agent))
             }
+            Provider::OpenAICompatible => {
+                let api_key = provider_config.api_key.as_deref().unwrap_or_default();
+                let url = provider_config.base_url().unwrap_or_default();
+                let client =
+                    openai::Client::builder(api_key).with_client(http_client).base_url(url).build();
+                let model = client.completion_model(&provider_config.model).completions_api();
+                let agent =
+                    configure(AgentBuilder::new(model), config, preamble, tool_events).build();
+                Ok(Self::OpenAICompatible(agent))
+            }
+            Provider::Groq => {
+                let api_key = provider_config.api_key.as_ref().unwrap();
+                let mut builder = groq::Client::builder(api_key).with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build();
+                let agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events)
+                        .build();
+                Ok(Self::Groq(agent))
+            }
+            Provider::XAI => {
+                let api_key = provider_config.api_key.as_ref().unwrap();
+                let mut builder = xai::Client::builder(api_key).with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build();
+                let agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events)
+                        .build();
+                Ok(Self::Xai(agent))
+            }
+            Provider::DeepSeek => {
+                let api_key = provider_config.api_key.as_ref().unwrap();
+                let mut builder = deepseek::Client::builder(api_key).with_client(http_client);
+                if let Some(url) = provider_config.base_url() {
+                    builder = builder.base_url(url);
+                }
+                let client = builder.build();
+                let agent =
+                    configure(client.agent(&provider_config.model), config, preamble, tool_events)
+                        .build();
+                Ok(Self::DeepSeek(agent))
+            }
+            Provider::LlamaCpp => {
+                Ok(Self::LlamaCpp(LlamaCppClient::new(config, preamble, http_client)))
+            }
         }
     }
 
-    pub async fn send(&self, message: Message) -> Result<Message> {
-        let response = match self {
-            Self::OpenAI(agent) => agent
-                .prompt(&message.content)
-                .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-            Self::Anthropic(agent) => agent
-                .prompt(&message.content)
+    async fn prompt(&self, prompt: &str, history: &[Message]) -> Result<String> {
+        with_agent!(self,
+            agent => agent
+                .prompt(prompt)
+                .with_history(&mut to_rig_history(history))
+                .multi_turn(MAX_TOOL_TURNS)
                 .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-            Self::Ollama(agent) => agent
-                .prompt(&message.content)
-                .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-        };
-        Ok(Message::assistant(response))
+                .map_err(|e| AgentError::classify(e.to_string())),
+            client => client.prompt(prompt, history).await)
     }
 
-    pub async fn send_stream(
+    async fn stream(
         &self,
-        message: Message,
-        tx: mpsc::UnboundedSender<StreamEvent>,
+        prompt: &str,
+        history: &[Message],
+        tx: &mpsc::UnboundedSender<StreamEvent>,
     ) -> Result<()> {
-        let response_text = match self {
-            Self::OpenAI(agent) => agent
-                .prompt(&message.content)
-                .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-            Self::Anthropic(agent) => agent
-                .prompt(&message.content)
-                .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-            Self::Ollama(agent) => agent
-                .prompt(&message.content)
-                .await
-                .map_err(|e| AgentError::RigError(e.to_string()))?,
-        };
+        with_agent!(self,
+        agent => {
+            let stream = agent
+                .stream_chat(prompt, to_rig_history(history))
+                .multi_turn(MAX_TOOL_TURNS)
+                .await;
+            forward_stream(stream, tx).await
+        },
+        client => {
+            let stream = client.stream_prompt(prompt, history).await?;
+            forward_stream(stream, tx).await
+        })
+    }
+}
 
-        let mut in_thinking = false;
-        let mut current_chunk = String::new();
-
-        for c in response_text.chars() {
-            current_chunk.push(c);
-
-            if current_chunk.ends_with("<thinking>") {
-                in_thinking = true;
-                current_chunk.clear();
-                let _ = tx.send(StreamEvent::ThinkingStart);
-            } else if current_chunk.ends_with("</thinking>") && in_thinking {
-                let thinking_text =
-                    current_chunk.strip_suffix("</thinking>").unwrap_or(&current_chunk).to_string();
-                if !thinking_text.is_empty() {
-                    let _ = tx.send(StreamEvent::ThinkingChunk(thinking_text));
-                }
-                let _ = tx.send(StreamEvent::ThinkingEnd);
-                in_thinking = false;
-                current_chunk.clear();
-            } else if current_chunk.len() >= 5 {
-                let to_send = current_chunk.clone();
-                current_chunk.clear();
-
-                if in_thinking {
-                    if tx.send(StreamEvent::ThinkingChunk(to_send)).is_err() {
-                        break;
-                    }
-                } else if tx.send(StreamEvent::ContentChunk(to_send)).is_err() {
-                    break;
-                }
+fn to_rig_history(history: &[Message]) -> Vec<rig::completion::Message> {
+    history
+        .iter()
+        .filter_map(|message| match (message.kind, &message.role) {
+            (MessageKind::Summary, _) => Some(rig::completion::Message::user(format!(
+                "Summary of our earlier conversation:\n{}",
+                message.content
+            ))),
+            (_, Role::User) => Some(rig::completion::Message::user(message.prompt_text())),
+            (_, Role::Assistant) => Some(rig::completion::Message::assistant(&message.content)),
+            // Onyx's own messages are for the user; the model has its preamble instead.
+            (_, Role::System) => None,
+        })
+        .collect()
+}
 
-                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-            }
-        }
+/// Translates rig's multi-turn stream into [`StreamEvent`]s with [`forward_pieces`].
+async fn forward_stream<S, R, E>(stream: S, tx: &mpsc::UnboundedSender<StreamEvent>) -> Result<()>
+where
+    S: Stream<Item = std::result::Result<MultiTurnStreamItem<R>, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let pieces = stream.map(|item| item.map(stream_piece).map_err(|e| e.to_string()));
+    forward_pieces(pieces, tx).await
+}
 
-        if !current_chunk.is_empty() {
-            if in_thinking {
-                let _ = tx.send(StreamEvent::ThinkingChunk(current_chunk));
-                let _ = tx.send(StreamEvent::ThinkingEnd);
-            } else {
-                let _ = tx.send(StreamEvent::ContentChunk(current_chunk));
+/// The part of `item` that [`forward_pieces`] acts on, if any.
+fn stream_piece<R>(item: MultiTurnStreamItem<R>) -> Option<StreamPiece> {
+    match item {
+        MultiTurnStreamItem::StreamItem(content) => match content {
+            // Anthropic repeats a finished thinking block as one signed item after its deltas.
+            StreamedAssistantContent::Reasoning(reasoning) if reasoning.signature.is_none() => {
+                Some(StreamPiece::Reasoning(reasoning.reasoning.concat()))
             }
+            StreamedAssistantContent::Text(text) => Some(StreamPiece::Text(text.text)),
+            _ => None,
+        },
+        MultiTurnStreamItem::FinalResponse(response) => {
+            Some(StreamPiece::Usage(convert_usage(response.usage())))
         }
-
-        let _ = tx.send(StreamEvent::Done);
-        Ok(())
+        _ => None,
     }
 }
+
+/// Providers that don't report usage leave rig's counters at zero.
+fn convert_usage(usage: rig::completion::Usage) -> Option<Usage> {
+    (usage.total_tokens > 0 || usage.input_tokens > 0 || usage.output_tokens > 0).then(|| Usage {
+        prompt_tokens: usage.input_tokens,
+        completion_tokens: usage.output_tokens,
+        total_tokens: usage.total_tokens.max(usage.input_tokens + usage.output_tokens),
+    })
+}
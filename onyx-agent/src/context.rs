@@ -0,0 +1,152 @@
+use tiktoken_rs::CoreBPE;
+
+use onyx_core::{Message, MessageKind, Provider, Role};
+
+/// Rough per-message cost of role markers and separators in chat formats.
+const MESSAGE_OVERHEAD: u64 = 4;
+
+pub enum TokenEstimator {
+    Bpe(Box<CoreBPE>),
+    CharHeuristic,
+}
+
+impl TokenEstimator {
+    /// Uses the model's BPE vocabulary for OpenAI; other providers get a chars/4 estimate,
+    /// which is close enough for budgeting without shipping every tokenizer.
+    pub fn for_model(provider: &Provider, model: &str) -> Self {
+        match provider {
+            Provider::OpenAI => tiktoken_rs::get_bpe_from_model(model)
+                .or_else(|_| tiktoken_rs::o200k_base())
+                .map(|bpe| Self::Bpe(Box::new(bpe)))
+                .unwrap_or(Self::CharHeuristic),
+            _ => Self::CharHeuristic,
+        }
+    }
+
+    pub fn estimate(&self, text: &str) -> u64 {
+        match self {
+            Self::Bpe(bpe) => bpe.encode_with_special_tokens(text).len() as u64,
+            Self::CharHeuristic => text.chars().count().div_ceil(4) as u64,
+        }
+    }
+
+    fn message_tokens(&self, message: &Message) -> u64 {
+        self.estimate(&message.prompt_text()) + MESSAGE_OVERHEAD
+    }
+}
+
+/// Drops the oldest messages from `history` until the system prompt, the remaining history and
+/// `prompt` fit within `limit` tokens, returning the dropped messages. The system prompt and
+/// the prompt itself are never dropped, and the history never starts with an assistant turn.
+/// A `limit` of zero disables trimming.
+pub fn trim_history(
+    history: &mut Vec<Message>,
+    prompt: &Message,
+    system_prompt: Option<&str>,
+    limit: u64,
+    estimator: &TokenEstimator,
+) -> Vec<Message> {
+    if limit == 0 {
+        return Vec::new();
+    }
+
+    let fixed = estimator.message_tokens(prompt)
+        + system_prompt.map_or(0, |system| estimator.estimate(system) + MESSAGE_OVERHEAD);
+    let costs: Vec<u64> = history.iter().map(|message| estimator.message_tokens(message)).collect();
+    let mut total = fixed + costs.iter().sum::<u64>();
+
+    let mut dropped = 0;
+    while dropped < history.len() && total > limit {
+        total -= costs[dropped];
+        dropped += 1;
+    }
+    while dropped < history.len() && is_assistant_turn(&history[dropped]) {
+        dropped += 1;
+    }
+
+    history.drain(..dropped).collect()
+}
+
+/// Summaries are sent to the model as user messages, so only real replies count here.
+fn is_assistant_turn(message: &Message) -> bool {
+    matches!(message.role, Role::Assistant) && message.kind != MessageKind::Summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Eight characters, so six tokens to the heuristic counting the message overhead.
+    fn user(n: u32) -> Message {
+        Message::user(format!("user {:03}", n))
+    }
+
+    fn assistant(n: u32) -> Message {
+        Message::assistant(format!("bot  {:03}", n))
+    }
+
+    fn contents(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|message| message.content.as_str()).collect()
+    }
+
+    fn trim(history: &mut Vec<Message>, system: Option<&str>, limit: u64) -> Vec<Message> {
+        trim_history(history, &user(99), system, limit, &TokenEstimator::CharHeuristic)
+    }
+
+    fn conversation() -> Vec<Message> {
+        vec![user(1), assistant(1), user(2), assistant(2)]
+    }
+
+    #[test]
+    fn nothing_is_dropped_while_it_fits() {
+        let mut history = conversation();
+        assert!(trim(&mut history, None, 30).is_empty());
+        assert!(trim(&mut history, None, 0).is_empty());
+        assert_eq!(history.len(), 4);
+    }
+
+    #[test]
+    fn oldest_messages_go_first() {
+        let mut history = conversation();
+        let dropped = trim(&mut history, None, 18);
+        assert_eq!(contents(&dropped), ["user 001", "bot  001"]);
+        assert_eq!(contents(&history), ["user 002", "bot  002"]);
+    }
+
+    #[test]
+    fn history_never_starts_with_a_reply() {
+        // Dropping the first message is enough, but the reply to it goes too.
+        let mut history = conversation();
+        let dropped = trim(&mut history, None, 24);
+        assert_eq!(contents(&dropped), ["user 001", "bot  001"]);
+        assert_eq!(contents(&history), ["user 002", "bot  002"]);
+    }
+
+    #[test]
+    fn a_summary_can_start_the_history() {
+        let mut history = vec![user(1), Message::summary("summary!"), user(2)];
+        let dropped = trim(&mut history, None, 18);
+        assert_eq!(contents(&dropped), ["user 001"]);
+        assert_eq!(contents(&history), ["summary!", "user 002"]);
+    }
+
+    #[test]
+    fn the_system_prompt_counts_but_stays() {
+        let mut history = conversation();
+        let dropped = trim(&mut history, Some("system!!"), 24);
+        assert_eq!(contents(&dropped), ["user 001", "bot  001"]);
+        assert_eq!(contents(&history), ["user 002", "bot  002"]);
+    }
+
+    #[test]
+    fn the_newest_message_is_never_dropped() {
+        // Not even the prompt alone fits: the whole history goes, but the prompt is still sent.
+        let mut history = conversation();
+        let prompt = user(99);
+        let dropped =
+            trim_history(&mut history, &prompt, Some("system"), 1, &TokenEstimator::CharHeuristic);
+        assert_eq!(dropped.len(), 4);
+        assert!(history.is_empty());
+        assert_eq!(prompt.content, "user 099");
+    }
+}
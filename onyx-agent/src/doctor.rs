@@ -0,0 +1,305 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use onyx_core::{Config, ConfigError, ConfigSchema, Memories, Provider, mask_secret};
+
+use crate::error::AgentError;
+use crate::models::{ModelInfo, list_models};
+use crate::ollama::ollama_url;
+
+/// How long the provider gets to answer before it is reported as unreachable.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    /// Worth fixing, but chatting still works.
+    Warning,
+    Failed,
+    /// Not run because an earlier check failed.
+    Skipped,
+}
+
+impl CheckStatus {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Passed => "✓",
+            Self::Warning => "!",
+            Self::Failed => "✗",
+            Self::Skipped => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a failure or warning.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn passed(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Passed, detail: detail.into(), hint: None }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warning, detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn failed(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Failed, detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn skipped(name: &'static str) -> Self {
+        let detail = "skipped after an earlier failure".to_string();
+        Self { name, status: CheckStatus::Skipped, detail, hint: None }
+    }
+}
+
+/// The results of [`diagnose`], one line per check when displayed.
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    /// Whether every hard requirement for chatting is met. Warnings don't count against it.
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|check| check.status != CheckStatus::Failed)
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{} {}: {}", check.status.symbol(), check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "    {}", hint)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the config file at `path` parses and that `config` is ready to chat: the API key
+/// is set and accepted, the provider answers and it offers the configured model.
+pub async fn diagnose(path: &Path, config: &Config) -> Diagnosis {
+    let mut checks = vec![check_config(path, config)];
+
+    if checks[0].status == CheckStatus::Failed {
+        checks.extend(["API key", "Provider", "Model"].map(Check::skipped));
+    } else {
+        let api_key = check_api_key(config);
+        if api_key.status == CheckStatus::Failed {
+            checks.extend([api_key, Check::skipped("Provider"), Check::skipped("Model")]);
+        } else {
+            checks.extend(check_provider(config, api_key).await);
+        }
+    }
+
+    checks.push(check_memories());
+    Diagnosis { checks }
+}
+
+/// Checks only that the active provider answers, accepts the key and offers the model, as a
+/// quick test of settings that may not be saved yet. Returns what the last check found, as an
+/// error if any check failed.
+pub async fn test_connection(config: &Config) -> Result<String, String> {
+    let api_key = check_api_key(config);
+    if api_key.status == CheckStatus::Failed {
+        return Err(api_key.detail);
+    }
+
+    let checks = check_provider(config, api_key).await;
+    match checks.iter().find(|check| check.status == CheckStatus::Failed) {
+        Some(failed) => Err(failed.detail.clone()),
+        None => Ok(checks[2].detail.clone()),
+    }
+}
+
+fn check_api_key(config: &Config) -> Check {
+    let provider = &config.active_provider;
+    let prefix = provider.field_prefix();
+
+    match config.validate() {
+        Err(ConfigError::MissingApiKey(..)) => Check::failed(
+            "API key",
+            format!("no API key for {}", provider),
+            format!("Set {}_api_key in /config or with `onyx config set`", prefix),
+        ),
+        Err(e @ ConfigError::UnsetVariable(..)) => Check::failed(
+            "API key",
+            e.to_string(),
+            format!("Export it before running onyx, or set {}_api_key directly", prefix),
+        ),
+        _ => match config.get_active_provider().api_key.as_deref() {
+            Some(key) if !key.is_empty() => Check::passed("API key", mask_secret(key)),
+            _ => Check::passed("API key", format!("not needed for {}", provider)),
+        },
+    }
+}
+
+fn check_config(path: &Path, config: &Config) -> Check {
+    const NAME: &str = "Config file";
+    let prefix = config.active_provider.field_prefix();
+
+    match Config::read_from(path) {
+        Err(ConfigError::ReadError(e)) if e.kind() == io::ErrorKind::NotFound => {
+            return Check::failed(
+                NAME,
+                format!("no config file at {}", path.display()),
+                "Run onyx once to create one with the defaults",
+            );
+        }
+        Err(e @ ConfigError::UnsetVariable(..)) => {
+            return Check::failed(NAME, e.to_string(), "Export it before running onyx");
+        }
+        Err(e) => {
+            return Check::failed(
+                NAME,
+                format!("{} ({})", e, path.display()),
+                "Fix the file by hand, or move it aside to start over with the defaults",
+            );
+        }
+        Ok(_) => {}
+    }
+
+    match config.validate() {
+        Err(e @ (ConfigError::InvalidUrl(..) | ConfigError::MissingUrl(..))) => {
+            return Check::failed(NAME, e.to_string(), "Fix the URL in /config");
+        }
+        Err(e @ ConfigError::InvalidValue(..)) => {
+            return Check::failed(
+                NAME,
+                e.to_string(),
+                "Fix it in /config or with `onyx config set`",
+            );
+        }
+        _ if config.get_active_provider().model.trim().is_empty() => {
+            return Check::failed(
+                NAME,
+                format!("no model set for {}", config.active_provider),
+                format!("Set {}_model in /config or with `onyx config set`", prefix),
+            );
+        }
+        _ => {}
+    }
+
+    Check::passed(
+        NAME,
+        format!(
+            "{}, using {} with {}",
+            path.display(),
+            config.active_provider,
+            config.get_active_provider().model
+        ),
+    )
+}
+
+/// Asks the provider for its models, which shows at once whether it can be reached, whether it
+/// accepts the key and whether the configured model exists. Returns the API key, provider and
+/// model checks, the first possibly downgraded if the key was rejected.
+async fn check_provider(config: &Config, api_key: Check) -> [Check; 3] {
+    let provider = &config.active_provider;
+    let prefix = provider.field_prefix();
+    let model = config.get_active_provider().model.trim();
+
+    let result = match tokio::time::timeout(REQUEST_TIMEOUT, list_models(config)).await {
+        Ok(result) => result,
+        Err(_) => Err(AgentError::NetworkError(format!(
+            "no answer within {}s",
+            REQUEST_TIMEOUT.as_secs()
+        ))),
+    };
+
+    match result {
+        Ok(models) => [
+            api_key,
+            Check::passed("Provider", format!("{} answered", provider)),
+            check_model(provider, model, &models),
+        ],
+        Err(AgentError::AuthenticationFailed(message)) => [
+            Check::failed(
+                "API key",
+                format!("rejected by {}: {}", provider, message),
+                format!("Check {}_api_key in /config or with `onyx config get`", prefix),
+            ),
+            Check::passed("Provider", format!("{} answered", provider)),
+            Check::skipped("Model"),
+        ],
+        // Servers that follow the OpenAI API loosely don't always have a model listing.
+        Err(AgentError::ModelNotFound(_)) => [
+            api_key,
+            Check::passed("Provider", format!("{} answered", provider)),
+            Check::warning(
+                "Model",
+                format!("{} doesn't list its models, so {} couldn't be checked", provider, model),
+                "Send a message to see whether it works",
+            ),
+        ],
+        Err(e) if *provider == Provider::Ollama && e.is_retryable() => [
+            api_key,
+            Check::failed(
+                "Provider",
+                format!("Ollama not reachable at {}", ollama_url(config)),
+                "Start Ollama with `ollama serve`, or fix ollama_url",
+            ),
+            Check::skipped("Model"),
+        ],
+        Err(e) => {
+            let hint = match provider {
+                Provider::Ollama => {
+                    "Start Ollama with `ollama serve`, or fix ollama_url".to_string()
+                }
+                Provider::LlamaCpp => "Start llama-server, or fix llama_cpp_url".to_string(),
+                _ if e.is_retryable() => {
+                    format!("Check your connection and {}_url, or try again later", prefix)
+                }
+                _ => format!("Check {}_url in /config", prefix),
+            };
+            [api_key, Check::failed("Provider", e.user_message(), hint), Check::skipped("Model")]
+        }
+    }
+}
+
+fn check_model(provider: &Provider, model: &str, models: &[ModelInfo]) -> Check {
+    // llama-server ignores the model name and answers with whichever model it has loaded.
+    if *provider == Provider::LlamaCpp {
+        let loaded: Vec<&str> = models.iter().map(|info| info.id.as_str()).collect();
+        return Check::passed("Model", format!("llama.cpp serves {}", loaded.join(", ")));
+    }
+
+    // Ollama lists models with their tag, and an untagged name means the `latest` one.
+    let available = models
+        .iter()
+        .any(|info| info.id == model || info.id.strip_suffix(":latest") == Some(model));
+    if available {
+        return Check::passed("Model", format!("{} is available", model));
+    }
+
+    let hint = match provider {
+        Provider::Ollama => {
+            format!("Download it with /pull {} (or `ollama pull`), or pick another", model)
+        }
+        _ => "Run /models to see what is, then switch with /model <name>".to_string(),
+    };
+    Check::failed("Model", format!("{} doesn't offer {}", provider, model), hint)
+}
+
+fn check_memories() -> Check {
+    const NAME: &str = "Memories";
+    match Memories::load() {
+        Ok(memories) => Check::passed(NAME, format!("{} pinned", memories.entries.len())),
+        Err(e) => {
+            let path = Memories::path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|_| "memories.json".to_string());
+            Check::warning(NAME, e.to_string(), format!("Fix or delete {}", path))
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AgentError {
+    #[error("Configuration error: {0}")]
+    ConfigError(#[from] onyx_core::ConfigError),
+
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("Rate limited{}", retry_after.map(|d| format!(" (retry after {}s)", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
+    #[error("Agent error: {0}")]
+    RigError(String),
+}
+
+pub type Result<T> = std::result::Result<T, AgentError>;
+
+impl AgentError {
+    /// Classifies an error from an HTTP status code, as returned directly by a provider.
+    pub fn from_status(status: u16, message: String, retry_after: Option<Duration>) -> Self {
+        match status {
+            401 | 403 => Self::AuthenticationFailed(message),
+            404 => Self::ModelNotFound(message),
+            408 => Self::NetworkError(message),
+            429 => Self::RateLimited {
+                retry_after: retry_after.or_else(|| parse_retry_after(&message)),
+            },
+            400 | 422 => Self::InvalidRequest(message),
+            500..=599 => Self::ProviderUnavailable(message),
+            _ => Self::RigError(message),
+        }
+    }
+
+    /// Classifies an error that only survives as text, which is all rig hands back: providers
+    /// report failures as strings, and its HTTP layer folds the status code into the message.
+    pub fn classify(message: String) -> Self {
+        if let Some(status) = status_code(&message) {
+            return Self::from_status(status, message, None);
+        }
+
+        let lower = message.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+
+        if mentions(&["rate limit", "rate_limit", "too many requests"]) {
+            Self::RateLimited { retry_after: parse_retry_after(&message) }
+        } else if mentions(&[
+            "api key",
+            "api_key",
+            "x-api-key",
+            "unauthorized",
+            "authentication",
+            "permission denied",
+        ]) {
+            Self::AuthenticationFailed(message)
+        } else if mentions(&["model_not_found", "model not found", "does not exist", "not_found"]) {
+            Self::ModelNotFound(message)
+        } else if mentions(&[
+            "error sending request",
+            "connection",
+            "dns error",
+            "timed out",
+            "timeout",
+            "stream ended",
+        ]) {
+            Self::NetworkError(message)
+        } else if mentions(&["overloaded", "service unavailable", "internal server error"]) {
+            Self::ProviderUnavailable(message)
+        } else if mentions(&["invalid_request", "invalid request", "bad request"]) {
+            Self::InvalidRequest(message)
+        } else {
+            Self::RigError(message)
+        }
+    }
+
+    /// Transient failures worth retrying; authentication and malformed requests fail fast.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited { .. } | Self::NetworkError(_) | Self::ProviderUnavailable(_)
+        )
+    }
+
+    /// A short, actionable description suitable for showing in the chat.
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::AuthenticationFailed(_) => "Invalid API key — open /config to fix it".to_string(),
+            Self::RateLimited { retry_after: Some(delay) } => {
+                format!("Rate limited, try again in {}s", delay.as_secs().max(1))
+            }
+            Self::RateLimited { retry_after: None } => {
+                "Rate limited, try again in a moment".to_string()
+            }
+            Self::NetworkError(message) => format!("Could not reach the provider: {}", message),
+            Self::ModelNotFound(_) => {
+                "Model not found — check the model name in /config".to_string()
+            }
+            Self::InvalidRequest(message) => {
+                format!("The provider rejected the request: {}", message)
+            }
+            Self::ProviderUnavailable(message) => {
+                format!("The provider is having trouble, try again later: {}", message)
+            }
+            Self::ConfigError(_) | Self::RigError(_) => self.to_string(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AgentError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => Self::from_status(status.as_u16(), error.to_string(), None),
+            None if error.is_connect() || error.is_timeout() || error.is_request() => {
+                Self::NetworkError(error.to_string())
+            }
+            None => Self::RigError(error.to_string()),
+        }
+    }
+}
+
+/// Turns a non-success response into a classified error, honouring its `Retry-After` header.
+pub(crate) async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    let message =
+        if body.is_empty() { status.to_string() } else { format!("{}: {}", status, body) };
+
+    Err(AgentError::from_status(status.as_u16(), message, retry_after))
+}
+
+/// Extracts the status code from messages like "Invalid status code: 401 Unauthorized".
+fn status_code(message: &str) -> Option<u16> {
+    let lower = message.to_lowercase();
+    let rest = &lower[lower.find("status")? + "status".len()..];
+    let rest = rest.strip_prefix(" code").unwrap_or(rest);
+    let rest = rest.trim_start_matches([' ', ':', '=']);
+
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    if digits.len() != 3 {
+        return None;
+    }
+    digits.parse().ok().filter(|status| (400..600).contains(status))
+}
+
+/// Extracts a delay from hints like "Please try again in 20s" or "retry after 1.5 seconds".
+fn parse_retry_after(message: &str) -> Option<Duration> {
+    let lower = message.to_lowercase();
+    let rest = ["try again in", "retry after", "retry-after:"]
+        .iter()
+        .find_map(|hint| lower.find(hint).map(|pos| &lower[pos + hint.len()..]))?
+        .trim_start();
+
+    let number: String = rest.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    let value: f64 = number.parse().ok()?;
+    let unit = rest[number.len()..].trim_start();
+
+    let seconds = if unit.starts_with("ms") { value / 1000.0 } else { value };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The variant `error` is, without its contents.
+    fn kind(error: &AgentError) -> &'static str {
+        match error {
+            AgentError::ConfigError(_) => "config",
+            AgentError::AuthenticationFailed(_) => "auth",
+            AgentError::RateLimited { .. } => "rate limited",
+            AgentError::NetworkError(_) => "network",
+            AgentError::ModelNotFound(_) => "model not found",
+            AgentError::InvalidRequest(_) => "invalid request",
+            AgentError::ProviderUnavailable(_) => "unavailable",
+            AgentError::RigError(_) => "other",
+        }
+    }
+
+    #[test]
+    fn classifies_status_codes_in_messages() {
+        for (message, expected) in [
+            ("Invalid status code: 401 Unauthorized", "auth"),
+            ("Invalid status code 403 Forbidden", "auth"),
+            ("HTTP status: 404 model gpt-9 not found", "model not found"),
+            ("Invalid status code: 400 Bad Request", "invalid request"),
+            ("status=422 unprocessable entity", "invalid request"),
+            ("Invalid status code: 429 Too Many Requests", "rate limited"),
+            ("Invalid status code: 500 Internal Server Error", "unavailable"),
+            ("Invalid status code: 502 Bad Gateway", "unavailable"),
+            ("Invalid status code: 503 Service Unavailable", "unavailable"),
+            ("Invalid status code: 408 Request Timeout", "network"),
+            ("Invalid status code: 418 I'm a teapot", "other"),
+        ] {
+            assert_eq!(kind(&AgentError::classify(message.to_string())), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn a_status_without_a_code_falls_back_to_keywords() {
+        for (message, expected) in [
+            ("stream status unknown: connection reset by peer", "network"),
+            ("status: 42, api key rejected", "auth"),
+            ("status 200 but the body was invalid_request", "invalid request"),
+            ("status 4000: model_not_found", "model not found"),
+            ("bad status", "other"),
+        ] {
+            assert_eq!(kind(&AgentError::classify(message.to_string())), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn classifies_by_keywords() {
+        for (message, expected) in [
+            ("Rate limit reached for requests", "rate limited"),
+            ("Too many requests, slow down", "rate limited"),
+            ("invalid x-api-key", "auth"),
+            ("The model `gpt-9` does not exist", "model not found"),
+            ("error sending request for url (https://api.openai.com/v1/responses)", "network"),
+            ("operation timed out", "network"),
+            ("Overloaded", "unavailable"),
+            ("invalid_request_error: messages: field required", "invalid request"),
+            ("tool call failed", "other"),
+        ] {
+            assert_eq!(kind(&AgentError::classify(message.to_string())), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn rate_limits_carry_the_suggested_delay() {
+        for (message, retry_after) in [
+            ("Invalid status code: 429 Please try again in 20s", Some(Duration::from_secs(20))),
+            ("rate limit exceeded, retry after 1500ms", Some(Duration::from_millis(1500))),
+            ("rate_limit_error: retry after 1.5 seconds", Some(Duration::from_millis(1500))),
+            ("Too many requests. Retry-After: 7", Some(Duration::from_secs(7))),
+            ("Too many requests", None),
+            ("rate limited, try again in a while", None),
+        ] {
+            match AgentError::classify(message.to_string()) {
+                AgentError::RateLimited { retry_after: actual } => {
+                    assert_eq!(actual, retry_after, "{}", message)
+                }
+                other => panic!("{} was classified as {:?}", message, other),
+            }
+        }
+    }
+
+    #[test]
+    fn a_retry_after_header_wins_over_the_message() {
+        let error = AgentError::from_status(
+            429,
+            "try again in 20s".to_string(),
+            Some(Duration::from_secs(3)),
+        );
+        assert!(
+            matches!(error, AgentError::RateLimited { retry_after } if retry_after == Some(Duration::from_secs(3)))
+        );
+    }
+
+    #[test]
+    fn only_transient_errors_are_retryable() {
+        let retryable = |message: &str| AgentError::classify(message.to_string()).is_retryable();
+        assert!(retryable("Invalid status code: 429 Too Many Requests"));
+        assert!(retryable("Invalid status code: 503 Service Unavailable"));
+        assert!(retryable("connection refused"));
+        assert!(!retryable("Invalid status code: 401 Unauthorized"));
+        assert!(!retryable("Invalid status code: 400 Bad Request"));
+        assert!(!retryable("Invalid status code: 404 Not Found"));
+    }
+}
@@ -0,0 +1,26 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::error::{AgentError, Result};
+use onyx_core::{ConfigError, ProviderConfig};
+
+/// An HTTP client that sends the provider's extra headers with every request. Their values
+/// are marked sensitive, so they never show up in debug output or logs.
+pub(crate) fn http_client(provider_config: &ProviderConfig) -> Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in provider_config.headers() {
+        let invalid = |reason: &str| {
+            AgentError::ConfigError(ConfigError::InvalidValue(
+                "extra_headers".to_string(),
+                format!("{} {}", name, reason),
+            ))
+        };
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| invalid("is not a valid header name"))?;
+        let mut value =
+            HeaderValue::from_str(value).map_err(|_| invalid("has an invalid value"))?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    Ok(reqwest::Client::builder().default_headers(headers).build()?)
+}
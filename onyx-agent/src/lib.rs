@@ -1,3 +1,20 @@
 mod chat;
+mod context;
+mod doctor;
+mod error;
+mod http;
+mod llama_cpp;
+mod models;
+mod ollama;
+mod pricing;
+mod retry;
+mod thinking;
+mod tools;
 
 pub use chat::{ChatAgent, StreamEvent};
+pub use doctor::{Check, CheckStatus, Diagnosis, diagnose, test_connection};
+pub use error::AgentError;
+pub use models::{ModelInfo, list_models};
+pub use ollama::{check_ollama, pull_model};
+pub use pricing::{model_price, reply_cost};
+pub use tools::ShellApproval;
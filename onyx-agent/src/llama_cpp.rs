@@ -0,0 +1,294 @@
+use futures::{Stream, StreamExt};
+use rig::agent::MultiTurnStreamItem;
+use rig::completion::Usage;
+use rig::streaming::StreamedAssistantContent;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::{AgentError, Result, check_status};
+use onyx_core::{Config, Message, MessageKind, Role};
+
+const DEFAULT_URL: &str = "http://localhost:8080";
+
+#[derive(Debug, Default, Deserialize)]
+struct CompletionChunk {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    stop: bool,
+    #[serde(default)]
+    tokens_evaluated: u64,
+    #[serde(default)]
+    tokens_predicted: u64,
+}
+
+pub struct LlamaCppClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    preamble: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u64>,
+}
+
+impl LlamaCppClient {
+    pub fn new(config: &Config, preamble: Option<&str>, http_client: reqwest::Client) -> Self {
+        let provider_config = config.get_active_provider();
+
+        Self {
+            http_client,
+            base_url: provider_config.base_url().unwrap_or(DEFAULT_URL).to_string(),
+            api_key: provider_config.api_key.clone().filter(|key| !key.is_empty()),
+            preamble: preamble.map(str::to_string),
+            temperature: provider_config.temperature,
+            max_tokens: provider_config.max_tokens,
+        }
+    }
+
+    /// The `/completion` endpoint takes raw text, so the conversation is rendered as a plain
+    /// User/Assistant transcript ending with the new prompt, the first turn included, so that
+    /// the model sees the same framing on every turn.
+    fn build_prompt(&self, prompt: &str, history: &[Message]) -> String {
+        let mut text = match &self.preamble {
+            Some(preamble) => format!("{}\n\n", preamble),
+            None => String::new(),
+        };
+
+        for message in history {
+            let speaker = match (message.kind, &message.role) {
+                (MessageKind::Summary, _) => "Summary of earlier conversation",
+                (_, Role::User) => "User",
+                (_, Role::Assistant) => "Assistant",
+                (_, Role::System) => "System",
+            };
+            text.push_str(&format!("{}: {}\n", speaker, message.prompt_text()));
+        }
+        text.push_str(&format!("User: {}\nAssistant:", prompt));
+        text
+    }
+
+    fn request(&self, prompt: &str, history: &[Message], stream: bool) -> reqwest::RequestBuilder {
+        let prompt = self.build_prompt(prompt, history);
+
+        let mut body = json!({ "prompt": prompt, "stream": stream });
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["n_predict"] = json!(max_tokens);
+        }
+
+        let request = self.http_client.post(format!("{}/completion", self.base_url)).json(&body);
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    pub async fn prompt(&self, prompt: &str, history: &[Message]) -> Result<String> {
+        let chunk: CompletionChunk =
+            check_status(self.request(prompt, history, false).send().await?).await?.json().await?;
+        Ok(chunk.content)
+    }
+
+    /// Streams a completion from the server's SSE endpoint, yielding items in the same shape
+    /// as rig's agents so the caller can treat every provider uniformly.
+    pub async fn stream_prompt(
+        &self,
+        prompt: &str,
+        history: &[Message],
+    ) -> Result<impl Stream<Item = Result<MultiTurnStreamItem<()>>> + Unpin + use<>> {
+        let response = check_status(self.request(prompt, history, true).send().await?).await?;
+        let state = SseState::new(Box::pin(response.bytes_stream()));
+        Ok(Box::pin(futures::stream::unfold(state, next_item)))
+    }
+}
+
+struct SseState<B> {
+    bytes: B,
+    buffer: Vec<u8>,
+    response: String,
+    pending_final: Option<Usage>,
+    /// Whether `bytes` has run out, leaving only what is in `buffer`.
+    ended: bool,
+    finished: bool,
+}
+
+impl<B> SseState<B> {
+    fn new(bytes: B) -> Self {
+        Self {
+            bytes,
+            buffer: Vec::new(),
+            response: String::new(),
+            pending_final: None,
+            ended: false,
+            finished: false,
+        }
+    }
+}
+
+async fn next_item<B, T>(
+    mut state: SseState<B>,
+) -> Option<(Result<MultiTurnStreamItem<()>>, SseState<B>)>
+where
+    B: Stream<Item = reqwest::Result<T>> + Unpin,
+    T: AsRef<[u8]>,
+{
+    loop {
+        if let Some(usage) = state.pending_final.take() {
+            state.finished = true;
+            let item = MultiTurnStreamItem::final_response(&state.response, usage);
+            return Some((Ok(item), state));
+        }
+
+        if state.finished {
+            return None;
+        }
+
+        if let Some(pos) = state.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = state.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+
+            let chunk: CompletionChunk = match serde_json::from_str(data.trim()) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    state.finished = true;
+                    return Some((Err(AgentError::RigError(e.to_string())), state));
+                }
+            };
+
+            if chunk.stop {
+                state.pending_final = Some(Usage {
+                    input_tokens: chunk.tokens_evaluated,
+                    output_tokens: chunk.tokens_predicted,
+                    total_tokens: chunk.tokens_evaluated + chunk.tokens_predicted,
+                });
+            }
+
+            if chunk.content.is_empty() {
+                continue;
+            }
+
+            state.response.push_str(&chunk.content);
+            let item =
+                MultiTurnStreamItem::StreamItem(StreamedAssistantContent::text(&chunk.content));
+            return Some((Ok(item), state));
+        }
+
+        if state.ended {
+            state.pending_final = Some(Usage::new());
+            continue;
+        }
+
+        match state.bytes.next().await {
+            Some(Ok(bytes)) => state.buffer.extend_from_slice(bytes.as_ref()),
+            Some(Err(e)) => {
+                state.finished = true;
+                return Some((Err(e.into()), state));
+            }
+            None => {
+                // The last line still counts without its newline.
+                state.ended = true;
+                if !state.buffer.is_empty() {
+                    state.buffer.push(b'\n');
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "data: {\"content\":\"Hel\"}\n\n\
+        data: {\"content\":\"lo é\"}\n\n\
+        data: {\"content\":\"!\",\"stop\":true,\"tokens_evaluated\":5,\"tokens_predicted\":3}\n\n";
+
+    /// What the SSE body arriving as `chunks` streams: the text, and the input and output
+    /// tokens of each final response.
+    async fn read(chunks: &[&[u8]]) -> Result<(String, Vec<(u64, u64)>)> {
+        let bytes = futures::stream::iter(
+            chunks.iter().map(|chunk| reqwest::Result::Ok(chunk.to_vec())).collect::<Vec<_>>(),
+        );
+        let mut items = Box::pin(futures::stream::unfold(SseState::new(bytes), next_item));
+
+        let (mut text, mut finals) = (String::new(), Vec::new());
+        while let Some(item) = items.next().await {
+            match item? {
+                MultiTurnStreamItem::StreamItem(StreamedAssistantContent::Text(chunk)) => {
+                    text.push_str(&chunk.text)
+                }
+                MultiTurnStreamItem::FinalResponse(response) => {
+                    let usage = response.usage();
+                    finals.push((usage.input_tokens, usage.output_tokens));
+                }
+                _ => {}
+            }
+        }
+        Ok((text, finals))
+    }
+
+    fn client(preamble: Option<&str>) -> LlamaCppClient {
+        LlamaCppClient {
+            http_client: reqwest::Client::new(),
+            base_url: DEFAULT_URL.to_string(),
+            api_key: None,
+            preamble: preamble.map(str::to_string),
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_events_split_anywhere() {
+        let body = BODY.as_bytes();
+        let expected = ("Hello é!".to_string(), vec![(5, 3)]);
+        assert_eq!(read(&[body]).await.unwrap(), expected);
+
+        for split in 1..body.len() {
+            let (first, rest) = body.split_at(split);
+            assert_eq!(read(&[first, rest]).await.unwrap(), expected, "split at {}", split);
+        }
+        let bytes: Vec<&[u8]> = body.chunks(1).collect();
+        assert_eq!(read(&bytes).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn a_last_line_without_a_newline_still_counts() {
+        let body = BODY.trim_end().as_bytes();
+        assert_eq!(read(&[body]).await.unwrap(), ("Hello é!".to_string(), vec![(5, 3)]));
+
+        let (first, rest) = body.split_at(body.len() - 10);
+        assert_eq!(read(&[first, rest]).await.unwrap(), ("Hello é!".to_string(), vec![(5, 3)]));
+    }
+
+    #[tokio::test]
+    async fn a_stream_without_a_stop_still_finishes() {
+        let body = b"data: {\"content\":\"partial\"}\n\n: keep-alive\n\n";
+        assert_eq!(read(&[body]).await.unwrap(), ("partial".to_string(), vec![(0, 0)]));
+        assert_eq!(read(&[]).await.unwrap(), (String::new(), vec![(0, 0)]));
+    }
+
+    #[tokio::test]
+    async fn malformed_events_are_errors() {
+        assert!(read(&[b"data: {\"content\":\n\n"]).await.is_err());
+    }
+
+    #[test]
+    fn every_turn_is_framed_as_a_transcript() {
+        let plain = client(None);
+        assert_eq!(plain.build_prompt("Hi", &[]), "User: Hi\nAssistant:");
+        assert_eq!(
+            plain.build_prompt("And you?", &[Message::user("Hi"), Message::assistant("Hello")]),
+            "User: Hi\nAssistant: Hello\nUser: And you?\nAssistant:"
+        );
+        assert_eq!(
+            client(Some("Be brief.")).build_prompt("Hi", &[]),
+            "Be brief.\n\nUser: Hi\nAssistant:"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use serde::Deserialize;
+
+use crate::error::{Result, check_status};
+use crate::http::http_client;
+use onyx_core::{Config, Provider};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Clone)]
+pub struct ModelInfo {
+    pub id: String,
+    pub details: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModels {
+    data: Vec<OpenAIModel>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModel {
+    id: String,
+    #[serde(default)]
+    owned_by: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicModels {
+    data: Vec<AnthropicModel>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicModel {
+    id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct OllamaModel {
+    name: String,
+    #[serde(default)]
+    details: OllamaModelDetails,
+}
+
+#[derive(Deserialize, Default)]
+struct OllamaModelDetails {
+    #[serde(default)]
+    parameter_size: Option<String>,
+    #[serde(default)]
+    quantization_level: Option<String>,
+}
+
+/// Fetches the active provider's model catalog, sorted by name.
+pub async fn list_models(config: &Config) -> Result<Vec<ModelInfo>> {
+    config.validate()?;
+
+    let provider_config = config.get_active_provider();
+    let api_key = provider_config.api_key.as_deref().filter(|key| !key.is_empty());
+    let base_url = |default: &str| provider_config.base_url().unwrap_or(default).to_string();
+    let client = http_client(provider_config)?;
+
+    let mut models = match &config.active_provider {
+        Provider::Anthropic => {
+            let url = format!("{}/v1/models?limit=1000", base_url("https://api.anthropic.com"));
+            let request = client
+                .get(url)
+                .header("x-api-key", api_key.unwrap_or_default())
+                .header("anthropic-version", ANTHROPIC_VERSION);
+            let response: AnthropicModels =
+                check_status(request.send().await?).await?.json().await?;
+            response
+                .data
+                .into_iter()
+                .map(|model| ModelInfo { id: model.id, details: model.display_name })
+                .collect()
+        }
+        Provider::Ollama => {
+            let url = format!("{}/api/tags", base_url("http://localhost:11434"));
+            let response: OllamaTags =
+                check_status(client.get(url).send().await?).await?.json().await?;
+            response
+                .models
+                .into_iter()
+                .map(|model| {
+                    let details = [model.details.parameter_size, model.details.quantization_level]
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    ModelInfo { id: model.name, details: Some(details).filter(|d| !d.is_empty()) }
+                })
+                .collect()
+        }
+        provider => {
+            let url = match provider {
+                Provider::XAI => format!("{}/v1/models", base_url("https://api.x.ai")),
+                Provider::DeepSeek => format!("{}/models", base_url("https://api.deepseek.com")),
+                Provider::Groq => {
+                    format!("{}/models", base_url("https://api.groq.com/openai/v1"))
+                }
+                Provider::LlamaCpp => format!("{}/v1/models", base_url("http://localhost:8080")),
+                _ => format!("{}/models", base_url("https://api.openai.com/v1")),
+            };
+            let mut request = client.get(url);
+            if let Some(api_key) = api_key {
+                request = request.bearer_auth(api_key);
+            }
+            let response: OpenAIModels = check_status(request.send().await?).await?.json().await?;
+            response
+                .data
+                .into_iter()
+                .map(|model| ModelInfo { id: model.id, details: model.owned_by })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
@@ -0,0 +1,98 @@
+//! Ollama's own API, for what the chat doesn't cover: checking that the server is up, and
+//! downloading models into it.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::chat::StreamEvent;
+use crate::error::{AgentError, Result, check_status};
+use crate::http::http_client;
+use onyx_core::{Config, Provider};
+
+const DEFAULT_URL: &str = "http://localhost:11434";
+
+/// How long Ollama gets to answer at startup before it is reported as not running.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// One line of the progress `/api/pull` streams back.
+#[derive(Deserialize)]
+struct PullStatus {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl PullStatus {
+    /// The status with how far along the download is, as "pulling 6a0746a1ec1a 42%".
+    fn describe(&self) -> String {
+        match (self.total, self.completed) {
+            (Some(total), Some(completed)) if total > 0 => {
+                format!("{} {}%", self.status, completed * 100 / total)
+            }
+            _ => self.status.clone(),
+        }
+    }
+}
+
+/// Where the Ollama server is, as configured or at its default address.
+pub fn ollama_url(config: &Config) -> String {
+    config.provider_config(&Provider::Ollama).base_url().unwrap_or(DEFAULT_URL).to_string()
+}
+
+/// Checks that Ollama answers when it is the active provider, so that a server that isn't running
+/// shows up at startup rather than as the first message failing. Returns what to tell the user.
+pub async fn check_ollama(config: &Config) -> Option<String> {
+    if config.active_provider != Provider::Ollama {
+        return None;
+    }
+
+    let url = ollama_url(config);
+    let client = http_client(config.provider_config(&Provider::Ollama)).ok()?;
+    let request = client.get(format!("{}/api/version", url)).timeout(PROBE_TIMEOUT).send();
+    match request.await {
+        Ok(_) => None,
+        Err(_) => Some(format!(
+            "Ollama not reachable at {} — is `ollama serve` running?\n\n\
+            Start it and send a message, or set ollama_url in /config if it runs elsewhere.",
+            url
+        )),
+    }
+}
+
+/// Downloads `model` into Ollama, sending each step as [`StreamEvent::Progress`].
+pub async fn pull_model(
+    config: &Config,
+    model: &str,
+    tx: &mpsc::UnboundedSender<StreamEvent>,
+) -> Result<()> {
+    let url = format!("{}/api/pull", ollama_url(config));
+    let client = http_client(config.provider_config(&Provider::Ollama))?;
+    let request = client.post(url).json(&json!({ "model": model }));
+    let mut response = check_status(request.send().await?).await?;
+
+    // Progress comes as one JSON object per line, and lines may be split across chunks.
+    let mut pending = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let Ok(status) = serde_json::from_slice::<PullStatus>(&line) else {
+                continue;
+            };
+            if let Some(error) = status.error {
+                return Err(AgentError::classify(error));
+            }
+            let _ = tx.send(StreamEvent::Progress(status.describe()));
+        }
+    }
+
+    let _ = tx.send(StreamEvent::Progress(format!("Pulled {}. It's ready to use.", model)));
+    Ok(())
+}
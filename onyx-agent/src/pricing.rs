@@ -0,0 +1,51 @@
+//! What models cost, for estimating what a conversation has cost so far.
+
+use onyx_core::{Config, ModelPrice, Usage};
+
+/// Dollars per 1,000 input and output tokens, by the start of the model's name. Prices change,
+/// so `pricing` in the config overrides any of these.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5", 0.00125, 0.01),
+    ("gpt-5-mini", 0.00025, 0.002),
+    ("gpt-5-nano", 0.00005, 0.0004),
+    ("gpt-4.1", 0.002, 0.008),
+    ("gpt-4.1-mini", 0.0004, 0.0016),
+    ("gpt-4.1-nano", 0.0001, 0.0004),
+    ("gpt-4o", 0.0025, 0.01),
+    ("gpt-4o-mini", 0.00015, 0.0006),
+    ("gpt-4-turbo", 0.01, 0.03),
+    ("gpt-3.5-turbo", 0.0005, 0.0015),
+    ("o1", 0.015, 0.06),
+    ("o1-mini", 0.0011, 0.0044),
+    ("o3", 0.002, 0.008),
+    ("o3-mini", 0.0011, 0.0044),
+    ("o4-mini", 0.0011, 0.0044),
+    ("claude-opus-4", 0.015, 0.075),
+    ("claude-opus-4-5", 0.005, 0.025),
+    ("claude-sonnet-4", 0.003, 0.015),
+    ("claude-haiku-4", 0.001, 0.005),
+    ("claude-3-7-sonnet", 0.003, 0.015),
+    ("claude-3-5-sonnet", 0.003, 0.015),
+    ("claude-3-5-haiku", 0.0008, 0.004),
+    ("claude-3-opus", 0.015, 0.075),
+    ("claude-3-haiku", 0.00025, 0.00125),
+];
+
+/// The price of `model`: the config's, or else the built-in one whose name it starts with,
+/// the longest if several do.
+pub fn model_price(config: &Config, model: &str) -> Option<ModelPrice> {
+    if let Some(price) = config.pricing.get(model) {
+        return Some(*price);
+    }
+
+    PRICES
+        .iter()
+        .filter(|(prefix, ..)| model.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+        .map(|&(_, input, output)| ModelPrice { input, output })
+}
+
+/// What a reply that used `usage` cost on the active model, if its price is known.
+pub fn reply_cost(config: &Config, usage: &Usage) -> Option<f64> {
+    model_price(config, &config.get_active_provider().model).map(|price| price.cost(usage))
+}
@@ -0,0 +1,224 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{AgentError, Result};
+use onyx_core::Config;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_retries: u32::try_from(config.max_retries).unwrap_or(u32::MAX),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+        }
+    }
+
+    /// Runs `operation` until it succeeds, fails with a non-retryable error, or the retry
+    /// budget is spent. `on_retry` is called with the attempt number and delay before each wait.
+    pub async fn run<T, F, Fut>(
+        &self,
+        operation: F,
+        on_retry: impl FnMut(u32, Duration),
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_if(operation, || true, on_retry).await
+    }
+
+    /// [`run`](Self::run), except that a failed attempt is only retried if `can_retry` allows
+    /// it, for when an attempt may have done something that mustn't happen twice.
+    pub async fn run_if<T, F, Fut>(
+        &self,
+        mut operation: F,
+        mut can_retry: impl FnMut() -> bool,
+        mut on_retry: impl FnMut(u32, Duration),
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(error) if error.is_retryable() && attempt < self.max_retries && can_retry() => {
+                    attempt += 1;
+                    let delay = self.delay(attempt, &error);
+                    tracing::warn!(attempt, ?delay, %error, "retrying request");
+                    on_retry(attempt, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                Err(error) => {
+                    tracing::error!(%error, "request failed");
+                    return Err(error);
+                }
+                ok => return ok,
+            }
+        }
+    }
+
+    /// Exponential backoff with equal jitter, deferring to the provider's hint when it gave one.
+    fn delay(&self, attempt: u32, error: &AgentError) -> Duration {
+        if let AgentError::RateLimited { retry_after: Some(retry_after) } = error {
+            return (*retry_after).min(MAX_BACKOFF);
+        }
+
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << (attempt - 1).min(16))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        let half = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn policy(max_retries: u32, initial_backoff: Duration) -> RetryPolicy {
+        RetryPolicy { max_retries, initial_backoff }
+    }
+
+    /// Runs `policy` over an operation that always fails with `error()`, returning how many
+    /// times it ran and the delays it was retried after.
+    async fn run_failing(
+        policy: RetryPolicy,
+        error: impl Fn() -> AgentError,
+    ) -> (u32, Vec<Duration>, AgentError) {
+        let calls = Cell::new(0);
+        let mut delays = Vec::new();
+        let result: Result<()> = policy
+            .run(
+                || {
+                    calls.set(calls.get() + 1);
+                    std::future::ready(Err(error()))
+                },
+                |_, delay| delays.push(delay),
+            )
+            .await;
+        (calls.get(), delays, result.unwrap_err())
+    }
+
+    /// The delay before retry number `attempt` after a network error.
+    fn backoff_delay(policy: RetryPolicy, attempt: u32) -> Duration {
+        policy.delay(attempt, &AgentError::NetworkError("timed out".to_string()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn authentication_and_invalid_requests_fail_immediately() {
+        let errors: [fn() -> AgentError; 2] = [
+            || AgentError::AuthenticationFailed("401 Unauthorized".to_string()),
+            || AgentError::InvalidRequest("400 Bad Request".to_string()),
+        ];
+        for error in errors {
+            let (calls, delays, error) =
+                run_failing(policy(5, Duration::from_secs(1)), error).await;
+            assert_eq!(calls, 1, "{}", error);
+            assert!(delays.is_empty(), "{}", error);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_after_max_retries() {
+        let (calls, delays, error) = run_failing(policy(3, Duration::from_millis(100)), || {
+            AgentError::NetworkError("connection reset".to_string())
+        })
+        .await;
+        assert_eq!(calls, 4);
+        assert_eq!(delays.len(), 3);
+        assert!(matches!(error, AgentError::NetworkError(_)));
+
+        let (calls, _, _) = run_failing(policy(0, Duration::from_millis(100)), || {
+            AgentError::ProviderUnavailable("503".to_string())
+        })
+        .await;
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_as_long_as_the_provider_asks() {
+        let start = tokio::time::Instant::now();
+        let (_, delays, _) = run_failing(policy(2, Duration::from_millis(100)), || {
+            AgentError::RateLimited { retry_after: Some(Duration::from_secs(20)) }
+        })
+        .await;
+        assert_eq!(delays, [Duration::from_secs(20); 2]);
+        assert_eq!(start.elapsed(), Duration::from_secs(40));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delays_are_capped() {
+        let (_, delays, _) = run_failing(policy(2, Duration::from_secs(3600)), || {
+            AgentError::RateLimited { retry_after: Some(Duration::from_secs(3600)) }
+        })
+        .await;
+        assert_eq!(delays, [MAX_BACKOFF; 2]);
+
+        let (_, delays, _) = run_failing(policy(20, Duration::from_secs(1)), || {
+            AgentError::NetworkError("timed out".to_string())
+        })
+        .await;
+        assert_eq!(delays.len(), 20);
+        for delay in delays {
+            assert!(delay <= MAX_BACKOFF, "{:?}", delay);
+        }
+        assert!(backoff_delay(policy(20, Duration::from_secs(1)), 20) >= MAX_BACKOFF / 2);
+    }
+
+    #[test]
+    fn backoff_grows_with_each_attempt() {
+        let policy = policy(5, Duration::from_secs(1));
+        for attempt in 1..=5 {
+            let backoff = Duration::from_secs(1 << (attempt - 1));
+            let delay = backoff_delay(policy, attempt);
+            assert!(backoff / 2 <= delay && delay <= backoff, "{}: {:?}", attempt, delay);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn can_retry_stops_retries() {
+        let calls = Cell::new(0);
+        let result: Result<()> = policy(5, Duration::from_millis(100))
+            .run_if(
+                || {
+                    calls.set(calls.get() + 1);
+                    std::future::ready(Err(AgentError::NetworkError("reset".to_string())))
+                },
+                || calls.get() < 2,
+                |_, _| {},
+            )
+            .await;
+        assert_eq!(calls.get(), 2);
+        assert!(matches!(result, Err(AgentError::NetworkError(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_retry_that_succeeds_returns_its_value() {
+        let calls = Cell::new(0);
+        let result = policy(3, Duration::from_millis(100))
+            .run(
+                || {
+                    calls.set(calls.get() + 1);
+                    std::future::ready(match calls.get() {
+                        1 => Err(AgentError::ProviderUnavailable("overloaded".to_string())),
+                        _ => Ok("reply"),
+                    })
+                },
+                |_, _| {},
+            )
+            .await;
+        assert_eq!(result.unwrap(), "reply");
+        assert_eq!(calls.get(), 2);
+    }
+}
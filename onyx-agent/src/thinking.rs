@@ -0,0 +1,351 @@
+use futures::{Stream, StreamExt};
+use onyx_core::Usage;
+use tokio::sync::mpsc;
+
+use crate::chat::StreamEvent;
+use crate::error::{AgentError, Result};
+
+/// Tag pairs recognised by default: `<think>` (DeepSeek-R1 distills, QwQ) and `<thinking>`.
+pub const DEFAULT_TAGS: &[(&str, &str)] = &[("<think>", "</think>"), ("<thinking>", "</thinking>")];
+
+/// Fallback for models that inline their reasoning in the text. Tags are stripped from the
+/// output and the text between them is routed to thinking events. Text that could be the start
+/// of a tag is held back until the next chunk decides it, so tags split across chunks still
+/// match. Tags do not nest: inside a block only its own closing tag is recognised, and an
+/// unclosed block is ended when the stream finishes.
+pub struct ThinkingTagScanner {
+    tags: Vec<(String, String)>,
+    open: Option<usize>,
+    buffer: String,
+}
+
+impl Default for ThinkingTagScanner {
+    fn default() -> Self {
+        Self::new(DEFAULT_TAGS)
+    }
+}
+
+impl ThinkingTagScanner {
+    pub fn new(tags: &[(&str, &str)]) -> Self {
+        Self {
+            tags: tags.iter().map(|(open, close)| (open.to_string(), close.to_string())).collect(),
+            open: None,
+            buffer: String::new(),
+        }
+    }
+
+    /// Feeds a chunk of streamed text. Returns `false` once the receiver has gone away.
+    pub fn feed(&mut self, text: &str, tx: &mpsc::UnboundedSender<StreamEvent>) -> bool {
+        self.buffer.push_str(text);
+
+        loop {
+            match self.next_tag() {
+                Some((pos, len, next)) => {
+                    let before: String = self.buffer.drain(..pos).collect();
+                    self.buffer.drain(..len);
+                    if !self.emit(before, tx) {
+                        return false;
+                    }
+                    self.open = next;
+                    let event = match next {
+                        Some(_) => StreamEvent::ThinkingStart,
+                        None => StreamEvent::ThinkingEnd,
+                    };
+                    if tx.send(event).is_err() {
+                        return false;
+                    }
+                }
+                None => {
+                    let ready = self.buffer.len() - self.held_back_len();
+                    let text: String = self.buffer.drain(..ready).collect();
+                    return self.emit(text, tx);
+                }
+            }
+        }
+    }
+
+    /// Flushes held-back text and closes a thinking block left open by the model.
+    pub fn finish(&mut self, tx: &mpsc::UnboundedSender<StreamEvent>) {
+        let remaining = std::mem::take(&mut self.buffer);
+        self.emit(remaining, tx);
+        if self.open.take().is_some() {
+            let _ = tx.send(StreamEvent::ThinkingEnd);
+        }
+    }
+
+    /// Finds the next tag that changes state, as `(position, tag length, new open index)`.
+    fn next_tag(&self) -> Option<(usize, usize, Option<usize>)> {
+        match self.open {
+            Some(index) => {
+                let close = &self.tags[index].1;
+                self.buffer.find(close.as_str()).map(|pos| (pos, close.len(), None))
+            }
+            None => self
+                .tags
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (open, _))| {
+                    self.buffer.find(open.as_str()).map(|pos| (pos, open.len(), Some(index)))
+                })
+                .min_by_key(|(pos, _, _)| *pos),
+        }
+    }
+
+    fn held_back_len(&self) -> usize {
+        let candidates: Vec<&str> = match self.open {
+            Some(index) => vec![self.tags[index].1.as_str()],
+            None => self.tags.iter().map(|(open, _)| open.as_str()).collect(),
+        };
+        candidates.into_iter().map(|tag| partial_tag_len(&self.buffer, tag)).max().unwrap_or(0)
+    }
+
+    fn emit(&self, text: String, tx: &mpsc::UnboundedSender<StreamEvent>) -> bool {
+        if text.is_empty() {
+            return true;
+        }
+        let event = match self.open {
+            Some(_) => StreamEvent::ThinkingChunk(text),
+            None => StreamEvent::ContentChunk(text),
+        };
+        tx.send(event).is_ok()
+    }
+}
+
+/// What [`forward_pieces`] acts on in the stream of a reply, taken out of rig's types.
+#[derive(Debug, Clone)]
+pub enum StreamPiece {
+    /// Reasoning the provider streams apart from the reply, as DeepSeek's `reasoning_content`
+    /// and Anthropic's thinking blocks.
+    Reasoning(String),
+    Text(String),
+    Usage(Option<Usage>),
+}
+
+/// Sends the events for a reply streamed as `pieces`, ending with [`StreamEvent::Done`].
+/// Reasoning goes to the thinking events, and so does text inside thinking tags. Items with
+/// nothing in them for the app are `None`, and an error ends the stream without `Done`.
+pub async fn forward_pieces<S>(mut pieces: S, tx: &mpsc::UnboundedSender<StreamEvent>) -> Result<()>
+where
+    S: Stream<Item = std::result::Result<Option<StreamPiece>, String>> + Unpin,
+{
+    let mut in_reasoning = false;
+    let mut tags = ThinkingTagScanner::default();
+    let mut usage = None;
+
+    while let Some(piece) = pieces.next().await {
+        match piece.map_err(AgentError::classify)? {
+            Some(StreamPiece::Reasoning(text)) => {
+                if !in_reasoning {
+                    in_reasoning = true;
+                    let _ = tx.send(StreamEvent::ThinkingStart);
+                }
+                if !text.is_empty() && tx.send(StreamEvent::ThinkingChunk(text)).is_err() {
+                    return Ok(());
+                }
+            }
+            Some(StreamPiece::Text(text)) => {
+                if in_reasoning {
+                    in_reasoning = false;
+                    let _ = tx.send(StreamEvent::ThinkingEnd);
+                }
+                if !tags.feed(&text, tx) {
+                    return Ok(());
+                }
+            }
+            Some(StreamPiece::Usage(reported)) => usage = reported,
+            None => {}
+        }
+    }
+
+    if in_reasoning {
+        let _ = tx.send(StreamEvent::ThinkingEnd);
+    }
+    tags.finish(tx);
+
+    let _ = tx.send(StreamEvent::Done(usage));
+    Ok(())
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&n| tag.is_char_boundary(n) && text.ends_with(&tag[..n]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The events sent to `rx`, with runs of text of the same kind joined up, as `text:...`,
+    /// `think:...`, `<start>`, `<end>` and `<done>`.
+    fn events(mut rx: mpsc::UnboundedReceiver<StreamEvent>) -> Vec<String> {
+        let mut events: Vec<String> = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            let event = match event {
+                StreamEvent::ThinkingStart => "<start>".to_string(),
+                StreamEvent::ThinkingEnd => "<end>".to_string(),
+                StreamEvent::ThinkingChunk(text) => format!("think:{}", text),
+                StreamEvent::ContentChunk(text) => format!("text:{}", text),
+                StreamEvent::Done(_) => "<done>".to_string(),
+                other => panic!("unexpected event {:?}", other),
+            };
+            match (events.last_mut(), event.split_once(':')) {
+                (Some(last), Some((kind, text))) if last.starts_with(&format!("{}:", kind)) => {
+                    last.push_str(text)
+                }
+                _ => events.push(event),
+            }
+        }
+        events
+    }
+
+    /// What the scanner sends for `chunks`.
+    fn scan(chunks: &[&str]) -> Vec<String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut scanner = ThinkingTagScanner::default();
+        for chunk in chunks {
+            assert!(scanner.feed(chunk, &tx));
+        }
+        scanner.finish(&tx);
+        events(rx)
+    }
+
+    /// What [`forward_pieces`] sends for a stream of `pieces`, and what it returns.
+    async fn forward(
+        pieces: Vec<std::result::Result<Option<StreamPiece>, String>>,
+    ) -> (Vec<String>, Result<()>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let result = forward_pieces(futures::stream::iter(pieces), &tx).await;
+        (events(rx), result)
+    }
+
+    fn reasoning(text: &str) -> std::result::Result<Option<StreamPiece>, String> {
+        Ok(Some(StreamPiece::Reasoning(text.to_string())))
+    }
+
+    fn text(text: &str) -> std::result::Result<Option<StreamPiece>, String> {
+        Ok(Some(StreamPiece::Text(text.to_string())))
+    }
+
+    #[test]
+    fn routes_tagged_text_to_thinking() {
+        for (open, close) in DEFAULT_TAGS {
+            assert_eq!(
+                scan(&[&format!("{}plan{}answer", open, close)]),
+                ["<start>", "think:plan", "<end>", "text:answer"]
+            );
+        }
+    }
+
+    #[test]
+    fn tags_split_mid_chunk() {
+        assert_eq!(
+            scan(&["Hi <thi", "nk>pl", "an</th", "ink>ans", "wer"]),
+            ["text:Hi ", "<start>", "think:plan", "<end>", "text:answer"]
+        );
+    }
+
+    #[test]
+    fn every_split_point_gives_the_same_events() {
+        let text = "a <thinking>b < c</thinking> d";
+        let whole = scan(&[text]);
+        assert_eq!(whole, ["text:a ", "<start>", "think:b < c", "<end>", "text: d"]);
+        for split in 1..text.len() {
+            assert_eq!(scan(&[&text[..split], &text[split..]]), whole, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn text_that_mentions_thinking_is_left_alone() {
+        assert_eq!(
+            scan(&["I was thinking about <b>thinking</b>, and a <th", "ought"]),
+            ["text:I was thinking about <b>thinking</b>, and a <thought"]
+        );
+    }
+
+    #[test]
+    fn a_tag_that_never_completes_is_released_at_the_end() {
+        assert_eq!(scan(&["x <thin"]), ["text:x <thin"]);
+    }
+
+    #[test]
+    fn tags_do_not_nest() {
+        assert_eq!(
+            scan(&["<think>a<think>b</think>c</think>d"]),
+            ["<start>", "think:a<think>b", "<end>", "text:c</think>d"]
+        );
+    }
+
+    #[test]
+    fn only_the_matching_close_tag_ends_a_block() {
+        assert_eq!(
+            scan(&["<thinking>a</think>b</thinking>c"]),
+            ["<start>", "think:a</think>b", "<end>", "text:c"]
+        );
+    }
+
+    #[test]
+    fn an_unclosed_block_ends_with_the_stream() {
+        assert_eq!(
+            scan(&["<think>never ", "closed</thi"]),
+            ["<start>", "think:never closed</thi", "<end>"]
+        );
+    }
+
+    #[test]
+    fn a_stray_close_tag_is_content() {
+        assert_eq!(scan(&["a</think>b"]), ["text:a</think>b"]);
+    }
+
+    #[test]
+    fn stops_once_the_receiver_is_gone() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        drop(rx);
+        assert!(!ThinkingTagScanner::default().feed("text", &tx));
+    }
+
+    #[tokio::test]
+    async fn reasoning_content_becomes_a_thinking_block() {
+        let (events, result) =
+            forward(vec![reasoning("Let me"), reasoning(" see"), text("The "), text("answer")])
+                .await;
+        assert!(result.is_ok());
+        assert_eq!(events, ["<start>", "think:Let me see", "<end>", "text:The answer", "<done>"]);
+    }
+
+    #[tokio::test]
+    async fn a_reply_that_is_all_reasoning_still_ends_the_block() {
+        let (events, _) = forward(vec![reasoning("hmm")]).await;
+        assert_eq!(events, ["<start>", "think:hmm", "<end>", "<done>"]);
+    }
+
+    #[tokio::test]
+    async fn without_reasoning_it_is_plain_content() {
+        let usage = Usage { prompt_tokens: 3, completion_tokens: 2, total_tokens: 5 };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let pieces = vec![text("Hello"), Ok(None), Ok(Some(StreamPiece::Usage(Some(usage))))];
+        forward_pieces(futures::stream::iter(pieces), &tx).await.unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(StreamEvent::ContentChunk(text)) if text == "Hello"));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(StreamEvent::Done(Some(Usage { total_tokens: 5, .. })))
+        ));
+    }
+
+    #[tokio::test]
+    async fn tags_in_the_text_are_thinking_too() {
+        let (events, _) = forward(vec![text("<think>a</th"), text("ink>b")]).await;
+        assert_eq!(events, ["<start>", "think:a", "<end>", "text:b", "<done>"]);
+    }
+
+    #[tokio::test]
+    async fn an_error_ends_the_stream_without_done() {
+        let (events, result) =
+            forward(vec![reasoning("a"), Err("connection reset".to_string()), text("b")]).await;
+        assert!(matches!(result, Err(AgentError::NetworkError(_))));
+        assert_eq!(events, ["<start>", "think:a"]);
+    }
+}
@@ -0,0 +1,483 @@
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use rig::tool::server::ToolServer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::chat::StreamEvent;
+use onyx_core::Config;
+use onyx_core::fs::{Entry, read_directory};
+
+/// Files larger than this are cut off so one read can't flood the context window.
+const MAX_READ_BYTES: u64 = 64 * 1024;
+
+/// Limits for `execute_shell`; output past the cap is drained and discarded.
+const SHELL_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_SHELL_OUTPUT_BYTES: u64 = 16 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ToolError {
+    #[error("{0} is outside the working directory")]
+    OutsideRoot(String),
+
+    #[error("{0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("the user declined to run `{0}`")]
+    Denied(String),
+
+    #[error("`{0}` timed out after {1}s")]
+    TimedOut(String, u64),
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PathArgs {
+    path: String,
+}
+
+/// Where tools report their activity. rig runs tools inside its multi-turn loop without
+/// surfacing them in the stream, so the agent points this at each request's channel instead.
+#[derive(Clone, Default)]
+pub struct ToolEvents {
+    tx: Arc<Mutex<Option<mpsc::UnboundedSender<StreamEvent>>>>,
+    /// Whether a tool has been called since the last [`clear_calls`](Self::clear_calls).
+    called: Arc<AtomicBool>,
+}
+
+impl ToolEvents {
+    pub fn attach(&self, tx: mpsc::UnboundedSender<StreamEvent>) {
+        *self.tx.lock().unwrap() = Some(tx);
+    }
+
+    pub fn clear_calls(&self) {
+        self.called.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether a tool call has started since the calls were last cleared. Once one has, the
+    /// request has had effects that running it again would repeat.
+    pub fn any_called(&self) -> bool {
+        self.called.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether anyone was listening.
+    fn send(&self, event: StreamEvent) -> bool {
+        match self.tx.lock().unwrap().as_ref() {
+            Some(tx) => tx.send(event).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// A shell command waiting for the user's go-ahead. Dropping it without a response counts as
+/// a denial.
+#[derive(Debug, Clone)]
+pub struct ShellApproval {
+    pub command: String,
+    pub working_dir: PathBuf,
+    reply: Arc<Mutex<Option<oneshot::Sender<bool>>>>,
+}
+
+impl ShellApproval {
+    pub fn respond(&self, approved: bool) {
+        if let Some(reply) = self.reply.lock().unwrap().take() {
+            let _ = reply.send(approved);
+        }
+    }
+}
+
+/// Wraps a tool so that each call and its outcome are reported to [`ToolEvents`].
+struct Reported<T> {
+    tool: T,
+    events: ToolEvents,
+}
+
+impl<T> Tool for Reported<T>
+where
+    T: Tool,
+    T::Args: Serialize,
+{
+    const NAME: &'static str = T::NAME;
+
+    type Error = T::Error;
+    type Args = T::Args;
+    type Output = T::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.tool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.events.called.store(true, Ordering::Relaxed);
+        self.events.send(StreamEvent::ToolCallStart {
+            name: T::NAME.to_string(),
+            arguments: describe_arguments(&serde_json::to_value(&args).unwrap_or_default()),
+        });
+
+        let result = self.tool.call(args).await;
+        let summary = match &result {
+            Ok(output) => {
+                describe_output(T::NAME, &serde_json::to_value(output).unwrap_or_default())
+            }
+            Err(error) => format!("error: {}", error),
+        };
+        self.events.send(StreamEvent::ToolCallResult { name: T::NAME.to_string(), summary });
+
+        result
+    }
+}
+
+/// The tools enabled in `config`, confined to the directory the app was started in.
+pub fn server(config: &Config, events: ToolEvents) -> ToolServer {
+    let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut server = ToolServer::new();
+
+    if config.tools_enabled {
+        server = server
+            .tool(Reported { tool: ListDirectory { root: root.clone() }, events: events.clone() })
+            .tool(Reported { tool: ReadFile { root: root.clone() }, events: events.clone() });
+    }
+    if config.shell_enabled {
+        server =
+            server.tool(Reported { tool: ExecuteShell { root, events: events.clone() }, events });
+    }
+
+    server
+}
+
+/// Resolves `path` against `root`, rejecting anything that escapes it once symlinks and `..`
+/// are followed.
+fn resolve(root: &Path, path: &str) -> Result<PathBuf, ToolError> {
+    let io_error = |error| ToolError::Io(path.to_string(), error);
+    let root = root.canonicalize().map_err(io_error)?;
+    let resolved = root.join(path).canonicalize().map_err(io_error)?;
+
+    if resolved.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(ToolError::OutsideRoot(path.to_string()))
+    }
+}
+
+fn path_parameters(description: &str) -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": { "type": "string", "description": description }
+        },
+        "required": ["path"]
+    })
+}
+
+pub struct ListDirectory {
+    root: PathBuf,
+}
+
+impl Tool for ListDirectory {
+    const NAME: &'static str = "list_directory";
+
+    type Error = ToolError;
+    type Args = PathArgs;
+    type Output = Vec<Entry>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "List the files and directories in a directory of the user's project."
+                .to_string(),
+            parameters: path_parameters("Directory path relative to the project root, e.g. \".\""),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = resolve(&self.root, &args.path)?;
+        read_directory(&path).map_err(|error| ToolError::Io(args.path, error))
+    }
+}
+
+pub struct ReadFile {
+    root: PathBuf,
+}
+
+impl Tool for ReadFile {
+    const NAME: &'static str = "read_file";
+
+    type Error = ToolError;
+    type Args = PathArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Read a text file from the user's project. Files over {} KiB are truncated.",
+                MAX_READ_BYTES / 1024
+            ),
+            parameters: path_parameters("File path relative to the project root"),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let io_error = |error| ToolError::Io(args.path.clone(), error);
+        let path = resolve(&self.root, &args.path)?;
+
+        let file = std::fs::File::open(&path).map_err(io_error)?;
+        let mut bytes = Vec::new();
+        file.take(MAX_READ_BYTES + 1).read_to_end(&mut bytes).map_err(io_error)?;
+
+        let truncated = bytes.len() as u64 > MAX_READ_BYTES;
+        bytes.truncate(MAX_READ_BYTES as usize);
+        let mut contents = String::from_utf8_lossy(&bytes).into_owned();
+        if truncated {
+            contents.push_str("\n[truncated]");
+        }
+        Ok(contents)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShellArgs {
+    command: String,
+}
+
+#[derive(Serialize)]
+pub struct ShellOutput {
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs commands through the platform shell, but only once the user has approved each one.
+pub struct ExecuteShell {
+    root: PathBuf,
+    events: ToolEvents,
+}
+
+impl ExecuteShell {
+    async fn approve(&self, command: &str) -> bool {
+        let (reply, response) = oneshot::channel();
+        let approval = ShellApproval {
+            command: command.to_string(),
+            working_dir: self.root.clone(),
+            reply: Arc::new(Mutex::new(Some(reply))),
+        };
+
+        self.events.send(StreamEvent::ShellApprovalRequested(approval))
+            && response.await.unwrap_or(false)
+    }
+}
+
+impl Tool for ExecuteShell {
+    const NAME: &'static str = "execute_shell";
+
+    type Error = ToolError;
+    type Args = ShellArgs;
+    type Output = ShellOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Run a shell command in the user's project directory. The user must approve each \
+                command and may decline. Commands are stopped after {}s.",
+                SHELL_TIMEOUT.as_secs()
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "The command line to run" }
+                },
+                "required": ["command"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if !self.approve(&args.command).await {
+            return Err(ToolError::Denied(args.command));
+        }
+
+        let io_error = |error| ToolError::Io(args.command.clone(), error);
+        let mut child = shell_command(&args.command)
+            .current_dir(&self.root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(io_error)?;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let run = async {
+            let (stdout, stderr) = tokio::join!(read_capped(stdout), read_capped(stderr));
+            let status = child.wait().await?;
+            std::io::Result::Ok(ShellOutput { exit_code: status.code(), stdout, stderr })
+        };
+
+        tokio::time::timeout(SHELL_TIMEOUT, run)
+            .await
+            .map_err(|_| ToolError::TimedOut(args.command.clone(), SHELL_TIMEOUT.as_secs()))?
+            .map_err(io_error)
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+    shell.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command);
+    shell
+}
+
+/// Reads up to [`MAX_SHELL_OUTPUT_BYTES`] and drains the rest so the child never blocks on a
+/// full pipe.
+async fn read_capped(pipe: Option<impl AsyncRead + Unpin>) -> String {
+    let Some(mut pipe) = pipe else {
+        return String::new();
+    };
+
+    let mut bytes = Vec::new();
+    let _ = (&mut pipe).take(MAX_SHELL_OUTPUT_BYTES).read_to_end(&mut bytes).await;
+    let drained = tokio::io::copy(&mut pipe, &mut tokio::io::sink()).await.unwrap_or(0);
+
+    let mut output = String::from_utf8_lossy(&bytes).into_owned();
+    if drained > 0 {
+        output.push_str(&format!("\n[truncated {} bytes]", drained));
+    }
+    output
+}
+
+/// Renders tool arguments for display, e.g. `{"path":"src/main.rs"}` as `src/main.rs`.
+fn describe_arguments(arguments: &serde_json::Value) -> String {
+    match arguments {
+        serde_json::Value::Object(map) => map
+            .values()
+            .map(|value| match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// A one-line account of what a tool returned, e.g. `12 entries` or `240 lines`.
+fn describe_output(name: &str, output: &serde_json::Value) -> String {
+    match (name, output) {
+        (ListDirectory::NAME, serde_json::Value::Array(entries)) => {
+            format!("{} entries", entries.len())
+        }
+        (ReadFile::NAME, serde_json::Value::String(contents)) => {
+            format!("{} lines", contents.lines().count())
+        }
+        (ExecuteShell::NAME, serde_json::Value::Object(output)) => match &output["exit_code"] {
+            serde_json::Value::Number(code) => format!("exit code {}", code),
+            _ => "terminated by signal".to_string(),
+        },
+        _ => "done".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// An empty directory of its own for the test called `name`, with a `root` directory in it
+    /// for the tools and `outside.txt` next to that.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("onyx-tools-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("root").join("src")).unwrap();
+        fs::write(dir.join("root").join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("outside.txt"), "secret\n").unwrap();
+        dir
+    }
+
+    async fn read(root: &Path, path: &str) -> Result<String, ToolError> {
+        ReadFile { root: root.to_path_buf() }.call(PathArgs { path: path.to_string() }).await
+    }
+
+    #[test]
+    fn paths_inside_the_root_resolve() {
+        let dir = test_dir("inside");
+        let root = dir.join("root");
+        let main = root.join("src").join("main.rs").canonicalize().unwrap();
+
+        for path in ["src/main.rs", "./src/main.rs", "src/../src/main.rs"] {
+            assert_eq!(resolve(&root, path).unwrap(), main, "{}", path);
+        }
+        assert_eq!(resolve(&root, main.to_str().unwrap()).unwrap(), main);
+        assert_eq!(resolve(&root, ".").unwrap(), root.canonicalize().unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parent_directories_cannot_escape() {
+        let dir = test_dir("parent");
+        let root = dir.join("root");
+
+        for path in ["..", "../outside.txt", "src/../../outside.txt"] {
+            assert!(matches!(resolve(&root, path), Err(ToolError::OutsideRoot(_))), "{}", path);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn absolute_paths_outside_are_rejected() {
+        let dir = test_dir("absolute");
+        let outside = dir.join("outside.txt");
+
+        let result = resolve(&dir.join("root"), outside.to_str().unwrap());
+        assert!(matches!(result, Err(ToolError::OutsideRoot(_))), "{:?}", result);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_out_of_the_root_are_rejected() {
+        let dir = test_dir("symlink");
+        let root = dir.join("root");
+        std::os::unix::fs::symlink(dir.join("outside.txt"), root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir, root.join("up")).unwrap();
+
+        for path in ["link.txt", "up", "up/outside.txt"] {
+            assert!(matches!(resolve(&root, path), Err(ToolError::OutsideRoot(_))), "{}", path);
+        }
+        assert!(matches!(read(&root, "link.txt").await, Err(ToolError::OutsideRoot(_))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_paths_are_errors() {
+        let dir = test_dir("missing");
+        assert!(matches!(resolve(&dir.join("root"), "nope.rs"), Err(ToolError::Io(..))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn long_files_are_truncated() {
+        let dir = test_dir("truncated");
+        let root = dir.join("root");
+        let limit = MAX_READ_BYTES as usize;
+        fs::write(root.join("exact.txt"), "a".repeat(limit)).unwrap();
+        fs::write(root.join("long.txt"), "b".repeat(limit + 10)).unwrap();
+
+        assert_eq!(read(&root, "exact.txt").await.unwrap(), "a".repeat(limit));
+        assert_eq!(
+            read(&root, "long.txt").await.unwrap(),
+            format!("{}\n[truncated]", "b".repeat(limit))
+        );
+        assert_eq!(read(&root, "src/main.rs").await.unwrap(), "fn main() {}\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
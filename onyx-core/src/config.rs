@@ -1,8 +1,17 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use chrono::format::{Item, StrftimeItems};
 use thiserror::Error;
 
+use crate::fs::{restrict_to_owner, write_private_file};
+use crate::migration::{Migration, run_migrations};
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Failed to determine home directory")]
@@ -14,29 +23,91 @@ pub enum ConfigError {
     #[error("Failed to parse config file: {0}")]
     ParseError(#[from] serde_json::Error),
 
+    #[error("Failed to parse config file: {0}")]
+    TomlParseError(#[from] toml::de::Error),
+
+    #[error("Failed to write config file: {0}")]
+    TomlWriteError(#[from] toml::ser::Error),
+
     #[error("{0} API key not configured. Please edit {1} and add your API key for {0}.")]
     MissingApiKey(String, String),
 
+    #[error("{0} URL not configured. Please edit {1} and add the server URL for {0}.")]
+    MissingUrl(String, String),
+
     #[error("Field not found: {0}")]
     FieldNotFound(String),
+
+    #[error("Invalid URL for {0}: '{1}' (expected http:// or https://)")]
+    InvalidUrl(String, String),
+
+    #[error("Environment variable {0} is not set, but {1} in the config file refers to it")]
+    UnsetVariable(String, String),
+
+    #[error("Failed to migrate config from version {0}: {1}")]
+    MigrationFailed(u64, String),
+
+    #[error("Invalid {0}: {1}")]
+    InvalidValue(String, String),
 }
 
 pub type ConfigResult<T> = std::result::Result<T, ConfigError>;
 
+/// Hides all but the ends of a secret, so it can be recognised without being revealed.
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let start: String = chars[..4].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", start, end)
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum FieldParseError {
+    #[error("must be a whole number")]
+    InvalidInteger,
+
+    #[error("must be a number")]
+    InvalidFloat,
+
+    #[error("must be on or off")]
+    InvalidBool,
+
+    #[error("must be between {} and {}", format_float(*.0), format_float(*.1))]
+    OutOfRange(f64, f64),
+}
+
+/// Formats `n` as briefly as it can be without losing precision, keeping a `.0` on whole
+/// numbers so they still read as decimals.
+fn format_float(n: f64) -> String {
+    let formatted = n.to_string();
+    if formatted.contains(['.', 'e', 'i', 'N']) { formatted } else { formatted + ".0" }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FieldType {
     String,
     OptionalString,
     Enum,
     U64,
+    OptionalU64,
+    F64,
+    OptionalF64,
+    Bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FieldValue {
     String(String),
     OptionalString(Option<String>),
     Enum(String),
     U64(u64),
+    OptionalU64(Option<u64>),
+    F64(f64),
+    OptionalF64(Option<f64>),
+    Bool(bool),
 }
 
 impl FieldValue {
@@ -46,6 +117,10 @@ impl FieldValue {
             FieldValue::OptionalString(_) => FieldType::OptionalString,
             FieldValue::Enum(_) => FieldType::Enum,
             FieldValue::U64(_) => FieldType::U64,
+            FieldValue::OptionalU64(_) => FieldType::OptionalU64,
+            FieldValue::F64(_) => FieldType::F64,
+            FieldValue::OptionalF64(_) => FieldType::OptionalF64,
+            FieldValue::Bool(_) => FieldType::Bool,
         }
     }
 
@@ -56,12 +131,36 @@ impl FieldValue {
             FieldValue::OptionalString(None) => String::new(),
             FieldValue::Enum(s) => s.clone(),
             FieldValue::U64(n) => n.to_string(),
+            FieldValue::OptionalU64(Some(n)) => n.to_string(),
+            FieldValue::F64(n) | FieldValue::OptionalF64(Some(n)) => format_float(*n),
+            FieldValue::OptionalU64(None) | FieldValue::OptionalF64(None) => String::new(),
+            FieldValue::Bool(true) => "on".to_string(),
+            FieldValue::Bool(false) => "off".to_string(),
         }
     }
 
-    pub fn from_string(s: String, field_type: FieldType) -> Self {
+    /// Parses `s` as a value of `field`, which must also fall within its range if it has one.
+    pub fn from_string(s: String, field: &FieldDescriptor) -> Result<Self, FieldParseError> {
+        let value = Self::parse(s, field.field_type)?;
+        if let (Some((min, max)), Some(n)) = (field.range, value.as_number())
+            && !(min..=max).contains(&n)
+        {
+            return Err(FieldParseError::OutOfRange(min, max));
+        }
+        Ok(value)
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match *self {
+            FieldValue::U64(n) | FieldValue::OptionalU64(Some(n)) => Some(n as f64),
+            FieldValue::F64(n) | FieldValue::OptionalF64(Some(n)) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn parse(s: String, field_type: FieldType) -> Result<Self, FieldParseError> {
         let trimmed = s.trim().to_string();
-        match field_type {
+        Ok(match field_type {
             FieldType::String => FieldValue::String(trimmed),
             FieldType::OptionalString => {
                 if trimmed.is_empty() {
@@ -71,11 +170,73 @@ impl FieldValue {
                 }
             }
             FieldType::Enum => FieldValue::Enum(trimmed),
-            FieldType::U64 => FieldValue::U64(trimmed.parse().unwrap_or(0)),
+            FieldType::U64 => {
+                FieldValue::U64(trimmed.parse().map_err(|_| FieldParseError::InvalidInteger)?)
+            }
+            FieldType::OptionalU64 if trimmed.is_empty() => FieldValue::OptionalU64(None),
+            FieldType::OptionalU64 => FieldValue::OptionalU64(Some(
+                trimmed.parse().map_err(|_| FieldParseError::InvalidInteger)?,
+            )),
+            FieldType::F64 => FieldValue::F64(parse_float(&trimmed)?),
+            FieldType::OptionalF64 if trimmed.is_empty() => FieldValue::OptionalF64(None),
+            FieldType::OptionalF64 => FieldValue::OptionalF64(Some(parse_float(&trimmed)?)),
+            FieldType::Bool => match trimmed.to_lowercase().as_str() {
+                "on" | "true" | "yes" => FieldValue::Bool(true),
+                "off" | "false" | "no" => FieldValue::Bool(false),
+                _ => return Err(FieldParseError::InvalidBool),
+            },
+        })
+    }
+}
+
+/// A check on a field's value beyond what its type allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validator {
+    /// An http:// or https:// URL with a host.
+    Url,
+    NoWhitespace,
+    /// A strftime format chrono can use.
+    TimestampFormat,
+}
+
+impl Validator {
+    fn check(self, value: &str) -> Result<(), String> {
+        match self {
+            Validator::Url if !is_valid_url(value) => {
+                Err("must be an http:// or https:// URL".to_string())
+            }
+            Validator::NoWhitespace if value.chars().any(char::is_whitespace) => {
+                Err("must not contain spaces".to_string())
+            }
+            Validator::TimestampFormat
+                if StrftimeItems::new(value).any(|item| item == Item::Error) =>
+            {
+                Err("is not a valid strftime format".to_string())
+            }
+            _ => Ok(()),
         }
     }
 }
 
+pub(crate) fn is_valid_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://")) else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+
+    !host.is_empty() && !host.starts_with(':') && !url.chars().any(char::is_whitespace)
+}
+
+/// Whether `name` is an HTTP header name: letters, digits and a few symbols, nothing else.
+pub(crate) fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+fn parse_float(s: &str) -> Result<f64, FieldParseError> {
+    s.parse().ok().filter(|n: &f64| n.is_finite()).ok_or(FieldParseError::InvalidFloat)
+}
+
 #[derive(Clone, Debug)]
 pub struct FieldDescriptor {
     pub id: String,
@@ -85,7 +246,12 @@ pub struct FieldDescriptor {
     pub field_type: FieldType,
     pub enum_values: Vec<String>,
     pub is_group: bool,
+    /// Whether the field holds a credential, which is masked wherever it is shown.
+    pub is_secret: bool,
     pub parent_id: Option<String>,
+    /// The lowest and highest value a number field accepts, inclusive.
+    pub range: Option<(f64, f64)>,
+    pub validator: Option<Validator>,
 }
 
 impl FieldDescriptor {
@@ -96,15 +262,23 @@ impl FieldDescriptor {
         section: impl Into<String>,
         field_type: FieldType,
     ) -> Self {
+        let enum_values = match field_type {
+            FieldType::Bool => vec!["on".to_string(), "off".to_string()],
+            _ => Vec::new(),
+        };
+
         Self {
             id: id.into(),
             label: label.into(),
             hint: hint.into(),
             section: section.into(),
             field_type,
-            enum_values: Vec::new(),
+            enum_values,
             is_group: false,
+            is_secret: false,
             parent_id: None,
+            range: None,
+            validator: None,
         }
     }
 
@@ -113,16 +287,63 @@ impl FieldDescriptor {
         self
     }
 
+    /// Makes this a header that the fields with it as their parent are shown under. It has no
+    /// value of its own.
     pub fn as_group(mut self) -> Self {
         self.is_group = true;
         self
     }
 
+    pub fn as_secret(mut self) -> Self {
+        self.is_secret = true;
+        self
+    }
+
     pub fn with_parent(mut self, parent_id: impl Into<String>) -> Self {
         self.parent_id = Some(parent_id.into());
         self
     }
 
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Runs the field's validator on `value`. Empty values pass, as do ones that refer to an
+    /// environment variable, which are checked once [`ConfigSchema::load`] has expanded them.
+    pub fn validate(&self, value: &FieldValue) -> Result<(), String> {
+        let text = match value {
+            FieldValue::String(text) | FieldValue::OptionalString(Some(text)) => text,
+            _ => return Ok(()),
+        };
+        match self.validator {
+            Some(validator) if !text.is_empty() && !text.contains('$') => validator.check(text),
+            _ => Ok(()),
+        }
+    }
+
+    /// `value` as text, masked with [`mask_secret`] if the field is secret and not `reveal`ed.
+    pub fn display_string(&self, value: &FieldValue, reveal: bool) -> String {
+        let text = value.as_display_string();
+        if self.is_secret && !reveal { mask_secret(&text) } else { text }
+    }
+
+    /// Whether the field holds a path, whose leading `~/` is expanded on load.
+    pub fn is_path(&self) -> bool {
+        self.id.ends_with("_directory") || self.id.ends_with("_file")
+    }
+
+    /// Whether environment variables in the field are expanded on load: only in keys, URLs and
+    /// paths, as a `$` in free text such as the system prompt is more likely meant literally.
+    pub fn expands_variables(&self) -> bool {
+        self.is_secret || self.is_path() || self.validator == Some(Validator::Url)
+    }
+
     pub fn get_value<C: ConfigSchema>(&self, config: &C) -> ConfigResult<FieldValue> {
         C::get_field_value_by_id(config, &self.id)
     }
@@ -136,10 +357,20 @@ impl FieldDescriptor {
     }
 }
 
-pub trait ConfigSchema: Sized + Serialize + for<'de> Deserialize<'de> + Default {
+pub trait ConfigSchema: Sized + Clone + Serialize + for<'de> Deserialize<'de> + Default {
     fn fields() -> Vec<FieldDescriptor>;
     fn get_field_value_by_id(config: &Self, id: &str) -> ConfigResult<FieldValue>;
     fn set_field_value_by_id(config: &mut Self, id: &str, value: FieldValue) -> ConfigResult<()>;
+    fn expansions(&self) -> &Expansions;
+    fn expansions_mut(&mut self) -> &mut Expansions;
+    /// The file the config was loaded from, if it was.
+    fn loaded_path(&self) -> Option<&Path>;
+    fn loaded_path_mut(&mut self) -> &mut Option<PathBuf>;
+
+    /// The migrations to run on a config file before it is deserialized, oldest first.
+    fn migrations() -> &'static [&'static dyn Migration] {
+        &[]
+    }
 
     fn sections() -> Vec<String> {
         let mut sections = Vec::new();
@@ -163,88 +394,527 @@ pub trait ConfigSchema: Sized + Serialize + for<'de> Deserialize<'de> + Default
     }
 
     fn set_field(&mut self, field_id: &str, value: FieldValue) -> ConfigResult<()> {
-        Self::fields()
-            .iter()
+        let field = Self::fields()
+            .into_iter()
             .find(|f| f.id == field_id)
-            .ok_or_else(|| ConfigError::FieldNotFound(field_id.to_string()))?
-            .set_value(self, value)
+            .ok_or_else(|| ConfigError::FieldNotFound(field_id.to_string()))?;
+        field
+            .validate(&value)
+            .map_err(|message| ConfigError::InvalidValue(field.id.clone(), message))?;
+        field.set_value(self, value)
+    }
+
+    /// The value `field` has in a config with nothing customized.
+    fn default_value(field: &FieldDescriptor) -> ConfigResult<FieldValue> {
+        field.get_value(&Self::default())
+    }
+
+    fn reset_field(&mut self, field_id: &str) -> ConfigResult<()> {
+        let field = Self::fields()
+            .into_iter()
+            .find(|f| f.id == field_id && !f.is_group)
+            .ok_or_else(|| ConfigError::FieldNotFound(field_id.to_string()))?;
+        field.set_value(self, Self::default_value(&field)?)
+    }
+
+    /// Resets every field. Unknown keys and the path it was loaded from are kept.
+    fn reset_all(&mut self) -> ConfigResult<()> {
+        let defaults = Self::default();
+        for field in Self::fields().iter().filter(|field| !field.is_group) {
+            field.set_value(self, field.get_value(&defaults)?)?;
+        }
+        Ok(())
+    }
+
+    /// Runs every field's validator, returning the first failure.
+    fn validate_fields(&self) -> ConfigResult<()> {
+        for field in Self::fields().iter().filter(|field| field.validator.is_some()) {
+            field
+                .validate(&field.get_value(self)?)
+                .map_err(|message| ConfigError::InvalidValue(field.id.clone(), message))?;
+        }
+        Ok(())
     }
 
     fn load() -> ConfigResult<Self> {
         Self::load_from(None)
     }
 
+    /// Loads the config at `custom_path`, or the default one, creating it if it doesn't exist.
+    /// Fields that can't be parsed are reset to their defaults, and the whole file only if it
+    /// can't be parsed at all. The config remembers the path for [`Self::save`].
     fn load_from(custom_path: Option<PathBuf>) -> ConfigResult<Self> {
-        let path = custom_path.clone().unwrap_or(Self::config_path()?);
+        let path = match custom_path {
+            Some(path) => path,
+            None => Self::config_path()?,
+        };
+        let mut config = Self::load_or_create(&path)?;
+        *config.loaded_path_mut() = Some(path);
+        Ok(config)
+    }
 
+    fn load_or_create(path: &Path) -> ConfigResult<Self> {
         if !path.exists() {
             let config = Self::default();
-            config.save_to(Some(path.clone()))?;
+            config.save_to(Some(path.to_path_buf()))?;
             eprintln!("Created default config at: {}", path.display());
             eprintln!("Please edit it to add your API keys.");
+            tracing::info!(path = %path.display(), "created default config");
             return Ok(config);
         }
 
-        let content = fs::read_to_string(&path)?;
+        let content = fs::read_to_string(path)?;
+        let mut value = match ConfigFormat::of(path).parse(&content) {
+            Ok(value) if value.is_object() => value,
+            result => {
+                let error = match result {
+                    Err(e) => e.to_string(),
+                    Ok(_) => "expected an object of settings".to_string(),
+                };
+                tracing::warn!(path = %path.display(), %error, "config could not be parsed");
+                eprintln!("Warning: Config file is corrupted.");
+                eprintln!("Error: {}", error);
+                Self::back_up(path)?;
 
-        match serde_json::from_str::<Self>(&content) {
-            Ok(config) => Ok(config),
+                let config = Self::default();
+                config.save_to(Some(path.to_path_buf()))?;
+                eprintln!("Created new default config at: {}", path.display());
+                return Ok(config);
+            }
+        };
+
+        let migrated = run_migrations(&mut value, Self::migrations())?;
+        if migrated {
+            eprintln!("Updated the config file to the current format.");
+            Self::back_up(path)?;
+        }
+
+        let mut config = match serde_json::from_value::<Self>(value.clone()) {
+            Ok(config) => {
+                tracing::debug!(path = %path.display(), "loaded config");
+                if migrated {
+                    config.save_to(Some(path.to_path_buf()))?;
+                }
+                config
+            }
             Err(e) => {
-                eprintln!("Warning: Config file is corrupted or outdated.");
-                eprintln!("Error: {}", e);
+                let (config, dropped) = Self::recover(&value);
+                tracing::warn!(
+                    path = %path.display(),
+                    error = %e,
+                    dropped = %dropped.join(", "),
+                    "reset unreadable config fields"
+                );
+                eprintln!("Warning: Some config fields couldn't be read: {}", e);
+                eprintln!("Reset to their defaults: {}", dropped.join(", "));
+                if !migrated {
+                    Self::back_up(path)?;
+                }
+                config.save_to(Some(path.to_path_buf()))?;
+                config
+            }
+        };
 
-                let backup_path = Self::backup_path()?;
-                fs::copy(&path, &backup_path)?;
-                eprintln!("Backed up old config to: {}", backup_path.display());
+        config.expand_values()?;
+        Ok(config)
+    }
 
-                let config = Self::default();
-                config.save_to(Some(path.clone()))?;
-                eprintln!("Created new default config at: {}", path.display());
+    /// Builds a config from the fields of `value` that can be read, leaving the others at their
+    /// defaults. Returns it along with the dotted names of the fields that were dropped.
+    fn recover(value: &Value) -> (Self, Vec<String>) {
+        let mut merged = serde_json::to_value(Self::default()).unwrap_or(Value::Null);
+        let mut dropped = Vec::new();
+        if let (Some(fields), true) = (value.as_object(), merged.is_object()) {
+            merge_readable::<Self>(&mut merged, "", fields, &mut dropped);
+        }
+
+        (serde_json::from_value(merged).unwrap_or_default(), dropped)
+    }
 
-                Ok(config)
+    fn back_up(path: &Path) -> ConfigResult<()> {
+        let backup_path = Self::backup_path(path)?;
+        fs::copy(path, &backup_path)?;
+        restrict_to_owner(&backup_path)?;
+        eprintln!("Backed up old config to: {}", backup_path.display());
+        Ok(())
+    }
+
+    /// Reads the config at `path` without creating or replacing it when it is missing or broken.
+    fn read_from(path: &Path) -> ConfigResult<Self> {
+        let mut value = ConfigFormat::of(path).parse(&fs::read_to_string(path)?)?;
+        run_migrations(&mut value, Self::migrations())?;
+        let mut config: Self = serde_json::from_value(value)?;
+        config.expand_values()?;
+        *config.loaded_path_mut() = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Expands environment variables in the fields that
+    /// [expand them](FieldDescriptor::expands_variables), and a leading `~/` in path fields,
+    /// remembering what was written so that saving puts it back. A variable that isn't set
+    /// expands to nothing, which is an error only for fields that can't be empty.
+    fn expand_values(&mut self) -> ConfigResult<()> {
+        let mut expansions = Expansions::default();
+
+        for field in
+            Self::fields().into_iter().filter(|field| !field.is_group && field.expands_variables())
+        {
+            let raw = match self.get_field(&field.id)? {
+                FieldValue::String(raw) | FieldValue::OptionalString(Some(raw)) => raw,
+                _ => continue,
+            };
+            let (expanded, unset) = expand(&raw, field.is_path());
+            if expanded == raw {
+                continue;
             }
+            if field.field_type == FieldType::String
+                && let Some(variable) = unset.first()
+            {
+                return Err(ConfigError::UnsetVariable(variable.clone(), field.id));
+            }
+
+            self.set_field(&field.id, string_value(field.field_type, expanded.clone()))?;
+            expansions.fields.insert(field.id, Expansion { raw, expanded, unset });
         }
+
+        *self.expansions_mut() = expansions;
+        Ok(())
     }
 
+    /// Saves to the file the config was loaded from, or the default one if it wasn't.
     fn save(&self) -> ConfigResult<()> {
-        self.save_to(None)
+        self.save_to(self.loaded_path().map(Path::to_path_buf))
     }
 
     fn save_to(&self, custom_path: Option<PathBuf>) -> ConfigResult<()> {
-        let path = custom_path.unwrap_or(Self::config_path()?);
+        let path = match custom_path {
+            Some(path) => path,
+            None => Self::default_config_path()?,
+        };
 
-        if let Some(parent) = path.parent() {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
             fs::create_dir_all(parent)?;
+            restrict_to_owner(parent)?;
+        }
+
+        // Fields still holding what they expanded to are written as they were in the file, so
+        // that a `${VAR}` isn't replaced by the secret it stands for.
+        let mut written = self.clone();
+        for (id, expansion) in &self.expansions().fields {
+            if self.get_field(id)?.as_display_string() == expansion.expanded {
+                let field_type = self.get_field(id)?.field_type();
+                written.set_field(id, string_value(field_type, expansion.raw.clone()))?;
+            }
         }
 
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&path, content)?;
+        let content = ConfigFormat::of(&path).serialize(&written)?;
+        write_private_file(&path, content.as_bytes())?;
+        tracing::info!(path = %path.display(), "saved config");
 
         Ok(())
     }
 
+    /// Where the config lives: `$ONYX_CONFIG_DIR` if set, otherwise `onyx` in
+    /// `$XDG_CONFIG_HOME` or the platform's config directory (`~/.config` on Linux).
     fn config_dir() -> ConfigResult<PathBuf> {
-        let home = dirs::home_dir().ok_or(ConfigError::NoHomeDir)?;
-        Ok(home.join(".onyx"))
+        if let Some(dir) = env_dir(CONFIG_DIR_VAR) {
+            return Ok(dir);
+        }
+        let base = env_dir("XDG_CONFIG_HOME").or_else(dirs::config_dir);
+        Ok(base.ok_or(ConfigError::NoHomeDir)?.join("onyx"))
     }
 
+    /// Where sessions, memories, input history and logs live: `$ONYX_CONFIG_DIR` if set,
+    /// otherwise `onyx` in `$XDG_DATA_HOME` or the platform's data directory (`~/.local/share`
+    /// on Linux).
+    fn data_dir() -> ConfigResult<PathBuf> {
+        if let Some(dir) = env_dir(CONFIG_DIR_VAR) {
+            return Ok(dir);
+        }
+        let base = env_dir("XDG_DATA_HOME").or_else(dirs::data_dir);
+        Ok(base.ok_or(ConfigError::NoHomeDir)?.join("onyx"))
+    }
+
+    /// Where everything was kept before Onyx followed the platform's conventions.
+    fn legacy_dir() -> ConfigResult<PathBuf> {
+        Ok(dirs::home_dir().ok_or(ConfigError::NoHomeDir)?.join(".onyx"))
+    }
+
+    /// Copies the config and data from [`Self::legacy_dir`] to their current places, the first
+    /// time Onyx runs without a config there. The old directory is left for the user to delete.
+    fn adopt_legacy_dir() -> ConfigResult<()> {
+        if env::var_os(CONFIG_DIR_VAR).is_some() {
+            return Ok(());
+        }
+        let (legacy, config_dir, data_dir) =
+            (Self::legacy_dir()?, Self::config_dir()?, Self::data_dir()?);
+        let has_config = |dir: &Path| CONFIG_FILES.iter().any(|name| dir.join(name).is_file());
+        if legacy == config_dir || !has_config(&legacy) || has_config(&config_dir) {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&config_dir)?;
+        restrict_to_owner(&config_dir)?;
+        for name in CONFIG_FILES.iter().filter(|name| legacy.join(name).is_file()) {
+            fs::copy(legacy.join(name), config_dir.join(name))?;
+            restrict_to_owner(&config_dir.join(name))?;
+        }
+        for name in ["sessions", "memories.json", "input_history"] {
+            copy_all(&legacy.join(name), &data_dir.join(name))?;
+        }
+
+        eprintln!("Copied the config from {} to {}", legacy.display(), config_dir.display());
+        eprintln!("and saved conversations to {}.", data_dir.display());
+        eprintln!("Onyx no longer reads {}, so it can be deleted.", legacy.display());
+        tracing::info!(
+            from = %legacy.display(),
+            config = %config_dir.display(),
+            data = %data_dir.display(),
+            "adopted legacy config directory"
+        );
+        Ok(())
+    }
+
+    /// [`Self::default_config_path`], after bringing over a config left in
+    /// [`Self::legacy_dir`].
     fn config_path() -> ConfigResult<PathBuf> {
-        Ok(Self::config_dir()?.join("config.json"))
+        Self::adopt_legacy_dir()?;
+        Self::default_config_path()
+    }
+
+    /// `config.toml` in [`Self::config_dir`] if there is one, otherwise `config.json`.
+    fn default_config_path() -> ConfigResult<PathBuf> {
+        let dir = Self::config_dir()?;
+        let toml = dir.join(CONFIG_FILES[0]);
+        Ok(if toml.exists() { toml } else { dir.join(CONFIG_FILES[1]) })
     }
 
-    fn backup_path() -> ConfigResult<PathBuf> {
+    /// Where to back up the config at `path` before replacing it: beside it, as
+    /// `config.backup.<time>.json` so that it keeps its extension.
+    fn backup_path(path: &Path) -> ConfigResult<PathBuf> {
         use std::time::{SystemTime, UNIX_EPOCH};
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        Ok(Self::config_dir()?.join(format!("config.json.backup.{}", timestamp)))
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(extension) => {
+                format!("{}.backup.{}.{}", stem, timestamp, extension.to_string_lossy())
+            }
+            None => format!("{}.backup.{}", stem, timestamp),
+        };
+        Ok(path.with_file_name(name))
     }
 
     fn config_path_display() -> String {
-        Self::config_path()
+        Self::default_config_path()
             .map(|p| p.display().to_string())
-            .unwrap_or_else(|_| "~/.onyx/config.json".to_string())
+            .unwrap_or_else(|_| CONFIG_FILES[1].to_string())
     }
 }
 
+/// Overrides both [`ConfigSchema::config_dir`] and [`ConfigSchema::data_dir`], mostly for tests.
+pub const CONFIG_DIR_VAR: &str = "ONYX_CONFIG_DIR";
+
+/// The names a config file can have in the config directory, most preferred first.
+const CONFIG_FILES: [&str; 2] = ["config.toml", "config.json"];
+
+/// The directory named by environment variable `name`, if it is set to an absolute path.
+/// Relative ones are ignored, as the XDG spec asks.
+fn env_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name).map(PathBuf::from).filter(|dir| dir.is_absolute())
+}
+
+/// Copies the file or directory tree at `from` to `to`, if there is anything at `from`.
+fn copy_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if from.is_file() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// The formats a config file can be written in, told apart by the file's extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// TOML for a `.toml` file, JSON for anything else.
+    pub fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn parse(self, content: &str) -> ConfigResult<Value> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+        })
+    }
+
+    pub fn serialize<T: Serialize>(self, config: &T) -> ConfigResult<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(config)?,
+            Self::Toml => toml::to_string_pretty(config)?,
+        })
+    }
+}
+
+/// Copies `fields` into the object at `pointer` in `merged` one at a time, keeping each only if
+/// `merged` still deserializes as a `T`. An object that can't be kept whole is merged field by
+/// field. The names of the fields left out are added to `dropped`.
+fn merge_readable<T: DeserializeOwned>(
+    merged: &mut Value,
+    pointer: &str,
+    fields: &Map<String, Value>,
+    dropped: &mut Vec<String>,
+) {
+    for (key, value) in fields {
+        let Some(target) = merged.pointer_mut(pointer).and_then(Value::as_object_mut) else {
+            return;
+        };
+        let previous = target.insert(key.clone(), value.clone());
+        if serde_json::from_value::<T>(merged.clone()).is_ok() {
+            continue;
+        }
+
+        let Some(target) = merged.pointer_mut(pointer).and_then(Value::as_object_mut) else {
+            return;
+        };
+        match (previous, value) {
+            (Some(previous @ Value::Object(_)), Value::Object(nested)) => {
+                target.insert(key.clone(), previous);
+                let child = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                merge_readable::<T>(merged, &child, nested, dropped);
+            }
+            (Some(previous), _) => {
+                target.insert(key.clone(), previous);
+                dropped.push(dotted_name(pointer, key));
+            }
+            (None, _) => {
+                target.remove(key);
+                dropped.push(dotted_name(pointer, key));
+            }
+        }
+    }
+}
+
+/// `openai.temperature` for the field `temperature` of the object at `/openai`.
+fn dotted_name(pointer: &str, key: &str) -> String {
+    let parents = pointer.split('/').skip(1).map(|part| part.replace("~1", "/").replace("~0", "~"));
+    parents.chain([key.to_string()]).collect::<Vec<_>>().join(".")
+}
+
+/// The string fields whose value in the config file was changed by expansion on load.
+#[derive(Debug, Clone, Default)]
+pub struct Expansions {
+    fields: HashMap<String, Expansion>,
+}
+
+#[derive(Debug, Clone)]
+struct Expansion {
+    raw: String,
+    expanded: String,
+    /// Variables it referred to that weren't set.
+    unset: Vec<String>,
+}
+
+impl Expansions {
+    /// The first unset variable that field `id` referred to, if any.
+    pub fn unset_variable(&self, id: &str) -> Option<&str> {
+        self.fields.get(id)?.unset.first().map(String::as_str)
+    }
+}
+
+fn string_value(field_type: FieldType, value: String) -> FieldValue {
+    match field_type {
+        FieldType::OptionalString if value.is_empty() => FieldValue::OptionalString(None),
+        FieldType::OptionalString => FieldValue::OptionalString(Some(value)),
+        _ => FieldValue::String(value),
+    }
+}
+
+/// Makes every field after a group, up to the end of its section, a child of that group.
+pub fn nest_under_groups(fields: &mut [FieldDescriptor]) {
+    let mut group: Option<(String, String)> = None;
+    for field in fields.iter_mut() {
+        if field.is_group {
+            group = Some((field.section.clone(), field.id.clone()));
+        } else if let Some((section, id)) = &group
+            && field.section == *section
+        {
+            field.parent_id = Some(id.clone());
+        }
+    }
+}
+
+/// Expands `${VAR}` and `$VAR` from the environment, with `$$` for a literal `$`, and a leading
+/// `~/` to the home directory if `is_path`. Returns the expansion and the variables that
+/// weren't set, which expand to nothing.
+fn expand(value: &str, is_path: bool) -> (String, Vec<String>) {
+    let mut expanded = String::new();
+    let mut unset = Vec::new();
+    let mut rest = value;
+
+    if is_path
+        && let Some(after_tilde) = rest.strip_prefix('~')
+        && (after_tilde.is_empty() || after_tilde.starts_with(['/', '\\']))
+        && let Some(home) = dirs::home_dir()
+    {
+        expanded.push_str(&home.to_string_lossy());
+        rest = after_tilde;
+    }
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        let (name, remaining) = if let Some(escaped) = after.strip_prefix('$') {
+            (None, escaped)
+        } else if let Some(braced) = after.strip_prefix('{')
+            && let Some(end) = braced.find('}')
+            && end > 0
+        {
+            (Some(&braced[..end]), &braced[end + 1..])
+        } else {
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if len == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+                (None, after)
+            } else {
+                (Some(&after[..len]), &after[len..])
+            }
+        };
+
+        match name {
+            Some(name) => match env::var(name) {
+                Ok(value) => expanded.push_str(&value),
+                Err(_) => unset.push(name.to_string()),
+            },
+            None => expanded.push('$'),
+        }
+        rest = remaining;
+    }
+
+    expanded.push_str(rest);
+    (expanded, unset)
+}
+
 #[macro_export]
 macro_rules! config_defaults {
     ($($field:ident => $value:expr),* $(,)?) => {
@@ -264,7 +934,7 @@ macro_rules! config_defaults {
 macro_rules! config_fields {
     {
         $(
-            [$section:expr] => {
+            [$section:expr] $(group $group:ident)? => {
                 $(
                     $id:ident: $ty:ident($($attrs:tt)*)
                 ),* $(,)?
@@ -273,13 +943,25 @@ macro_rules! config_fields {
     } => {
         impl $crate::config::ConfigSchema for Config {
             fn fields() -> Vec<$crate::config::FieldDescriptor> {
-                vec![
+                let mut fields = vec![
                     $(
+                        $(
+                            $crate::config::FieldDescriptor::new(
+                                stringify!($group),
+                                $section,
+                                "",
+                                $section,
+                                $crate::config::FieldType::String,
+                            )
+                            .as_group(),
+                        )?
                         $(
                             config_fields!(@field $id, $ty, $section, $($attrs)*),
                         )*
                     )*
-                ]
+                ];
+                $crate::config::nest_under_groups(&mut fields);
+                fields
             }
 
             fn get_field_value_by_id(c: &Config, id: &str) -> $crate::config::ConfigResult<$crate::config::FieldValue> {
@@ -304,9 +986,39 @@ macro_rules! config_fields {
                 }
                 Ok(())
             }
+
+            fn expansions(&self) -> &$crate::config::Expansions {
+                &self.expansions
+            }
+
+            fn expansions_mut(&mut self) -> &mut $crate::config::Expansions {
+                &mut self.expansions
+            }
+
+            fn loaded_path(&self) -> Option<&std::path::Path> {
+                self.config_path.as_deref()
+            }
+
+            fn loaded_path_mut(&mut self) -> &mut Option<std::path::PathBuf> {
+                &mut self.config_path
+            }
+
+            fn migrations() -> &'static [&'static dyn $crate::migration::Migration] {
+                $crate::migration::MIGRATIONS
+            }
         }
     };
 
+    (@field $id:ident, $ty:ident, $section:expr, $label:expr, $hint:expr, $($path:tt).+, secret) => {
+        config_fields!(@field $id, $ty, $section, $label, $hint, $($path).+).as_secret()
+    };
+    (@field $id:ident, $ty:ident, $section:expr, $label:expr, $hint:expr, $($path:tt).+, validate($validator:ident)) => {
+        config_fields!(@field $id, $ty, $section, $label, $hint, $($path).+)
+            .with_validator($crate::config::Validator::$validator)
+    };
+    (@field $id:ident, $ty:ident, $section:expr, $label:expr, $hint:expr, $($path:tt).+, $min:literal ..= $max:literal) => {
+        config_fields!(@field $id, $ty, $section, $label, $hint, $($path).+).with_range($min, $max)
+    };
     (@field $id:ident, $ty:ident, $section:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
         {
             #[allow(unused_mut)]
@@ -328,6 +1040,18 @@ macro_rules! config_fields {
     (@get U64, $c:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
         $crate::config::FieldValue::U64($c.$($path).+)
     };
+    (@get OptionalU64, $c:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        $crate::config::FieldValue::OptionalU64($c.$($path).+)
+    };
+    (@get F64, $c:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        $crate::config::FieldValue::F64($c.$($path).+)
+    };
+    (@get OptionalF64, $c:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        $crate::config::FieldValue::OptionalF64($c.$($path).+)
+    };
+    (@get Bool, $c:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        $crate::config::FieldValue::Bool($c.$($path).+)
+    };
 
     (@set String, $c:expr, $v:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
         if let $crate::config::FieldValue::String(val) = $v {
@@ -349,4 +1073,229 @@ macro_rules! config_fields {
             $c.$($path).+ = val;
         }
     };
+    (@set OptionalU64, $c:expr, $v:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        if let $crate::config::FieldValue::OptionalU64(val) = $v {
+            $c.$($path).+ = val;
+        }
+    };
+    (@set F64, $c:expr, $v:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        if let $crate::config::FieldValue::F64(val) = $v {
+            $c.$($path).+ = val;
+        }
+    };
+    (@set OptionalF64, $c:expr, $v:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        if let $crate::config::FieldValue::OptionalF64(val) = $v {
+            $c.$($path).+ = val;
+        }
+    };
+    (@set Bool, $c:expr, $v:expr, $label:expr, $hint:expr, $($path:tt).+ $(, $enum_vals:expr)?) => {
+        if let $crate::config::FieldValue::Bool(val) = $v {
+            $c.$($path).+ = val;
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::test_dir;
+    use crate::schema::{Config, Provider};
+    use crate::types::ModelPrice;
+
+    const UNSET: &str = "ONYX_TEST_UNSET_VARIABLE";
+
+    /// Points [`CONFIG_DIR_VAR`] at an empty directory of its own, so that a test that slips
+    /// through to the default config can't touch the real one.
+    fn default_dir() -> PathBuf {
+        static DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = test_dir("default");
+            // SAFETY: set once, before any test reads it; the other variables the tests read
+            // are only ever read.
+            unsafe { env::set_var(CONFIG_DIR_VAR, &dir) };
+            dir
+        })
+        .clone()
+    }
+
+    fn home() -> String {
+        env::var("HOME").unwrap()
+    }
+
+    #[test]
+    fn expands_both_forms() {
+        let (expanded, unset) = expand("a ${HOME} b $HOME/c", false);
+        assert_eq!(expanded, format!("a {} b {}/c", home(), home()));
+        assert!(unset.is_empty());
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal_dollar() {
+        assert_eq!(
+            expand("costs $$5 and $${HOME}", false),
+            ("costs $5 and ${HOME}".into(), vec![])
+        );
+    }
+
+    #[test]
+    fn a_dollar_without_a_name_stays() {
+        assert_eq!(expand("$5, ${} and $", false), ("$5, ${} and $".into(), vec![]));
+    }
+
+    #[test]
+    fn unset_variables_expand_to_nothing_and_are_reported() {
+        let (expanded, unset) = expand(&format!("key-${{{}}}-${}", UNSET, UNSET), false);
+        assert_eq!(expanded, "key--");
+        assert_eq!(unset, [UNSET, UNSET]);
+    }
+
+    #[test]
+    fn tilde_only_in_paths_and_only_leading() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().into_owned();
+        assert_eq!(expand("~/models", true).0, format!("{}/models", home));
+        assert_eq!(expand("~", true).0, home);
+        assert_eq!(expand("~/models", false).0, "~/models");
+        assert_eq!(expand("~user/models", true).0, "~user/models");
+        assert_eq!(expand("a/~/b", true).0, "a/~/b");
+    }
+
+    #[test]
+    fn only_keys_urls_and_paths_expand_on_load() {
+        let dir = test_dir("expand-load");
+        let path = dir.join("config.json");
+        let mut config = Config {
+            system_prompt: Some(format!("quote prices in $USD and ${}", UNSET)),
+            theme_file: Some("~/theme.json".to_string()),
+            ..Config::default()
+        };
+        config.openai.api_key = Some("${HOME}".to_string());
+        config.anthropic.api_key = Some(format!("${}", UNSET));
+        config.openai.url = Some("http://localhost$HOME".to_string());
+        config.save_to(Some(path.clone())).unwrap();
+
+        let loaded = Config::load_from(Some(path.clone())).unwrap();
+        assert_eq!(loaded.system_prompt, config.system_prompt);
+        assert_eq!(loaded.openai.api_key, Some(home()));
+        assert_eq!(loaded.expansions().unset_variable("anthropic_api_key"), Some(UNSET));
+        assert_eq!(loaded.openai.url, Some(format!("http://localhost{}", home())));
+        let home_dir = dirs::home_dir().unwrap();
+        assert_eq!(loaded.theme_file, Some(home_dir.join("theme.json").to_string_lossy().into()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saving_writes_back_what_was_expanded() {
+        let dir = test_dir("expand-save");
+        let path = dir.join("config.json");
+        let mut config = Config::default();
+        config.openai.api_key = Some("${HOME}".to_string());
+        config.anthropic.api_key = Some("$HOME".to_string());
+        config.save_to(Some(path.clone())).unwrap();
+
+        let mut loaded = Config::load_from(Some(path.clone())).unwrap();
+        // A field changed since loading is saved as it now is.
+        loaded.anthropic.api_key = Some("sk-new".to_string());
+        loaded.save().unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"${HOME}\""), "{}", saved);
+        assert!(saved.contains("\"sk-new\""), "{}", saved);
+        assert!(!saved.contains(&format!("\"{}\"", home())), "{}", saved);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_custom_path_is_loaded_and_saved_without_the_default_one() {
+        let default_dir = default_dir();
+        let dir = test_dir("custom-path");
+        let path = dir.join("custom.json");
+
+        let mut config = Config::load_from(Some(path.clone())).unwrap();
+        assert!(path.is_file());
+        assert_eq!(config.loaded_path(), Some(path.as_path()));
+
+        config.system_prompt = Some("from the custom file".to_string());
+        config.save().unwrap();
+        let reloaded = Config::load_from(Some(path.clone())).unwrap();
+        assert_eq!(reloaded.system_prompt, config.system_prompt);
+
+        assert_eq!(Config::default_config_path().unwrap(), default_dir.join(CONFIG_FILES[1]));
+        assert_eq!(fs::read_dir(&default_dir).unwrap().count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_config_round_trips_through_toml_and_json() {
+        let dir = test_dir("formats");
+        let mut config = Config {
+            active_provider: Provider::Anthropic,
+            system_prompt: Some("Answer in \"plain\" words.\nNo lists.".to_string()),
+            context_token_limit: 64000,
+            tools_enabled: true,
+            custom_commands: [("review".to_string(), "Review this: {args}".to_string())].into(),
+            pricing: [("local".to_string(), ModelPrice { input: 0.25, output: 1.5 })].into(),
+            ..Config::default()
+        };
+        config.anthropic.api_key = Some("sk-ant".to_string());
+        config.anthropic.temperature = Some(0.7);
+        config.openai.extra_headers = Some([("X-Gateway".to_string(), "on".to_string())].into());
+
+        let loaded: Vec<Value> = ["config.toml", "config.json"]
+            .into_iter()
+            .map(|name| {
+                let path = dir.join(name);
+                config.save_to(Some(path.clone())).unwrap();
+                serde_json::to_value(Config::load_from(Some(path)).unwrap()).unwrap()
+            })
+            .collect();
+
+        assert_eq!(loaded[0], serde_json::to_value(&config).unwrap());
+        assert_eq!(loaded[0], loaded[1]);
+        assert!(fs::read_to_string(dir.join("config.toml")).unwrap().contains("[anthropic]"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_broken_toml_config_is_backed_up_as_toml() {
+        let dir = test_dir("broken-toml");
+        let path = dir.join("config.toml");
+        fs::write(&path, "theme = [unclosed").unwrap();
+
+        let config = Config::load_from(Some(path.clone())).unwrap();
+        assert_eq!(config.theme, Config::default().theme);
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names.len(), 2, "{:?}", names);
+        assert!(names.iter().all(|name| name.ends_with(".toml")), "{:?}", names);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_thinking_budget_must_be_one_anthropic_accepts() {
+        let mut config = Config { active_provider: Provider::Anthropic, ..Config::default() };
+        config.anthropic.api_key = Some("sk-ant-key".to_string());
+
+        for (budget, max_tokens, valid) in [
+            (None, Some(512), true),
+            (Some(1024), None, true),
+            (Some(1024), Some(4096), true),
+            (Some(1023), None, false),
+            (Some(0), Some(4096), false),
+            (Some(4096), Some(4096), false),
+            (Some(8192), Some(4096), false),
+        ] {
+            config.anthropic.thinking_budget_tokens = budget;
+            config.anthropic.max_tokens = max_tokens;
+            match config.validate() {
+                Ok(()) => assert!(valid, "{:?} with max {:?} passed", budget, max_tokens),
+                Err(ConfigError::InvalidValue(field, _)) => {
+                    assert!(!valid, "{:?} with max {:?} failed", budget, max_tokens);
+                    assert_eq!(field, "anthropic_thinking_budget_tokens");
+                }
+                Err(error) => panic!("unexpected error: {}", error),
+            }
+        }
+    }
 }
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::fs::format_size;
+use crate::markup::{Block, split_blocks};
+use crate::schema::Config;
+use crate::types::{Message, MessageKind, Role};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+    Html,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "md"),
+            Self::Json => write!(f, "json"),
+            Self::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "html" | "htm" => Ok(Self::Html),
+            _ => Err(format!("Unknown export format '{}'. Use md, json or html", s)),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Guesses the format from a file name such as `chat.html`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        path.rsplit_once('.').and_then(|(_, extension)| extension.parse().ok())
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Renders the conversation in `format`. Notices are UI feedback rather than conversation, so
+/// they are left out.
+pub fn render(
+    format: ExportFormat,
+    messages: &[Message],
+    title: Option<&str>,
+    config: &Config,
+) -> io::Result<String> {
+    let messages: Vec<&Message> = messages
+        .iter()
+        .filter(|msg| !matches!(msg.kind, MessageKind::Notice | MessageKind::Error))
+        .collect();
+    let title = title.unwrap_or("Onyx Conversation");
+
+    match format {
+        ExportFormat::Markdown => Ok(to_markdown(&messages, title, config)),
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&messages)?),
+        ExportFormat::Html => Ok(to_html(&messages, title, config)),
+    }
+}
+
+fn speaker<'a>(message: &Message, config: &'a Config) -> &'a str {
+    match (message.kind, &message.role) {
+        (MessageKind::Summary, _) => "Summary",
+        (_, Role::User) => config.user_name(),
+        (_, Role::Assistant) => config.assistant_name(),
+        (_, Role::System) => "System",
+    }
+}
+
+fn to_markdown(messages: &[&Message], title: &str, config: &Config) -> String {
+    let mut out = format!("# {}\n", title);
+
+    for message in messages {
+        out.push_str(&format!(
+            "\n### {} — {}\n\n",
+            speaker(message, config),
+            config.format_timestamp(message.timestamp)
+        ));
+
+        for attachment in &message.attachments {
+            out.push_str(&format!(
+                "> 📎 `{}` ({})\n\n",
+                attachment.path,
+                format_size(attachment.size())
+            ));
+        }
+        if let Some(thinking) = message.thinking.as_deref().filter(|t| !t.trim().is_empty()) {
+            out.push_str(&format!(
+                "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n",
+                thinking.trim_end()
+            ));
+        }
+        for call in &message.tool_calls {
+            out.push_str(&format!("- 🔧 `{}({})`", call.name, call.arguments));
+            if let Some(result) = &call.result {
+                out.push_str(&format!(" → {}", result));
+            }
+            out.push('\n');
+        }
+        if !message.tool_calls.is_empty() {
+            out.push('\n');
+        }
+
+        out.push_str(message.content.trim_end());
+        out.push('\n');
+        if let Some(stats) = &message.stats {
+            out.push_str(&format!("\n*{}*\n", stats));
+        }
+    }
+
+    out
+}
+
+fn to_html(messages: &[&Message], title: &str, config: &Config) -> String {
+    let mut body = String::new();
+
+    for message in messages {
+        let class = match (message.kind, &message.role) {
+            (MessageKind::Summary, _) => "summary",
+            (_, Role::User) => "user",
+            (_, Role::Assistant) => "assistant",
+            (_, Role::System) => "system",
+        };
+        body.push_str(&format!(
+            "<section class=\"{}\">\n<h3>{} <time>{}</time></h3>\n",
+            class,
+            escape_html(speaker(message, config)),
+            escape_html(&config.format_timestamp(message.timestamp))
+        ));
+
+        for attachment in &message.attachments {
+            body.push_str(&format!(
+                "<p class=\"meta\">📎 {} ({})</p>\n",
+                escape_html(&attachment.path),
+                format_size(attachment.size())
+            ));
+        }
+        if let Some(thinking) = message.thinking.as_deref().filter(|t| !t.trim().is_empty()) {
+            body.push_str(&format!(
+                "<details><summary>Thinking</summary><pre>{}</pre></details>\n",
+                escape_html(thinking.trim_end())
+            ));
+        }
+        for call in &message.tool_calls {
+            let result = call.result.as_deref().map(|r| format!(" → {}", r)).unwrap_or_default();
+            body.push_str(&format!(
+                "<p class=\"meta\">🔧 {}({}){}</p>\n",
+                escape_html(&call.name),
+                escape_html(&call.arguments),
+                escape_html(&result)
+            ));
+        }
+
+        for block in split_blocks(&message.content) {
+            match block {
+                Block::Text(text) => {
+                    body.push_str(&format!("<p class=\"text\">{}</p>\n", escape_html(text)))
+                }
+                Block::Code { language, code, .. } => body.push_str(&format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                    escape_html(language),
+                    escape_html(code)
+                )),
+            }
+        }
+        if let Some(stats) = &message.stats {
+            body.push_str(&format!("<p class=\"meta\">{}</p>\n", escape_html(&stats.to_string())));
+        }
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+        <html lang=\"en\">\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <title>{title}</title>\n\
+        <style>\n\
+        body {{ font-family: system-ui, sans-serif; max-width: 48rem; margin: 2rem auto; \
+        padding: 0 1rem; line-height: 1.5; }}\n\
+        section {{ border-left: 3px solid #888; padding-left: 1rem; margin-bottom: 1.5rem; }}\n\
+        section.user {{ border-color: #3b82f6; }}\n\
+        section.assistant {{ border-color: #10b981; }}\n\
+        section.system {{ border-color: #eab308; }}\n\
+        h3 {{ margin-bottom: 0.25rem; }}\n\
+        time, .meta, details {{ color: #666; font-size: 0.85rem; font-weight: normal; }}\n\
+        .text {{ white-space: pre-wrap; margin: 0.5rem 0; }}\n\
+        pre {{ background: #f4f4f5; padding: 0.75rem; overflow-x: auto; margin: 0.5rem 0; }}\n\
+        details pre {{ white-space: pre-wrap; background: none; padding: 0; }}\n\
+        </style>\n\
+        </head>\n\
+        <body>\n\
+        <h1>{title}</h1>\n\
+        {body}\
+        </body>\n\
+        </html>\n",
+        title = escape_html(title),
+        body = body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
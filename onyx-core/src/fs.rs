@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// How much of a file is inspected for NUL bytes when deciding whether it is binary.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FileError {
+    #[error("file is too large ({}, limit is {})", format_size(*size), format_size(*limit))]
+    TooLarge { size: u64, limit: u64 },
+
+    #[error("looks like a binary file")]
+    Binary,
+
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+impl Entry {
+    /// Dotfiles, which browsing and completion skip unless asked for explicitly.
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
+}
+
+/// Lists the entries of `path`, directories first and then by name.
+pub fn read_directory(path: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+        });
+    }
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Reads a UTF-8 text file of at most `max_bytes`, rejecting anything that looks binary.
+pub fn read_text_file(path: &Path, max_bytes: u64) -> Result<String, FileError> {
+    let file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    if size > max_bytes {
+        return Err(FileError::TooLarge { size, limit: max_bytes });
+    }
+
+    let mut bytes = Vec::new();
+    file.take(max_bytes + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(FileError::TooLarge { size: bytes.len() as u64, limit: max_bytes });
+    }
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Err(FileError::Binary);
+    }
+
+    String::from_utf8(bytes).map_err(|_| FileError::Binary)
+}
+
+/// Replaces `path` with `contents` so that it is never left half-written: they go to a temporary
+/// file beside it, which is synced and then renamed over it. If `path` is a symlink, the file it
+/// leads to is replaced instead, so the link survives. On Unix the file is readable by its owner
+/// only, as it may hold secrets.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = resolve_links(path)?;
+    let temp_path = temp_path(&path);
+
+    let write = || {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &path)?;
+
+        // The rename itself only survives a crash once the directory holding it is synced. Not
+        // every filesystem can sync a directory, and the file is in place either way.
+        #[cfg(unix)]
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            let _ = fs::File::open(parent).and_then(|dir| dir.sync_all());
+        }
+        Ok(())
+    };
+
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// Where [`write_private_file`] writes `path` before renaming it into place.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(".tmp.{}", std::process::id()));
+    path.with_file_name(temp_name)
+}
+
+/// The file `path` leads to once every symlink on the way is followed, whether or not it exists.
+fn resolve_links(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    // As many links as Linux follows before giving up on a loop.
+    for _ in 0..40 {
+        match fs::read_link(&path) {
+            Ok(target) => {
+                path = match path.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                }
+            }
+            Err(_) => return Ok(path),
+        }
+    }
+    Err(io::Error::other(format!("too many levels of symbolic links: {}", path.display())))
+}
+
+/// Makes `path` readable by its owner only. Does nothing outside Unix.
+pub fn restrict_to_owner(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if path.is_dir() { 0o700 } else { 0o600 };
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Formats a byte count for display, e.g. `512 B` or `4.2 KB`.
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+
+    let bytes_f = bytes as f64;
+    if bytes_f < KB {
+        format!("{} B", bytes)
+    } else if bytes_f < MB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{:.1} MB", bytes_f / MB)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An empty directory of its own for the test called `name`.
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("onyx-fs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn replaces_the_file() {
+        let dir = test_dir("replaces");
+        let path = dir.join("config.json");
+        fs::write(&path, "old").unwrap();
+
+        write_private_file(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_file_as_it_was() {
+        let dir = test_dir("failed");
+        let path = dir.join("config.json");
+        fs::write(&path, r#"{"valid": true}"#).unwrap();
+        // Something in the temporary file's place that can't be written to.
+        fs::create_dir(temp_path(&path)).unwrap();
+
+        assert!(write_private_file(&path, b"{\"val").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"valid": true}"#);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_temp_file_left_by_a_crash_is_never_read() {
+        let dir = test_dir("leftover");
+        let path = dir.join("config.json");
+        fs::write(&path, r#"{"valid": true}"#).unwrap();
+        fs::write(temp_path(&path), "{\"val").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"valid": true}"#);
+
+        write_private_file(&path, b"{}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("symlink");
+        fs::create_dir(dir.join("dotfiles")).unwrap();
+        let target = dir.join("dotfiles").join("config.json");
+        fs::write(&target, "old").unwrap();
+        let link = dir.join("link.json");
+        std::os::unix::fs::symlink("dotfiles/config.json", &link).unwrap();
+
+        write_private_file(&link, b"new").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
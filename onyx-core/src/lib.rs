@@ -1,7 +1,25 @@
 pub mod config;
+pub mod export;
+pub mod fs;
+pub mod markup;
+mod memory;
+pub mod migration;
 mod schema;
+mod session;
+mod stats;
 mod types;
 
-pub use config::{ConfigError, ConfigResult, ConfigSchema, FieldDescriptor, FieldType, FieldValue};
-pub use schema::{Config, Provider, ProviderConfig};
-pub use types::{CursorStyle, Message, Role};
+pub use config::{
+    ConfigError, ConfigResult, ConfigSchema, Expansions, FieldDescriptor, FieldParseError,
+    FieldType, FieldValue, Validator, mask_secret,
+};
+pub use memory::{Memories, Memory};
+pub use schema::{
+    ColorMode, Config, InputMode, LogLevel, Provider, ProviderConfig, ThemeName, TimestampDisplay,
+};
+pub use session::{Session, SessionSummary};
+pub use stats::{LifetimeStats, UsageTotals};
+pub use types::{
+    Attachment, CursorStyle, HistoryStrategy, Message, MessageKind, MessageStats, ModelPrice,
+    ResponseTimer, Role, ToolCall, Usage, format_cost,
+};
@@ -0,0 +1,311 @@
+use std::ops::Range;
+
+/// A run of message text: either prose or a fenced code block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block<'a> {
+    Text(&'a str),
+    Code {
+        language: &'a str,
+        code: &'a str,
+        /// False while the closing fence hasn't arrived yet, e.g. mid-stream.
+        closed: bool,
+    },
+}
+
+/// Splits `text` at its ``` fences. A fence closes with at least as many backticks as opened
+/// it, so fences nested in a longer fence stay part of the code.
+pub fn split_blocks(text: &str) -> Vec<Block<'_>> {
+    let mut parser = BlockParser::default();
+    parser.update(text);
+    if parser.parsed < text.len() {
+        parser.parse_line(text, parser.parsed, text.len());
+    }
+    parser.blocks(text)
+}
+
+/// Where a finished block lies in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BlockRange {
+    Text(Range<usize>),
+    Code { language: Range<usize>, code: Range<usize> },
+}
+
+/// The fence a code block opened with: its length, its language, and where its code starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OpenFence {
+    ticks: usize,
+    language: Range<usize>,
+    code_start: usize,
+}
+
+/// [`split_blocks`] for text that is still arriving. Only finished lines are parsed, and each
+/// once: the line still being written can't open or close a fence, so a half-typed ``` doesn't
+/// turn the rest of the message into code and back from one chunk to the next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockParser {
+    /// How much of the text has been parsed; always the end of a line.
+    parsed: usize,
+    text_start: usize,
+    open: Option<OpenFence>,
+    blocks: Vec<BlockRange>,
+}
+
+impl BlockParser {
+    /// Parses the lines of `text` finished since the last call. `text` is expected to extend
+    /// what was given before; if it is shorter, parsing starts over.
+    pub fn update(&mut self, text: &str) {
+        if text.len() < self.parsed || !text.is_char_boundary(self.parsed) {
+            *self = Self::default();
+        }
+
+        while let Some(newline) = text[self.parsed..].find('\n') {
+            let end = self.parsed + newline + 1;
+            self.parse_line(text, self.parsed, end);
+        }
+    }
+
+    fn parse_line(&mut self, text: &str, line_start: usize, end: usize) {
+        self.parsed = end;
+        let line = &text[line_start..end];
+        let fence = line.trim();
+        let ticks = fence.len() - fence.trim_start_matches('`').len();
+
+        match &self.open {
+            None if ticks >= 3 && !fence[ticks..].contains('`') => {
+                if line_start > self.text_start {
+                    self.blocks
+                        .push(BlockRange::Text(strip_newline(text, self.text_start..line_start)));
+                }
+                let language = fence[ticks..].trim();
+                let language_start = language.as_ptr() as usize - text.as_ptr() as usize;
+                self.open = Some(OpenFence {
+                    ticks,
+                    language: language_start..language_start + language.len(),
+                    code_start: end,
+                });
+            }
+            Some(open) if ticks >= open.ticks && fence.len() == ticks => {
+                self.blocks.push(BlockRange::Code {
+                    language: open.language.clone(),
+                    code: strip_newline(text, open.code_start..line_start),
+                });
+                self.open = None;
+                self.text_start = end;
+            }
+            _ => {}
+        }
+    }
+
+    /// The blocks of `text`, as last given to [`update`](Self::update). Whatever follows the
+    /// last finished line belongs to the block before it.
+    pub fn blocks<'a>(&self, text: &'a str) -> Vec<Block<'a>> {
+        let mut blocks: Vec<Block<'a>> = self
+            .blocks
+            .iter()
+            .map(|block| match block {
+                BlockRange::Text(range) => Block::Text(&text[range.clone()]),
+                BlockRange::Code { language, code } => Block::Code {
+                    language: &text[language.clone()],
+                    code: &text[code.clone()],
+                    closed: true,
+                },
+            })
+            .collect();
+
+        match &self.open {
+            Some(open) => blocks.push(Block::Code {
+                language: &text[open.language.clone()],
+                code: &text[open.code_start..],
+                closed: false,
+            }),
+            None if self.text_start < text.len() => {
+                blocks.push(Block::Text(&text[self.text_start..]))
+            }
+            None => {}
+        }
+
+        blocks
+    }
+}
+
+fn strip_newline(text: &str, range: Range<usize>) -> Range<usize> {
+    if text[range.clone()].ends_with('\n') { range.start..range.end - 1 } else { range }
+}
+
+/// A link in message text: a bare URL, or a markdown `[text](url)` taking up all of `range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link<'a> {
+    pub range: Range<usize>,
+    pub url: &'a str,
+}
+
+/// The http(s) links in `text`, in order.
+pub fn find_links(text: &str) -> Vec<Link<'_>> {
+    let mut links = Vec::new();
+    let mut position = 0;
+
+    while let Some(found) = text[position..].find("http") {
+        let start = position + found;
+        let Some(url) = url_at(text, start) else {
+            position = start + "http".len();
+            continue;
+        };
+        let end = start + url.len();
+
+        // A markdown link covers its text too: `[text](` just before, `)` just after.
+        let markdown = text[..start]
+            .strip_suffix("](")
+            .filter(|_| text[end..].starts_with(')'))
+            .and_then(|before| before.rfind('[').filter(|&open| !before[open..].contains('\n')));
+        match markdown {
+            Some(open) if open >= links.last().map_or(0, |link: &Link| link.range.end) => {
+                links.push(Link { range: open..end + 1, url })
+            }
+            _ => links.push(Link { range: start..end, url }),
+        }
+        position = end;
+    }
+
+    links
+}
+
+/// The URL starting at `start`, without the punctuation that usually follows one in prose.
+fn url_at(text: &str, start: usize) -> Option<&str> {
+    let rest = &text[start..];
+    if !rest.starts_with("https://") && !rest.starts_with("http://") {
+        return None;
+    }
+    if text[..start].chars().next_back().is_some_and(|c| c.is_alphanumeric()) {
+        return None;
+    }
+
+    let end = rest
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+        .unwrap_or(rest.len());
+    let mut url = &rest[..end];
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*']);
+        // A closing bracket belongs to the URL only if it opened one.
+        let trimmed = match trimmed.chars().next_back() {
+            Some(close @ (')' | ']')) => {
+                let open = if close == ')' { '(' } else { '[' };
+                if trimmed.matches(open).count() < trimmed.matches(close).count() {
+                    &trimmed[..trimmed.len() - 1]
+                } else {
+                    trimmed
+                }
+            }
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            break;
+        }
+        url = trimmed;
+    }
+
+    (url.len() > rest.find("://")? + 3).then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str =
+        "Here is the fix:\n\n```rust\nlet a = 1;\n\n```nested\n```\nIt works now.\n";
+
+    /// Every frame of `text` arriving `size` bytes at a time, as the streaming message shows it.
+    fn frames(text: &str, size: usize) -> Vec<(usize, Vec<Block<'_>>)> {
+        let mut parser = BlockParser::default();
+        (size..text.len() + size)
+            .step_by(size)
+            .map(|end| {
+                let end = end.min(text.len());
+                parser.update(&text[..end]);
+                (end, parser.blocks(&text[..end]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_fences() {
+        assert_eq!(
+            split_blocks(MESSAGE),
+            [
+                Block::Text("Here is the fix:\n"),
+                Block::Code { language: "rust", code: "let a = 1;\n\n```nested", closed: true },
+                Block::Text("It works now.\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_longer_fence_keeps_shorter_ones_as_code() {
+        let text = "````md\n```\ninner\n```\n````";
+        assert_eq!(
+            split_blocks(text),
+            [Block::Code { language: "md", code: "```\ninner\n```", closed: true }]
+        );
+    }
+
+    #[test]
+    fn an_unclosed_fence_runs_to_the_end() {
+        assert_eq!(
+            split_blocks("text\n```py\nprint()"),
+            [Block::Text("text"), Block::Code { language: "py", code: "print()", closed: false }]
+        );
+    }
+
+    #[test]
+    fn streaming_in_small_chunks_never_misclassifies_earlier_text() {
+        let fence_end = MESSAGE.find("```rust\n").unwrap() + "```rust\n".len();
+        let close_end = MESSAGE.find("```\nIt").unwrap() + "```\n".len();
+
+        for (end, blocks) in frames(MESSAGE, 3) {
+            let shown = &MESSAGE[..end];
+            // The paragraph before the fence is text in every frame.
+            assert!(
+                matches!(blocks[0], Block::Text(text) if MESSAGE.starts_with(text)),
+                "{:?}: {:?}",
+                shown,
+                blocks
+            );
+            let code_blocks = blocks.iter().filter(|block| matches!(block, Block::Code { .. }));
+            match end {
+                // A half-typed fence is still text until its line ends.
+                _ if end < fence_end => {
+                    assert_eq!(blocks, [Block::Text(shown)], "{:?}", shown)
+                }
+                // Inside the block, the nested fence stays code and nothing after it is text.
+                _ if end < close_end => {
+                    assert_eq!(code_blocks.count(), 1, "{:?}: {:?}", shown, blocks);
+                    assert!(
+                        matches!(blocks.last(), Some(Block::Code { closed: false, .. })),
+                        "{:?}: {:?}",
+                        shown,
+                        blocks
+                    );
+                }
+                _ => {
+                    assert_eq!(blocks[..2], split_blocks(MESSAGE)[..2], "{:?}", shown);
+                    assert!(matches!(blocks.last(), Some(Block::Text(_))), "{:?}", blocks);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_chunk_size_ends_where_parsing_whole_does() {
+        for size in 1..=MESSAGE.len() {
+            let (_, last) = frames(MESSAGE, size).pop().unwrap();
+            assert_eq!(last, split_blocks(MESSAGE), "chunks of {}", size);
+        }
+    }
+
+    #[test]
+    fn shorter_text_starts_over() {
+        let mut parser = BlockParser::default();
+        parser.update("```\ncode\n");
+        parser.update("plain\n");
+        assert_eq!(parser.blocks("plain\n"), [Block::Text("plain\n")]);
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::{ConfigResult, ConfigSchema};
+use crate::schema::Config;
+
+/// A fact the user asked the assistant to keep in mind across conversations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Memory {
+    pub text: String,
+    #[serde(default = "SystemTime::now")]
+    pub created_at: SystemTime,
+}
+
+/// Pinned memories, stored in `memories.json` in the data directory and sent with every request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Memories {
+    pub entries: Vec<Memory>,
+}
+
+impl Memories {
+    pub fn load() -> ConfigResult<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> ConfigResult<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn path() -> ConfigResult<PathBuf> {
+        Ok(Config::data_dir()?.join("memories.json"))
+    }
+
+    pub fn add(&mut self, text: impl Into<String>) {
+        self.entries.push(Memory { text: text.into(), created_at: SystemTime::now() });
+    }
+
+    /// Removes the memory with the given 1-based number, as listed by [`Memories::numbered`].
+    pub fn remove(&mut self, number: usize) -> Option<Memory> {
+        (1..=self.entries.len()).contains(&number).then(|| self.entries.remove(number - 1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn numbered(&self) -> String {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, memory)| format!("  {}. {}", i + 1, memory.text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The memories as a block to append to the system prompt, if there are any.
+    pub fn as_context(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let facts: Vec<String> =
+            self.entries.iter().map(|memory| format!("- {}", memory.text)).collect();
+        Some(format!("The user asked you to remember the following:\n{}", facts.join("\n")))
+    }
+}
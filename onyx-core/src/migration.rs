@@ -0,0 +1,216 @@
+use serde_json::{Map, Value};
+use strum::IntoEnumIterator;
+
+use crate::config::{ConfigError, ConfigResult};
+use crate::schema::{Provider, ProviderConfig};
+
+/// The key holding the version of the config format.
+pub const VERSION_KEY: &str = "config_version";
+
+/// Brings a config file from one version of the format to the next, before it is deserialized.
+pub trait Migration: Sync {
+    /// The version this migrates from. The result is at the next version. It takes `self`,
+    /// unlike most `from_` methods, so that migrations can be listed as trait objects.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_version(&self) -> u64;
+    fn migrate(&self, config: Value) -> ConfigResult<Value>;
+}
+
+/// The migrations for [`crate::Config`], oldest first. A new one goes at the end, along with a
+/// bump of [`CURRENT_VERSION`].
+pub static MIGRATIONS: &[&dyn Migration] = &[&NestProviderFields];
+
+/// The version written by this build.
+pub const CURRENT_VERSION: u64 = 1;
+
+/// Runs the migrations from the version `config` is at, treating one without a version as 0.
+/// Returns whether any ran. A config from a newer version is left alone.
+pub fn run_migrations(config: &mut Value, migrations: &[&dyn Migration]) -> ConfigResult<bool> {
+    let mut version = config.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0);
+    let mut migrated = false;
+
+    while let Some(migration) = migrations.iter().find(|m| m.from_version() == version) {
+        tracing::info!(from = version, "migrating config");
+        *config = migration.migrate(std::mem::take(config))?;
+        version += 1;
+        if let Some(fields) = config.as_object_mut() {
+            fields.insert(VERSION_KEY.to_string(), version.into());
+        }
+        migrated = true;
+    }
+
+    Ok(migrated)
+}
+
+/// Version 0 configs may hold provider settings under their flat ids, as shown by the config
+/// editor and `onyx config` (`openai_api_key`), rather than in the provider's section. This
+/// moves them into the section, unless it already sets them.
+struct NestProviderFields;
+
+impl Migration for NestProviderFields {
+    fn from_version(&self) -> u64 {
+        0
+    }
+
+    fn migrate(&self, mut config: Value) -> ConfigResult<Value> {
+        let fields = config.as_object_mut().ok_or_else(|| {
+            ConfigError::MigrationFailed(self.from_version(), "not a JSON object".to_string())
+        })?;
+
+        let provider_fields: Vec<String> = match serde_json::to_value(ProviderConfig::default())? {
+            Value::Object(defaults) => defaults.into_iter().map(|(name, _)| name).collect(),
+            _ => Vec::new(),
+        };
+
+        for provider in Provider::iter() {
+            let prefix = provider.field_prefix();
+            for name in &provider_fields {
+                let Some(value) = fields.remove(&format!("{}_{}", prefix, name)) else {
+                    continue;
+                };
+                let section = fields
+                    .entry(prefix)
+                    .or_insert_with(|| Value::Object(Map::new()))
+                    .as_object_mut()
+                    .ok_or_else(|| {
+                        ConfigError::MigrationFailed(
+                            self.from_version(),
+                            format!("{} is not an object", prefix),
+                        )
+                    })?;
+                section.entry(name.as_str()).or_insert(value);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigSchema;
+    use crate::fs::tests::test_dir;
+    use crate::schema::Config;
+    use serde_json::json;
+    use std::fs;
+
+    /// A config in the flat format of version 0, before provider sections.
+    fn v0_config() -> Value {
+        json!({
+            "active_provider": "anthropic",
+            "openai_api_key": "sk-openai",
+            "openai_model": "gpt-4o",
+            "anthropic_api_key": "sk-anthropic",
+            "anthropic_url": "https://proxy.example.com",
+            "anthropic_temperature": 0.5,
+            "openai_compatible_model": "local",
+            "system_prompt": "Be brief.",
+            "qdrant_url": "http://qdrant:6334",
+            "tools_enabled": true
+        })
+    }
+
+    /// Appends its version to `steps`, to show which migrations ran and in what order.
+    struct Step(u64);
+
+    impl Migration for Step {
+        fn from_version(&self) -> u64 {
+            self.0
+        }
+
+        fn migrate(&self, mut config: Value) -> ConfigResult<Value> {
+            let steps = config["steps"].as_array_mut().unwrap();
+            steps.push(self.0.into());
+            Ok(config)
+        }
+    }
+
+    #[test]
+    fn a_v0_config_becomes_a_current_one_with_its_values() {
+        let mut value = v0_config();
+        assert!(run_migrations(&mut value, MIGRATIONS).unwrap());
+        assert_eq!(value[VERSION_KEY], CURRENT_VERSION);
+
+        let config: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(config.active_provider, Provider::Anthropic);
+        assert_eq!(config.openai.api_key.as_deref(), Some("sk-openai"));
+        assert_eq!(config.openai.model, "gpt-4o");
+        assert_eq!(config.anthropic.api_key.as_deref(), Some("sk-anthropic"));
+        assert_eq!(config.anthropic.url.as_deref(), Some("https://proxy.example.com"));
+        assert_eq!(config.anthropic.temperature, Some(0.5));
+        assert_eq!(config.openai_compatible.model, "local");
+        assert_eq!(config.system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(config.qdrant_url, "http://qdrant:6334");
+        assert!(config.tools_enabled);
+        assert!(config.extra.is_empty(), "left over: {:?}", config.extra);
+    }
+
+    #[test]
+    fn values_already_in_a_section_win() {
+        let mut value = json!({
+            "openai_model": "gpt-4o",
+            "openai": {"model": "gpt-5"}
+        });
+        run_migrations(&mut value, MIGRATIONS).unwrap();
+        assert_eq!(value["openai"], json!({"model": "gpt-5"}));
+        assert!(value.get("openai_model").is_none());
+    }
+
+    #[test]
+    fn a_current_config_is_left_alone() {
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        let before = value.clone();
+        assert!(!run_migrations(&mut value, MIGRATIONS).unwrap());
+        assert_eq!(value, before);
+    }
+
+    #[test]
+    fn migrations_run_in_order_from_the_current_version() {
+        let migrations: &[&dyn Migration] = &[&Step(2), &Step(0), &Step(1)];
+
+        let mut value = json!({"steps": []});
+        assert!(run_migrations(&mut value, migrations).unwrap());
+        assert_eq!(value, json!({"steps": [0, 1, 2], VERSION_KEY: 3}));
+
+        let mut value = json!({"steps": [], VERSION_KEY: 2});
+        run_migrations(&mut value, migrations).unwrap();
+        assert_eq!(value["steps"], json!([2]));
+
+        let mut value = json!({"steps": [], VERSION_KEY: 7});
+        assert!(!run_migrations(&mut value, migrations).unwrap());
+    }
+
+    #[test]
+    fn a_section_that_is_not_an_object_fails() {
+        let mut value = json!({"openai": "gpt-4o", "openai_model": "gpt-4o"});
+        let error = run_migrations(&mut value, MIGRATIONS).unwrap_err();
+        assert!(matches!(error, ConfigError::MigrationFailed(0, _)), "{error}");
+    }
+
+    #[test]
+    fn loading_a_v0_file_saves_it_migrated_after_a_backup() {
+        let dir = test_dir("migrate-load");
+        let path = dir.join("config.json");
+        let original = serde_json::to_string_pretty(&v0_config()).unwrap();
+        fs::write(&path, &original).unwrap();
+
+        let config = Config::load_from(Some(path.clone())).unwrap();
+        assert_eq!(config.config_version, CURRENT_VERSION);
+        assert_eq!(config.openai.model, "gpt-4o");
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved[VERSION_KEY], CURRENT_VERSION);
+        assert_eq!(saved["anthropic"]["api_key"], "sk-anthropic");
+        assert!(saved.get("anthropic_api_key").is_none());
+
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|backup| *backup != path)
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), original);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,24 @@
 use crate::config::*;
-use crate::types::CursorStyle;
+use crate::migration::CURRENT_VERSION;
+use crate::types::{CursorStyle, HistoryStrategy, ModelPrice};
 use crate::{config_defaults, config_fields};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
+use tracing::level_filters::LevelFilter;
 
 #[derive(
-    Debug, Clone, Serialize, Deserialize, PartialEq, Default, Display, EnumString, EnumIter,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
 )]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
@@ -18,6 +30,192 @@ pub enum Provider {
     Anthropic,
     #[strum(serialize = "Ollama")]
     Ollama,
+    #[serde(rename = "openai_compatible")]
+    #[strum(serialize = "OpenAI-Compatible")]
+    OpenAICompatible,
+    #[strum(serialize = "Groq")]
+    Groq,
+    #[strum(serialize = "xAI")]
+    XAI,
+    #[strum(serialize = "DeepSeek")]
+    DeepSeek,
+    #[serde(rename = "llama_cpp")]
+    #[strum(serialize = "llama.cpp")]
+    LlamaCpp,
+}
+
+impl Provider {
+    /// The prefix of this provider's fields in the config schema, as in `openai_api_key`, and
+    /// the id of the group they are shown under.
+    pub fn field_prefix(&self) -> &'static str {
+        match self {
+            Self::OpenAI => "openai",
+            Self::Anthropic => "anthropic",
+            Self::Ollama => "ollama",
+            Self::OpenAICompatible => "openai_compatible",
+            Self::Groq => "groq",
+            Self::XAI => "xai",
+            Self::DeepSeek => "deepseek",
+            Self::LlamaCpp => "llama_cpp",
+        }
+    }
+
+    /// The provider a config field such as `openai_compatible_url`, or its group, belongs to.
+    pub fn of_field(field_id: &str) -> Option<Self> {
+        Self::iter()
+            .filter(|provider| {
+                field_id
+                    .strip_prefix(provider.field_prefix())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('_'))
+            })
+            .max_by_key(|provider| provider.field_prefix().len())
+    }
+}
+
+/// The built-in colour themes. The config editor and `/theme` list these variants, so a new
+/// theme only needs a variant here and its palette in the TUI.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum ThemeName {
+    #[default]
+    Default,
+    Monokai,
+    Light,
+    HighContrast,
+}
+
+/// How many colours the terminal is assumed to support. `Auto` decides from the environment.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Truecolor,
+    #[serde(rename = "256")]
+    #[strum(serialize = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    #[strum(serialize = "16")]
+    Ansi16,
+    None,
+}
+
+/// How the time of each message is shown in the chat. Logs and exports always use
+/// [`Config::timestamp_format`].
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum TimestampDisplay {
+    #[default]
+    Absolute,
+    /// How long ago, such as "4m ago".
+    Relative,
+    Hidden,
+}
+
+/// How keys edit the input. `Vim` adds a normal mode, entered with Esc, with vi motions and
+/// operators.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum InputMode {
+    #[default]
+    Default,
+    Vim,
+}
+
+/// How much goes into the debug log under `logs` in the data directory.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            Self::Off => LevelFilter::OFF,
+            Self::Error => LevelFilter::ERROR,
+            Self::Warn => LevelFilter::WARN,
+            Self::Info => LevelFilter::INFO,
+            Self::Debug => LevelFilter::DEBUG,
+            Self::Trace => LevelFilter::TRACE,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Default, Deserialize)]
@@ -26,46 +224,193 @@ pub struct ProviderConfig {
     pub api_key: Option<String>,
     pub model: String,
     pub url: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub thinking_budget_tokens: Option<u64>,
+    /// OpenAI's organization ID, sent as the `OpenAI-Organization` header.
+    pub organization: Option<String>,
+    /// Headers sent with every request, for gateways that want their own, such as
+    /// `"extra_headers": {"X-Gateway-Key": "..."}`. Only set in the config file.
+    pub extra_headers: Option<BTreeMap<String, String>>,
+}
+
+impl ProviderConfig {
+    pub fn base_url(&self) -> Option<&str> {
+        self.url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+    }
+
+    /// The headers to send with every request: the extra ones, and the organization if set.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        let organization = self
+            .organization
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| ("OpenAI-Organization", id));
+        self.extra_headers
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(organization)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// The version of the file format, for [`crate::migration`].
+    pub config_version: u64,
     pub active_provider: Provider,
     pub openai: ProviderConfig,
     pub anthropic: ProviderConfig,
     pub ollama: ProviderConfig,
+    pub openai_compatible: ProviderConfig,
+    pub groq: ProviderConfig,
+    pub xai: ProviderConfig,
+    pub deepseek: ProviderConfig,
+    pub llama_cpp: ProviderConfig,
+    pub system_prompt: Option<String>,
+    pub max_retries: u64,
+    pub initial_backoff_ms: u64,
+    pub context_token_limit: u64,
+    pub history_strategy: HistoryStrategy,
+    pub tools_enabled: bool,
+    pub shell_enabled: bool,
+    pub attachment_max_bytes: u64,
+    pub resume_last_session: bool,
+    pub watch_config_file: bool,
+    pub confirm_destructive_actions: bool,
+    pub confirm_duplicate_send: bool,
+    pub log_directory: Option<String>,
+    pub log_level: LogLevel,
+    pub input_history_limit: u64,
+    pub input_history_skip_commands: bool,
     pub qdrant_url: String,
     pub qdrant_api_key: Option<String>,
+    pub theme: ThemeName,
+    pub theme_file: Option<String>,
+    pub color_mode: ColorMode,
+    pub input_mode: InputMode,
+    /// How many lines the input grows to before it scrolls instead.
+    pub input_max_lines: u64,
+    pub show_status_bar: bool,
+    /// A line of the main keys under the chat's title, for when the welcome has gone.
+    pub show_hint_bar: bool,
+    pub show_message_stats: bool,
+    pub user_display_name: String,
+    /// The name shown on replies; without one, the model's name is shown.
+    pub assistant_display_name: Option<String>,
+    pub align_user_messages_right: bool,
     pub timestamp_format: String,
+    pub timestamp_display: TimestampDisplay,
     pub cursor_style: CursorStyle,
     pub cursor_blink_interval: u64,
+    /// The user's own slash commands: prompt templates by command name, without the `/`.
+    pub custom_commands: BTreeMap<String, String>,
+    /// Prices by model name, for models the built-in table doesn't know or has wrong.
+    pub pricing: BTreeMap<String, ModelPrice>,
+    /// Keys this version doesn't know, kept so that saving doesn't lose them.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// The file this config was loaded from, which saving writes back to.
     #[serde(skip)]
     pub config_path: Option<PathBuf>,
+    #[serde(skip)]
+    pub expansions: Expansions,
 }
 
 config_defaults! {
+    config_version => CURRENT_VERSION,
     active_provider => Provider::OpenAI,
     openai => ProviderConfig {
         api_key: None,
         model: "gpt-5-nano".to_string(),
         url: None,
+        ..Default::default()
     },
     anthropic => ProviderConfig {
         api_key: None,
         model: "claude-3-5-sonnet-20241022".to_string(),
         url: None,
+        ..Default::default()
     },
     ollama => ProviderConfig {
         api_key: None,
         model: "llama3.2".to_string(),
         url: Some("http://localhost:11434".to_string()),
+        ..Default::default()
     },
+    openai_compatible => ProviderConfig {
+        api_key: None,
+        model: "local-model".to_string(),
+        url: None,
+        ..Default::default()
+    },
+    groq => ProviderConfig {
+        api_key: None,
+        model: "llama-3.3-70b-versatile".to_string(),
+        url: None,
+        ..Default::default()
+    },
+    xai => ProviderConfig {
+        api_key: None,
+        model: "grok-3-mini".to_string(),
+        url: None,
+        ..Default::default()
+    },
+    deepseek => ProviderConfig {
+        api_key: None,
+        model: "deepseek-chat".to_string(),
+        url: None,
+        ..Default::default()
+    },
+    llama_cpp => ProviderConfig {
+        api_key: None,
+        model: "default".to_string(),
+        url: Some("http://localhost:8080".to_string()),
+        ..Default::default()
+    },
+    system_prompt => None,
+    max_retries => 3u64,
+    initial_backoff_ms => 1000u64,
+    context_token_limit => 32000u64,
+    history_strategy => HistoryStrategy::default(),
+    tools_enabled => false,
+    shell_enabled => false,
+    attachment_max_bytes => 102400u64,
+    resume_last_session => false,
+    watch_config_file => true,
+    confirm_destructive_actions => true,
+    confirm_duplicate_send => false,
+    log_directory => None,
+    log_level => LogLevel::default(),
+    input_history_limit => 500u64,
+    input_history_skip_commands => false,
     qdrant_url => "http://localhost:6334".to_string(),
     qdrant_api_key => None,
+    theme => ThemeName::default(),
+    theme_file => None,
+    color_mode => ColorMode::default(),
+    input_mode => InputMode::default(),
+    input_max_lines => 8u64,
+    show_status_bar => true,
+    show_hint_bar => false,
+    show_message_stats => false,
+    user_display_name => "You".to_string(),
+    assistant_display_name => Some("Onyx".to_string()),
+    align_user_messages_right => false,
     timestamp_format => "%Y-%m-%d %H:%M:%S".to_string(),
+    timestamp_display => TimestampDisplay::default(),
     cursor_style => CursorStyle::default(),
     cursor_blink_interval => 500u64,
+    custom_commands => BTreeMap::new(),
+    pricing => BTreeMap::new(),
+    extra => serde_json::Map::new(),
     config_path => None,
+    expansions => Expansions::default(),
 }
 
 config_fields! {
@@ -75,37 +420,398 @@ config_fields! {
             "Select which AI provider to use",
             active_provider,
             Provider::iter().map(|p| p.to_string()).collect()
+        ),
+        system_prompt: OptionalString(
+            "System Prompt",
+            "Standing instructions for the assistant (leave empty for none)",
+            system_prompt
+        ),
+        max_retries: U64(
+            "Max Retries",
+            "Retries for rate limits and network errors (0 to disable)",
+            max_retries
+        ),
+        initial_backoff_ms: U64(
+            "Initial Backoff",
+            "Delay before the first retry in milliseconds, doubled each time",
+            initial_backoff_ms
+        ),
+        context_token_limit: U64(
+            "Context Token Limit",
+            "Oldest messages are dropped beyond this many tokens (0 to disable)",
+            context_token_limit
+        ),
+        history_strategy: Enum(
+            "History Strategy",
+            "Drop or summarize messages beyond the context limit",
+            history_strategy,
+            HistoryStrategy::iter().map(|strategy| strategy.to_string()).collect()
+        ),
+        tools_enabled: Bool(
+            "Tools",
+            "Let the assistant list and read files under the working directory",
+            tools_enabled
+        ),
+        shell_enabled: Bool(
+            "Shell Commands",
+            "Let the assistant run shell commands, each one only after you approve it",
+            shell_enabled
+        ),
+        attachment_max_bytes: U64(
+            "Attachment Size Limit",
+            "Largest file /attach accepts, in bytes",
+            attachment_max_bytes
+        ),
+        resume_last_session: Bool(
+            "Resume Last Session",
+            "Reopen the most recent conversation at startup",
+            resume_last_session
+        ),
+        watch_config_file: Bool(
+            "Watch Config File",
+            "Reload the config when the file is edited outside Onyx",
+            watch_config_file
+        ),
+        confirm_destructive_actions: Bool(
+            "Confirm Destructive Actions",
+            "Ask before clearing the chat, or quitting during a response or with unsaved messages",
+            confirm_destructive_actions
+        ),
+        confirm_duplicate_send: Bool(
+            "Confirm Duplicate Send",
+            "Ask before sending the same message as your last one again",
+            confirm_duplicate_send
+        ),
+        log_directory: OptionalString(
+            "Log Directory",
+            "Where /save writes logs, relative to the config directory (empty for the current one)",
+            log_directory
+        ),
+        log_level: Enum(
+            "Debug Log Level",
+            "How much to write to the debug log (see /debug) for troubleshooting (off to disable)",
+            log_level,
+            LogLevel::iter().map(|level| level.to_string()).collect()
+        ),
+        input_history_limit: U64(
+            "Input History Size",
+            "Past inputs kept for recall with Ctrl+P/Ctrl+N and Ctrl+H (0 to disable)",
+            input_history_limit
+        ),
+        input_history_skip_commands: Bool(
+            "Skip Commands in History",
+            "Leave /commands out of the input history",
+            input_history_skip_commands
+        )
+    }
+
+    ["OpenAI"] group openai => {
+        openai_api_key: OptionalString("API Key", "Required", openai.api_key, secret),
+        openai_model: String(
+            "Model",
+            "e.g., gpt-4, gpt-3.5-turbo",
+            openai.model,
+            validate(NoWhitespace)
+        ),
+        openai_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            openai.url,
+            validate(Url)
+        ),
+        openai_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            openai.temperature,
+            0.0..=2.0
+        ),
+        openai_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            openai.max_tokens
+        )
+    }
+
+    ["Anthropic"] group anthropic => {
+        anthropic_api_key: OptionalString("API Key", "Required", anthropic.api_key, secret),
+        anthropic_model: String(
+            "Model",
+            "e.g., claude-3-5-sonnet-20241022",
+            anthropic.model,
+            validate(NoWhitespace)
+        ),
+        anthropic_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            anthropic.url,
+            validate(Url)
+        ),
+        anthropic_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            anthropic.temperature,
+            0.0..=1.0
+        ),
+        anthropic_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            anthropic.max_tokens
+        ),
+        anthropic_thinking_budget_tokens: OptionalU64(
+            "Thinking Budget",
+            "Extended thinking tokens, min 1024 and below Max Tokens (leave empty to disable)",
+            anthropic.thinking_budget_tokens
+        )
+    }
+
+    ["Ollama"] group ollama => {
+        ollama_api_key: OptionalString(
+            "API Key",
+            "Not required for Ollama",
+            ollama.api_key,
+            secret
+        ),
+        ollama_model: String(
+            "Model",
+            "e.g., llama3.2, mistral",
+            ollama.model,
+            validate(NoWhitespace)
+        ),
+        ollama_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            ollama.url,
+            validate(Url)
+        ),
+        ollama_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            ollama.temperature,
+            0.0..=2.0
+        ),
+        ollama_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            ollama.max_tokens
+        )
+    }
+
+    ["Custom (OpenAI-compatible)"] group openai_compatible => {
+        openai_compatible_api_key: OptionalString(
+            "API Key",
+            "Optional (only if the server requires one)",
+            openai_compatible.api_key,
+            secret
+        ),
+        openai_compatible_model: String(
+            "Model",
+            "Model name as reported by the server",
+            openai_compatible.model,
+            validate(NoWhitespace)
+        ),
+        openai_compatible_url: OptionalString(
+            "URL",
+            "Required, e.g., http://localhost:1234/v1 for LM Studio",
+            openai_compatible.url,
+            validate(Url)
+        ),
+        openai_compatible_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            openai_compatible.temperature,
+            0.0..=2.0
+        ),
+        openai_compatible_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            openai_compatible.max_tokens
+        )
+    }
+
+    ["Groq"] group groq => {
+        groq_api_key: OptionalString("API Key", "Required", groq.api_key, secret),
+        groq_model: String(
+            "Model",
+            "e.g., llama-3.3-70b-versatile, mixtral-8x7b-32768",
+            groq.model,
+            validate(NoWhitespace)
+        ),
+        groq_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            groq.url,
+            validate(Url)
+        ),
+        groq_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            groq.temperature,
+            0.0..=2.0
+        ),
+        groq_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            groq.max_tokens
         )
     }
 
-    ["OpenAI"] => {
-        openai_api_key: OptionalString("API Key", "Required", openai.api_key),
-        openai_model: String("Model", "e.g., gpt-4, gpt-3.5-turbo", openai.model),
-        openai_url: OptionalString("URL", "Optional (leave empty for default)", openai.url)
+    ["xAI"] group xai => {
+        xai_api_key: OptionalString("API Key", "Required", xai.api_key, secret),
+        xai_model: String(
+            "Model",
+            "e.g., grok-3-mini, grok-4-0709",
+            xai.model,
+            validate(NoWhitespace)
+        ),
+        xai_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            xai.url,
+            validate(Url)
+        ),
+        xai_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            xai.temperature,
+            0.0..=2.0
+        ),
+        xai_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            xai.max_tokens
+        )
     }
 
-    ["Anthropic"] => {
-        anthropic_api_key: OptionalString("API Key", "Required", anthropic.api_key),
-        anthropic_model: String("Model", "e.g., claude-3-5-sonnet-20241022", anthropic.model),
-        anthropic_url: OptionalString("URL", "Optional (leave empty for default)", anthropic.url)
+    ["DeepSeek"] group deepseek => {
+        deepseek_api_key: OptionalString("API Key", "Required", deepseek.api_key, secret),
+        deepseek_model: String(
+            "Model",
+            "e.g., deepseek-chat, deepseek-reasoner",
+            deepseek.model,
+            validate(NoWhitespace)
+        ),
+        deepseek_url: OptionalString(
+            "URL",
+            "Optional (leave empty for default)",
+            deepseek.url,
+            validate(Url)
+        ),
+        deepseek_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for provider default)",
+            deepseek.temperature,
+            0.0..=2.0
+        ),
+        deepseek_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for provider default)",
+            deepseek.max_tokens
+        )
     }
 
-    ["Ollama"] => {
-        ollama_api_key: OptionalString("API Key", "Not required for Ollama", ollama.api_key),
-        ollama_model: String("Model", "e.g., llama3.2, mistral", ollama.model),
-        ollama_url: OptionalString("URL", "Optional (leave empty for default)", ollama.url)
+    ["llama.cpp"] group llama_cpp => {
+        llama_cpp_api_key: OptionalString(
+            "API Key",
+            "Optional (only if the server was started with --api-key)",
+            llama_cpp.api_key,
+            secret
+        ),
+        llama_cpp_model: String(
+            "Model",
+            "Informational only (the server serves the model it was started with)",
+            llama_cpp.model,
+            validate(NoWhitespace)
+        ),
+        llama_cpp_url: OptionalString("URL", "llama.cpp server URL", llama_cpp.url, validate(Url)),
+        llama_cpp_temperature: OptionalF64(
+            "Temperature",
+            "e.g., 0.7 (leave empty for server default)",
+            llama_cpp.temperature,
+            0.0..=2.0
+        ),
+        llama_cpp_max_tokens: OptionalU64(
+            "Max Tokens",
+            "Response length limit (leave empty for server default)",
+            llama_cpp.max_tokens
+        )
     }
 
     ["Qdrant"] => {
-        qdrant_url: String("Qdrant URL", "Vector database URL", qdrant_url),
-        qdrant_api_key: OptionalString("Qdrant API Key", "Optional Qdrant API key", qdrant_api_key)
+        qdrant_url: String("Qdrant URL", "Vector database URL", qdrant_url, validate(Url)),
+        qdrant_api_key: OptionalString(
+            "Qdrant API Key",
+            "Optional Qdrant API key",
+            qdrant_api_key,
+            secret
+        )
     }
 
     ["Display"] => {
+        theme: Enum(
+            "Theme",
+            "Colour scheme for the interface",
+            theme,
+            ThemeName::iter().map(|theme| theme.to_string()).collect()
+        ),
+        theme_file: OptionalString(
+            "Theme File",
+            "JSON or TOML file overriding individual theme colours (leave empty for none)",
+            theme_file
+        ),
+        color_mode: Enum(
+            "Color Mode",
+            "Colours the terminal supports (auto detects; none disables colour)",
+            color_mode,
+            ColorMode::iter().map(|mode| mode.to_string()).collect()
+        ),
+        input_mode: Enum(
+            "Input Mode",
+            "Keybindings for the input (vim adds a normal mode, entered with Esc)",
+            input_mode,
+            InputMode::iter().map(|mode| mode.to_string()).collect()
+        ),
+        input_max_lines: U64(
+            "Input Height",
+            "Lines the input grows to as you type, before it scrolls (e.g., 8)",
+            input_max_lines
+        ),
+        show_status_bar: Bool(
+            "Status Bar",
+            "Show the provider, model, session and token usage below the input",
+            show_status_bar
+        ),
+        show_hint_bar: Bool(
+            "Hint Bar",
+            "Show the keys to send, open commands and get help at the top of the chat",
+            show_hint_bar
+        ),
+        show_message_stats: Bool(
+            "Message Stats",
+            "Show time to first token, tokens per second and total time under each reply",
+            show_message_stats
+        ),
+        user_display_name: String("Your Name", "Name shown on your messages", user_display_name),
+        assistant_display_name: OptionalString(
+            "Assistant Name",
+            "Name shown on replies (leave empty to show the model's name)",
+            assistant_display_name
+        ),
+        align_user_messages_right: Bool(
+            "Align Your Messages Right",
+            "Show your messages on the right, at most 70% of the chat wide",
+            align_user_messages_right
+        ),
         timestamp_format: String(
             "Timestamp Format",
             "strftime format (e.g., %Y-%m-%d %H:%M:%S)",
-            timestamp_format
+            timestamp_format,
+            validate(TimestampFormat)
+        ),
+        timestamp_display: Enum(
+            "Timestamp Display",
+            "Show message times as written, as how long ago (e.g., 4m ago), or not at all",
+            timestamp_display,
+            TimestampDisplay::iter().map(|display| display.to_string()).collect()
         ),
         cursor_style: Enum(
             "Cursor Style",
@@ -128,31 +834,270 @@ config_fields! {
 
 impl Config {
     pub fn get_active_provider(&self) -> &ProviderConfig {
+        self.provider_config(&self.active_provider)
+    }
+
+    pub fn get_active_provider_mut(&mut self) -> &mut ProviderConfig {
         match self.active_provider {
+            Provider::OpenAI => &mut self.openai,
+            Provider::Anthropic => &mut self.anthropic,
+            Provider::Ollama => &mut self.ollama,
+            Provider::OpenAICompatible => &mut self.openai_compatible,
+            Provider::Groq => &mut self.groq,
+            Provider::XAI => &mut self.xai,
+            Provider::DeepSeek => &mut self.deepseek,
+            Provider::LlamaCpp => &mut self.llama_cpp,
+        }
+    }
+
+    pub fn provider_config(&self, provider: &Provider) -> &ProviderConfig {
+        match provider {
             Provider::OpenAI => &self.openai,
             Provider::Anthropic => &self.anthropic,
             Provider::Ollama => &self.ollama,
+            Provider::OpenAICompatible => &self.openai_compatible,
+            Provider::Groq => &self.groq,
+            Provider::XAI => &self.xai,
+            Provider::DeepSeek => &self.deepseek,
+            Provider::LlamaCpp => &self.llama_cpp,
         }
     }
 
     pub fn validate(&self) -> ConfigResult<()> {
+        self.validate_urls()?;
+        self.validate_headers()?;
+        self.validate_thinking_budget()?;
+        self.validate_fields()?;
+
         let provider = self.get_active_provider();
         let provider_name = self.active_provider.to_string();
+        let config_file = match &self.config_path {
+            Some(path) => path.display().to_string(),
+            None => Self::config_path_display(),
+        };
 
-        if let Provider::Ollama = self.active_provider {
-            return Ok(());
+        match self.active_provider {
+            Provider::Ollama | Provider::LlamaCpp => return Ok(()),
+            Provider::OpenAICompatible => {
+                if provider.base_url().is_none() {
+                    return Err(ConfigError::MissingUrl(provider_name, config_file));
+                }
+                return Ok(());
+            }
+            _ => {}
         }
 
         if provider.api_key.is_none() || provider.api_key.as_ref().unwrap().is_empty() {
-            return Err(ConfigError::MissingApiKey(provider_name, Self::config_path_display()));
+            let field = format!("{}_api_key", self.active_provider.field_prefix());
+            if let Some(variable) = self.expansions.unset_variable(&field) {
+                return Err(ConfigError::UnsetVariable(variable.to_string(), field));
+            }
+            return Err(ConfigError::MissingApiKey(provider_name, config_file));
         }
 
         Ok(())
     }
 
+    fn validate_urls(&self) -> ConfigResult<()> {
+        let provider_urls = Provider::iter().filter_map(|provider| {
+            let url = self.provider_config(&provider).base_url()?;
+            Some((format!("{} URL", provider), url))
+        });
+
+        for (name, url) in
+            provider_urls.chain([("Qdrant URL".to_string(), self.qdrant_url.as_str())])
+        {
+            if !is_valid_url(url) {
+                return Err(ConfigError::InvalidUrl(name, url.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Holds the Anthropic thinking budget to what the API accepts: at least
+    /// [`MIN_THINKING_BUDGET_TOKENS`], and less than the max tokens when those are set. Anything
+    /// else gets every request rejected.
+    fn validate_thinking_budget(&self) -> ConfigResult<()> {
+        let Some(budget) = self.anthropic.thinking_budget_tokens else {
+            return Ok(());
+        };
+        let invalid = |message| {
+            Err(ConfigError::InvalidValue("anthropic_thinking_budget_tokens".into(), message))
+        };
+
+        if budget < MIN_THINKING_BUDGET_TOKENS {
+            return invalid(format!("must be at least {}", MIN_THINKING_BUDGET_TOKENS));
+        }
+        match self.anthropic.max_tokens {
+            Some(max_tokens) if budget >= max_tokens => {
+                invalid(format!("must be less than the max tokens ({})", max_tokens))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects header names and values that no HTTP request could carry, so that a typo is
+    /// reported here rather than as a failed request.
+    fn validate_headers(&self) -> ConfigResult<()> {
+        for provider in Provider::iter() {
+            let field = format!("{}_extra_headers", provider.field_prefix());
+            for (name, value) in self.provider_config(&provider).headers() {
+                if !is_valid_header_name(name) {
+                    return Err(ConfigError::InvalidValue(
+                        field,
+                        format!("'{}' is not a valid header name", name),
+                    ));
+                }
+                if value.contains(['\r', '\n']) {
+                    return Err(ConfigError::InvalidValue(
+                        field,
+                        format!("the value of {} must be on one line", name),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether `other` holds the same settings, so that switching to it would change nothing.
+    pub fn same_settings(&self, other: &Config) -> bool {
+        serde_json::to_value(self).ok() == serde_json::to_value(other).ok()
+    }
+
+    /// Whether `other` differs in more than how the chat is displayed, the custom commands,
+    /// the prices and the confirmations, which take effect without rebuilding the agent.
+    pub fn differs_beyond_display(&self, other: &Config) -> bool {
+        let mut adjusted = self.clone();
+        adjusted.theme = other.theme;
+        adjusted.theme_file = other.theme_file.clone();
+        adjusted.color_mode = other.color_mode;
+        adjusted.input_mode = other.input_mode;
+        adjusted.input_max_lines = other.input_max_lines;
+        adjusted.show_status_bar = other.show_status_bar;
+        adjusted.show_hint_bar = other.show_hint_bar;
+        adjusted.show_message_stats = other.show_message_stats;
+        adjusted.user_display_name = other.user_display_name.clone();
+        adjusted.assistant_display_name = other.assistant_display_name.clone();
+        adjusted.align_user_messages_right = other.align_user_messages_right;
+        adjusted.timestamp_format = other.timestamp_format.clone();
+        adjusted.timestamp_display = other.timestamp_display;
+        adjusted.cursor_style = other.cursor_style;
+        adjusted.cursor_blink_interval = other.cursor_blink_interval;
+        adjusted.custom_commands = other.custom_commands.clone();
+        adjusted.pricing = other.pricing.clone();
+        adjusted.confirm_destructive_actions = other.confirm_destructive_actions;
+        adjusted.confirm_duplicate_send = other.confirm_duplicate_send;
+        !adjusted.same_settings(other)
+    }
+
+    /// The custom commands that can be typed, as `/name` with their templates. Names may be
+    /// written with or without the slash, and those containing whitespace are skipped.
+    pub fn custom_commands(&self) -> impl Iterator<Item = (String, &str)> {
+        self.custom_commands.iter().filter_map(|(name, template)| {
+            let name = name.trim().trim_start_matches('/');
+            (!name.is_empty() && !name.contains(char::is_whitespace))
+                .then(|| (format!("/{}", name), template.as_str()))
+        })
+    }
+
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.system_prompt.as_deref().map(str::trim).filter(|p| !p.is_empty())
+    }
+
+    pub fn log_directory(&self) -> Option<&str> {
+        self.log_directory.as_deref().map(str::trim).filter(|dir| !dir.is_empty())
+    }
+
+    /// Every API key and header value in the config, so they can be kept out of logs.
+    pub fn secrets(&self) -> Vec<&str> {
+        let header_values = Provider::iter()
+            .flat_map(|provider| self.provider_config(&provider).headers().map(|(_, value)| value));
+        Provider::iter()
+            .filter_map(|provider| self.provider_config(&provider).api_key.as_deref())
+            .chain(self.qdrant_api_key.as_deref())
+            .chain(header_values)
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .collect()
+    }
+
+    /// The name shown on the user's messages in the chat and in exports.
+    pub fn user_name(&self) -> &str {
+        Some(self.user_display_name.trim()).filter(|name| !name.is_empty()).unwrap_or("You")
+    }
+
+    /// The name shown on replies in the chat and in exports: the configured one, or else the
+    /// active model's.
+    pub fn assistant_name(&self) -> &str {
+        self.assistant_display_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&self.get_active_provider().model)
+    }
+
+    pub fn theme_file(&self) -> Option<&str> {
+        self.theme_file.as_deref().map(str::trim).filter(|file| !file.is_empty())
+    }
+
+    /// Expands a leading `~` and resolves relative paths against the config directory.
+    pub fn resolve_path(&self, path: &str) -> ConfigResult<PathBuf> {
+        let expanded = match path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+                let home = dirs::home_dir().ok_or(ConfigError::NoHomeDir)?;
+                home.join(rest.trim_start_matches(['/', '\\']))
+            }
+            _ => PathBuf::from(path),
+        };
+
+        if expanded.is_absolute() { Ok(expanded) } else { Ok(Self::config_dir()?.join(expanded)) }
+    }
+
+    /// The date part of [`Self::timestamp_format`], or `%Y-%m-%d` if it has none.
+    pub fn date_format(&self) -> &str {
+        format_part(&self.timestamp_format, DATE_SPECIFIERS).unwrap_or("%Y-%m-%d")
+    }
+
+    /// The time part of [`Self::timestamp_format`], or `%H:%M:%S` if it has none.
+    pub fn time_format(&self) -> &str {
+        format_part(&self.timestamp_format, TIME_SPECIFIERS).unwrap_or("%H:%M:%S")
+    }
+
     pub fn format_timestamp(&self, timestamp: std::time::SystemTime) -> String {
         use chrono::{DateTime, Local};
         let datetime: DateTime<Local> = timestamp.into();
         datetime.format(&self.timestamp_format).to_string()
     }
 }
+
+/// The strftime specifiers that format part of a date or of a time of day.
+/// The smallest extended thinking budget Anthropic accepts.
+const MIN_THINKING_BUDGET_TOKENS: u64 = 1024;
+
+const DATE_SPECIFIERS: &str = "YCymbBhdeaAwuUWGgVjDFvx";
+const TIME_SPECIFIERS: &str = "HkIlPpMSfRTXrZz";
+
+/// The stretch of `format` from its first specifier in `specifiers` to its last, with the
+/// text between them, if it has any.
+fn format_part<'a>(format: &'a str, specifiers: &str) -> Option<&'a str> {
+    let mut part: Option<(usize, usize)> = None;
+    let mut chars = format.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        // Skips padding flags, widths and the `.`/`:` of specifiers like `%.3f` and `%:z`.
+        let Some((end, specifier)) =
+            chars.find(|(_, c)| !matches!(c, '-' | '_' | '0'..='9' | '.' | ':' | '#'))
+        else {
+            break;
+        };
+        if specifiers.contains(specifier) {
+            let end = end + specifier.len_utf8();
+            part = Some(part.map_or((start, end), |(first, _)| (first, end)));
+        }
+    }
+    part.map(|(start, end)| &format[start..end])
+}
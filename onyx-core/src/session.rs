@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::{ConfigResult, ConfigSchema};
+use crate::fs::write_private_file;
+use crate::schema::Config;
+use crate::types::{Message, Role};
+
+/// How many characters of the opening message are used as a session's title.
+const TITLE_LENGTH: usize = 60;
+
+/// A saved conversation, stored as `sessions/<id>.json` in the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+/// A saved session as listed by [`Session::list`], without its messages.
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    pub message_count: usize,
+    pub modified: SystemTime,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self { id: Self::new_id(), title: None, messages: Vec::new() }
+    }
+
+    /// Ids are creation timestamps, so they are unique per run and sort chronologically.
+    pub fn new_id() -> String {
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string()
+    }
+
+    pub fn save(&self) -> ConfigResult<()> {
+        let dir = Self::dir()?;
+        fs::create_dir_all(&dir)?;
+        write_private_file(&Self::path(&self.id)?, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(id: &str) -> ConfigResult<Self> {
+        let content = fs::read_to_string(Self::path(id)?)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn delete(id: &str) -> ConfigResult<()> {
+        fs::remove_file(Self::path(id)?)?;
+        Ok(())
+    }
+
+    /// The most recently saved session, if there is one.
+    pub fn load_latest() -> ConfigResult<Option<Self>> {
+        Self::list()?.first().map(|summary| Self::load(&summary.id)).transpose()
+    }
+
+    /// Saved sessions, most recently modified first. Files that can't be read are skipped.
+    pub fn list() -> ConfigResult<Vec<SessionSummary>> {
+        let dir = Self::dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions: Vec<SessionSummary> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                let content = fs::read_to_string(entry.path()).ok()?;
+                let session: Self = serde_json::from_str(&content).ok()?;
+                Some(SessionSummary {
+                    title: session.display_title(),
+                    message_count: session.messages.len(),
+                    id: session.id,
+                    modified,
+                })
+            })
+            .collect();
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.modified));
+        Ok(sessions)
+    }
+
+    /// The session's title, falling back to the first line of the opening user message,
+    /// shortened to fit in a list.
+    pub fn display_title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+
+        let first_line = self
+            .messages
+            .iter()
+            .find(|msg| matches!(msg.role, Role::User))
+            .and_then(|msg| msg.content.lines().find(|line| !line.trim().is_empty()))
+            .map(str::trim);
+
+        match first_line {
+            Some(line) if line.chars().count() > TITLE_LENGTH => {
+                format!("{}…", line.chars().take(TITLE_LENGTH).collect::<String>())
+            }
+            Some(line) => line.to_string(),
+            None => "(untitled)".to_string(),
+        }
+    }
+
+    fn dir() -> ConfigResult<PathBuf> {
+        Ok(Config::data_dir()?.join("sessions"))
+    }
+
+    fn path(id: &str) -> ConfigResult<PathBuf> {
+        Ok(Self::dir()?.join(format!("{}.json", id)))
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
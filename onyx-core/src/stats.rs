@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::{ConfigResult, ConfigSchema};
+use crate::schema::Config;
+use crate::types::{Message, MessageKind, Role};
+
+/// Totals over a set of replies: one session's, or every one since the stats were reset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UsageTotals {
+    pub responses: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// In dollars, over the replies whose model's price was known.
+    pub cost: Option<f64>,
+    /// The replies that were timed, and what their times add up to, for the averages.
+    pub timed_responses: u64,
+    pub latency_ms: u64,
+    pub generating_ms: u64,
+    pub output_tokens: u64,
+    /// Replies by the provider that gave them.
+    pub requests: BTreeMap<String, u64>,
+}
+
+impl UsageTotals {
+    /// The totals of the replies in `messages`, which don't say which provider gave them.
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = &'a Message>) -> Self {
+        let mut totals = Self::default();
+        for message in messages {
+            if message.kind == MessageKind::Chat && matches!(message.role, Role::Assistant) {
+                totals.add(message);
+            }
+        }
+        totals
+    }
+
+    /// Counts `message`, a finished reply from `provider`.
+    pub fn record(&mut self, provider: &str, message: &Message) {
+        self.add(message);
+        *self.requests.entry(provider.to_string()).or_default() += 1;
+    }
+
+    fn add(&mut self, message: &Message) {
+        self.responses += 1;
+        if let Some(usage) = &message.usage {
+            self.prompt_tokens += usage.prompt_tokens;
+            self.completion_tokens += usage.completion_tokens;
+        }
+        if let Some(cost) = message.cost {
+            self.cost = Some(self.cost.unwrap_or_default() + cost);
+        }
+        if let Some(stats) = &message.stats {
+            self.timed_responses += 1;
+            self.latency_ms += stats.total_ms;
+            self.generating_ms += match stats.first_token_ms {
+                Some(first) if first < stats.total_ms => stats.total_ms - first,
+                _ => stats.total_ms,
+            };
+            self.output_tokens += stats.output_tokens;
+        }
+    }
+
+    /// The average time a reply took, in seconds.
+    pub fn average_latency(&self) -> Option<f64> {
+        (self.timed_responses > 0)
+            .then(|| self.latency_ms as f64 / 1000.0 / self.timed_responses as f64)
+    }
+
+    /// Output speed over every timed reply, once content started arriving.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        (self.output_tokens > 0 && self.generating_ms > 0)
+            .then(|| self.output_tokens as f64 * 1000.0 / self.generating_ms as f64)
+    }
+}
+
+/// Usage across every session, stored in `stats.json` in the data directory and updated as
+/// each reply finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    /// When counting started, or was last reset.
+    #[serde(default = "SystemTime::now")]
+    pub since: SystemTime,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+impl Default for LifetimeStats {
+    fn default() -> Self {
+        Self { since: SystemTime::now(), totals: UsageTotals::default() }
+    }
+}
+
+impl LifetimeStats {
+    pub fn load() -> ConfigResult<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> ConfigResult<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn path() -> ConfigResult<PathBuf> {
+        Ok(Config::data_dir()?.join("stats.json"))
+    }
+
+    /// Adds `message`, a finished reply from `provider`, to the saved stats.
+    pub fn record(provider: &str, message: &Message) -> ConfigResult<()> {
+        let mut stats = Self::load()?;
+        stats.totals.record(provider, message);
+        stats.save()
+    }
+
+    /// Starts counting again from now.
+    pub fn reset() -> ConfigResult<()> {
+        Self::default().save()
+    }
+}
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
+use strum::{Display, EnumIter, EnumString};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
@@ -59,6 +60,190 @@ impl CursorStyle {
 pub enum Role {
     User,
     Assistant,
+    /// Onyx itself: welcome text, command output and errors. Never sent to the model as a turn.
+    System,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Default,
+    Display,
+    EnumString,
+    EnumIter,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum HistoryStrategy {
+    #[default]
+    Truncate,
+    Summarize,
+}
+
+/// Separates the conversation from local output (command responses, hints) that is shown in
+/// the chat but never sent to the model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    #[default]
+    Chat,
+    Notice,
+    /// A request that failed, shown in place of the reply and never sent to the model.
+    Error,
+    /// Stands in for earlier messages that were condensed to fit the context window.
+    Summary,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// What a model costs, in dollars per 1,000 tokens.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input + usage.completion_tokens as f64 * self.output)
+            / 1000.0
+    }
+}
+
+/// A cost in dollars, with more decimals for the fractions of a cent a reply usually costs.
+pub fn format_cost(cost: f64) -> String {
+    if cost < 1.0 { format!("${:.4}", cost) } else { format!("${:.2}", cost) }
+}
+
+/// How long a reply took to stream, recorded when it finishes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct MessageStats {
+    /// From sending the request to the first piece of content.
+    pub first_token_ms: Option<u64>,
+    pub total_ms: u64,
+    pub output_tokens: u64,
+    /// Set when the provider reported no usage and `output_tokens` was estimated from the text.
+    #[serde(default)]
+    pub estimated_tokens: bool,
+}
+
+impl MessageStats {
+    /// Output speed once content started arriving, so the wait for the first token doesn't
+    /// drag it down.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let generating_ms = match self.first_token_ms {
+            Some(first) if first < self.total_ms => self.total_ms - first,
+            _ => self.total_ms,
+        };
+        (self.output_tokens > 0 && generating_ms > 0)
+            .then(|| self.output_tokens as f64 * 1000.0 / generating_ms as f64)
+    }
+}
+
+impl fmt::Display for MessageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = |ms: u64| ms as f64 / 1000.0;
+        if let Some(first) = self.first_token_ms {
+            write!(f, "{:.1}s to first token · ", seconds(first))?;
+        }
+        if let Some(rate) = self.tokens_per_second() {
+            let approximate = if self.estimated_tokens { "~" } else { "" };
+            write!(f, "{}{:.0} tok/s · ", approximate, rate)?;
+        }
+        write!(f, "{:.1}s total", seconds(self.total_ms))
+    }
+}
+
+/// Times a streaming reply from the moment the request is sent.
+pub struct ResponseTimer {
+    started: Instant,
+    first_token: Option<Duration>,
+    streamed_chars: usize,
+}
+
+impl ResponseTimer {
+    pub fn start() -> Self {
+        Self { started: Instant::now(), first_token: None, streamed_chars: 0 }
+    }
+
+    pub fn record_chunk(&mut self, chunk: &str) {
+        if self.first_token.is_none() && !chunk.is_empty() {
+            self.first_token = Some(self.started.elapsed());
+        }
+        self.streamed_chars += chunk.chars().count();
+    }
+
+    /// Falls back to a chars/4 estimate of the output when `usage` is missing.
+    pub fn finish(&self, usage: Option<&Usage>) -> MessageStats {
+        let (output_tokens, estimated_tokens) = match usage {
+            Some(usage) if usage.completion_tokens > 0 => (usage.completion_tokens, false),
+            _ => (self.streamed_chars.div_ceil(4) as u64, true),
+        };
+        MessageStats {
+            first_token_ms: self.first_token.map(|elapsed| elapsed.as_millis() as u64),
+            total_ms: self.started.elapsed().as_millis() as u64,
+            output_tokens,
+            estimated_tokens,
+        }
+    }
+}
+
+/// A file attached to a user message, sent to the model ahead of the message text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub path: String,
+    pub contents: String,
+}
+
+impl Attachment {
+    pub fn file_name(&self) -> &str {
+        std::path::Path::new(&self.path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.path)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.contents.len() as u64
+    }
+
+    /// Wraps the contents in a code fence long enough not to clash with any backticks inside.
+    fn fenced(&self) -> String {
+        let longest_run = self.contents.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        let language = std::path::Path::new(&self.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+
+        format!(
+            "{}:\n{}{}\n{}\n{}",
+            self.path,
+            fence,
+            language,
+            self.contents.trim_end_matches('\n'),
+            fence
+        )
+    }
+}
+
+/// A tool the model invoked while producing a reply; `result` is filled in once it returns.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: String,
+    #[serde(default)]
+    pub result: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +256,23 @@ pub struct Message {
     pub is_streaming: bool,
     #[serde(default = "SystemTime::now")]
     pub timestamp: SystemTime,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub stats: Option<MessageStats>,
+    /// What the reply cost in dollars, estimated from its usage when the model's price is known.
+    #[serde(default)]
+    pub cost: Option<f64>,
+    #[serde(default)]
+    pub kind: MessageKind,
+    /// Set once the message has been folded into a summary; it stays visible but is no longer
+    /// sent to the model.
+    #[serde(default)]
+    pub summarized: bool,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 impl Message {
@@ -81,6 +283,13 @@ impl Message {
             thinking: None,
             is_streaming: false,
             timestamp: SystemTime::now(),
+            usage: None,
+            stats: None,
+            cost: None,
+            kind: MessageKind::Chat,
+            summarized: false,
+            tool_calls: Vec::new(),
+            attachments: Vec::new(),
         }
     }
 
@@ -91,9 +300,32 @@ impl Message {
             thinking: None,
             is_streaming: false,
             timestamp: SystemTime::now(),
+            usage: None,
+            stats: None,
+            cost: None,
+            kind: MessageKind::Chat,
+            summarized: false,
+            tool_calls: Vec::new(),
+            attachments: Vec::new(),
         }
     }
 
+    pub fn system(content: impl Into<String>) -> Self {
+        Self { role: Role::System, ..Self::assistant(content) }
+    }
+
+    pub fn notice(content: impl Into<String>) -> Self {
+        Self { kind: MessageKind::Notice, ..Self::system(content) }
+    }
+
+    pub fn error(content: impl Into<String>) -> Self {
+        Self { kind: MessageKind::Error, ..Self::system(content) }
+    }
+
+    pub fn summary(content: impl Into<String>) -> Self {
+        Self { kind: MessageKind::Summary, ..Self::assistant(content) }
+    }
+
     pub fn assistant_streaming() -> Self {
         Self {
             role: Role::Assistant,
@@ -101,7 +333,25 @@ impl Message {
             thinking: None,
             is_streaming: true,
             timestamp: SystemTime::now(),
+            usage: None,
+            stats: None,
+            cost: None,
+            kind: MessageKind::Chat,
+            summarized: false,
+            tool_calls: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// The content as sent to the model, with any attachments prepended in fenced blocks.
+    pub fn prompt_text(&self) -> String {
+        if self.attachments.is_empty() {
+            return self.content.clone();
         }
+
+        let mut text: Vec<String> = self.attachments.iter().map(Attachment::fenced).collect();
+        text.push(self.content.clone());
+        text.join("\n\n")
     }
 
     pub fn append_content(&mut self, chunk: impl Into<String>) {
@@ -120,7 +370,98 @@ impl Message {
         }
     }
 
+    pub fn reset_content(&mut self) {
+        self.content.clear();
+        self.thinking = None;
+        self.usage = None;
+        self.tool_calls.clear();
+    }
+
+    pub fn start_tool_call(&mut self, name: impl Into<String>, arguments: impl Into<String>) {
+        self.tool_calls.push(ToolCall {
+            name: name.into(),
+            arguments: arguments.into(),
+            result: None,
+        });
+    }
+
+    /// Attaches `result` to the most recent pending call to `name`.
+    pub fn finish_tool_call(&mut self, name: &str, result: impl Into<String>) {
+        if let Some(call) =
+            self.tool_calls.iter_mut().rev().find(|call| call.name == name && call.result.is_none())
+        {
+            call.result = Some(result.into());
+        }
+    }
+
     pub fn finish_streaming(&mut self) {
         self.is_streaming = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn roles_serialize_by_name() {
+        for (role, name) in
+            [(Role::User, "User"), (Role::Assistant, "Assistant"), (Role::System, "System")]
+        {
+            assert_eq!(serde_json::to_value(&role).unwrap(), json!(name));
+            let parsed: Role = serde_json::from_value(json!(name)).unwrap();
+            assert_eq!(serde_json::to_value(parsed).unwrap(), json!(name));
+        }
+    }
+
+    #[test]
+    fn system_messages_round_trip() {
+        let message = Message::system("Welcome to Onyx");
+        let json = serde_json::to_string(&message).unwrap();
+        let parsed: Message = serde_json::from_str(&json).unwrap();
+
+        assert!(matches!(parsed.role, Role::System));
+        assert_eq!(parsed.content, "Welcome to Onyx");
+        assert_eq!(parsed.kind, MessageKind::Chat);
+    }
+
+    #[test]
+    fn notices_and_errors_are_system_messages() {
+        assert!(matches!(Message::notice("saved").role, Role::System));
+        assert_eq!(Message::notice("saved").kind, MessageKind::Notice);
+        assert!(matches!(Message::error("failed").role, Role::System));
+        assert_eq!(Message::error("failed").kind, MessageKind::Error);
+    }
+
+    #[test]
+    fn messages_from_before_system_roles_still_load() {
+        let messages: Vec<Message> = serde_json::from_value(json!([
+            {"role": "User", "content": "hello", "is_streaming": false},
+            {"role": "Assistant", "content": "hi", "usage": null}
+        ]))
+        .unwrap();
+
+        assert!(matches!(messages[0].role, Role::User));
+        assert!(matches!(messages[1].role, Role::Assistant));
+        assert!(messages.iter().all(|message| message.kind == MessageKind::Chat));
+    }
+
+    #[test]
+    fn unknown_roles_are_rejected() {
+        assert!(serde_json::from_value::<Role>(json!("Tool")).is_err());
+    }
+
+    #[test]
+    fn history_strategies_are_named_as_in_the_config_file() {
+        use strum::IntoEnumIterator;
+
+        for strategy in HistoryStrategy::iter() {
+            let name = strategy.to_string();
+            assert_eq!(serde_json::to_value(strategy).unwrap(), json!(name));
+            assert_eq!(name.parse::<HistoryStrategy>().unwrap(), strategy);
+        }
+        assert_eq!("Summarize".parse::<HistoryStrategy>().unwrap(), HistoryStrategy::Summarize);
+        assert!("drop".parse::<HistoryStrategy>().is_err());
+    }
+}
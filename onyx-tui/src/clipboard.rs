@@ -0,0 +1,23 @@
+/// The system clipboard, opened on first use. The handle is kept for the life of the app
+/// because on X11 copied text is only served while it is alive.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    fn handle(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        if self.inner.is_none() {
+            self.inner = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.inner.as_mut().expect("clipboard was just opened"))
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<(), arboard::Error> {
+        self.handle()?.set_text(text)
+    }
+
+    pub fn paste(&mut self) -> Result<String, arboard::Error> {
+        self.handle()?.get_text()
+    }
+}
@@ -0,0 +1,257 @@
+use onyx_core::ColorMode;
+use ratatui::style::{Color, Style};
+use std::env;
+
+/// The colours a terminal can actually show. Themes are written in RGB and converted down to
+/// this once, when the theme is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    Monochrome,
+}
+
+impl ColorDepth {
+    pub fn from_mode(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Auto => Self::detect(),
+            ColorMode::Truecolor => Self::TrueColor,
+            ColorMode::Ansi256 => Self::Ansi256,
+            ColorMode::Ansi16 => Self::Ansi16,
+            ColorMode::None => Self::Monochrome,
+        }
+    }
+
+    /// Guesses from `NO_COLOR`, `COLORTERM` and `TERM`, erring on the side of fewer colours.
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        Self::from_env(&var("NO_COLOR"), &var("COLORTERM"), &var("TERM"))
+    }
+
+    /// [`detect`](Self::detect) for the given values of `NO_COLOR`, `COLORTERM` and `TERM`.
+    fn from_env(no_color: &str, colorterm: &str, term: &str) -> Self {
+        if !no_color.is_empty() {
+            return Self::Monochrome;
+        }
+        if matches!(colorterm.to_lowercase().as_str(), "truecolor" | "24bit") {
+            return Self::TrueColor;
+        }
+
+        let term = term.to_lowercase();
+        match term.as_str() {
+            "dumb" => Self::Monochrome,
+            // Windows terminals don't set TERM and have handled RGB since Windows 10.
+            "" if cfg!(windows) => Self::TrueColor,
+            _ if term.contains("256color") => Self::Ansi256,
+            _ => Self::Ansi16,
+        }
+    }
+
+    pub fn convert(self, style: Style) -> Style {
+        match self {
+            Self::TrueColor => style,
+            Self::Monochrome => Style { fg: None, bg: None, ..style },
+            Self::Ansi256 | Self::Ansi16 => Style {
+                fg: style.fg.map(|color| self.convert_color(color)),
+                bg: style.bg.map(|color| self.convert_color(color)),
+                ..style
+            },
+        }
+    }
+
+    fn convert_color(self, color: Color) -> Color {
+        let Color::Rgb(r, g, b) = color else {
+            return color;
+        };
+        match self {
+            Self::Ansi256 => to_ansi256(r, g, b),
+            Self::Ansi16 => to_ansi16(r, g, b),
+            Self::TrueColor | Self::Monochrome => color,
+        }
+    }
+}
+
+/// The nearest entry of the xterm 6×6×6 colour cube or its grey ramp.
+fn to_ansi256(r: u8, g: u8, b: u8) -> Color {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |value: u8| {
+        (0..LEVELS.len()).min_by_key(|&i| LEVELS[i].abs_diff(value)).unwrap_or_default()
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)].iter().map(|&(a, b)| (a.abs_diff(b) as u32).pow(2)).sum::<u32>()
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let grey_step = (average.saturating_sub(8) / 10).min(23);
+    let grey = (8 + 10 * grey_step) as u8;
+
+    if distance((grey, grey, grey)) < distance(cube) {
+        Color::Indexed(232 + grey_step as u8)
+    } else {
+        Color::Indexed(cube_index as u8)
+    }
+}
+
+/// Maps by hue rather than by RGB distance: the basic palette is so sparse that the nearest
+/// entry for most pastel colours is plain grey, which would make every role look the same.
+fn to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max as u16 + min as u16) / 2;
+    let chroma = max - min;
+
+    if chroma < 48 {
+        return match lightness {
+            0..=40 => Color::Black,
+            41..=150 => Color::DarkGray,
+            151..=220 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+
+    let (r, g, b, chroma) = (r as f32, g as f32, b as f32, chroma as f32);
+    let hue = if max as f32 == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max as f32 == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+
+    let (normal, bright) = match hue {
+        h if !(30.0..330.0).contains(&h) => (Color::Red, Color::LightRed),
+        h if h < 75.0 => (Color::Yellow, Color::LightYellow),
+        h if h < 150.0 => (Color::Green, Color::LightGreen),
+        h if h < 210.0 => (Color::Cyan, Color::LightCyan),
+        h if h < 260.0 => (Color::Blue, Color::LightBlue),
+        _ => (Color::Magenta, Color::LightMagenta),
+    };
+    if lightness >= 128 { bright } else { normal }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Theme;
+    use ratatui::style::Modifier;
+
+    fn styles(theme: &Theme) -> [(&'static str, Style); 12] {
+        [
+            ("user_message", theme.user_message),
+            ("assistant_message", theme.assistant_message),
+            ("system_message", theme.system_message),
+            ("input_active", theme.input_active),
+            ("input_inactive", theme.input_inactive),
+            ("border", theme.border),
+            ("border_focused", theme.border_focused),
+            ("title", theme.title),
+            ("help_text", theme.help_text),
+            ("error", theme.error),
+            ("success", theme.success),
+            ("link", theme.link),
+        ]
+    }
+
+    #[test]
+    fn dark_themes_stay_legible_in_16_colours() {
+        for (name, theme) in [("default", Theme::default_theme()), ("monokai", Theme::monokai())] {
+            let theme = theme.with_depth(ColorDepth::Ansi16);
+            for (key, style) in styles(&theme) {
+                assert_ne!(style.fg, Some(Color::Black), "{} {}", name, key);
+                assert!(
+                    !matches!(style.fg, Some(Color::Rgb(..) | Color::Indexed(_))),
+                    "{} {}: {:?}",
+                    name,
+                    key,
+                    style.fg
+                );
+            }
+
+            let roles = [
+                ("user_message", theme.user_message),
+                ("assistant_message", theme.assistant_message),
+                ("error", theme.error),
+                ("success", theme.success),
+            ];
+            for (i, (key, style)) in roles.iter().enumerate() {
+                for (other_key, other) in &roles[i + 1..] {
+                    assert_ne!(style, other, "{}: {} and {}", name, key, other_key);
+                }
+            }
+            // The message roles differ in colour, not just in weight.
+            let colours = [theme.user_message.fg, theme.assistant_message.fg, theme.error.fg];
+            for (i, colour) in colours.iter().enumerate() {
+                assert!(!colours[i + 1..].contains(colour), "{}: {:?}", name, colours);
+            }
+        }
+    }
+
+    #[test]
+    fn ansi16_maps_by_hue_and_lightness() {
+        assert_eq!(to_ansi16(170, 30, 30), Color::Red);
+        assert_eq!(to_ansi16(243, 139, 168), Color::LightRed);
+        assert_eq!(to_ansi16(166, 227, 161), Color::LightGreen);
+        assert_eq!(to_ansi16(40, 60, 160), Color::Blue);
+        assert_eq!(to_ansi16(20, 20, 20), Color::Black);
+        assert_eq!(to_ansi16(88, 91, 112), Color::DarkGray);
+        assert_eq!(to_ansi16(250, 250, 250), Color::White);
+    }
+
+    #[test]
+    fn ansi256_picks_the_cube_or_the_grey_ramp() {
+        assert_eq!(to_ansi256(0, 0, 0), Color::Indexed(16));
+        assert_eq!(to_ansi256(255, 255, 255), Color::Indexed(231));
+        assert_eq!(to_ansi256(255, 0, 0), Color::Indexed(196));
+        assert_eq!(to_ansi256(95, 135, 175), Color::Indexed(67));
+        assert_eq!(to_ansi256(128, 128, 128), Color::Indexed(244));
+        assert_eq!(to_ansi256(30, 30, 30), Color::Indexed(234));
+        // Close to grey, but nearer the grey ramp than any cube colour.
+        assert_eq!(to_ansi256(100, 102, 98), Color::Indexed(241));
+    }
+
+    #[test]
+    fn convert_only_changes_rgb_colours() {
+        let style =
+            Style::default().fg(Color::Rgb(255, 0, 0)).bg(Color::Blue).add_modifier(Modifier::BOLD);
+
+        assert_eq!(ColorDepth::TrueColor.convert(style), style);
+        assert_eq!(
+            ColorDepth::Ansi256.convert(style),
+            style.fg(Color::Indexed(196)).bg(Color::Blue)
+        );
+        assert_eq!(ColorDepth::Ansi16.convert(style), style.fg(Color::Red).bg(Color::Blue));
+    }
+
+    #[test]
+    fn monochrome_keeps_modifiers_and_drops_colours() {
+        let style = Style::default()
+            .fg(Color::Rgb(255, 0, 0))
+            .bg(Color::Rgb(0, 0, 0))
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+
+        let converted = ColorDepth::Monochrome.convert(style);
+        assert_eq!(converted.fg, None);
+        assert_eq!(converted.bg, None);
+        assert_eq!(converted.add_modifier, Modifier::BOLD | Modifier::UNDERLINED);
+    }
+
+    #[test]
+    fn detects_the_depth_from_the_environment() {
+        assert_eq!(
+            ColorDepth::from_env("1", "truecolor", "xterm-256color"),
+            ColorDepth::Monochrome
+        );
+        assert_eq!(ColorDepth::from_env("", "truecolor", "xterm"), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env("", "24bit", ""), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env("", "", "xterm-256color"), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_env("", "", "screen-256color"), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_env("", "", "dumb"), ColorDepth::Monochrome);
+        assert_eq!(ColorDepth::from_env("", "", "xterm"), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::from_env("", "yes", "linux"), ColorDepth::Ansi16);
+    }
+}
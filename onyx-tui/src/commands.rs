@@ -0,0 +1,674 @@
+//! The slash commands: what the `/` menu offers, and what typing one runs.
+
+use std::ops::Range;
+
+use onyx_core::Config;
+
+use crate::ui::App;
+
+/// A command typed into the input as `/name [arguments]`.
+pub(crate) struct Command {
+    pub name: &'static str,
+    /// Other names it can be typed as, which the menu doesn't show.
+    pub aliases: &'static [&'static str],
+    pub description: &'static str,
+    /// The argument it takes, as shown in the menu, such as `<title>` or `[name]`.
+    pub argument: Option<&'static str>,
+    /// Runs the command, returning what to show in the chat, if anything.
+    pub run: fn(&mut App, &Args) -> Option<String>,
+}
+
+impl Command {
+    fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    /// How it is typed, as `/name <argument>`.
+    pub fn usage(&self) -> String {
+        match self.argument {
+            Some(argument) => format!("{} {}", self.name, argument),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+/// The words typed after a command's name.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Args {
+    raw: String,
+    words: Vec<String>,
+}
+
+impl Args {
+    pub fn parse(text: &str) -> Self {
+        Self { raw: text.trim().to_string(), words: split_words(text) }
+    }
+
+    /// Everything after the command, as it was typed.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Everything after the command, without the quotes if it is a single quoted string, for
+    /// arguments such as paths that may contain spaces either way.
+    pub fn text(&self) -> &str {
+        match self.words.as_slice() {
+            [word] => word,
+            _ => &self.raw,
+        }
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+}
+
+/// Splits `text` at whitespace, keeping a "quoted" or 'quoted' string together as one word
+/// without its quotes. A quote that isn't closed runs to the end.
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        let mut quote = None;
+        while let Some(&c) = chars.peek() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => word.push(c),
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c.is_whitespace() => break,
+                None => word.push(c),
+            }
+            chars.next();
+        }
+        words.push(word);
+    }
+
+    words
+}
+
+/// Every command, in the order the menu lists them.
+pub(crate) const COMMANDS: &[Command] = &[
+    Command {
+        name: "/help",
+        aliases: &["/?"],
+        description: "Show every key and command",
+        argument: None,
+        run: |app, _| {
+            app.open_help();
+            None
+        },
+    },
+    Command {
+        name: "/config",
+        aliases: &[],
+        description: "Open configuration editor",
+        argument: Some("[field]"),
+        run: |app, args| match args.words().first() {
+            Some(field) => app.open_config_editor_at(field),
+            None => {
+                app.open_config_editor();
+                None
+            }
+        },
+    },
+    Command {
+        name: "/now",
+        aliases: &[],
+        description: "Insert current date and time",
+        argument: None,
+        run: |app, _| inline_text("/now", app.get_config()),
+    },
+    Command {
+        name: "/date",
+        aliases: &[],
+        description: "Insert today's date",
+        argument: None,
+        run: |app, _| inline_text("/date", app.get_config()),
+    },
+    Command {
+        name: "/time",
+        aliases: &[],
+        description: "Insert the current time",
+        argument: None,
+        run: |app, _| inline_text("/time", app.get_config()),
+    },
+    Command {
+        name: "/save",
+        aliases: &[],
+        description: "Save conversation to log file",
+        argument: Some("[path]"),
+        run: |app, args| {
+            Some(match app.save_conversation_log(args.text()) {
+                Ok(filename) => format!("Conversation saved to: {}", filename),
+                Err(e) => format!("Failed to save conversation: {}", e),
+            })
+        },
+    },
+    Command {
+        name: "/export",
+        aliases: &[],
+        description: "Export conversation as Markdown, JSON or HTML",
+        argument: Some("[md|json|html] [path]"),
+        run: |app, args| Some(app.export(args)),
+    },
+    Command {
+        name: "/provider",
+        aliases: &[],
+        description: "Show or switch the active provider",
+        argument: Some("[name]"),
+        run: |app, args| Some(app.switch_provider(args.text())),
+    },
+    Command {
+        name: "/model",
+        aliases: &[],
+        description: "Show or switch the active model",
+        argument: Some("[name]"),
+        run: |app, args| Some(app.switch_model(args.text())),
+    },
+    Command {
+        name: "/theme",
+        aliases: &[],
+        description: "Show or switch the colour theme",
+        argument: Some("[name|reload]"),
+        run: |app, args| Some(app.switch_theme(args.text())),
+    },
+    Command {
+        name: "/search",
+        aliases: &[],
+        description: "Search the conversation",
+        argument: Some("[text]"),
+        run: |app, args| {
+            app.start_search(args.raw());
+            None
+        },
+    },
+    Command {
+        name: "/notifications",
+        aliases: &[],
+        description: "Show recent warnings and errors",
+        argument: None,
+        run: |app, _| Some(app.list_notifications()),
+    },
+    Command {
+        name: "/debug",
+        aliases: &[],
+        description: "Show the end of the debug log",
+        argument: None,
+        run: |app, _| Some(app.show_log_tail()),
+    },
+    Command {
+        name: "/links",
+        aliases: &[],
+        description: "List the links in the last reply",
+        argument: None,
+        run: |app, _| Some(app.list_links()),
+    },
+    Command {
+        name: "/open",
+        aliases: &[],
+        description: "Open a link from the last reply in the browser",
+        argument: Some("<number>"),
+        run: |app, args| app.open_link(args.text()),
+    },
+    Command {
+        name: "/models",
+        aliases: &[],
+        description: "List models available from the active provider",
+        argument: None,
+        run: |app, _| {
+            app.request_models();
+            None
+        },
+    },
+    Command {
+        name: "/stats",
+        aliases: &[],
+        description: "Show usage for this session and all time, or reset it",
+        argument: Some("[reset]"),
+        run: |app, args| Some(app.stats(args.text())),
+    },
+    Command {
+        name: "/pull",
+        aliases: &[],
+        description: "Download a model into Ollama",
+        argument: Some("[model]"),
+        run: |app, args| app.request_pull(args.text()),
+    },
+    Command {
+        name: "/doctor",
+        aliases: &[],
+        description: "Check the config and the connection to the provider",
+        argument: None,
+        run: |app, _| {
+            app.request_doctor();
+            Some("Checking the config and provider...".to_string())
+        },
+    },
+    Command {
+        name: "/remember",
+        aliases: &[],
+        description: "Pin a fact for every conversation",
+        argument: Some("<text>"),
+        run: |app, args| Some(app.remember(args.raw())),
+    },
+    Command {
+        name: "/forget",
+        aliases: &[],
+        description: "List or remove pinned facts",
+        argument: Some("[number]"),
+        run: |app, args| Some(app.forget(args.text())),
+    },
+    Command {
+        name: "/attach",
+        aliases: &[],
+        description: "Attach a file to the next message",
+        argument: Some("[path]"),
+        run: |app, args| app.attach(args.text()),
+    },
+    Command {
+        name: "/detach",
+        aliases: &[],
+        description: "Remove pending attachments",
+        argument: None,
+        run: |app, _| Some(app.detach()),
+    },
+    Command {
+        name: "/edit",
+        aliases: &[],
+        description: "Edit and resend your last message",
+        argument: None,
+        run: |app, _| app.edit_last_message(),
+    },
+    Command {
+        name: "/undo",
+        aliases: &[],
+        description: "Bring back the messages replaced by the last edit",
+        argument: None,
+        run: |app, _| app.undo_edit(),
+    },
+    Command {
+        name: "/new",
+        aliases: &[],
+        description: "Start a new conversation",
+        argument: None,
+        run: |app, _| {
+            Some(match app.clear_chat() {
+                Ok(()) => "Started a new conversation".to_string(),
+                Err(e) => {
+                    format!("Started a new conversation, but the last one wasn't saved: {}", e)
+                }
+            })
+        },
+    },
+    Command {
+        name: "/clear",
+        aliases: &[],
+        description: "Clear the chat, like Ctrl+L",
+        argument: None,
+        run: |app, _| {
+            app.request_clear_chat();
+            None
+        },
+    },
+    Command {
+        name: "/sessions",
+        aliases: &[],
+        description: "Browse saved conversations",
+        argument: None,
+        run: |app, _| {
+            app.open_session_browser();
+            None
+        },
+    },
+    Command {
+        name: "/rename",
+        aliases: &[],
+        description: "Rename this conversation",
+        argument: Some("<title>"),
+        run: |app, args| Some(app.rename_session(args.text())),
+    },
+    Command {
+        name: "/quit",
+        aliases: &["/exit"],
+        description: "Quit Onyx",
+        argument: None,
+        run: |app, _| {
+            app.request_quit();
+            None
+        },
+    },
+];
+
+/// The first line of a custom command's template, which describes it.
+pub(crate) fn template_summary(template: &str) -> &str {
+    template.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default()
+}
+
+/// The text an inline command such as `/now` stands for, in the configured timestamp format.
+/// Completing one from the menu puts this in the input in its place; sent on its own, it is
+/// shown. Typed anywhere else, as in `docs/nowhere.md`, it is left as it is.
+pub(crate) fn inline_text(name: &str, config: &Config) -> Option<String> {
+    let format = match name {
+        "/now" => config.timestamp_format.as_str(),
+        "/date" => config.date_format(),
+        "/time" => config.time_format(),
+        _ => return None,
+    };
+    Some(chrono::Local::now().format(format).to_string())
+}
+
+/// Whether `name` is the name or an alias of a built-in command.
+pub(crate) fn is_built_in(name: &str) -> bool {
+    COMMANDS.iter().any(|command| command.is_named(name))
+}
+
+/// The command `name` stands for: the one with that name or alias, or else the only one whose
+/// name starts with it. Otherwise, explains why there isn't one.
+pub(crate) fn find(name: &str) -> Result<&'static Command, String> {
+    if let Some(command) = COMMANDS.iter().find(|command| command.is_named(name)) {
+        return Ok(command);
+    }
+
+    let matches: Vec<&Command> =
+        COMMANDS.iter().filter(|command| command.name.starts_with(name)).collect();
+    match matches.as_slice() {
+        [command] => Ok(command),
+        [] => Err(format!("Unknown command {} — try /help", name)),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|command| command.name).collect();
+            Err(format!("Ambiguous command {}: did you mean {}?", name, names.join(", ")))
+        }
+    }
+}
+
+/// Runs the command typed as `input`, returning what to show in the chat, if anything. A
+/// custom command has to be typed in full, and one named like a built-in command is hidden by
+/// it.
+pub(crate) fn dispatch(app: &mut App, input: &str) -> Option<String> {
+    let input = input.trim();
+    let (name, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+    let template = app
+        .get_config()
+        .custom_commands()
+        .find(|(custom, _)| custom == name)
+        .map(|(_, template)| template.to_string());
+    match (find(name), template) {
+        (Ok(command), _) if command.is_named(name) => run(app, command, rest),
+        (_, Some(template)) => {
+            tracing::debug!(command = name, "running custom command");
+            let (text, stop) = expand_template(&template, rest.trim());
+            app.fill_input(text, stop);
+            None
+        }
+        (Ok(command), None) => run(app, command, rest),
+        (Err(message), None) => Some(message),
+    }
+}
+
+fn run(app: &mut App, command: &Command, rest: &str) -> Option<String> {
+    tracing::debug!(command = command.name, "running command");
+    (command.run)(app, &Args::parse(rest))
+}
+
+/// Fills the `{input}` and `{selection}` placeholders of a custom command's template with the
+/// text typed after the command, if any. Returns the text along with the first placeholder
+/// left in it, where the cursor stops.
+fn expand_template(template: &str, input: &str) -> (String, Option<Range<usize>>) {
+    let mut text = template.to_string();
+    if !input.is_empty() {
+        text = text.replace("{input}", input).replace("{selection}", input);
+    }
+
+    let stop = text.match_indices('{').find_map(|(start, _)| {
+        let end = start + text[start..].find('}')? + 1;
+        let name = &text[start + 1..end - 1];
+        (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+            .then_some(start..end)
+    });
+    (text, stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::tests::{app_with, input, press, selected, type_text};
+    use crossterm::event::KeyCode;
+    use onyx_core::{Message, ThemeName};
+
+    fn words(text: &str) -> Vec<String> {
+        split_words(text)
+    }
+
+    fn app_with_commands(commands: &[(&str, &str)]) -> App {
+        let custom_commands =
+            commands.iter().map(|(name, template)| (name.to_string(), template.to_string()));
+        app_with(Config { custom_commands: custom_commands.collect(), ..Config::default() })
+    }
+
+    #[test]
+    fn splits_words_at_whitespace() {
+        assert_eq!(words("  one two\tthree  "), ["one", "two", "three"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn keeps_quoted_strings_together() {
+        assert_eq!(words(r#"md "my notes.md""#), ["md", "my notes.md"]);
+        assert_eq!(words("'it is' \"it's\""), ["it is", "it's"]);
+        assert_eq!(words(r#"a"b c"d"#), ["ab cd"]);
+        assert_eq!(words(r#""""#), [""]);
+    }
+
+    #[test]
+    fn an_unclosed_quote_runs_to_the_end() {
+        assert_eq!(words(r#"save "my notes.md"#), ["save", "my notes.md"]);
+    }
+
+    #[test]
+    fn args_keep_the_raw_text() {
+        let args = Args::parse(r#"  "my notes.md"  "#);
+        assert_eq!(args.raw(), r#""my notes.md""#);
+        assert_eq!(args.text(), "my notes.md");
+
+        let args = Args::parse(r#"md "my notes.md""#);
+        assert_eq!(args.text(), r#"md "my notes.md""#);
+        assert_eq!(args.words(), ["md", "my notes.md"]);
+    }
+
+    #[test]
+    fn finds_commands_by_name_alias_or_unique_prefix() {
+        assert_eq!(find("/help").unwrap().name, "/help");
+        assert_eq!(find("/?").unwrap().name, "/help");
+        assert_eq!(find("/exit").unwrap().name, "/quit");
+        assert_eq!(find("/prov").unwrap().name, "/provider");
+        // A full name wins over the longer names it starts.
+        assert_eq!(find("/model").unwrap().name, "/model");
+    }
+
+    #[test]
+    fn explains_unknown_and_ambiguous_commands() {
+        assert_eq!(find("/foo").err().unwrap(), "Unknown command /foo — try /help");
+        let error = find("/de").err().unwrap();
+        assert_eq!(error, "Ambiguous command /de: did you mean /debug, /detach?");
+    }
+
+    #[test]
+    fn every_name_and_alias_is_unique() {
+        let mut names: Vec<&str> = COMMANDS
+            .iter()
+            .flat_map(|command| command.aliases.iter().chain([&command.name]))
+            .copied()
+            .collect();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+        assert!(COMMANDS.iter().all(|command| is_built_in(command.name)));
+    }
+
+    #[test]
+    fn dispatch_reports_unknown_commands_instead_of_sending_them() {
+        let mut app = app_with(Config::default());
+        assert_eq!(
+            dispatch(&mut app, "/foo bar").as_deref(),
+            Some("Unknown command /foo — try /help")
+        );
+    }
+
+    #[test]
+    fn dispatch_passes_the_arguments() {
+        let mut app = app_with(Config::default());
+        let reply = dispatch(&mut app, "/theme   nonsense  ").unwrap();
+        assert!(reply.starts_with("Unknown theme 'nonsense'"), "{}", reply);
+        let reply = dispatch(&mut app, "/th nonsense").unwrap();
+        assert!(reply.starts_with("Unknown theme 'nonsense'"), "{}", reply);
+    }
+
+    #[test]
+    fn dispatch_fills_the_input_from_a_custom_command() {
+        let mut app = app_with_commands(&[("review", "Review this:\n{input}\nFocus on {topic}")]);
+        assert_eq!(dispatch(&mut app, "/review fn main() {}"), None);
+        assert_eq!(input(&app), "Review this:\nfn main() {}\nFocus on {topic}");
+        assert_eq!(selected(&app), Some("{topic}"));
+    }
+
+    #[test]
+    fn custom_commands_must_be_typed_in_full_and_yield_to_built_ins() {
+        let mut app = app_with_commands(&[("review", "Review {input}"), ("/help", "Custom help")]);
+        assert_eq!(dispatch(&mut app, "/rev").as_deref(), Some("Unknown command /rev — try /help"));
+
+        assert_eq!(dispatch(&mut app, "/help"), None);
+        assert!(input(&app).is_empty());
+    }
+
+    #[test]
+    fn templates_keep_placeholders_without_input() {
+        assert_eq!(expand_template("Explain {input}", ""), ("Explain {input}".into(), Some(8..15)));
+        assert_eq!(
+            expand_template("Fix {selection} in {lang}", "x"),
+            ("Fix x in {lang}".into(), Some(9..15))
+        );
+        assert_eq!(expand_template("Use {} or { x }", ""), ("Use {} or { x }".into(), None));
+    }
+
+    fn app_with_stamp() -> App {
+        app_with(Config { timestamp_format: "[stamp]".to_string(), ..Config::default() })
+    }
+
+    #[test]
+    fn inline_commands_expand_when_completed_from_the_menu() {
+        let mut app = app_with_stamp();
+        type_text(&mut app, "meet at /no");
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(input(&app), "meet at [stamp]");
+
+        let mut app = app_with_stamp();
+        type_text(&mut app, "/now");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(input(&app), "[stamp]");
+        assert!(app.take_input().is_none(), "completing an inline command doesn't send");
+    }
+
+    #[test]
+    fn inline_commands_inside_words_or_quotes_are_left_alone() {
+        for text in ["see docs/nowhere.md please", "he said \"/now\" twice", "/now and then"] {
+            let mut app = app_with_stamp();
+            type_text(&mut app, text);
+            assert_eq!(input(&app), text);
+        }
+    }
+
+    #[test]
+    fn an_inline_command_sent_on_its_own_is_shown() {
+        let mut app = app_with_stamp();
+        assert_eq!(dispatch(&mut app, "/now").as_deref(), Some("[stamp]"));
+    }
+
+    fn app_with_conversation(confirm_destructive_actions: bool) -> App {
+        let mut app = app_with(Config { confirm_destructive_actions, ..Config::default() });
+        app.add_message(Message::user("hello"));
+        app.add_message(Message::assistant("hi"));
+        app
+    }
+
+    #[test]
+    fn clear_empties_the_chat() {
+        let mut app = app_with_conversation(false);
+        assert_eq!(dispatch(&mut app, "/clear"), None);
+        assert!(app.history().is_empty());
+    }
+
+    #[test]
+    fn clear_asks_first_when_confirming() {
+        let mut app = app_with_conversation(true);
+        dispatch(&mut app, "/clear");
+        assert_eq!(app.history().len(), 2);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.history().len(), 2);
+
+        dispatch(&mut app, "/clear");
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.history().is_empty());
+    }
+
+    #[test]
+    fn quit_and_its_alias_quit() {
+        for name in ["/quit", "/exit"] {
+            let mut app = app_with(Config::default());
+            assert_eq!(dispatch(&mut app, name), None);
+            assert!(app.should_quit(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn quit_asks_first_with_unsaved_messages() {
+        let mut app = app_with_conversation(true);
+        // The first two were autosaved.
+        app.add_message(Message::user("one more"));
+        dispatch(&mut app, "/quit");
+        assert!(!app.should_quit());
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn theme_switches_and_saves_the_theme() {
+        let path = std::env::temp_dir()
+            .join(format!("onyx-tui-theme-command-{}.json", std::process::id()));
+        let mut app = app_with(Config { config_path: Some(path.clone()), ..Config::default() });
+
+        let reply = dispatch(&mut app, "/theme monokai").unwrap();
+        assert_eq!(reply, "Switched to the monokai theme");
+        assert_eq!(app.get_config().theme, ThemeName::Monokai);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"theme\": \"monokai\""), "{}", saved);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn theme_without_a_name_lists_the_themes() {
+        let mut app = app_with(Config::default());
+        let reply = dispatch(&mut app, "/theme").unwrap();
+        assert!(reply.starts_with("Current theme: default (available: "), "{}", reply);
+        assert!(reply.contains("monokai"), "{}", reply);
+        assert_eq!(app.get_config().theme, ThemeName::Default);
+    }
+
+    #[test]
+    fn the_menu_completes_the_new_commands() {
+        for (typed, command) in [("/cle", "/clear"), ("/qu", "/quit"), ("/them", "/theme")] {
+            let mut app = app_with(Config::default());
+            type_text(&mut app, typed);
+            press(&mut app, KeyCode::Tab);
+            assert!(input(&app).starts_with(command), "{} became {:?}", typed, input(&app));
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use onyx_core::{Config, ConfigSchema, FieldDescriptor, FieldType, FieldValue};
+use std::collections::HashSet;
+use std::time::Instant;
+
+use onyx_core::{
+    Config, ConfigSchema, FieldDescriptor, FieldParseError, FieldType, FieldValue, Provider,
+    mask_secret,
+};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -7,13 +13,39 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation},
 };
 
+use unicode_width::UnicodeWidthStr;
+
 use crate::scroll::ScrollManager;
 use crate::text_input::TextInputState;
 use crate::theme::Theme;
-use crate::widgets::ConfigFieldWidget;
+use crate::widgets::{ConfigFieldWidget, spinner_char, wrap_text};
+
+/// How far the fields of a group are indented under its header.
+const GROUP_INDENT: usize = 2;
+
+/// How many fields PageUp and PageDown move the selection by.
+const PAGE_FIELDS: usize = 10;
+
+/// How long each frame of the connection test's spinner lasts, in milliseconds.
+const SPINNER_FRAME_MS: u128 = 80;
+
+/// A "test connection" run, made with the settings the editor had when it started.
+struct ConnectionTest {
+    id: u64,
+    provider: Provider,
+    /// The section it was started from, where its progress and result are shown.
+    section: String,
+    started: Instant,
+    /// What came of it, once the provider has answered.
+    result: Option<Result<String, String>>,
+}
 
 pub struct ConfigEditor {
     pub config: Config,
+    /// Compared against to mark the fields that haven't been customized.
+    defaults: Config,
+    /// The config as it was last loaded or saved, to tell whether there are edits to lose.
+    saved: Config,
     fields: Vec<FieldDescriptor>,
     sections: Vec<String>,
     selected_index: usize,
@@ -21,6 +53,21 @@ pub struct ConfigEditor {
     input_state: TextInputState,
     pub show_enum_menu: bool,
     pub enum_menu_selected: usize,
+    /// Whether the "reset all to defaults" confirmation is showing.
+    pub confirming_reset: bool,
+    /// What the fields are filtered by, once `/` has been pressed.
+    filter: Option<TextInputState>,
+    /// Whether keys go to the filter rather than the field list.
+    pub filtering: bool,
+    /// The ids of the groups whose fields are hidden, unless a filter is showing them.
+    collapsed: HashSet<String>,
+    /// The secret field shown in the clear, until the selection moves or editing ends.
+    revealed: Option<usize>,
+    connection_test: Option<ConnectionTest>,
+    /// A test waiting to be picked up by [`Self::take_connection_test_request`].
+    test_request: Option<(u64, Config)>,
+    next_test_id: u64,
+    error: Option<String>,
     scroll_manager: ScrollManager,
 }
 
@@ -28,9 +75,18 @@ impl ConfigEditor {
     pub fn new(config: Config) -> Self {
         let sections = Config::sections();
         let fields = Config::fields();
-
-        Self {
+        // Only the active provider's settings start out expanded.
+        let active_group = config.active_provider.field_prefix();
+        let collapsed = fields
+            .iter()
+            .filter(|field| field.is_group && field.id != active_group)
+            .map(|field| field.id.clone())
+            .collect();
+
+        let mut editor = Self {
+            saved: config.clone(),
             config,
+            defaults: Config::default(),
             sections,
             fields,
             selected_index: 0,
@@ -38,8 +94,38 @@ impl ConfigEditor {
             input_state: TextInputState::new(),
             show_enum_menu: false,
             enum_menu_selected: 0,
+            confirming_reset: false,
+            filter: None,
+            filtering: false,
+            collapsed,
+            revealed: None,
+            connection_test: None,
+            test_request: None,
+            next_test_id: 0,
+            error: None,
             scroll_manager: ScrollManager::new(),
+        };
+        editor.select_first_match();
+        editor
+    }
+
+    /// Opens on `field_id` instead of the first field, expanding its group so it is shown. An
+    /// id that isn't a field leaves the selection where it was.
+    pub fn with_focus(mut self, field_id: &str) -> Self {
+        if let Some(index) =
+            self.fields.iter().position(|field| field.id == field_id && !field.is_group)
+        {
+            if let Some(parent) = &self.fields[index].parent_id {
+                self.collapsed.remove(parent);
+            }
+            self.selected_index = index;
         }
+        self
+    }
+
+    /// Whether `field_id` names a field that can be edited, rather than a group or nothing.
+    pub fn has_field(field_id: &str) -> bool {
+        Config::fields().iter().any(|field| field.id == field_id && !field.is_group)
     }
 
     fn current_field(&self) -> &FieldDescriptor {
@@ -55,33 +141,38 @@ impl ConfigEditor {
         field.get_value(&self.config).map(|v| v.as_display_string()).unwrap_or_default()
     }
 
-    fn set_current_value(&mut self, value: String) {
-        let field_id = self.current_field().id.clone();
-        let field_type = self.current_field().field_type;
-        let is_group = self.current_field().is_group;
-
-        if is_group {
-            return;
+    fn set_current_value(&mut self, value: String) -> Result<(), String> {
+        let field = self.current_field().clone();
+        if field.is_group {
+            return Ok(());
         }
 
-        let field_value = FieldValue::from_string(value, field_type);
-        let _ = self.config.set_field(&field_id, field_value);
+        let field_value =
+            FieldValue::from_string(value, &field).map_err(|e: FieldParseError| e.to_string())?;
+        field.validate(&field_value)?;
+        self.config.set_field(&field.id, field_value).map_err(|e| e.to_string())
     }
 
-    pub fn start_editing(&mut self) {
-        let is_group = self.current_field().is_group;
-        let field_type = self.current_field().field_type;
-        let enum_values = self.current_field().enum_values.clone();
+    pub fn has_unsaved_changes(&self) -> bool {
+        !self.config.same_settings(&self.saved)
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = self.config.clone();
+    }
 
-        if is_group {
+    pub fn start_editing(&mut self) {
+        if !self.is_selectable(self.selected_index) || self.is_group_selected() {
             return;
         }
+        let field_type = self.current_field().field_type;
+        let enum_values = self.current_field().enum_values.clone();
 
         self.editing = true;
         let value = self.current_value();
         self.input_state = TextInputState::with_text(value.clone());
 
-        if field_type == FieldType::Enum {
+        if matches!(field_type, FieldType::Enum | FieldType::Bool) {
             self.show_enum_menu = true;
             self.enum_menu_selected = enum_values
                 .iter()
@@ -90,25 +181,156 @@ impl ConfigEditor {
         }
     }
 
+    pub fn is_group_selected(&self) -> bool {
+        self.current_field().is_group
+    }
+
+    /// Expands the selected group if it is collapsed, or collapses it.
+    pub fn toggle_group(&mut self) {
+        let id = self.current_field().id.clone();
+        if self.is_group_selected() && !self.collapsed.remove(&id) {
+            self.collapsed.insert(id);
+        }
+    }
+
+    fn is_collapsed(&self, field: &FieldDescriptor) -> bool {
+        self.filter.is_none() && self.collapsed.contains(&field.id)
+    }
+
+    /// Shows or hides the selected field's value, if it is secret.
+    pub fn toggle_reveal(&mut self) {
+        if !self.current_field().is_secret {
+            return;
+        }
+        self.revealed = match self.revealed {
+            Some(index) if index == self.selected_index => None,
+            _ => Some(self.selected_index),
+        };
+    }
+
+    fn is_masked(&self, field_index: usize) -> bool {
+        self.fields[field_index].is_secret && self.revealed != Some(field_index)
+    }
+
+    /// Asks for the provider of the selected field's section to be tried with the settings as
+    /// they are in the editor, saved or not. Fields outside any provider's section test the
+    /// active provider.
+    pub fn request_connection_test(&mut self) {
+        let field = self.current_field();
+        let provider =
+            Provider::of_field(&field.id).unwrap_or_else(|| self.config.active_provider.clone());
+        let section = field.section.clone();
+
+        let mut config = self.config.clone();
+        config.active_provider = provider.clone();
+        self.next_test_id += 1;
+        self.test_request = Some((self.next_test_id, config));
+        self.connection_test = Some(ConnectionTest {
+            id: self.next_test_id,
+            provider,
+            section,
+            started: Instant::now(),
+            result: None,
+        });
+    }
+
+    /// The config to test, with its id for [`Self::finish_connection_test`], once per request.
+    pub fn take_connection_test_request(&mut self) -> Option<(u64, Config)> {
+        self.test_request.take()
+    }
+
+    /// Records the outcome of test `id`, unless a newer test has started since.
+    pub fn finish_connection_test(&mut self, id: u64, result: Result<String, String>) {
+        if let Some(test) = &mut self.connection_test
+            && test.id == id
+        {
+            test.result = Some(result);
+        }
+    }
+
+    pub fn is_testing_connection(&self) -> bool {
+        self.connection_test.as_ref().is_some_and(|test| test.result.is_none())
+    }
+
+    fn connection_test_line(&self, section: &str, theme: &Theme) -> Option<Line<'static>> {
+        let test = self.connection_test.as_ref().filter(|test| test.section == section)?;
+        Some(match &test.result {
+            None => {
+                let frame = (test.started.elapsed().as_millis() / SPINNER_FRAME_MS) as usize;
+                Line::from(Span::styled(
+                    format!("  {} Testing {}...", spinner_char(frame), test.provider),
+                    theme.help_text,
+                ))
+            }
+            Some(Ok(detail)) => Line::from(Span::styled(
+                format!("  ✓ {} OK ({})", test.provider, detail),
+                theme.success,
+            )),
+            Some(Err(error)) => {
+                Line::from(Span::styled(format!("  ✗ {}: {}", test.provider, error), theme.error))
+            }
+        })
+    }
+
     pub fn cancel_editing(&mut self) {
+        self.revealed = None;
         self.editing = false;
         self.input_state.clear();
         self.show_enum_menu = false;
+        self.error = None;
     }
 
     pub fn save_current_field(&mut self) {
         let field = self.current_field();
 
-        if field.field_type == FieldType::Enum {
-            if self.enum_menu_selected < field.enum_values.len() {
-                let selected_value = field.enum_values[self.enum_menu_selected].clone();
-                self.set_current_value(selected_value);
+        let value = if matches!(field.field_type, FieldType::Enum | FieldType::Bool) {
+            match field.enum_values.get(self.enum_menu_selected) {
+                Some(value) => value.clone(),
+                None => return self.cancel_editing(),
             }
         } else {
-            self.set_current_value(self.input_state.text().to_string());
+            self.input_state.text().to_string()
+        };
+
+        match self.set_current_value(value) {
+            Ok(()) => self.cancel_editing(),
+            Err(e) => self.error = Some(e),
         }
+    }
 
-        self.cancel_editing();
+    /// Saves the field being edited, if any, ahead of saving the whole config. Returns false if
+    /// its value doesn't parse, in which case it stays selected and in editing mode with the
+    /// error under it.
+    pub fn commit_pending_edit(&mut self) -> bool {
+        if self.editing {
+            self.save_current_field();
+        }
+        self.error.is_none()
+    }
+
+    /// Puts the selected field back to its default. Like any edit, it isn't saved until Ctrl+S.
+    pub fn reset_current_field(&mut self) {
+        let field = self.current_field();
+        if self.is_selectable(self.selected_index) {
+            let id = field.id.clone();
+            let _ = self.config.reset_field(&id);
+        }
+    }
+
+    pub fn request_reset_all(&mut self) {
+        self.confirming_reset = true;
+    }
+
+    pub fn answer_reset_all(&mut self, confirmed: bool) {
+        self.confirming_reset = false;
+        if confirmed {
+            let _ = self.config.reset_all();
+        }
+    }
+
+    fn is_default(&self, field: &FieldDescriptor) -> bool {
+        !field.is_group
+            && field.get_value(&self.config).ok() == field.get_value(&self.defaults).ok()
     }
 
     pub fn insert_char(&mut self, c: char) {
@@ -141,49 +363,167 @@ impl ConfigEditor {
     }
 
     pub fn next_field(&mut self) {
-        if self.selected_index < self.fields.len() - 1 {
-            self.selected_index += 1;
-        }
+        self.step_selection(1, true);
     }
 
     pub fn prev_field(&mut self) {
-        if self.selected_index > 0 {
-            self.selected_index -= 1;
+        self.step_selection(1, false);
+    }
+
+    pub fn page_down(&mut self) {
+        self.step_selection(PAGE_FIELDS, true);
+    }
+
+    pub fn page_up(&mut self) {
+        self.step_selection(PAGE_FIELDS, false);
+    }
+
+    pub fn select_first(&mut self) {
+        self.step_selection(self.fields.len(), false);
+        self.scroll_manager.scroll_to_top();
+    }
+
+    pub fn select_last(&mut self) {
+        self.step_selection(self.fields.len(), true);
+        self.scroll_manager.scroll_to_bottom();
+    }
+
+    /// Moves the selection up to `steps` selectable fields forward or back, stopping at the end.
+    fn step_selection(&mut self, steps: usize, forward: bool) {
+        for _ in 0..steps {
+            let next = if forward {
+                (self.selected_index + 1..self.fields.len())
+                    .find(|&index| self.is_selectable(index))
+            } else {
+                (0..self.selected_index).rev().find(|&index| self.is_selectable(index))
+            };
+            match next {
+                Some(index) => self.selected_index = index,
+                None => break,
+            }
         }
     }
 
-    pub fn enum_menu_up(&mut self) {
-        if self.enum_menu_selected > 0 {
-            self.enum_menu_selected -= 1;
+    /// Selects the first field of the next section that has any shown.
+    pub fn next_section(&mut self) {
+        let section = &self.current_field().section;
+        if let Some(next) = (self.selected_index + 1..self.fields.len())
+            .find(|&index| self.fields[index].section != *section && self.is_selectable(index))
+        {
+            self.selected_index = next;
         }
     }
 
-    pub fn enum_menu_down(&mut self) {
-        let field = self.current_field();
-        let enum_count = field.enum_values.len();
-        if self.enum_menu_selected < enum_count.saturating_sub(1) {
-            self.enum_menu_selected += 1;
+    /// Selects the first shown field of the current section, or of the one before if that is
+    /// already selected.
+    pub fn prev_section(&mut self) {
+        let first_in = |editor: &Self, section: &str| {
+            (0..editor.fields.len()).find(|&index| {
+                editor.fields[index].section == section && editor.is_selectable(index)
+            })
+        };
+        let section = self.current_field().section.clone();
+        match first_in(self, &section) {
+            Some(first) if first < self.selected_index => self.selected_index = first,
+            _ => {
+                let prev = (0..self.selected_index)
+                    .rev()
+                    .find(|&index| self.is_selectable(index))
+                    .map(|index| self.fields[index].section.clone());
+                if let Some(first) = prev.and_then(|prev| first_in(self, &prev)) {
+                    self.selected_index = first;
+                }
+            }
+        }
+    }
+
+    pub fn start_filter(&mut self) {
+        self.filter.get_or_insert_with(TextInputState::new);
+        self.filtering = true;
+    }
+
+    /// Applies `edit` to the filter text, then moves the selection to the first match if the
+    /// selected field no longer is one.
+    pub fn edit_filter(&mut self, edit: impl FnOnce(&mut TextInputState)) {
+        if let Some(filter) = &mut self.filter {
+            edit(filter);
+        }
+        if !self.is_selectable(self.selected_index) {
+            self.select_first_match();
+        }
+    }
+
+    /// Stops typing into the filter and selects its first match, leaving the list filtered.
+    pub fn confirm_filter(&mut self) {
+        self.filtering = false;
+        if self.filter.as_ref().is_some_and(TextInputState::is_empty) {
+            self.filter = None;
         }
+        self.select_first_match();
     }
 
-    pub fn scroll_up(&mut self) {
-        self.scroll_manager.scroll_up(1);
+    /// Drops the filter, expanding the group of the selected field so that it stays in view.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+        self.filtering = false;
+        if let Some(parent) = &self.fields[self.selected_index].parent_id {
+            self.collapsed.remove(parent);
+        }
     }
 
-    pub fn scroll_down(&mut self) {
-        self.scroll_manager.scroll_down(1);
+    pub fn has_filter(&self) -> bool {
+        self.filter.is_some()
     }
 
-    pub fn scroll_page_up(&mut self) {
-        self.scroll_manager.scroll_page_up();
+    fn select_first_match(&mut self) {
+        if let Some(first) = (0..self.fields.len()).find(|&index| self.is_selectable(index)) {
+            self.selected_index = first;
+        }
     }
 
-    pub fn scroll_page_down(&mut self) {
-        self.scroll_manager.scroll_page_down();
+    /// Whether field `index` can be selected: it is shown, and isn't a group header while
+    /// filtering, when every group is expanded.
+    fn is_selectable(&self, index: usize) -> bool {
+        self.is_visible(index) && !(self.fields[index].is_group && self.filter.is_some())
     }
 
-    pub fn scroll_to_top(&mut self) {
-        self.scroll_manager.scroll_to_top();
+    /// Whether field `index` is shown. With a filter, that is when it matches, or for a group
+    /// when any of its fields do. Without one, it is every field outside a collapsed group.
+    fn is_visible(&self, index: usize) -> bool {
+        let field = &self.fields[index];
+        if self.filter.is_none() {
+            return field.parent_id.as_ref().is_none_or(|parent| !self.collapsed.contains(parent));
+        }
+        self.matches_filter(field)
+            || field.is_group
+                && self.fields.iter().any(|child| {
+                    child.parent_id.as_ref() == Some(&field.id) && self.matches_filter(child)
+                })
+    }
+
+    /// Whether `field` matches the filter by label, id or section, ignoring case.
+    fn matches_filter(&self, field: &FieldDescriptor) -> bool {
+        let Some(query) = self.filter.as_ref().map(|filter| filter.text().trim().to_lowercase())
+        else {
+            return true;
+        };
+        [&field.label, &field.id, &field.section]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+
+    pub fn enum_menu_up(&mut self) {
+        if self.enum_menu_selected > 0 {
+            self.enum_menu_selected -= 1;
+        }
+    }
+
+    pub fn enum_menu_down(&mut self) {
+        let field = self.current_field();
+        let enum_count = field.enum_values.len();
+        if self.enum_menu_selected < enum_count.saturating_sub(1) {
+            self.enum_menu_selected += 1;
+        }
     }
 
     pub fn render(
@@ -214,17 +554,30 @@ impl ConfigEditor {
         let inner = block.inner(dialog_area);
         frame.render_widget(block, dialog_area);
 
+        let filter_height = if self.filter.is_some() { 2 } else { 0 };
+        let hint = self.hint_lines(inner.width as usize);
+        let hint_height = if hint.is_empty() { 0 } else { hint.len() as u16 + 1 };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(3)])
+            .constraints([
+                Constraint::Length(filter_height),
+                Constraint::Min(1),
+                Constraint::Length(hint_height),
+                Constraint::Length(4),
+            ])
             .split(inner);
 
-        self.render_fields(frame, chunks[0], theme, terminal_cursor);
-        self.render_footer(frame, chunks[1], theme);
+        self.render_filter(frame, chunks[0], theme, terminal_cursor);
+        self.render_fields(frame, chunks[1], theme, terminal_cursor);
+        self.render_hint(frame, chunks[2], hint, theme);
+        self.render_footer(frame, chunks[3], theme);
 
         if self.show_enum_menu {
             self.render_enum_menu(frame, dialog_area, theme);
         }
+        if self.confirming_reset {
+            self.render_reset_confirmation(frame, dialog_area, theme);
+        }
     }
 
     fn render_fields(
@@ -239,54 +592,100 @@ impl ConfigEditor {
         let mut current_line: usize = 0;
         let mut cursor_position: Option<(u16, u16)> = None;
 
+        // Consecutive collapsed groups are listed without blank lines between them.
+        let mut after_collapsed_group = false;
         for section in &self.sections {
-            if !lines.is_empty() {
+            let shown: Vec<usize> = (0..self.fields.len())
+                .filter(|&index| &self.fields[index].section == section && self.is_visible(index))
+                .collect();
+            let Some(&first) = shown.first() else {
+                continue;
+            };
+            let group = Some(&self.fields[first]).filter(|field| field.is_group);
+            let collapsed = group.is_some_and(|group| self.is_collapsed(group));
+
+            let listed = after_collapsed_group && collapsed;
+            if !lines.is_empty() && !listed {
                 lines.push(Line::from(""));
                 current_line += 1;
             }
-            lines.push(Line::from(Span::styled(
-                format!("═══ {} ═══", section),
-                theme.title.add_modifier(Modifier::BOLD),
-            )));
+            after_collapsed_group = collapsed;
+
+            // A group's header stands in for the section's.
+            match group {
+                Some(group) => {
+                    let is_selected = first == self.selected_index;
+                    if is_selected {
+                        selected_line = current_line;
+                    }
+                    lines.push(self.group_header(group, is_selected, theme));
+                }
+                None => lines.push(Line::from(Span::styled(
+                    format!("═══ {} ═══", section),
+                    theme.title.add_modifier(Modifier::BOLD),
+                ))),
+            }
             current_line += 1;
+            if let Some(line) = self.connection_test_line(section, theme) {
+                lines.push(line);
+                current_line += 1;
+            }
+            if collapsed {
+                continue;
+            }
             lines.push(Line::from(""));
             current_line += 1;
 
-            for field in &self.fields {
-                if &field.section == section {
-                    let field_index =
-                        self.fields.iter().position(|f| f.id == field.id).unwrap_or(0);
-                    let is_selected = field_index == self.selected_index;
-                    let is_editing = is_selected && self.editing && !self.show_enum_menu;
+            for field_index in shown {
+                let field = &self.fields[field_index];
+                if field.is_group {
+                    continue;
+                }
+                let is_selected = field_index == self.selected_index;
+                let is_editing = is_selected && self.editing && !self.show_enum_menu;
 
-                    if is_selected {
-                        selected_line = current_line;
-                    }
+                if is_selected {
+                    selected_line = current_line;
+                }
 
-                    let display_value = if is_editing {
-                        self.input_state.text().to_string()
-                    } else {
-                        self.get_display_value(field)
-                    };
-
-                    let widget = ConfigFieldWidget::new(
-                        field.label.clone(),
-                        display_value,
-                        is_selected,
-                        is_editing,
-                        self.input_state.cursor_position(),
-                        theme,
-                    );
-
-                    lines.push(widget.render());
-
-                    if is_editing {
-                        let line_in_viewport =
-                            current_line.saturating_sub(self.scroll_manager.position());
-                        cursor_position =
-                            widget.get_cursor_position(area, area.y + line_in_viewport as u16);
-                    }
+                // A masked value shows one `*` per character typed, each one byte long.
+                let text = self.input_state.text();
+                let cursor = self.input_state.cursor_position();
+                let (display_value, cursor) = if !is_editing {
+                    (self.get_display_value(field, self.is_masked(field_index)), cursor)
+                } else if self.is_masked(field_index) {
+                    ("*".repeat(text.chars().count()), text[..cursor].chars().count())
+                } else {
+                    (text.to_string(), cursor)
+                };
+
+                let widget = ConfigFieldWidget::new(
+                    field.label.clone(),
+                    display_value,
+                    is_selected,
+                    is_editing,
+                    cursor,
+                    theme,
+                )
+                .at_default(self.is_default(field))
+                .indented(if field.parent_id.is_some() { GROUP_INDENT } else { 0 });
+
+                lines.push(widget.render());
+
+                if is_editing {
+                    let line_in_viewport =
+                        current_line.saturating_sub(self.scroll_manager.position());
+                    cursor_position =
+                        widget.get_cursor_position(area, area.y + line_in_viewport as u16);
+                }
+
+                current_line += 1;
 
+                if is_selected && let Some(error) = &self.error {
+                    lines.push(Line::from(Span::styled(
+                        format!("    ✗ {} {}", field.label, error),
+                        theme.error,
+                    )));
                     current_line += 1;
                 }
             }
@@ -315,7 +714,33 @@ impl ConfigEditor {
         }
     }
 
-    fn get_display_value(&self, field: &FieldDescriptor) -> String {
+    /// The line a group is headed by: its name, with an arrow showing whether its fields are
+    /// expanded, and how many there are while they aren't.
+    fn group_header(
+        &self,
+        group: &FieldDescriptor,
+        is_selected: bool,
+        theme: &Theme,
+    ) -> Line<'static> {
+        let prefix = if is_selected { "▶ " } else { "  " };
+        let style = if is_selected {
+            theme.input_active.add_modifier(Modifier::BOLD)
+        } else {
+            theme.title.add_modifier(Modifier::BOLD)
+        };
+
+        if !self.is_collapsed(group) {
+            return Line::from(Span::styled(format!("{}▾ {}", prefix, group.label), style));
+        }
+        let count =
+            self.fields.iter().filter(|field| field.parent_id.as_ref() == Some(&group.id)).count();
+        Line::from(vec![
+            Span::styled(format!("{}▸ {}", prefix, group.label), style),
+            Span::styled(format!("  ({} fields)", count), theme.help_text),
+        ])
+    }
+
+    fn get_display_value(&self, field: &FieldDescriptor, masked: bool) -> String {
         let value = field
             .get_value(&self.config)
             .ok()
@@ -326,35 +751,109 @@ impl ConfigEditor {
                     .find(|ev| ev.to_lowercase() == s.to_lowercase())
                     .cloned()
                     .unwrap_or_else(|| s.clone()),
-                FieldValue::OptionalString(Some(s)) if field.id.contains("api_key") => {
-                    Self::mask_api_key(s)
-                }
+                FieldValue::OptionalString(Some(s)) if masked => mask_secret(s),
                 FieldValue::OptionalString(Some(s)) => s.clone(),
                 FieldValue::OptionalString(None) => String::new(),
                 FieldValue::String(s) => s.clone(),
                 FieldValue::U64(n) => n.to_string(),
+                FieldValue::OptionalU64(_)
+                | FieldValue::F64(_)
+                | FieldValue::OptionalF64(_)
+                | FieldValue::Bool(_) => v.as_display_string(),
             })
             .unwrap_or_default();
 
         if value.is_empty() { "(empty)".to_string() } else { value }
     }
 
-    fn mask_api_key(key: &str) -> String {
-        if key.is_empty() {
-            return String::new();
+    fn render_filter(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        theme: &Theme,
+        terminal_cursor: &crate::cursor::TerminalCursor,
+    ) {
+        let Some(filter) = &self.filter else {
+            return;
+        };
+
+        let style = if self.filtering { theme.input_active } else { theme.help_text };
+        let mut spans = vec![
+            Span::styled("/ ", theme.help_text),
+            Span::styled(filter.text().to_string(), style),
+        ];
+        if !(0..self.fields.len()).any(|index| self.is_visible(index)) {
+            spans.push(Span::styled("  (no matching fields)", theme.help_text));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+
+        if self.filtering && terminal_cursor.is_visible() {
+            let typed = filter.text()[..filter.cursor_position()].width();
+            frame.set_cursor_position((area.x + 2 + typed as u16, area.y));
+        }
+    }
+
+    /// What the selected field is for, wrapped to `width`, or how to work the menu while an
+    /// enum value is being picked. At most three lines, so the field list keeps its room.
+    fn hint_lines(&self, width: usize) -> Vec<String> {
+        const MAX_LINES: usize = 3;
+
+        let field = self.current_field();
+        let hint = if self.show_enum_menu {
+            "↑/↓ to choose a value, Enter to pick it, Esc to keep the current one"
+        } else if field.is_group || !self.is_visible(self.selected_index) {
+            ""
+        } else {
+            field.hint.as_str()
+        };
+
+        let mut lines = wrap_text(hint.trim(), width.max(1));
+        lines.retain(|line| !line.is_empty());
+        lines.truncate(MAX_LINES);
+        lines
+    }
+
+    fn render_hint(&self, frame: &mut Frame, area: Rect, hint: Vec<String>, theme: &Theme) {
+        if hint.is_empty() {
+            return;
         }
-        let len = key.len();
-        if len <= 8 { "*".repeat(len) } else { format!("{}...{}", &key[..4], &key[len - 4..]) }
+        let style = theme.help_text.add_modifier(Modifier::ITALIC);
+        let lines: Vec<Line> =
+            hint.into_iter().map(|line| Line::from(Span::styled(line, style))).collect();
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::TOP).border_style(theme.border));
+        frame.render_widget(paragraph, area);
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
-        let hints = if self.editing {
-            "[Enter] Save  [Esc] Cancel  [←/→] Move cursor"
+        let secret = self.current_field().is_secret;
+        let first_line = if self.is_group_selected() {
+            "[↑/↓] Select  [PgUp/PgDn/Home/End] Jump  [Enter] Toggle  [Ctrl+S] Save  \
+             [Esc] Close"
         } else {
-            "[↑/↓] Scroll  [Tab/Shift+Tab] Navigate fields  [Enter] Edit  [Ctrl+S] Save  [Esc] Close"
+            "[↑/↓] Select  [PgUp/PgDn/Home/End] Jump  [Enter] Edit  [Ctrl+S] Save  [Esc] Close"
+        };
+        let hints = if self.editing && secret {
+            vec!["[Enter] Save  [Esc] Cancel  [←/→] Move cursor  [Ctrl+T] Show/hide"]
+        } else if self.editing {
+            vec!["[Enter] Save  [Esc] Cancel  [←/→] Move cursor"]
+        } else if self.filtering {
+            vec!["[Enter] Go to first match  [Esc] Clear filter"]
+        } else {
+            vec![
+                first_line,
+                "[/] Filter  [←/→] Sections  [Ctrl+R] Reset field  [Ctrl+Shift+R] Reset all",
+                if secret {
+                    "[Ctrl+K] Test connection  [Ctrl+T] Show/hide key"
+                } else {
+                    "[Ctrl+K] Test connection"
+                },
+            ]
         };
 
-        let footer = Paragraph::new(Line::from(Span::styled(hints, theme.help_text)))
+        let lines: Vec<Line> =
+            hints.into_iter().map(|hint| Line::from(Span::styled(hint, theme.help_text))).collect();
+        let footer = Paragraph::new(lines)
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::TOP).border_style(theme.border));
 
@@ -398,4 +897,176 @@ impl ConfigEditor {
         let paragraph = Paragraph::new(lines);
         frame.render_widget(paragraph, inner);
     }
+
+    fn render_reset_confirmation(&self, frame: &mut Frame, parent_area: Rect, theme: &Theme) {
+        let width = 44;
+        let height = 5;
+        let area = Rect {
+            x: parent_area.x + parent_area.width.saturating_sub(width) / 2,
+            y: parent_area.y + parent_area.height.saturating_sub(height) / 2,
+            width: width.min(parent_area.width),
+            height: height.min(parent_area.height),
+        };
+
+        frame.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.error)
+            .title(Span::styled(" Reset All ", theme.title));
+
+        let lines = vec![
+            Line::from("Reset every field to its default?"),
+            Line::from(""),
+            Line::from(Span::styled("[y] Reset  [n] Keep", theme.help_text)),
+        ];
+        let paragraph = Paragraph::new(lines).alignment(Alignment::Center).block(block);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::TerminalCursor;
+    use crate::ui::tests::screen_text;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn draw(editor: &mut ConfigEditor, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let theme = Theme::default();
+        terminal
+            .draw(|frame| editor.render(frame, frame.area(), &theme, &TerminalCursor::default()))
+            .unwrap();
+        screen_text(terminal.backend().buffer())
+    }
+
+    fn rows(screen: &str, range: std::ops::Range<usize>) -> Vec<&str> {
+        screen.lines().skip(range.start).take(range.len()).collect()
+    }
+
+    #[test]
+    fn the_hint_of_the_selected_field_sits_above_the_keys() {
+        let mut editor = ConfigEditor::new(Config::default()).with_focus("timestamp_format");
+        let screen = draw(&mut editor, 60, 16);
+        assert_eq!(
+            rows(&screen, 8..12),
+            [
+                "│▶ Timestamp Format       : %Y-%m-%d %H:%M:%S (default)   ↓│",
+                "│──────────────────────────────────────────────────────────│",
+                "│strftime format (e.g., %Y-%m-%d %H:%M:%S)                 │",
+                "│──────────────────────────────────────────────────────────│",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_hint_wraps_in_a_narrow_dialog() {
+        let mut editor = ConfigEditor::new(Config::default()).with_focus("timestamp_format");
+        let screen = draw(&mut editor, 40, 10);
+        assert_eq!(
+            rows(&screen, 2..6),
+            [
+                "│──────────────────────────────────────│",
+                "│strftime format (e.g., %Y-%m-%d       │",
+                "│%H:%M:%S)                             │",
+                "│──────────────────────────────────────│",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_hint_follows_the_selection() {
+        let mut editor = ConfigEditor::new(Config::default()).with_focus("timestamp_format");
+        editor.prev_field();
+        editor.prev_field();
+        let screen = draw(&mut editor, 90, 30);
+        assert!(
+            screen.contains("│Name shown on replies (leave empty to show the model's name)"),
+            "{}",
+            screen
+        );
+        assert!(!screen.contains("strftime"), "{}", screen);
+    }
+
+    #[test]
+    fn picking_an_enum_value_explains_the_keys() {
+        let mut editor = ConfigEditor::new(Config::default()).with_focus("history_strategy");
+        editor.start_editing();
+        assert_eq!(
+            editor.hint_lines(40),
+            ["↑/↓ to choose a value, Enter to pick it,", "Esc to keep the current one"]
+        );
+    }
+
+    #[test]
+    fn the_hint_is_at_most_three_lines() {
+        let editor = ConfigEditor::new(Config::default()).with_focus("tools_enabled");
+        assert_eq!(
+            editor.hint_lines(100),
+            ["Let the assistant list and read files under the working directory"]
+        );
+        let lines = editor.hint_lines(10);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.width() <= 10), "{:?}", lines);
+    }
+
+    /// Sizes from the smallest the app draws at up to a roomy one.
+    const SIZES: [(u16, u16); 4] = [(40, 10), (40, 12), (60, 16), (90, 30)];
+
+    /// Whether `screen` shows the selected field or group, marked as selected.
+    fn selected_row(editor: &ConfigEditor, screen: &str) -> bool {
+        let label: String = editor.current_field().label.chars().take(12).collect();
+        screen.lines().any(|line| line.contains("▶ ") && line.contains(&label))
+    }
+
+    #[test]
+    fn a_focused_field_is_on_screen_in_small_terminals() {
+        let last = Config::fields().into_iter().rfind(|field| !field.is_group).unwrap().id;
+        for id in ["anthropic_api_key", "system_prompt", "timestamp_format", last.as_str()] {
+            for (width, height) in SIZES {
+                let mut editor = ConfigEditor::new(Config::default()).with_focus(id);
+                assert_eq!(editor.current_field().id, id);
+                let screen = draw(&mut editor, width, height);
+                assert!(
+                    selected_row(&editor, &screen),
+                    "{} at {}x{}:\n{}",
+                    id,
+                    width,
+                    height,
+                    screen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_selection_stays_on_screen_going_down_and_back_up() {
+        for (width, height) in SIZES {
+            let mut editor = ConfigEditor::new(Config::default());
+            for _ in 0..editor.fields.len() {
+                editor.next_field();
+                let screen = draw(&mut editor, width, height);
+                assert!(selected_row(&editor, &screen), "{}x{}:\n{}", width, height, screen);
+            }
+            for _ in 0..editor.fields.len() {
+                editor.prev_field();
+                let screen = draw(&mut editor, width, height);
+                assert!(selected_row(&editor, &screen), "{}x{}:\n{}", width, height, screen);
+            }
+        }
+    }
+
+    #[test]
+    fn paging_keeps_the_selection_on_screen() {
+        let mut editor = ConfigEditor::new(Config::default());
+        for _ in 0..4 {
+            editor.page_down();
+            let screen = draw(&mut editor, 40, 10);
+            assert!(selected_row(&editor, &screen), "{}", screen);
+        }
+        editor.page_up();
+        let screen = draw(&mut editor, 40, 10);
+        assert!(selected_row(&editor, &screen), "{}", screen);
+    }
 }
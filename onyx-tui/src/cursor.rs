@@ -9,8 +9,11 @@ use ratatui::{
     widgets::{Block, Borders},
 };
 use std::io::stdout;
-use std::time::Instant;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use onyx_core::CursorStyle;
 
@@ -24,29 +27,47 @@ pub type Result<T> = std::result::Result<T, CursorError>;
 
 pub struct TerminalCursor {
     style: CursorStyle,
-    blink_interval_ms: u128,
+    blink_interval: Duration,
     visible: bool,
     last_blink_time: Instant,
     last_activity_time: Instant,
     needs_apply: bool,
+    /// Set while the terminal is out of focus, when the cursor stays shown instead of blinking.
+    paused: bool,
 }
 
 impl TerminalCursor {
     pub fn new(style: CursorStyle, blink_interval_ms: u64) -> Self {
         Self {
             style,
-            blink_interval_ms: blink_interval_ms as u128,
+            blink_interval: Duration::from_millis(blink_interval_ms),
             visible: true,
             last_blink_time: Instant::now(),
             last_activity_time: Instant::now(),
             needs_apply: true,
+            paused: false,
         }
     }
 
+    /// Stops or restarts the blinking, as the terminal loses or regains focus.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.on_activity();
+    }
+
+    fn is_blinking(&self) -> bool {
+        self.style.is_blinking() && !self.paused
+    }
+
     pub fn is_visible(&self) -> bool {
         self.visible
     }
 
+    /// Whether the cursor changed since it was last applied, and so needs drawing again.
+    pub fn needs_apply(&self) -> bool {
+        self.needs_apply
+    }
+
     pub fn on_activity(&mut self) {
         self.last_activity_time = Instant::now();
         if !self.visible {
@@ -56,7 +77,7 @@ impl TerminalCursor {
     }
 
     pub fn update(&mut self) {
-        if !self.style.is_blinking() {
+        if !self.is_blinking() {
             if !self.visible {
                 self.visible = true;
                 self.needs_apply = true;
@@ -65,9 +86,8 @@ impl TerminalCursor {
         }
 
         let now = Instant::now();
-        let time_since_activity = now.duration_since(self.last_activity_time).as_millis();
 
-        if time_since_activity < self.blink_interval_ms {
+        if now.duration_since(self.last_activity_time) < self.blink_interval {
             if !self.visible {
                 self.visible = true;
                 self.needs_apply = true;
@@ -76,15 +96,34 @@ impl TerminalCursor {
             return;
         }
 
-        let elapsed = now.duration_since(self.last_blink_time).as_millis();
-
-        if elapsed >= self.blink_interval_ms {
+        let elapsed = now.duration_since(self.last_blink_time);
+        if elapsed >= self.blink_interval {
             self.visible = !self.visible;
-            self.last_blink_time = now;
             self.needs_apply = true;
+            // Keep to the interval's rhythm even if this frame came late, unless it came so late
+            // that catching up would flicker.
+            self.last_blink_time = if elapsed < self.blink_interval * 2 {
+                self.last_blink_time + self.blink_interval
+            } else {
+                now
+            };
         }
     }
 
+    /// How long until the cursor next blinks, so the event loop can wake up in time to draw it.
+    /// `None` when it doesn't blink, or is paused.
+    pub fn time_until_next_blink(&self) -> Option<Duration> {
+        if !self.is_blinking() {
+            return None;
+        }
+
+        let since_activity = self.last_activity_time.elapsed();
+        if since_activity < self.blink_interval {
+            return Some(self.blink_interval - since_activity);
+        }
+        Some(self.blink_interval.saturating_sub(self.last_blink_time.elapsed()))
+    }
+
     pub fn apply(&mut self) -> Result<()> {
         if !self.needs_apply {
             return Ok(());
@@ -119,31 +158,63 @@ pub struct CursorPosition {
 }
 
 impl CursorPosition {
+    /// Where the cursor at `cursor_index` shows in `text` laid out by [`wrap_rows`] and scrolled
+    /// down by `scroll` rows. `None` when that row is scrolled out of view.
     pub fn calculate(
         text: &str,
         cursor_index: usize,
         area: Rect,
         has_border: bool,
+        scroll: u16,
     ) -> Option<Self> {
         let inner =
             if has_border { Block::default().borders(Borders::ALL).inner(area) } else { area };
 
-        let text_before_cursor = if cursor_index == 0 {
-            ""
-        } else if cursor_index >= text.len() {
-            text
-        } else {
-            &text[..cursor_index]
-        };
+        let cursor_index = cursor_index.min(text.len());
+        let rows = wrap_rows(text, inner.width as usize);
+        let row = cursor_row(&rows, cursor_index);
+        let visible_row = (row as u16).checked_sub(scroll).filter(|&row| row < inner.height)?;
 
-        let visual_width = text_before_cursor.chars().count();
+        let visual_width = text[rows[row].start..cursor_index].width();
         let cursor_x = inner.x + visual_width as u16;
-        let cursor_y = inner.y;
+        let cursor_y = inner.y + visible_row;
 
         Some(Self { x: cursor_x, y: cursor_y })
     }
 }
 
+/// Splits `text` into rows at most `width` columns wide, as byte ranges, breaking between any
+/// two graphemes so that every character stays where the cursor math puts it. A full last row
+/// is followed by an empty one, where the cursor goes after it.
+pub fn wrap_rows(text: &str, width: usize) -> Vec<Range<usize>> {
+    if width == 0 {
+        return std::iter::once(0..text.len()).collect();
+    }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut row_width = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        if row_width > 0 && row_width + grapheme_width > width {
+            rows.push(start..i);
+            start = i;
+            row_width = 0;
+        }
+        row_width += grapheme_width;
+    }
+    rows.push(start..text.len());
+    if row_width >= width {
+        rows.push(text.len()..text.len());
+    }
+    rows
+}
+
+/// The index of the row in `rows` holding the cursor at `cursor_index`.
+pub fn cursor_row(rows: &[Range<usize>], cursor_index: usize) -> usize {
+    rows.iter().rposition(|row| row.start <= cursor_index).unwrap_or(0)
+}
+
 pub struct InlineCursor {
     style: CursorStyle,
 }
@@ -170,3 +241,68 @@ impl Default for InlineCursor {
         Self::new(CursorStyle::Block)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(text: &str, width: usize) -> Vec<&str> {
+        wrap_rows(text, width).into_iter().map(|row| &text[row]).collect()
+    }
+
+    fn position(text: &str, cursor_index: usize, width: u16) -> (u16, u16) {
+        let area = Rect::new(0, 0, width, 10);
+        let position = CursorPosition::calculate(text, cursor_index, area, false, 0).unwrap();
+        (position.x, position.y)
+    }
+
+    #[test]
+    fn wide_characters_move_to_the_next_row_whole() {
+        assert_eq!(rows("日本語", 4), ["日本", "語"]);
+        assert_eq!(rows("a日本", 4), ["a日", "本"]);
+        assert_eq!(rows("ab😀cd", 3), ["ab", "😀c", "d"]);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_letter() {
+        assert_eq!(rows("cafe\u{301}s", 4), ["cafe\u{301}", "s"]);
+        assert_eq!(rows("e\u{301}e\u{301}e\u{301}", 2), ["e\u{301}e\u{301}", "e\u{301}"]);
+    }
+
+    #[test]
+    fn emoji_sequences_are_never_split() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let text = format!("a{family}b");
+        assert_eq!(rows(&text, 2), ["a", family, "b"]);
+    }
+
+    #[test]
+    fn a_full_last_row_is_followed_by_an_empty_one() {
+        assert_eq!(rows("日本", 4), ["日本", ""]);
+        assert_eq!(rows("日本", 5), ["日本"]);
+        assert_eq!(rows("", 4), [""]);
+    }
+
+    #[test]
+    fn a_character_wider_than_the_row_gets_a_row_of_its_own() {
+        assert_eq!(rows("a日b", 1), ["a", "日", "b", ""]);
+    }
+
+    #[test]
+    fn the_cursor_counts_columns_not_bytes() {
+        let text = "a日😀e\u{301}b";
+        assert_eq!(position(text, 0, 20), (0, 0));
+        assert_eq!(position(text, "a".len(), 20), (1, 0));
+        assert_eq!(position(text, "a日".len(), 20), (3, 0));
+        assert_eq!(position(text, "a日😀".len(), 20), (5, 0));
+        assert_eq!(position(text, "a日😀e\u{301}".len(), 20), (6, 0));
+    }
+
+    #[test]
+    fn the_cursor_follows_wrapped_wide_characters() {
+        let text = "日本語";
+        assert_eq!(position(text, "日本".len(), 4), (0, 1));
+        assert_eq!(position(text, text.len(), 4), (2, 1));
+        assert_eq!(position("日本", "日本".len(), 4), (0, 1));
+    }
+}
@@ -0,0 +1,195 @@
+use onyx_core::fs::{Entry, format_size, read_directory};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation},
+};
+use std::path::{Path, PathBuf};
+
+use crate::scroll::ScrollManager;
+use crate::theme::Theme;
+
+/// Browses the working directory for a file to attach. Paths are kept relative so they read
+/// the same in the chat as when typed into `/attach`.
+pub struct FilePicker {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    pub error: Option<String>,
+    scroll_manager: ScrollManager,
+}
+
+impl FilePicker {
+    pub fn new() -> Self {
+        let mut picker = Self {
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            selected: 0,
+            error: None,
+            scroll_manager: ScrollManager::new(),
+        };
+        picker.open(PathBuf::new());
+        picker
+    }
+
+    /// Switches to `dir`, staying put with an error message if it can't be read.
+    fn open(&mut self, dir: PathBuf) {
+        let path = if dir.as_os_str().is_empty() { Path::new(".") } else { dir.as_path() };
+
+        match read_directory(path) {
+            Ok(entries) => {
+                self.entries = entries.into_iter().filter(|entry| !entry.is_hidden()).collect();
+                self.dir = dir;
+                self.selected = 0;
+                self.error = None;
+                self.scroll_manager.scroll_to_top();
+            }
+            Err(e) => self.error = Some(format!("Cannot open {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Descends into the selected directory, or returns the selected file's path.
+    pub fn enter(&mut self) -> Option<String> {
+        let entry = self.entries.get(self.selected)?;
+        let path = self.dir.join(&entry.name);
+
+        if entry.is_dir {
+            self.open(path);
+            None
+        } else {
+            Some(path.to_string_lossy().into_owned())
+        }
+    }
+
+    /// Goes up one directory, returning `false` when already at the starting directory.
+    pub fn back(&mut self) -> bool {
+        let mut parent = self.dir.clone();
+        if !parent.pop() {
+            return false;
+        }
+        self.open(parent);
+        true
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let dialog_width = area.width.min(70);
+        let dialog_height = area.height.min(24);
+
+        let dialog_area = Rect {
+            x: (area.width.saturating_sub(dialog_width)) / 2,
+            y: (area.height.saturating_sub(dialog_height)) / 2,
+            width: dialog_width,
+            height: dialog_height,
+        };
+
+        frame.render_widget(Clear, dialog_area);
+
+        let location = if self.dir.as_os_str().is_empty() {
+            "./".to_string()
+        } else {
+            format!("{}/", self.dir.display())
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_focused)
+            .title(Span::styled(format!(" Attach a file — {} ", location), theme.title))
+            .title_alignment(Alignment::Center);
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(3)])
+            .split(inner);
+
+        self.render_entries(frame, chunks[0], theme);
+        self.render_footer(frame, chunks[1], theme);
+    }
+
+    fn render_entries(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let name_width = (area.width as usize).saturating_sub(16);
+
+        let lines: Vec<Line> = if self.entries.is_empty() {
+            vec![Line::from(Span::styled("  (empty directory)", theme.help_text))]
+        } else {
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let is_selected = i == self.selected;
+                    let prefix = if is_selected { "▶ " } else { "  " };
+                    let (icon, name, size) = if entry.is_dir {
+                        ("📁 ", format!("{}/", entry.name), String::new())
+                    } else {
+                        ("   ", entry.name.clone(), format_size(entry.size))
+                    };
+                    let style = if is_selected {
+                        theme.input_active.add_modifier(Modifier::BOLD)
+                    } else {
+                        theme.assistant_message
+                    };
+
+                    Line::from(vec![
+                        Span::styled(prefix, style),
+                        Span::raw(icon),
+                        Span::styled(format!("{:<name_width$}", name), style),
+                        Span::styled(format!("{:>10}", size), theme.help_text),
+                    ])
+                })
+                .collect()
+        };
+
+        let content_length = lines.len();
+        let viewport_height = area.height as usize;
+
+        self.scroll_manager.ensure_visible(self.selected, viewport_height, content_length);
+        self.scroll_manager.update(content_length, viewport_height);
+
+        frame.render_widget(
+            Paragraph::new(lines).scroll((self.scroll_manager.position() as u16, 0)),
+            area,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓")),
+            area,
+            self.scroll_manager.scrollbar_state_mut(),
+        );
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let line = match &self.error {
+            Some(error) => Line::from(Span::styled(format!("✗ {}", error), theme.error)),
+            None => Line::from(Span::styled(
+                "[↑/↓] Move  [Enter] Open/Attach  [Esc] Back/Close",
+                theme.help_text,
+            )),
+        };
+
+        let footer = Paragraph::new(line)
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::TOP).border_style(theme.border));
+
+        frame.render_widget(footer, area);
+    }
+}
+
+impl Default for FilePicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
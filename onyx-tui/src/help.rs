@@ -0,0 +1,188 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation},
+};
+
+use onyx_core::Config;
+
+use crate::commands::{self, COMMANDS};
+use crate::keymap::KEY_GROUPS;
+use crate::scroll::ScrollManager;
+use crate::theme::Theme;
+
+/// One titled list in the help: the keys for a part of the app, or the commands.
+struct HelpSection {
+    title: String,
+    entries: Vec<(String, String)>,
+}
+
+/// The full-screen help opened with F1 or `/help`, listing every key from
+/// [`KEY_GROUPS`] and every command, the user's own included.
+pub struct HelpOverlay {
+    sections: Vec<HelpSection>,
+    scroll_manager: ScrollManager,
+}
+
+impl HelpOverlay {
+    pub fn new(config: &Config) -> Self {
+        let mut sections: Vec<HelpSection> = KEY_GROUPS
+            .iter()
+            .map(|group| HelpSection {
+                title: group.context.to_string(),
+                entries: group
+                    .keys
+                    .iter()
+                    .map(|(keys, action)| (keys.to_string(), action.to_string()))
+                    .collect(),
+            })
+            .collect();
+
+        let built_in = COMMANDS.iter().map(|command| {
+            let mut description = command.description.to_string();
+            if !command.aliases.is_empty() {
+                description.push_str(&format!(" (also {})", command.aliases.join(", ")));
+            }
+            (command.usage(), description)
+        });
+        sections.push(HelpSection { title: "Commands".to_string(), entries: built_in.collect() });
+
+        let custom: Vec<(String, String)> = config
+            .custom_commands()
+            .filter(|(name, _)| !commands::is_built_in(name))
+            .map(|(name, template)| (name, commands::template_summary(template).to_string()))
+            .collect();
+        if !custom.is_empty() {
+            sections.push(HelpSection { title: "Your commands".to_string(), entries: custom });
+        }
+
+        let mut scroll_manager = ScrollManager::new();
+        scroll_manager.scroll_to_top();
+        Self { sections, scroll_manager }
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll_manager.scroll_up(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll_manager.scroll_down(amount);
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_manager.scroll_page_up();
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_manager.scroll_page_down();
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_manager.scroll_to_top();
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_manager.scroll_to_bottom();
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let dialog_width = area.width.saturating_sub(4).min(100);
+        let dialog_height = area.height.saturating_sub(2);
+
+        let dialog_area = Rect {
+            x: (area.width.saturating_sub(dialog_width)) / 2,
+            y: (area.height.saturating_sub(dialog_height)) / 2,
+            width: dialog_width,
+            height: dialog_height,
+        };
+
+        frame.render_widget(Clear, dialog_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_focused)
+            .title(Span::styled(" Help ", theme.title))
+            .title_alignment(Alignment::Center);
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)])
+            .split(inner);
+
+        self.render_sections(frame, chunks[0], theme);
+
+        let footer = Paragraph::new(Line::from(Span::styled(
+            "[↑/↓] Scroll  [PgUp/PgDn/Home/End] Jump  [Esc] Close",
+            theme.help_text,
+        )))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::TOP).border_style(theme.border));
+        frame.render_widget(footer, chunks[1]);
+    }
+
+    fn render_sections(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        // The keys line up in a column as wide as the longest of them.
+        let key_width = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.entries)
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        let mut lines = Vec::new();
+        for section in &self.sections {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(Span::styled(
+                format!(" {}", section.title),
+                theme.title.add_modifier(Modifier::BOLD),
+            )));
+            for (keys, action) in &section.entries {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("   {:width$}  ", keys, width = key_width), theme.success),
+                    Span::styled(action.clone(), theme.help_text),
+                ]));
+            }
+        }
+
+        let content_length = lines.len();
+        self.scroll_manager.update(content_length, area.height as usize);
+
+        frame.render_widget(
+            Paragraph::new(lines).scroll((self.scroll_manager.position() as u16, 0)),
+            area,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓")),
+            area,
+            self.scroll_manager.scrollbar_state_mut(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_command_and_alias() {
+        let help = HelpOverlay::new(&Config::default());
+        let commands = help.sections.iter().find(|section| section.title == "Commands").unwrap();
+
+        assert_eq!(commands.entries.len(), COMMANDS.len());
+        for (command, (usage, description)) in COMMANDS.iter().zip(&commands.entries) {
+            assert_eq!(*usage, command.usage());
+            assert!(description.starts_with(command.description));
+            assert!(command.aliases.iter().all(|alias| description.contains(alias)));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation},
+};
+
+use crate::input_history::InputHistory;
+use crate::scroll::ScrollManager;
+use crate::theme::Theme;
+
+/// Filters the input history as you type; Enter puts the selected entry back into the input.
+pub struct HistorySearch {
+    query: String,
+    matches: Vec<String>,
+    selected: usize,
+    scroll_manager: ScrollManager,
+}
+
+impl HistorySearch {
+    pub fn new(history: &InputHistory) -> Self {
+        Self {
+            query: String::new(),
+            matches: history.search(""),
+            selected: 0,
+            scroll_manager: ScrollManager::new(),
+        }
+    }
+
+    pub fn push_char(&mut self, c: char, history: &InputHistory) {
+        self.query.push(c);
+        self.refresh(history);
+    }
+
+    pub fn pop_char(&mut self, history: &InputHistory) {
+        self.query.pop();
+        self.refresh(history);
+    }
+
+    fn refresh(&mut self, history: &InputHistory) {
+        self.matches = history.search(&self.query);
+        self.selected = 0;
+        self.scroll_manager.scroll_to_top();
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.matches.get(self.selected).map(String::as_str)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let dialog_width = area.width.min(90);
+        let dialog_height = area.height.min(20);
+
+        let dialog_area = Rect {
+            x: (area.width.saturating_sub(dialog_width)) / 2,
+            y: (area.height.saturating_sub(dialog_height)) / 2,
+            width: dialog_width,
+            height: dialog_height,
+        };
+
+        frame.render_widget(Clear, dialog_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_focused)
+            .title(Span::styled(" Input History ", theme.title))
+            .title_alignment(Alignment::Center);
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1), Constraint::Length(3)])
+            .split(inner);
+
+        let query = Paragraph::new(Line::from(vec![
+            Span::styled(" Search: ", theme.help_text),
+            Span::styled(format!("{}█", self.query), theme.input_active),
+        ]))
+        .block(Block::default().borders(Borders::BOTTOM).border_style(theme.border));
+        frame.render_widget(query, chunks[0]);
+
+        self.render_matches(frame, chunks[1], theme);
+
+        let footer = Paragraph::new(Line::from(Span::styled(
+            "[↑/↓] Move  [Enter] Use  [Esc] Close",
+            theme.help_text,
+        )))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::TOP).border_style(theme.border));
+        frame.render_widget(footer, chunks[2]);
+    }
+
+    fn render_matches(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let lines: Vec<Line> = if self.matches.is_empty() {
+            vec![Line::from(Span::styled("  No matching inputs", theme.help_text))]
+        } else {
+            self.matches
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let is_selected = i == self.selected;
+                    let prefix = if is_selected { "▶ " } else { "  " };
+                    let style = if is_selected {
+                        theme.input_active.add_modifier(Modifier::BOLD)
+                    } else {
+                        theme.assistant_message
+                    };
+                    Line::from(vec![
+                        Span::styled(prefix, style),
+                        Span::styled(entry.clone(), style),
+                    ])
+                })
+                .collect()
+        };
+
+        let content_length = lines.len();
+        let viewport_height = area.height as usize;
+
+        self.scroll_manager.ensure_visible(self.selected, viewport_height, content_length);
+        self.scroll_manager.update(content_length, viewport_height);
+
+        frame.render_widget(
+            Paragraph::new(lines).scroll((self.scroll_manager.position() as u16, 0)),
+            area,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓")),
+            area,
+            self.scroll_manager.scrollbar_state_mut(),
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use onyx_core::{Config, ConfigResult, ConfigSchema};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// Previously submitted inputs, kept in `input_history` in the data directory (one per line,
+/// oldest first) and recalled with ↑/↓ or Ctrl+P/Ctrl+N.
+pub struct InputHistory {
+    entries: Vec<String>,
+    limit: usize,
+    /// The entry being shown while browsing, and the unsent text it replaced.
+    position: Option<usize>,
+    draft: String,
+}
+
+impl InputHistory {
+    pub fn new(limit: usize) -> Self {
+        Self { entries: Vec::new(), limit, position: None, draft: String::new() }
+    }
+
+    /// Loads the saved history, starting empty if the file is missing or unreadable.
+    pub fn load(limit: usize) -> Self {
+        let mut history = Self::new(limit);
+        if let Ok(content) = Self::path().and_then(|path| Ok(fs::read_to_string(path)?)) {
+            history.entries = content.lines().map(str::to_string).collect();
+            history.truncate();
+        }
+        history
+    }
+
+    fn save(&self) -> ConfigResult<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        fs::write(&path, content)?;
+        Ok(())
+    }
+
+    fn path() -> ConfigResult<PathBuf> {
+        Ok(Config::data_dir()?.join("input_history"))
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.limit);
+        self.entries.drain(..excess);
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.truncate();
+    }
+
+    /// Records a submitted input and stops browsing. Repeats of the newest entry are skipped.
+    pub fn push(&mut self, input: &str) -> ConfigResult<()> {
+        self.stop_browsing();
+
+        // Entries are stored one per line, so anything multi-line is flattened.
+        let entry = input.trim().replace('\n', " ");
+        if entry.is_empty() || self.limit == 0 || self.entries.last() == Some(&entry) {
+            return Ok(());
+        }
+
+        self.entries.push(entry);
+        self.truncate();
+        self.save()
+    }
+
+    pub fn stop_browsing(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    pub fn is_browsing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Steps back to an older entry, remembering `current` as the draft when browsing starts.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+        self.position = Some(position);
+        self.entries.get(position).map(String::as_str)
+    }
+
+    /// Steps forward to a newer entry, handing back the draft once past the newest one.
+    pub fn next(&mut self) -> Option<String> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(self.entries[position].clone())
+        } else {
+            self.position = None;
+            Some(std::mem::take(&mut self.draft))
+        }
+    }
+
+    /// Distinct entries containing `query` (ignoring case), newest first.
+    pub fn search(&self, query: &str) -> Vec<String> {
+        let query = query.to_lowercase();
+        let mut seen = HashSet::new();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.to_lowercase().contains(&query) && seen.insert(entry.as_str()))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,98 @@
+//! The keybindings, grouped by where they apply, as `/help` and the F1 help list them.
+
+/// The keys that work in one part of the app, each with what it does.
+pub(crate) struct KeyGroup {
+    pub context: &'static str,
+    pub keys: &'static [(&'static str, &'static str)],
+}
+
+/// What the hint bar names, by the description of the key that does it.
+const HINTS: &[(&str, &str)] =
+    &[("Send", "send"), ("Open the menu", "commands"), ("Show this help", "help")];
+
+/// The keys of the hint bar with what they do, as "Enter" and "send". Only the first of a
+/// key's alternatives is shown.
+pub(crate) fn hints() -> impl Iterator<Item = (&'static str, &'static str)> {
+    HINTS.iter().filter_map(|&(description, label)| {
+        let (key, _) = KEY_GROUPS
+            .iter()
+            .flat_map(|group| group.keys)
+            .find(|(_, does)| *does == description)?;
+        Some((key.split(" or ").next().unwrap_or(key), label))
+    })
+}
+
+/// Every keybinding. Changing a key in a handler means changing it here too.
+pub(crate) const KEY_GROUPS: &[KeyGroup] = &[
+    KeyGroup {
+        context: "Chat",
+        keys: &[
+            ("F1 or ? (empty input)", "Show this help"),
+            ("↑/↓", "Scroll up/down, or recall earlier inputs while typing"),
+            ("PgUp/PgDn", "Scroll page up/down"),
+            ("Home/End", "Jump to top/bottom (start/end of the input while typing)"),
+            ("Esc", "Cancel response"),
+            ("Ctrl+P/Ctrl+N", "Previous/next input from history"),
+            ("Ctrl+H", "Search input history"),
+            ("Ctrl+R", "Search back through inputs in place (again for older)"),
+            ("Ctrl+F", "Search the conversation"),
+            ("F6 or Ctrl+↑/↓", "Move focus between the input and the chat"),
+            ("↑/↓ then Enter in the chat", "Select and copy a message"),
+            ("T in the chat", "Cycle timestamps: absolute, relative, hidden"),
+            ("Alt+↑", "Edit and resend your last message (Esc cancels)"),
+            ("Ctrl+O", "Pick a file to attach"),
+            ("Ctrl+L", "Clear chat"),
+            ("Ctrl+C", "Quit (copies if text is selected, cancels response while processing)"),
+        ],
+    },
+    KeyGroup {
+        context: "Input editing",
+        keys: &[
+            ("Enter", "Send"),
+            ("Ctrl+←/→ or Alt+B/F", "Move by word (add Shift to select)"),
+            ("Ctrl+A", "Select the whole input"),
+            ("Ctrl+Z/Ctrl+Y", "Undo/redo (Ctrl+Shift+Z also redoes)"),
+            ("Ctrl+E", "Move to the end of the input"),
+            ("Ctrl+W or Ctrl+Backspace", "Delete the previous word"),
+            ("Ctrl+U/Ctrl+K", "Delete to the start/end of the input"),
+            ("Ctrl+Delete or Alt+D", "Delete the next word"),
+            ("Ctrl+C/Ctrl+X/Ctrl+V", "Copy, cut or paste the input selection"),
+            ("Ctrl+D", "Clear the input, or quit when it is empty"),
+        ],
+    },
+    KeyGroup {
+        context: "Vim input mode",
+        keys: &[
+            ("Esc", "Normal mode"),
+            ("h/l/w/b/0/$", "Move the cursor"),
+            ("x, d + motion, dd", "Delete"),
+            ("u/Ctrl+R", "Undo/redo"),
+            ("i/a/I/A/o", "Back to insert mode"),
+            ("j/k, gg/G", "Scroll the chat, jump to top/bottom"),
+        ],
+    },
+    KeyGroup {
+        context: "Command menu",
+        keys: &[
+            ("/", "Open the menu"),
+            ("↑/↓", "Select a command"),
+            ("Tab", "Complete the command"),
+            ("Enter", "Complete, and run it if there is nothing more to type"),
+        ],
+    },
+    KeyGroup {
+        context: "Config editor",
+        keys: &[
+            ("↑/↓ or Tab/Shift+Tab", "Select a field"),
+            ("PgUp/PgDn/Home/End", "Jump"),
+            ("←/→ or [/]", "Previous/next section"),
+            ("Enter", "Edit the field, or open/close a provider"),
+            ("/", "Filter the fields"),
+            ("Ctrl+S", "Save"),
+            ("Ctrl+R/Ctrl+Shift+R", "Reset the field/all fields"),
+            ("Ctrl+K", "Test the provider's connection"),
+            ("Ctrl+T", "Show/hide secrets"),
+            ("Esc", "Cancel the edit, or close"),
+        ],
+    },
+];
@@ -0,0 +1,120 @@
+use onyx_core::markup::BlockParser;
+use ratatui::{style::Style, text::Line};
+use std::ops::Range;
+
+/// How a message is to be shown, apart from its content. A cached rendering is reused only
+/// while this stays the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderKey {
+    pub width: usize,
+    pub selected: bool,
+    pub show_stats: bool,
+    /// How long ago the message was sent, when timestamps are shown that way, so that the
+    /// rendering is redone as it changes.
+    pub age: Option<String>,
+    pub matches: Vec<Range<usize>>,
+    pub current_match: Option<Range<usize>>,
+}
+
+pub struct RenderedMessage {
+    pub lines: Vec<Line<'static>>,
+    /// The line showing the current search match, if the message has it.
+    pub match_line: Option<usize>,
+    pub links: Vec<LinkArea>,
+}
+
+/// Where part of a link shows in a rendered message: a wrapped link has one of these per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkArea {
+    pub line: usize,
+    pub columns: Range<usize>,
+    pub url: String,
+}
+
+struct Entry {
+    key: RenderKey,
+    rendered: RenderedMessage,
+}
+
+/// The rendered lines of each message, kept between frames so that only the messages that
+/// changed are wrapped again. Messages are followed by a blank line when laid out.
+#[derive(Default)]
+pub struct MessageLayout {
+    entries: Vec<Option<Entry>>,
+    /// The blocks found so far in the message being streamed, and its index. Unlike its
+    /// rendering, this survives each chunk, so that only the new lines are parsed.
+    streaming: Option<(usize, BlockParser)>,
+}
+
+impl MessageLayout {
+    /// Forgets the rendering of message `index`, after its content changed.
+    pub fn invalidate(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            *entry = None;
+        }
+    }
+
+    /// Forgets every rendering, after messages moved or the theme or config changed.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.streaming = None;
+    }
+
+    /// Parses what has arrived of message `index`, which is still streaming.
+    pub fn parse_streaming(&mut self, index: usize, content: &str) {
+        let parser = match &mut self.streaming {
+            Some((streaming, parser)) if *streaming == index => parser,
+            slot => &mut slot.insert((index, BlockParser::default())).1,
+        };
+        parser.update(content);
+    }
+
+    /// The rendering of message `index` for `key`, calling `render` only if there is none.
+    /// `render` gets the message's blocks so far if it is the one being streamed.
+    pub fn get_or_render(
+        &mut self,
+        index: usize,
+        key: RenderKey,
+        render: impl FnOnce(&RenderKey, Option<&BlockParser>) -> RenderedMessage,
+    ) -> &RenderedMessage {
+        if self.entries.len() <= index {
+            self.entries.resize_with(index + 1, || None);
+        }
+
+        let parser = self
+            .streaming
+            .as_ref()
+            .filter(|(streaming, _)| *streaming == index)
+            .map(|(_, parser)| parser);
+        let entry = &mut self.entries[index];
+        if entry.as_ref().is_some_and(|entry| entry.key != key) {
+            *entry = None;
+        }
+        &entry.get_or_insert_with(|| Entry { rendered: render(&key, parser), key }).rendered
+    }
+
+    /// The lines of the first `count` messages, each followed by a blank line. Every message
+    /// must have been rendered first.
+    pub fn lines(&self, count: usize) -> impl Iterator<Item = &Line<'static>> {
+        static BLANK: Line<'static> =
+            Line { spans: Vec::new(), style: Style::new(), alignment: None };
+        self.entries[..count]
+            .iter()
+            .flatten()
+            .flat_map(|entry| entry.rendered.lines.iter().chain(std::iter::once(&BLANK)))
+    }
+
+    /// The links in the first `count` messages, with the line each is on counted as in
+    /// [`lines`](Self::lines).
+    pub fn links(&self, count: usize) -> impl Iterator<Item = (usize, &LinkArea)> {
+        self.entries[..count]
+            .iter()
+            .flatten()
+            .scan(0, |start, entry| {
+                let offset = *start;
+                *start += entry.rendered.lines.len() + 1;
+                Some(entry.rendered.links.iter().map(move |link| (offset + link.line, link)))
+            })
+            .flatten()
+    }
+}
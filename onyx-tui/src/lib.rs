@@ -1,10 +1,27 @@
+mod clipboard;
+mod color;
+mod commands;
 mod config_editor;
 mod cursor;
+mod file_picker;
+mod help;
+mod history_search;
+mod input_history;
+mod keymap;
+mod layout;
+mod links;
+mod notifications;
+mod reverse_search;
 mod scroll;
+mod search;
+mod session_browser;
 mod text_input;
 mod theme;
 mod ui;
+mod vim;
 mod widgets;
 
-pub use theme::Theme;
+pub use color::ColorDepth;
+pub use notifications::NotificationLevel;
+pub use theme::{Theme, ThemeError};
 pub use ui::App;
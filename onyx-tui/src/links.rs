@@ -0,0 +1,80 @@
+//! Clickable links: OSC 8 hyperlinks over the URLs on screen, and opening a URL in the browser.
+
+use crossterm::cursor::{MoveTo, RestorePosition, SavePosition};
+use crossterm::queue;
+use crossterm::style::Print;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::buffer::Buffer;
+use ratatui::style::Style;
+use std::io::{self, Write, stdout};
+use std::process::{Command, Stdio};
+
+/// Part of a link on screen: a run of cells on one row, which a wrapped link has several of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenLink {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub url: String,
+}
+
+/// Whether the terminal is known to turn OSC 8 sequences into links. Those that don't may print
+/// them as garbage, so links stay plain styled text anywhere else.
+pub fn supports_hyperlinks() -> bool {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    // Inside tmux or screen the sequences only get through if configured to.
+    if !var("TMUX").is_empty() || var("TERM").starts_with("screen") {
+        return false;
+    }
+
+    matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper" | "rio" | "Tabby"
+    ) || ["KITTY_WINDOW_ID", "WT_SESSION", "KONSOLE_VERSION", "ALACRITTY_WINDOW_ID"]
+        .iter()
+        .any(|name| !var(name).is_empty())
+        || var("VTE_VERSION").parse::<u32>().is_ok_and(|version| version >= 5000)
+        || matches!(var("TERM").as_str(), "xterm-kitty" | "foot" | "wezterm" | "xterm-ghostty")
+}
+
+/// Redraws the cells of `links` from `buffer` wrapped in OSC 8 sequences, after ratatui has
+/// drawn the frame. Cells that no longer have `style`, because a dialog or a toast covers them,
+/// are left alone.
+pub fn write_hyperlinks(links: &[ScreenLink], buffer: &Buffer, style: Style) -> io::Result<()> {
+    let mut backend = CrosstermBackend::new(stdout());
+    queue!(backend, SavePosition)?;
+
+    for link in links {
+        let cells: Vec<_> = (link.x..link.x + link.width)
+            .filter_map(|x| buffer.cell((x, link.y)).map(|cell| (x, link.y, cell)))
+            .collect();
+        let styled = |cell: &ratatui::buffer::Cell| {
+            Some(cell.fg) == style.fg && cell.modifier.contains(style.add_modifier)
+        };
+        if cells.is_empty() || !cells.iter().all(|(_, _, cell)| styled(cell)) {
+            continue;
+        }
+
+        queue!(backend, MoveTo(link.x, link.y), Print(format!("\x1b]8;;{}\x1b\\", link.url)))?;
+        backend.draw(cells.into_iter())?;
+        queue!(backend, Print("\x1b]8;;\x1b\\"))?;
+    }
+
+    queue!(backend, RestorePosition)?;
+    Write::flush(&mut backend)
+}
+
+/// Opens `url` in the default browser without waiting for it.
+pub fn open_url(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn()?;
+    Ok(())
+}
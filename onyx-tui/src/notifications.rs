@@ -0,0 +1,89 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// How many notifications `/notifications` can look back on.
+const HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Info => "Info",
+            Self::Warning => "Warning",
+            Self::Error => "Error",
+        }
+    }
+}
+
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub text: String,
+    pub time: DateTime<Local>,
+    shown_at: Instant,
+    dismissed: bool,
+}
+
+/// Warnings and errors from background work, shown briefly as toasts and kept for review.
+#[derive(Default)]
+pub struct Notifications {
+    recent: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: NotificationLevel, text: String) {
+        if self.recent.len() == HISTORY_LIMIT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Notification {
+            level,
+            text,
+            time: Local::now(),
+            shown_at: Instant::now(),
+            dismissed: false,
+        });
+    }
+
+    /// The notifications still shown as toasts, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.recent.iter().filter(|notification| !notification.dismissed)
+    }
+
+    /// All notifications kept for review, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Notification> {
+        self.recent.iter()
+    }
+
+    /// Dismisses the toasts that have been shown long enough. Returns whether any were.
+    pub fn expire(&mut self) -> bool {
+        let mut expired = false;
+        for notification in self.recent.iter_mut().filter(|n| !n.dismissed) {
+            if notification.shown_at.elapsed() >= TOAST_DURATION {
+                notification.dismissed = true;
+                expired = true;
+            }
+        }
+        expired
+    }
+
+    pub fn dismiss_all(&mut self) {
+        for notification in &mut self.recent {
+            notification.dismissed = true;
+        }
+    }
+
+    /// How long until the next toast expires, if any are shown.
+    pub fn time_until_expiry(&self) -> Option<Duration> {
+        self.toasts()
+            .map(|notification| TOAST_DURATION.saturating_sub(notification.shown_at.elapsed()))
+            .min()
+    }
+}
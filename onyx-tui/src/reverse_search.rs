@@ -0,0 +1,121 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::input_history::InputHistory;
+use crate::text_input::TextInputState;
+use crate::theme::Theme;
+
+/// How many matches the popup lists at once.
+const VISIBLE_MATCHES: usize = 5;
+
+/// Ctrl+R: searches back through earlier inputs as you type, as shells do. The match shows in
+/// the input itself, and Ctrl+R again moves on to an older one.
+pub struct ReverseSearch {
+    query: String,
+    matches: Vec<String>,
+    /// Which match is shown, counting back from the newest.
+    index: usize,
+    /// The input from before the search, which cancelling puts back.
+    original: TextInputState,
+}
+
+impl ReverseSearch {
+    pub fn new(history: &InputHistory, original: TextInputState) -> Self {
+        Self { query: String::new(), matches: history.search(""), index: 0, original }
+    }
+
+    pub fn push_char(&mut self, c: char, history: &InputHistory) {
+        self.query.push(c);
+        self.refresh(history);
+    }
+
+    pub fn pop_char(&mut self, history: &InputHistory) {
+        self.query.pop();
+        self.refresh(history);
+    }
+
+    fn refresh(&mut self, history: &InputHistory) {
+        self.matches = history.search(&self.query);
+        self.index = 0;
+    }
+
+    /// Moves on to the next older match, staying on the oldest once there.
+    pub fn older(&mut self) {
+        if self.index + 1 < self.matches.len() {
+            self.index += 1;
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.matches.get(self.index).map(String::as_str)
+    }
+
+    pub fn original(&self) -> &TextInputState {
+        &self.original
+    }
+
+    pub fn into_original(self) -> TextInputState {
+        self.original
+    }
+
+    /// Draws the query and the matches around the current one just above `input_area`, where
+    /// the command menu goes.
+    pub fn render(&self, frame: &mut Frame, input_area: Rect, theme: &Theme) {
+        let shown = self.matches.len().clamp(1, VISIBLE_MATCHES);
+        let height = (shown as u16 + 2).min(input_area.y);
+        let width = 70.min(input_area.width.saturating_sub(4));
+        if height < 3 || width == 0 {
+            return;
+        }
+        let area = Rect { x: input_area.x + 2, y: input_area.y - height, width, height };
+
+        frame.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_focused)
+            .title(Line::from(vec![
+                Span::styled(" reverse-i-search: ", theme.title),
+                Span::styled(format!("{}█ ", self.query), theme.input_active),
+            ]))
+            .title_bottom(Line::from(vec![
+                Span::styled(" [Ctrl+R] ", theme.success),
+                Span::styled("older ", theme.help_text),
+                Span::styled("• ", theme.border),
+                Span::styled("[Enter] ", theme.success),
+                Span::styled("edit ", theme.help_text),
+                Span::styled("• ", theme.border),
+                Span::styled("[Esc] ", theme.success),
+                Span::styled("cancel ", theme.help_text),
+            ]));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let lines: Vec<Line> = if self.matches.is_empty() {
+            vec![Line::from(Span::styled("  No matching inputs", theme.help_text))]
+        } else {
+            // The window of matches moves along once the current one reaches its end.
+            let first = (self.index + 1).saturating_sub(inner.height as usize);
+            self.matches
+                .iter()
+                .enumerate()
+                .skip(first)
+                .map(|(i, entry)| {
+                    let (marker, style) = if i == self.index {
+                        (" ▶ ", theme.success.add_modifier(Modifier::BOLD))
+                    } else {
+                        ("   ", theme.assistant_message)
+                    };
+                    let entry = entry.lines().next().unwrap_or_default().to_string();
+                    Line::from(vec![Span::styled(marker, style), Span::styled(entry, style)])
+                })
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+}
@@ -5,12 +5,23 @@ const SCROLL_PAGE_AMOUNT: usize = 10;
 pub struct ScrollManager {
     position: usize,
     scrollbar_state: ScrollbarState,
+    /// Whether the view follows the bottom. Only the user scrolling turns this off, and only
+    /// scrolling back down to the bottom (or jumping there) turns it on again.
     auto_scroll: bool,
+    max_position: usize,
+    /// Content arrived below the view while the user was scrolled up.
+    new_content: bool,
 }
 
 impl ScrollManager {
     pub fn new() -> Self {
-        Self { position: 0, scrollbar_state: ScrollbarState::default(), auto_scroll: true }
+        Self {
+            position: 0,
+            scrollbar_state: ScrollbarState::default(),
+            auto_scroll: true,
+            max_position: 0,
+            new_content: false,
+        }
     }
 
     pub fn position(&self) -> usize {
@@ -23,6 +34,26 @@ impl ScrollManager {
 
     pub fn enable_auto_scroll(&mut self) {
         self.auto_scroll = true;
+        self.new_content = false;
+    }
+
+    /// Notes that the content grew without the user asking to see it, e.g. a streamed chunk.
+    /// The view keeps following the bottom if it was, and otherwise stays put.
+    pub fn content_changed(&mut self) {
+        if !self.auto_scroll {
+            self.new_content = true;
+        }
+    }
+
+    /// Notes that `count` lines went from the top of the content, so that what is in view
+    /// stays in view rather than jumping down by as much.
+    pub fn lines_removed_above(&mut self, count: usize) {
+        self.position = self.position.saturating_sub(count);
+        self.max_position = self.max_position.saturating_sub(count);
+    }
+
+    pub fn has_new_content(&self) -> bool {
+        self.new_content
     }
 
     pub fn scroll_to_top(&mut self) {
@@ -31,7 +62,7 @@ impl ScrollManager {
     }
 
     pub fn scroll_to_bottom(&mut self) {
-        self.auto_scroll = true;
+        self.enable_auto_scroll();
     }
 
     pub fn scroll_up(&mut self, amount: usize) {
@@ -40,8 +71,12 @@ impl ScrollManager {
     }
 
     pub fn scroll_down(&mut self, amount: usize) {
-        self.position = self.position.saturating_add(amount);
-        self.auto_scroll = false;
+        self.position = self.position.saturating_add(amount).min(self.max_position);
+        if self.position >= self.max_position {
+            self.enable_auto_scroll();
+        } else {
+            self.auto_scroll = false;
+        }
     }
 
     pub fn scroll_page_up(&mut self) {
@@ -53,8 +88,9 @@ impl ScrollManager {
     }
 
     pub fn update(&mut self, content_length: usize, viewport_height: usize) {
+        self.max_position = content_length.saturating_sub(viewport_height);
         self.position = if self.auto_scroll {
-            content_length.saturating_sub(viewport_height)
+            self.max_position
         } else {
             self.position.min(content_length.saturating_sub(1))
         };
@@ -67,16 +103,17 @@ impl ScrollManager {
         if line < self.position {
             self.position = line;
         } else if line >= self.position + viewport_height {
-            self.position = line.saturating_sub(viewport_height - 1);
+            self.position = line.saturating_sub(viewport_height.saturating_sub(1));
         }
 
         let max_scroll = content_length.saturating_sub(viewport_height);
         self.position = self.position.min(max_scroll);
+        self.auto_scroll = false;
     }
 
     pub fn reset(&mut self) {
         self.position = 0;
-        self.auto_scroll = true;
+        self.enable_auto_scroll();
     }
 }
 
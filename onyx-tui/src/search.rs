@@ -0,0 +1,159 @@
+use onyx_core::Message;
+use std::ops::Range;
+
+/// Where a search match is: which message, and which bytes of its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub message: usize,
+    pub range: Range<usize>,
+}
+
+/// A case-insensitive search through the conversation's message content, started with
+/// `/search` or Ctrl+F.
+pub struct ConversationSearch {
+    query: String,
+    editing: bool,
+    matches: Vec<SearchMatch>,
+    current: usize,
+    /// Set when the current match changes, so the view can scroll to it.
+    moved: bool,
+}
+
+impl ConversationSearch {
+    /// Starts a search for `query`, or prompts for one if it is empty.
+    pub fn new(query: &str, messages: &[Message]) -> Self {
+        let mut search = Self {
+            query: query.to_string(),
+            editing: query.is_empty(),
+            matches: Vec::new(),
+            current: 0,
+            moved: true,
+        };
+        search.refresh(messages);
+        search
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    pub fn start_editing(&mut self) {
+        self.editing = true;
+    }
+
+    pub fn finish_editing(&mut self) {
+        self.editing = false;
+    }
+
+    pub fn push_char(&mut self, c: char, messages: &[Message]) {
+        self.query.push(c);
+        self.current = 0;
+        self.refresh(messages);
+    }
+
+    pub fn pop_char(&mut self, messages: &[Message]) {
+        self.query.pop();
+        self.current = 0;
+        self.refresh(messages);
+    }
+
+    /// Finds the matches again after the messages changed, staying on the current one if it
+    /// still exists.
+    pub fn refresh(&mut self, messages: &[Message]) {
+        let current = self.matches.get(self.current).cloned();
+        self.matches = messages
+            .iter()
+            .enumerate()
+            .flat_map(|(message, msg)| {
+                find_matches(&msg.content, &self.query)
+                    .into_iter()
+                    .map(move |range| SearchMatch { message, range })
+            })
+            .collect();
+
+        match current.and_then(|current| self.matches.iter().position(|m| *m == current)) {
+            Some(position) => self.current = position,
+            None => {
+                self.current = self.current.min(self.matches.len().saturating_sub(1));
+                self.moved = true;
+            }
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+            self.moved = true;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = self.current.checked_sub(1).unwrap_or(self.matches.len() - 1);
+            self.moved = true;
+        }
+    }
+
+    pub fn current_match(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current)
+    }
+
+    pub fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    /// The matches within one message.
+    pub fn ranges_in(&self, message: usize) -> Vec<Range<usize>> {
+        self.matches.iter().filter(|m| m.message == message).map(|m| m.range.clone()).collect()
+    }
+
+    /// Which match is current, as "3/17".
+    pub fn position(&self) -> String {
+        if self.matches.is_empty() {
+            "0/0".to_string()
+        } else {
+            format!("{}/{}", self.current + 1, self.matches.len())
+        }
+    }
+}
+
+/// The byte ranges of `text` that match `query`, ignoring case. Matches don't overlap.
+pub fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+        if let Some(end) = match_at(text, start, &query) {
+            matches.push(start..end);
+            search_from = end;
+        }
+    }
+    matches
+}
+
+/// Where a match of the lowercased `query` starting at `start` ends, if there is one.
+fn match_at(text: &str, start: usize, query: &[char]) -> Option<usize> {
+    let mut remaining = query.iter();
+    for (offset, c) in text[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            if remaining.next() != Some(&lower) {
+                return None;
+            }
+        }
+        if remaining.as_slice().is_empty() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+    None
+}
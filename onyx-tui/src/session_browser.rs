@@ -0,0 +1,196 @@
+use onyx_core::{Config, Session, SessionSummary};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation},
+};
+
+use crate::scroll::ScrollManager;
+use crate::theme::Theme;
+
+/// Lists saved sessions so an older conversation can be reopened or deleted.
+pub struct SessionBrowser {
+    sessions: Vec<SessionSummary>,
+    selected: usize,
+    confirming_delete: bool,
+    pub error: Option<String>,
+    scroll_manager: ScrollManager,
+}
+
+impl SessionBrowser {
+    pub fn new() -> Self {
+        let mut browser = Self {
+            sessions: Vec::new(),
+            selected: 0,
+            confirming_delete: false,
+            error: None,
+            scroll_manager: ScrollManager::new(),
+        };
+        browser.refresh();
+        browser
+    }
+
+    fn refresh(&mut self) {
+        match Session::list() {
+            Ok(sessions) => self.sessions = sessions,
+            Err(e) => self.error = Some(format!("Cannot list sessions: {}", e)),
+        }
+        self.selected = self.selected.min(self.sessions.len().saturating_sub(1));
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.sessions.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_id(&self) -> Option<&str> {
+        self.sessions.get(self.selected).map(|session| session.id.as_str())
+    }
+
+    pub fn is_confirming_delete(&self) -> bool {
+        self.confirming_delete
+    }
+
+    /// Asks for confirmation before [`SessionBrowser::delete_selected`] is allowed to run.
+    pub fn request_delete(&mut self) {
+        self.confirming_delete = self.selected_id().is_some();
+    }
+
+    pub fn cancel_delete(&mut self) {
+        self.confirming_delete = false;
+    }
+
+    /// Deletes the selected session, returning its id.
+    pub fn delete_selected(&mut self) -> Option<String> {
+        self.confirming_delete = false;
+        let id = self.selected_id()?.to_string();
+
+        match Session::delete(&id) {
+            Ok(()) => {
+                self.error = None;
+                self.refresh();
+                Some(id)
+            }
+            Err(e) => {
+                self.error = Some(format!("Cannot delete session: {}", e));
+                None
+            }
+        }
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme, config: &Config) {
+        let dialog_width = area.width.min(90);
+        let dialog_height = area.height.min(24);
+
+        let dialog_area = Rect {
+            x: (area.width.saturating_sub(dialog_width)) / 2,
+            y: (area.height.saturating_sub(dialog_height)) / 2,
+            width: dialog_width,
+            height: dialog_height,
+        };
+
+        frame.render_widget(Clear, dialog_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_focused)
+            .title(Span::styled(" Sessions ", theme.title))
+            .title_alignment(Alignment::Center);
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(3)])
+            .split(inner);
+
+        self.render_sessions(frame, chunks[0], theme, config);
+        self.render_footer(frame, chunks[1], theme);
+    }
+
+    fn render_sessions(&mut self, frame: &mut Frame, area: Rect, theme: &Theme, config: &Config) {
+        let lines: Vec<Line> = if self.sessions.is_empty() {
+            vec![Line::from(Span::styled("  No saved sessions yet", theme.help_text))]
+        } else {
+            self.sessions
+                .iter()
+                .enumerate()
+                .map(|(i, session)| {
+                    let is_selected = i == self.selected;
+                    let prefix = if is_selected { "▶ " } else { "  " };
+                    let style = if is_selected {
+                        theme.input_active.add_modifier(Modifier::BOLD)
+                    } else {
+                        theme.assistant_message
+                    };
+                    let details = format!(
+                        "  {} messages • {}",
+                        session.message_count,
+                        config.format_timestamp(session.modified)
+                    );
+
+                    Line::from(vec![
+                        Span::styled(prefix, style),
+                        Span::styled(session.title.clone(), style),
+                        Span::styled(details, theme.help_text),
+                    ])
+                })
+                .collect()
+        };
+
+        let content_length = lines.len();
+        let viewport_height = area.height as usize;
+
+        self.scroll_manager.ensure_visible(self.selected, viewport_height, content_length);
+        self.scroll_manager.update(content_length, viewport_height);
+
+        frame.render_widget(
+            Paragraph::new(lines).scroll((self.scroll_manager.position() as u16, 0)),
+            area,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("↑"))
+                .end_symbol(Some("↓")),
+            area,
+            self.scroll_manager.scrollbar_state_mut(),
+        );
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let line = if self.confirming_delete {
+            let title = self.sessions.get(self.selected).map_or("", |session| &session.title);
+            Line::from(Span::styled(
+                format!("Delete \"{}\"? [y] Delete  [n] Keep", title),
+                theme.error,
+            ))
+        } else if let Some(error) = &self.error {
+            Line::from(Span::styled(format!("✗ {}", error), theme.error))
+        } else {
+            Line::from(Span::styled(
+                "[↑/↓] Move  [Enter] Open  [d] Delete  [Esc] Close",
+                theme.help_text,
+            ))
+        };
+
+        let footer = Paragraph::new(line)
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::TOP).border_style(theme.border));
+
+        frame.render_widget(footer, area);
+    }
+}
+
+impl Default for SessionBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,11 @@
 use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
 
 const UNDO_GROUP_INTERVAL_MS: u128 = 500;
 const MAX_UNDO_HISTORY: usize = 100;
 
+/// The text being typed. `cursor_position` and `selection_start` are byte offsets that always
+/// sit on grapheme boundaries, so an emoji or accented letter moves and deletes as one unit.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TextInputState {
     text: String,
@@ -46,6 +49,12 @@ impl TextInputState {
         self.selection_start = None;
     }
 
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection_range()
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| &self.text[start..end])
+    }
+
     pub fn take_text(&mut self) -> String {
         self.cursor_position = 0;
         self.clear_selection();
@@ -55,22 +64,29 @@ impl TextInputState {
     pub fn insert_char(&mut self, c: char) {
         if let Some((start, end)) = self.selection_range() {
             self.text.replace_range(start..end, &c.to_string());
-            self.cursor_position = start + 1;
+            self.cursor_position = start + c.len_utf8();
             self.clear_selection();
         } else {
             self.text.insert(self.cursor_position, c);
-            self.cursor_position += 1;
+            self.cursor_position += c.len_utf8();
         }
     }
 
+    /// Inserts `text` at the cursor, replacing the selection if there is one.
+    pub fn insert_str(&mut self, text: &str) {
+        let (start, end) =
+            self.selection_range().unwrap_or((self.cursor_position, self.cursor_position));
+        self.replace_range(start, end, text);
+    }
+
     pub fn delete_char_before(&mut self) {
         if let Some((start, end)) = self.selection_range() {
             self.text.replace_range(start..end, "");
             self.cursor_position = start;
             self.clear_selection();
         } else if self.cursor_position > 0 {
-            self.cursor_position -= 1;
-            self.text.remove(self.cursor_position);
+            let start = self.previous_boundary(self.cursor_position);
+            self.replace_range(start, self.cursor_position, "");
         }
     }
 
@@ -80,7 +96,8 @@ impl TextInputState {
             self.cursor_position = start;
             self.clear_selection();
         } else if self.cursor_position < self.text.len() {
-            self.text.remove(self.cursor_position);
+            let end = self.next_boundary(self.cursor_position);
+            self.text.replace_range(self.cursor_position..end, "");
         }
     }
 
@@ -89,16 +106,14 @@ impl TextInputState {
             if self.selection_start.is_none() {
                 self.selection_start = Some(self.cursor_position);
             }
-            if self.cursor_position > 0 {
-                self.cursor_position -= 1;
-            }
+            self.cursor_position = self.previous_boundary(self.cursor_position);
         } else if self.has_selection() {
             if let Some((start, _)) = self.selection_range() {
                 self.cursor_position = start;
             }
             self.clear_selection();
-        } else if self.cursor_position > 0 {
-            self.cursor_position -= 1;
+        } else {
+            self.cursor_position = self.previous_boundary(self.cursor_position);
         }
     }
 
@@ -107,19 +122,121 @@ impl TextInputState {
             if self.selection_start.is_none() {
                 self.selection_start = Some(self.cursor_position);
             }
-            if self.cursor_position < self.text.len() {
-                self.cursor_position += 1;
-            }
+            self.cursor_position = self.next_boundary(self.cursor_position);
         } else if self.has_selection() {
             if let Some((_, end)) = self.selection_range() {
                 self.cursor_position = end;
             }
             self.clear_selection();
-        } else if self.cursor_position < self.text.len() {
-            self.cursor_position += 1;
+        } else {
+            self.cursor_position = self.next_boundary(self.cursor_position);
         }
     }
 
+    pub fn move_cursor_word_left(&mut self, with_selection: bool) {
+        self.move_cursor_to(self.word_start_before(self.cursor_position), with_selection);
+    }
+
+    pub fn move_cursor_word_right(&mut self, with_selection: bool) {
+        self.move_cursor_to(self.word_end_after(self.cursor_position), with_selection);
+    }
+
+    /// Moves to the start of the next word, as vim's `w` does.
+    pub fn move_cursor_next_word_start(&mut self, with_selection: bool) {
+        self.move_cursor_to(self.word_start_after(self.cursor_position), with_selection);
+    }
+
+    pub fn move_cursor_home(&mut self, with_selection: bool) {
+        self.move_cursor_to(0, with_selection);
+    }
+
+    pub fn move_cursor_end(&mut self, with_selection: bool) {
+        self.move_cursor_to(self.text.len(), with_selection);
+    }
+
+    fn move_cursor_to(&mut self, position: usize, with_selection: bool) {
+        if with_selection {
+            if self.selection_start.is_none() {
+                self.selection_start = Some(self.cursor_position);
+            }
+        } else {
+            self.clear_selection();
+        }
+        self.cursor_position = position;
+    }
+
+    /// Deletes back to the start of the previous word, or the selection if there is one.
+    pub fn delete_word_before(&mut self) {
+        if self.has_selection() {
+            self.delete_char_before();
+        } else {
+            let start = self.word_start_before(self.cursor_position);
+            self.replace_range(start, self.cursor_position, "");
+        }
+    }
+
+    /// Deletes up to the end of the next word, or the selection if there is one.
+    pub fn delete_word_after(&mut self) {
+        if self.has_selection() {
+            self.delete_char_after();
+        } else {
+            let end = self.word_end_after(self.cursor_position);
+            self.replace_range(self.cursor_position, end, "");
+        }
+    }
+
+    /// Deletes up to the start of the next word, as vim's `dw` does.
+    pub fn delete_to_next_word_start(&mut self) {
+        let end = self.word_start_after(self.cursor_position);
+        self.replace_range(self.cursor_position, end, "");
+    }
+
+    pub fn delete_to_start(&mut self) {
+        self.replace_range(0, self.cursor_position, "");
+    }
+
+    pub fn delete_to_end(&mut self) {
+        self.replace_range(self.cursor_position, self.text.len(), "");
+    }
+
+    fn previous_boundary(&self, position: usize) -> usize {
+        self.text[..position].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, position: usize) -> usize {
+        self.text[position..].graphemes(true).next().map_or(position, |g| position + g.len())
+    }
+
+    fn word_start_before(&self, position: usize) -> usize {
+        let mut graphemes = self.text[..position].grapheme_indices(true).rev().peekable();
+        while graphemes.next_if(|(_, g)| !is_word(g)).is_some() {}
+        let mut start = 0;
+        while let Some((i, _)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            start = i;
+        }
+        start
+    }
+
+    fn word_end_after(&self, position: usize) -> usize {
+        let mut graphemes = self.text[position..].grapheme_indices(true).peekable();
+        while graphemes.next_if(|(_, g)| !is_word(g)).is_some() {}
+        while graphemes.next_if(|(_, g)| is_word(g)).is_some() {}
+        graphemes.peek().map_or(self.text.len(), |(i, _)| position + i)
+    }
+
+    fn word_start_after(&self, position: usize) -> usize {
+        let mut graphemes = self.text[position..].grapheme_indices(true).peekable();
+        while graphemes.next_if(|(_, g)| is_word(g)).is_some() {}
+        while graphemes.next_if(|(_, g)| !is_word(g)).is_some() {}
+        graphemes.peek().map_or(self.text.len(), |(i, _)| position + i)
+    }
+
+    /// Selects the bytes from `start` to `end`, with the cursor at `end`.
+    pub fn select(&mut self, start: usize, end: usize) {
+        self.selection_start = Some(start);
+        self.cursor_position = end;
+    }
+
     pub fn select_all(&mut self) {
         self.selection_start = Some(0);
         self.cursor_position = self.text.len();
@@ -142,6 +259,12 @@ impl TextInputState {
     }
 }
 
+/// Words are runs of alphanumerics, with any marks combined with them; whitespace, punctuation
+/// and emoji separate them. Going by graphemes keeps word motions off the middle of one.
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
 impl Default for TextInputState {
     fn default() -> Self {
         Self::new()
@@ -165,25 +288,32 @@ impl UndoManager {
         let should_save = force || elapsed.as_millis() > UNDO_GROUP_INTERVAL_MS;
 
         if should_save {
-            if self.position < self.history.len() {
-                self.history.truncate(self.position + 1);
-            }
+            self.record(state);
+            self.last_save_time = now;
+        }
+    }
 
-            if self.history.last() != Some(state) {
-                self.history.push(state.clone());
-                self.position = self.history.len() - 1;
+    /// Makes `state` the newest entry, discarding anything that could have been redone.
+    fn record(&mut self, state: &TextInputState) {
+        self.history.truncate(self.position + 1);
 
-                if self.history.len() > MAX_UNDO_HISTORY {
-                    self.history.remove(0);
-                    self.position = self.position.saturating_sub(1);
-                }
-            }
+        if self.history.last() != Some(state) {
+            self.history.push(state.clone());
+            self.position = self.history.len() - 1;
 
-            self.last_save_time = now;
+            if self.history.len() > MAX_UNDO_HISTORY {
+                self.history.remove(0);
+                self.position = self.position.saturating_sub(1);
+            }
         }
     }
 
-    pub fn undo(&mut self) -> Option<TextInputState> {
+    /// Steps back from `current`, which is recorded first so that redo can return to it.
+    pub fn undo(&mut self, current: &TextInputState) -> Option<TextInputState> {
+        if self.history.get(self.position) != Some(current) {
+            self.record(current);
+        }
+
         if self.position > 0 {
             self.position -= 1;
             Some(self.history[self.position].clone())
@@ -192,6 +322,22 @@ impl UndoManager {
         }
     }
 
+    /// Steps forward again after an undo. Once `current` has been edited since, there is
+    /// nothing left to redo.
+    pub fn redo(&mut self, current: &TextInputState) -> Option<TextInputState> {
+        if self.history.get(self.position) != Some(current) {
+            self.history.truncate(self.position + 1);
+            return None;
+        }
+
+        if self.position + 1 < self.history.len() {
+            self.position += 1;
+            Some(self.history[self.position].clone())
+        } else {
+            None
+        }
+    }
+
     pub fn clear(&mut self) {
         self.history = vec![TextInputState::new()];
         self.position = 0;
@@ -204,3 +350,256 @@ impl Default for UndoManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> TextInputState {
+        let mut state = TextInputState::new();
+        for c in text.chars() {
+            state.insert_char(c);
+        }
+        state
+    }
+
+    #[test]
+    fn typing_mixed_text_keeps_the_cursor_at_the_end() {
+        let state = typed("a日本👍🏽e\u{301}");
+        assert_eq!(state.text(), "a日本👍🏽e\u{301}");
+        assert_eq!(state.cursor_position(), state.text().len());
+    }
+
+    #[test]
+    fn arrows_move_over_whole_graphemes() {
+        let mut state = typed("a日👍🏽e\u{301}");
+        let mut stops = vec![state.cursor_position()];
+        while state.cursor_position() > 0 {
+            state.move_cursor_left(false);
+            stops.push(state.cursor_position());
+        }
+        let after = |text: &str| text.len();
+        assert_eq!(stops, [after("a日👍🏽e\u{301}"), after("a日👍🏽"), after("a日"), after("a"), 0]);
+
+        state.move_cursor_right(false);
+        state.move_cursor_right(false);
+        assert_eq!(state.cursor_position(), after("a日"));
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_whole_graphemes() {
+        let mut state = typed("x👨‍👩‍👧y漢e\u{301}");
+        state.delete_char_before();
+        assert_eq!(state.text(), "x👨‍👩‍👧y漢");
+        state.delete_char_before();
+        assert_eq!(state.text(), "x👨‍👩‍👧y");
+
+        state.move_cursor_home(false);
+        state.move_cursor_right(false);
+        state.delete_char_after();
+        assert_eq!(state.text(), "xy");
+        assert_eq!(state.cursor_position(), 1);
+    }
+
+    #[test]
+    fn typing_over_a_selection_of_wide_characters() {
+        let mut state = typed("ab日本語🎉c");
+        state.move_cursor_left(false);
+        state.move_cursor_left(true);
+        state.move_cursor_left(true);
+        assert_eq!(state.selected_text(), Some("語🎉"));
+
+        state.insert_char('é');
+        assert_eq!(state.text(), "ab日本éc");
+        assert_eq!(state.cursor_position(), "ab日本é".len());
+
+        state.move_cursor_left(true);
+        state.move_cursor_left(true);
+        state.delete_char_before();
+        assert_eq!(state.text(), "ab日c");
+    }
+
+    #[test]
+    fn word_motions_keep_combining_marks_with_their_letter() {
+        let mut state = TextInputState::with_text("cafe\u{301} ok".to_string());
+        state.move_cursor_home(false);
+        state.move_cursor_word_right(false);
+        assert_eq!(state.cursor_position(), "cafe\u{301}".len());
+
+        let mut state = TextInputState::with_text("ok e\u{301}te\u{301}".to_string());
+        state.move_cursor_word_left(false);
+        assert_eq!(state.cursor_position(), 3);
+        state.move_cursor_next_word_start(false);
+        assert_eq!(state.cursor_position(), state.text().len());
+    }
+
+    #[test]
+    fn emoji_separate_words() {
+        let mut state = TextInputState::with_text("hi👋漢字ok 🎉".to_string());
+        state.move_cursor_home(false);
+        state.move_cursor_word_right(false);
+        assert_eq!(state.cursor_position(), 2);
+        state.move_cursor_word_right(false);
+        assert_eq!(state.cursor_position(), "hi👋漢字ok".len());
+        state.move_cursor_word_right(false);
+        assert_eq!(state.cursor_position(), state.text().len());
+    }
+
+    /// Where the cursor stops going from the end of `text` back to the start by words, and then
+    /// forward again.
+    fn word_stops(text: &str) -> (Vec<usize>, Vec<usize>) {
+        let mut state = TextInputState::with_text(text.to_string());
+        let mut back = Vec::new();
+        while state.cursor_position() > 0 {
+            state.move_cursor_word_left(false);
+            back.push(state.cursor_position());
+        }
+        let mut forward = Vec::new();
+        while state.cursor_position() < text.len() {
+            state.move_cursor_word_right(false);
+            forward.push(state.cursor_position());
+        }
+        (back, forward)
+    }
+
+    #[test]
+    fn consecutive_separators_are_skipped_together() {
+        assert_eq!(word_stops("one,  two--(three)"), (vec![12, 6, 0], vec![3, 9, 17, 18]));
+        assert_eq!(word_stops("  ..  "), (vec![0], vec![6]));
+    }
+
+    #[test]
+    fn unicode_letters_and_digits_make_words() {
+        let text = "naïve Straße 42€ Ελλάδα";
+        let (back, forward) = word_stops(text);
+        let at = |word: &str| text.find(word).unwrap();
+        let end = |word: &str| at(word) + word.len();
+        assert_eq!(back, [at("Ελλάδα"), at("42"), at("Straße"), 0]);
+        assert_eq!(forward, [end("naïve"), end("Straße"), end("42"), end("Ελλάδα")]);
+    }
+
+    #[test]
+    fn selecting_by_words_extends_the_selection() {
+        let mut state = TextInputState::with_text("alpha beta gamma".to_string());
+        state.move_cursor_word_left(true);
+        state.move_cursor_word_left(true);
+        assert_eq!(state.selected_text(), Some("beta gamma"));
+        state.move_cursor_word_right(true);
+        assert_eq!(state.selected_text(), Some(" gamma"));
+        state.move_cursor_word_left(false);
+        assert!(!state.has_selection());
+    }
+
+    #[test]
+    fn deleting_words() {
+        let mut state = TextInputState::with_text("alpha, beta;; gamma".to_string());
+        state.delete_word_before();
+        assert_eq!(state.text(), "alpha, beta;; ");
+        state.delete_word_before();
+        assert_eq!(state.text(), "alpha, ");
+
+        state.move_cursor_home(false);
+        state.delete_word_after();
+        assert_eq!(state.text(), ", ");
+        state.delete_word_after();
+        assert_eq!(state.text(), "");
+    }
+
+    #[test]
+    fn deleting_a_word_takes_the_selection_instead() {
+        let mut state = TextInputState::with_text("alpha beta".to_string());
+        state.move_cursor_left(true);
+        state.move_cursor_left(true);
+        state.delete_word_before();
+        assert_eq!(state.text(), "alpha be");
+    }
+
+    fn state(text: &str) -> TextInputState {
+        TextInputState::with_text(text.to_string())
+    }
+
+    /// An undo manager that has been through `texts`, each its own step.
+    fn undo_history(texts: &[&str]) -> UndoManager {
+        let mut undo = UndoManager::new();
+        for text in texts {
+            undo.save(&state(text), true);
+        }
+        undo
+    }
+
+    #[test]
+    fn undo_then_redo_comes_back() {
+        let mut undo = undo_history(&["a", "ab"]);
+        let current = state("abc");
+
+        let back = undo.undo(&current).unwrap();
+        assert_eq!(back.text(), "ab");
+        let back = undo.undo(&back).unwrap();
+        assert_eq!(back.text(), "a");
+
+        let forward = undo.redo(&back).unwrap();
+        assert_eq!(forward.text(), "ab");
+        let forward = undo.redo(&forward).unwrap();
+        assert_eq!(forward.text(), "abc");
+        assert!(undo.redo(&forward).is_none());
+    }
+
+    #[test]
+    fn typing_after_an_undo_drops_what_could_be_redone() {
+        let mut undo = undo_history(&["a", "ab"]);
+        let back = undo.undo(&state("abc")).unwrap();
+        assert_eq!(back.text(), "ab");
+
+        let mut edited = back.clone();
+        edited.insert_char('X');
+        assert!(undo.redo(&edited).is_none());
+
+        // The edit is what undo returns from now on, and "abc" is gone for good.
+        let back = undo.undo(&edited).unwrap();
+        assert_eq!(back.text(), "ab");
+        let forward = undo.redo(&back).unwrap();
+        assert_eq!(forward.text(), "abX");
+        assert!(undo.redo(&forward).is_none());
+    }
+
+    #[test]
+    fn undo_restores_the_cursor_and_selection() {
+        let mut selected = state("hello world");
+        selected.select(0, 5);
+        let mut undo = UndoManager::new();
+        undo.save(&selected, true);
+
+        let back = undo.undo(&state("world")).unwrap();
+        assert_eq!(back, selected);
+        assert_eq!(back.selected_text(), Some("hello"));
+    }
+
+    #[test]
+    fn nothing_to_undo_at_the_start() {
+        let mut undo = UndoManager::new();
+        let back = undo.undo(&state("a")).unwrap();
+        assert_eq!(back.text(), "");
+        assert!(undo.undo(&back).is_none());
+    }
+
+    #[test]
+    fn history_keeps_only_the_newest_steps() {
+        let texts: Vec<String> = (0..MAX_UNDO_HISTORY + 50).map(|i| i.to_string()).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let mut undo = undo_history(&texts);
+
+        let mut current = state(texts.last().unwrap());
+        let mut steps = 0;
+        while let Some(back) = undo.undo(&current) {
+            current = back;
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_UNDO_HISTORY - 1);
+        assert_eq!(current.text(), texts[texts.len() - MAX_UNDO_HISTORY]);
+
+        while let Some(forward) = undo.redo(&current) {
+            current = forward;
+        }
+        assert_eq!(current.text(), *texts.last().unwrap());
+    }
+}
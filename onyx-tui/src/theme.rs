@@ -1,4 +1,26 @@
+use crate::color::ColorDepth;
+use onyx_core::ThemeName;
+use onyx_core::config::ConfigFormat;
 use ratatui::style::{Color, Modifier, Style};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ThemeError {
+    #[error("could not read theme file {path}: {source}")]
+    Read { path: String, source: std::io::Error },
+
+    #[error("theme file {path} is not an object of styles: {reason}")]
+    Parse { path: String, reason: String },
+
+    #[error("unknown theme key '{0}'")]
+    UnknownKey(String),
+
+    #[error("invalid value for theme key '{key}': {reason}")]
+    InvalidValue { key: String, reason: String },
+}
 
 #[derive(Debug, Clone)]
 pub struct Theme {
@@ -13,6 +35,7 @@ pub struct Theme {
     pub help_text: Style,
     pub error: Style,
     pub success: Style,
+    pub link: Style,
 }
 
 impl Default for Theme {
@@ -22,6 +45,82 @@ impl Default for Theme {
 }
 
 impl Theme {
+    pub fn from_name(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Default => Self::default_theme(),
+            ThemeName::Monokai => Self::monokai(),
+            ThemeName::Light => Self::light(),
+            ThemeName::HighContrast => Self::high_contrast(),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ThemeError> {
+        Self::default().with_file(path)
+    }
+
+    /// Overrides the styles named in the file at `path`, e.g.
+    /// `{"user_message": "#8ab4f8", "error": {"fg": "#f38ba8", "modifiers": ["bold"]}}`.
+    /// A `.toml` file is read as TOML and anything else as JSON, as config files are. Keys the
+    /// file leaves out keep their current style.
+    pub fn with_file(mut self, path: &Path) -> Result<Self, ThemeError> {
+        let display = path.display().to_string();
+        let content = fs::read_to_string(path)
+            .map_err(|source| ThemeError::Read { path: display.clone(), source })?;
+        let entries: serde_json::Map<String, Value> = match ConfigFormat::of(path) {
+            ConfigFormat::Json => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(&content).map_err(|e| e.to_string()),
+        }
+        .map_err(|reason| ThemeError::Parse { path: display, reason })?;
+
+        for (key, value) in entries {
+            let Some(style) = self.style_mut(&key) else {
+                return Err(ThemeError::UnknownKey(key));
+            };
+            *style =
+                parse_style(&value).map_err(|reason| ThemeError::InvalidValue { key, reason })?;
+        }
+        Ok(self)
+    }
+
+    /// Converts every style to what a terminal of the given depth can show.
+    pub fn with_depth(mut self, depth: ColorDepth) -> Self {
+        for style in [
+            &mut self.user_message,
+            &mut self.assistant_message,
+            &mut self.system_message,
+            &mut self.input_active,
+            &mut self.input_inactive,
+            &mut self.border,
+            &mut self.border_focused,
+            &mut self.title,
+            &mut self.help_text,
+            &mut self.error,
+            &mut self.success,
+            &mut self.link,
+        ] {
+            *style = depth.convert(*style);
+        }
+        self
+    }
+
+    fn style_mut(&mut self, key: &str) -> Option<&mut Style> {
+        Some(match key {
+            "user_message" => &mut self.user_message,
+            "assistant_message" => &mut self.assistant_message,
+            "system_message" => &mut self.system_message,
+            "input_active" => &mut self.input_active,
+            "input_inactive" => &mut self.input_inactive,
+            "border" => &mut self.border,
+            "border_focused" => &mut self.border_focused,
+            "title" => &mut self.title,
+            "help_text" => &mut self.help_text,
+            "error" => &mut self.error,
+            "success" => &mut self.success,
+            "link" => &mut self.link,
+            _ => return None,
+        })
+    }
+
     pub fn default_theme() -> Self {
         Self {
             user_message: Style::default()
@@ -43,6 +142,7 @@ impl Theme {
                 .add_modifier(Modifier::ITALIC),
             error: Style::default().fg(Color::Rgb(243, 139, 168)).add_modifier(Modifier::BOLD),
             success: Style::default().fg(Color::Rgb(166, 227, 161)).add_modifier(Modifier::BOLD),
+            link: Style::default().fg(Color::Rgb(137, 220, 235)).add_modifier(Modifier::UNDERLINED),
         }
     }
 
@@ -65,6 +165,237 @@ impl Theme {
             help_text: Style::default().fg(Color::Rgb(117, 113, 94)).add_modifier(Modifier::ITALIC),
             error: Style::default().fg(Color::Rgb(249, 38, 114)).add_modifier(Modifier::BOLD),
             success: Style::default().fg(Color::Rgb(166, 226, 46)).add_modifier(Modifier::BOLD),
+            link: Style::default().fg(Color::Rgb(102, 217, 239)).add_modifier(Modifier::UNDERLINED),
+        }
+    }
+
+    /// For terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            user_message: Style::default()
+                .fg(Color::Rgb(30, 102, 245))
+                .add_modifier(Modifier::BOLD),
+            assistant_message: Style::default().fg(Color::Rgb(64, 160, 43)),
+            system_message: Style::default().fg(Color::Rgb(223, 142, 29)),
+            input_active: Style::default()
+                .fg(Color::Rgb(136, 57, 239))
+                .add_modifier(Modifier::BOLD),
+            input_inactive: Style::default().fg(Color::Rgb(108, 111, 133)),
+            border: Style::default().fg(Color::Rgb(172, 176, 190)),
+            border_focused: Style::default()
+                .fg(Color::Rgb(136, 57, 239))
+                .add_modifier(Modifier::BOLD),
+            title: Style::default().fg(Color::Rgb(23, 146, 153)).add_modifier(Modifier::BOLD),
+            help_text: Style::default()
+                .fg(Color::Rgb(108, 111, 133))
+                .add_modifier(Modifier::ITALIC),
+            error: Style::default().fg(Color::Rgb(210, 15, 57)).add_modifier(Modifier::BOLD),
+            success: Style::default().fg(Color::Rgb(64, 160, 43)).add_modifier(Modifier::BOLD),
+            link: Style::default().fg(Color::Rgb(4, 165, 229)).add_modifier(Modifier::UNDERLINED),
+        }
+    }
+
+    /// Sticks to the basic ANSI colours so the terminal's own palette decides the contrast.
+    pub fn high_contrast() -> Self {
+        Self {
+            user_message: Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            assistant_message: Style::default().fg(Color::White),
+            system_message: Style::default().fg(Color::LightYellow),
+            input_active: Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            input_inactive: Style::default().fg(Color::Gray),
+            border: Style::default().fg(Color::Gray),
+            border_focused: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            title: Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            help_text: Style::default().fg(Color::Gray),
+            error: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            success: Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            link: Style::default().fg(Color::LightBlue).add_modifier(Modifier::UNDERLINED),
+        }
+    }
+}
+
+/// A style is either a bare foreground colour or an object with `fg`, `bg` and `modifiers`.
+fn parse_style(value: &Value) -> Result<Style, String> {
+    let spec = match value {
+        Value::String(color) => return Ok(Style::default().fg(parse_color(color)?)),
+        Value::Object(spec) => spec,
+        _ => return Err("expected a hex colour or an object with fg, bg and modifiers".to_string()),
+    };
+
+    let mut style = Style::default();
+    for (property, value) in spec {
+        match (property.as_str(), value) {
+            ("fg", Value::String(color)) => style = style.fg(parse_color(color)?),
+            ("bg", Value::String(color)) => style = style.bg(parse_color(color)?),
+            ("modifiers", Value::Array(modifiers)) => {
+                for modifier in modifiers {
+                    style = style.add_modifier(parse_modifier(modifier)?);
+                }
+            }
+            ("fg" | "bg", _) => return Err(format!("{} must be a hex colour string", property)),
+            ("modifiers", _) => return Err("modifiers must be a list of names".to_string()),
+            _ => return Err(format!("unknown property '{}'", property)),
         }
     }
+    Ok(style)
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    let invalid = || format!("'{}' is not a hex colour like #8ab4f8", color);
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn parse_modifier(modifier: &Value) -> Result<Modifier, String> {
+    match modifier.as_str().map(str::to_lowercase).as_deref() {
+        Some("bold") => Ok(Modifier::BOLD),
+        Some("dim") => Ok(Modifier::DIM),
+        Some("italic") => Ok(Modifier::ITALIC),
+        Some("underlined" | "underline") => Ok(Modifier::UNDERLINED),
+        Some("reversed") => Ok(Modifier::REVERSED),
+        Some("crossed_out") => Ok(Modifier::CROSSED_OUT),
+        _ => Err(format!(
+            "unknown modifier {}; use bold, dim, italic, underlined, reversed or crossed_out",
+            modifier
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes `content` to a theme file called `file_name`, extension included.
+    fn theme_file(file_name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("onyx-theme-tests-{}-{file_name}", std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn load_file(file_name: &str, content: &str) -> Result<Theme, ThemeError> {
+        let path = theme_file(file_name, content);
+        let theme = Theme::from_file(&path);
+        fs::remove_file(path).unwrap();
+        theme
+    }
+
+    fn load(name: &str, content: &str) -> Result<Theme, ThemeError> {
+        load_file(&format!("{name}.json"), content)
+    }
+
+    #[test]
+    fn parses_colours_and_full_styles() {
+        let theme = load(
+            "full",
+            r##"{
+                "user_message": "#8ab4f8",
+                "error": {"fg": "#F38BA8", "bg": "#000000", "modifiers": ["bold", "Underline"]}
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.user_message, Style::default().fg(Color::Rgb(0x8a, 0xb4, 0xf8)));
+        assert_eq!(
+            theme.error,
+            Style::default()
+                .fg(Color::Rgb(0xf3, 0x8b, 0xa8))
+                .bg(Color::Rgb(0, 0, 0))
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+    }
+
+    #[test]
+    fn reads_toml_files_as_toml() {
+        let theme = load_file(
+            "full.toml",
+            r##"
+                user_message = "#8ab4f8"
+
+                [error]
+                fg = "#F38BA8"
+                modifiers = ["bold", "underline"]
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.user_message, Style::default().fg(Color::Rgb(0x8a, 0xb4, 0xf8)));
+        assert_eq!(
+            theme.error,
+            Style::default()
+                .fg(Color::Rgb(0xf3, 0x8b, 0xa8))
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+        assert_eq!(theme.title, Theme::default().title);
+
+        let error = load_file("unknown.toml", "titel = \"#ffffff\"").unwrap_err();
+        assert!(matches!(error, ThemeError::UnknownKey(key) if key == "titel"));
+        let error = load_file("broken.toml", "title = [").unwrap_err();
+        assert!(matches!(error, ThemeError::Parse { .. }), "{error}");
+        // JSON in a .toml file is read as TOML, and isn't valid TOML.
+        let error = load_file("json.toml", r##"{"title": "#ffffff"}"##).unwrap_err();
+        assert!(matches!(error, ThemeError::Parse { .. }), "{error}");
+    }
+
+    #[test]
+    fn keys_left_out_keep_the_default_style() {
+        let theme = load("partial", r##"{"link": "#ffffff"}"##).unwrap();
+        let default = Theme::default();
+
+        assert_eq!(theme.link, Style::default().fg(Color::Rgb(255, 255, 255)));
+        assert_eq!(theme.user_message, default.user_message);
+        assert_eq!(theme.border_focused, default.border_focused);
+        assert_eq!(theme.error, default.error);
+    }
+
+    #[test]
+    fn an_empty_file_is_the_default_theme() {
+        let theme = load("empty", "{}").unwrap();
+        assert_eq!(theme.title, Theme::default().title);
+    }
+
+    #[test]
+    fn rejects_bad_hex_colours() {
+        for colour in ["8ab4f8", "#8ab4f", "#8ab4f80", "#gggggg", "#8ab4é8"] {
+            let content = format!(r#"{{"title": "{colour}"}}"#);
+            let error = load("hex", &content).unwrap_err();
+            assert!(
+                matches!(&error, ThemeError::InvalidValue { key, .. } if key == "title"),
+                "{colour}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys_properties_and_modifiers() {
+        let error = load("key", r##"{"titel": "#ffffff"}"##).unwrap_err();
+        assert!(matches!(error, ThemeError::UnknownKey(key) if key == "titel"));
+
+        let error = load("property", r##"{"title": {"colour": "#ffffff"}}"##).unwrap_err();
+        assert!(error.to_string().contains("unknown property 'colour'"), "{error}");
+
+        let error = load("modifier", r#"{"title": {"modifiers": ["blink"]}}"#).unwrap_err();
+        assert!(error.to_string().contains("unknown modifier"), "{error}");
+
+        let error = load("value", r#"{"title": 3}"#).unwrap_err();
+        assert!(matches!(error, ThemeError::InvalidValue { .. }), "{error}");
+    }
+
+    #[test]
+    fn reports_files_that_are_not_objects() {
+        assert!(matches!(load("array", "[]").unwrap_err(), ThemeError::Parse { .. }));
+        assert!(matches!(load("broken", "{\"title\":").unwrap_err(), ThemeError::Parse { .. }));
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let path = std::env::temp_dir().join("onyx-theme-tests-missing.json");
+        assert!(matches!(Theme::from_file(&path).unwrap_err(), ThemeError::Read { .. }));
+    }
 }
@@ -1,364 +1,2107 @@
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     Frame,
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation},
 };
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
 use thiserror::Error;
+use unicode_width::UnicodeWidthStr;
 
+use crate::clipboard::Clipboard;
+use crate::color::ColorDepth;
+use crate::commands::{self, Args};
 use crate::config_editor::ConfigEditor;
 use crate::cursor::TerminalCursor;
+use crate::file_picker::FilePicker;
+use crate::help::HelpOverlay;
+use crate::history_search::HistorySearch;
+use crate::input_history::InputHistory;
+use crate::keymap;
+use crate::layout::{MessageLayout, RenderKey};
+use crate::links::{self, ScreenLink};
+use crate::notifications::{NotificationLevel, Notifications};
+use crate::reverse_search::ReverseSearch;
 use crate::scroll::ScrollManager;
+use crate::search::ConversationSearch;
+use crate::session_browser::SessionBrowser;
 use crate::text_input::{TextInputState, UndoManager};
-use crate::theme::Theme;
-use crate::widgets::{HelpWidget, InputWidget, MessageWidget};
-use onyx_core::{Config, ConfigSchema, Message};
+use crate::theme::{Theme, ThemeError};
+use crate::vim::{Motion, VimAction, VimState};
+use crate::widgets::{
+    Completion, HelpWidget, InputWidget, MessageWidget, centered, relative_time, spinner_char,
+    wrap_text,
+};
+use onyx_core::export::{self, ExportFormat};
+use onyx_core::fs::{format_size, read_directory, read_text_file};
+use onyx_core::markup::find_links;
+use onyx_core::{
+    Attachment, Config, ConfigResult, ConfigSchema, InputMode, LifetimeStats, Memories, Message,
+    MessageKind, Provider, Role, Session, ThemeName, TimestampDisplay, UsageTotals, format_cost,
+};
 
 #[derive(Debug, Error)]
 pub enum UiError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("could not write {path}: {source}")]
+    Write { path: String, source: std::io::Error },
+
+    #[error("{0}")]
+    Config(#[from] onyx_core::ConfigError),
+
+    #[error("{0}")]
+    Theme(#[from] ThemeError),
 }
 
 pub type Result<T> = std::result::Result<T, UiError>;
 
+/// How many finished messages may pile up before the session is written to disk.
+const AUTOSAVE_INTERVAL: usize = 2;
+
+const SPINNER_FRAME: Duration = Duration::from_millis(80);
+
+/// The shortest wait between frames while the terminal is out of focus, so that the spinner
+/// and toasts don't keep waking the app when nobody is looking.
+const UNFOCUSED_FRAME: Duration = Duration::from_millis(500);
+
+/// The smallest terminal the layout fits in. Anything smaller gets a note asking for more room.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 10;
+
+/// How often relative timestamps ("4m ago") are brought up to date.
+const RELATIVE_TIME_REFRESH: Duration = Duration::from_secs(30);
+
+/// How much of the debug log `/debug` shows.
+const DEBUG_LOG_LINES: usize = 20;
+
+/// A shell command the assistant wants to run, shown until the user approves or denies it.
+#[derive(Debug, Clone)]
+struct Confirmation {
+    command: String,
+    working_dir: String,
+}
+
+/// An action that would lose the conversation or a response, leave the app or repeat a
+/// message, waiting for the user to confirm it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingAction {
+    ClearChat,
+    Quit,
+    OpenLink(String),
+    SendDuplicate,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppMode {
     Chat,
     Config,
+    FilePicker,
+    Sessions,
+    HistorySearch,
+    Help,
 }
 
 pub struct App {
     messages: Vec<Message>,
     input_state: TextInputState,
     undo_manager: UndoManager,
+    vim: VimState,
     should_quit: bool,
     show_help: bool,
     submit: bool,
+    /// Set once sending the same message as the last one again has been confirmed.
+    duplicate_confirmed: bool,
     scroll_manager: ScrollManager,
     theme: Theme,
     input_focused: bool,
+    /// The message highlighted while the chat pane has focus.
+    selected_message: Option<usize>,
+    selection_moved: bool,
+    message_copied: bool,
+    search: Option<ConversationSearch>,
     is_processing: bool,
+    agent_ready: bool,
+    /// See [`Self::set_setup_field`].
+    setup_field: Option<String>,
+    cancel_requested: bool,
+    retry_attempt: Option<u32>,
+    context_dropped: usize,
     spinner_state: usize,
+    processing_started: Instant,
+    message_layout: MessageLayout,
+    /// Whether links are drawn as OSC 8 hyperlinks, and the ones in view as of the last draw.
+    hyperlinks: bool,
+    screen_links: Vec<ScreenLink>,
+    /// How message times are shown, which `T` in the chat cycles through without saving.
+    timestamp_display: TimestampDisplay,
+    timestamps_refreshed: Instant,
+    notifications: Notifications,
+    /// Set when anything on screen may have changed since the last draw.
+    needs_redraw: bool,
     show_command_menu: bool,
     command_menu_selected: usize,
-    available_commands: Vec<(&'static str, &'static str)>,
     config: Config,
     mode: AppMode,
     config_editor: Option<ConfigEditor>,
+    file_picker: Option<FilePicker>,
+    session_browser: Option<SessionBrowser>,
+    input_history: InputHistory,
+    clipboard: Clipboard,
+    history_search: Option<HistorySearch>,
+    reverse_search: Option<ReverseSearch>,
+    help: Option<HelpOverlay>,
     config_saved: bool,
+    /// Whether closing the config editor is waiting on Save / Discard / Cancel.
+    confirming_config_close: bool,
+    agent_rebuild_requested: bool,
+    models_requested: bool,
+    /// The model `/pull` asked for, until the event loop starts downloading it.
+    pull_requested: Option<String>,
+    doctor_requested: bool,
+    confirmation: Option<Confirmation>,
+    confirmation_response: Option<bool>,
+    pending_action: Option<PendingAction>,
+    attachments: Vec<Attachment>,
+    session_id: String,
+    session_title: Option<String>,
+    log_file: Option<PathBuf>,
+    title_requested: bool,
+    title_request_pending: bool,
+    unsaved_messages: usize,
+    /// The user message being edited, by index into `messages`. Sending the input replaces it
+    /// and everything after it.
+    editing_message: Option<usize>,
+    /// What the last edit replaced and where it was, for `/undo` to bring back.
+    replaced_messages: Option<(usize, Vec<Message>)>,
     terminal_cursor: TerminalCursor,
+    /// Whether the terminal window has focus, as last reported by the terminal. Terminals that
+    /// don't report it are taken to always have it.
+    focused: bool,
 }
 
 impl App {
     pub fn new(config: Config) -> Self {
         let terminal_cursor =
             TerminalCursor::new(config.cursor_style, config.cursor_blink_interval);
-        Self {
-            messages: Vec::new(),
+        let session = match config.resume_last_session.then(Session::load_latest) {
+            Some(Ok(Some(session))) => session,
+            _ => Session::new(),
+        };
+        let input_history = InputHistory::load(config.input_history_limit as usize);
+        let mut app = Self {
+            show_help: session.messages.is_empty(),
+            messages: session.messages,
             input_state: TextInputState::new(),
             undo_manager: UndoManager::new(),
+            vim: VimState::default(),
             should_quit: false,
-            show_help: true,
             submit: false,
+            duplicate_confirmed: false,
             scroll_manager: ScrollManager::new(),
-            theme: Theme::default(),
+            theme: Theme::from_name(config.theme),
             input_focused: true,
+            selected_message: None,
+            selection_moved: false,
+            message_copied: false,
+            search: None,
             is_processing: false,
+            agent_ready: false,
+            setup_field: None,
+            cancel_requested: false,
+            retry_attempt: None,
+            context_dropped: 0,
             spinner_state: 0,
+            processing_started: Instant::now(),
+            message_layout: MessageLayout::default(),
+            hyperlinks: links::supports_hyperlinks(),
+            screen_links: Vec::new(),
+            timestamp_display: config.timestamp_display,
+            timestamps_refreshed: Instant::now(),
+            notifications: Notifications::default(),
+            needs_redraw: true,
             show_command_menu: false,
             command_menu_selected: 0,
-            available_commands: vec![
-                ("/help", "Show help information"),
-                ("/config", "Open configuration editor"),
-                ("/now", "Insert current date and time"),
-                ("/save", "Save conversation to log file"),
-            ],
             config,
             mode: AppMode::Chat,
             config_editor: None,
+            file_picker: None,
+            session_browser: None,
+            input_history,
+            clipboard: Clipboard::default(),
+            history_search: None,
+            reverse_search: None,
+            help: None,
             config_saved: false,
+            confirming_config_close: false,
+            agent_rebuild_requested: false,
+            models_requested: false,
+            pull_requested: None,
+            doctor_requested: false,
+            confirmation: None,
+            confirmation_response: None,
+            pending_action: None,
+            attachments: Vec::new(),
+            session_id: session.id,
+            session_title: session.title,
+            log_file: None,
+            title_requested: false,
+            title_request_pending: false,
+            unsaved_messages: 0,
+            editing_message: None,
+            replaced_messages: None,
             terminal_cursor,
+            focused: true,
+        };
+        if let Err(e) = app.apply_theme() {
+            app.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not load the theme file: {}", e),
+            );
+        }
+        app
+    }
+
+    /// Rebuilds the theme from the config, with the theme file (if any) layered over the named
+    /// theme, then fits it to the terminal's colours. On error the named theme is used as is.
+    fn apply_theme(&mut self) -> Result<()> {
+        self.message_layout.clear();
+        let depth = ColorDepth::from_mode(self.config.color_mode);
+        let theme = Theme::from_name(self.config.theme);
+        self.theme = theme.clone().with_depth(depth);
+
+        if let Some(file) = self.config.theme_file() {
+            let path = self.config.resolve_path(file)?;
+            self.theme = theme.with_file(&path)?.with_depth(depth);
         }
+        Ok(())
     }
 
+    /// Opens the config editor. While the agent can't be built, it opens on the field that
+    /// needs setting, already being edited.
     pub fn open_config_editor(&mut self) {
-        self.config_editor = Some(ConfigEditor::new(self.config.clone()));
+        let mut editor = ConfigEditor::new(self.config.clone());
+        if !self.agent_ready
+            && let Some(field) = &self.setup_field
+        {
+            editor = editor.with_focus(field);
+            editor.start_editing();
+        }
+        self.config_editor = Some(editor);
         self.mode = AppMode::Config;
     }
 
+    /// Opens the config editor on `field_id`, as `/config <field>` does.
+    pub(crate) fn open_config_editor_at(&mut self, field_id: &str) -> Option<String> {
+        if !ConfigEditor::has_field(field_id) {
+            return Some(format!(
+                "Unknown config field '{}'. Run `onyx config list` to see them all.",
+                field_id
+            ));
+        }
+        self.config_editor = Some(ConfigEditor::new(self.config.clone()).with_focus(field_id));
+        self.mode = AppMode::Config;
+        None
+    }
+
     pub fn close_config_editor(&mut self) {
         self.config_editor = None;
         self.mode = AppMode::Chat;
         self.config_saved = false;
+        self.confirming_config_close = false;
     }
 
-    pub fn save_config_from_editor(&mut self) -> Result<()> {
-        if let Some(editor) = &self.config_editor {
-            self.config = editor.config.clone();
-            self.config
-                .save()
-                .map_err(|e| UiError::IoError(std::io::Error::other(e.to_string())))?;
-            self.config_saved = true;
-            self.terminal_cursor =
-                TerminalCursor::new(self.config.cursor_style, self.config.cursor_blink_interval);
-        }
-        Ok(())
+    pub fn open_file_picker(&mut self) {
+        self.file_picker = Some(FilePicker::new());
+        self.mode = AppMode::FilePicker;
     }
 
-    pub fn get_config(&self) -> &Config {
-        &self.config
+    pub fn close_file_picker(&mut self) {
+        self.file_picker = None;
+        self.mode = AppMode::Chat;
     }
 
-    pub fn add_message(&mut self, message: Message) {
-        self.messages.push(message);
-        self.scroll_manager.enable_auto_scroll();
+    pub fn open_session_browser(&mut self) {
+        self.session_browser = Some(SessionBrowser::new());
+        self.mode = AppMode::Sessions;
     }
 
-    pub fn update_last_message<F>(&mut self, update_fn: F)
-    where
-        F: FnOnce(&mut Message),
-    {
-        if let Some(last_msg) = self.messages.last_mut() {
-            update_fn(last_msg);
-            self.scroll_manager.enable_auto_scroll();
-        }
+    pub fn close_session_browser(&mut self) {
+        self.session_browser = None;
+        self.mode = AppMode::Chat;
     }
 
-    pub fn get_last_message_mut(&mut self) -> Option<&mut Message> {
-        self.messages.last_mut()
+    pub fn open_history_search(&mut self) {
+        self.history_search = Some(HistorySearch::new(&self.input_history));
+        self.mode = AppMode::HistorySearch;
     }
 
-    pub fn take_input(&mut self) -> Option<String> {
-        if !self.submit {
-            return None;
-        }
-        self.submit = false;
-        if self.input_state.is_empty() {
-            return None;
-        }
-
-        let input = self.input_state.take_text();
+    pub fn close_history_search(&mut self) {
+        self.history_search = None;
+        self.mode = AppMode::Chat;
+    }
 
+    /// Starts a Ctrl+R search from the input, remembering what was in it.
+    fn open_reverse_search(&mut self) {
         self.show_command_menu = false;
-        self.command_menu_selected = 0;
-        self.undo_manager.clear();
+        self.reverse_search =
+            Some(ReverseSearch::new(&self.input_history, self.input_state.clone()));
+        self.show_reverse_search_match();
+    }
 
-        Some(Self::expand_now_command(&input))
+    /// Puts the current Ctrl+R match in the input. Without one, the input stays as it was.
+    fn show_reverse_search_match(&mut self) {
+        if let Some(entry) = self.reverse_search.as_ref().and_then(ReverseSearch::current) {
+            self.input_state = TextInputState::with_text(entry.to_string());
+        }
     }
 
-    pub fn should_quit(&self) -> bool {
-        self.should_quit
+    /// Keys while a Ctrl+R search is open. `None` when there is none; other keys are ignored
+    /// until it is accepted or cancelled.
+    fn handle_reverse_search_event(&mut self, key: KeyEvent) -> Option<bool> {
+        let search = self.reverse_search.as_mut()?;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('r') if ctrl => search.older(),
+            KeyCode::Char(c) if !ctrl => search.push_char(c, &self.input_history),
+            KeyCode::Backspace => search.pop_char(&self.input_history),
+            KeyCode::Enter => {
+                // The match is already in the input; Ctrl+Z brings back what it replaced.
+                let search = self.reverse_search.take()?;
+                if self.input_state != *search.original() {
+                    self.undo_manager.save(search.original(), true);
+                }
+                return Some(true);
+            }
+            KeyCode::Esc => {
+                let search = self.reverse_search.take()?;
+                self.input_state = search.into_original();
+                return Some(true);
+            }
+            _ => return Some(false),
+        }
+        self.show_reverse_search_match();
+        Some(true)
     }
 
-    pub fn set_processing(&mut self, processing: bool) {
-        self.is_processing = processing;
+    pub fn open_help(&mut self) {
+        self.help = Some(HelpOverlay::new(&self.config));
+        self.mode = AppMode::Help;
+        self.show_command_menu = false;
     }
 
-    pub fn tick_spinner(&mut self) {
-        self.spinner_state = self.spinner_state.wrapping_add(1);
+    pub fn close_help(&mut self) {
+        self.help = None;
+        self.mode = AppMode::Chat;
     }
 
-    pub fn clear_chat(&mut self) {
-        self.messages.clear();
-        self.scroll_manager.reset();
+    /// Takes the welcome away, keeping the messages in view where they were.
+    fn hide_help(&mut self) {
+        if self.show_help {
+            self.show_help = false;
+            self.scroll_manager.lines_removed_above(HelpWidget::new(&self.theme).render().len());
+        }
     }
 
-    pub fn save_conversation_log(&self) -> Result<String> {
-        use std::fs;
-        use std::time::{SystemTime, UNIX_EPOCH};
+    /// Replaces the input with a recalled entry. The replaced text goes onto the undo stack, so
+    /// Ctrl+Z brings it back.
+    fn recall_input(&mut self, text: String) {
+        self.undo_manager.save(&self.input_state, true);
+        self.input_state = TextInputState::with_text(text);
+        self.show_command_menu = false;
+        self.command_menu_selected = 0;
+        self.hide_help();
+    }
 
-        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    /// Puts the last message the user sent back in the input, with its attachments, as Alt+Up
+    /// and `/edit` do. Sending it then replaces that message and the replies to it.
+    pub(crate) fn edit_last_message(&mut self) -> Option<String> {
+        if self.is_processing {
+            return Some("Wait for the response to finish before editing.".to_string());
+        }
+        let Some(index) = self
+            .messages
+            .iter()
+            .rposition(|msg| matches!(msg.role, Role::User) && msg.kind == MessageKind::Chat)
+        else {
+            return Some("There is no message to edit yet.".to_string());
+        };
 
-        let filename = format!("onyx-conversation-{}.log", timestamp);
+        let message = &self.messages[index];
+        let (text, attachments) = (message.content.clone(), message.attachments.clone());
+        self.recall_input(text);
+        self.attachments = attachments;
+        self.editing_message = Some(index);
+        self.focus_input();
+        None
+    }
 
-        let mut log_content = String::new();
-        log_content.push_str("Onyx Conversation Log\n");
-        log_content
-            .push_str(&format!("Generated: {}\n", self.config.format_timestamp(SystemTime::now())));
-        log_content.push_str(&format!("{}\n\n", "=".repeat(80)));
+    /// Leaves the message being edited as it was, emptying the input. Ctrl+Z brings the
+    /// edited text back.
+    fn cancel_edit(&mut self) {
+        self.editing_message = None;
+        self.undo_manager.save(&self.input_state, true);
+        self.input_state.clear();
+        self.attachments.clear();
+        self.update_command_menu();
+    }
 
-        for msg in &self.messages {
-            let role = match msg.role {
-                onyx_core::Role::User => "USER",
-                onyx_core::Role::Assistant => "ASSISTANT",
-            };
-            let timestamp = self.config.format_timestamp(msg.timestamp);
-            log_content.push_str(&format!("[{}] {} at {}\n", role, role, timestamp));
-            log_content.push_str(&format!("{}\n", "-".repeat(80)));
-            log_content.push_str(&msg.content);
-            log_content.push_str(&format!("\n\n{}\n\n", "=".repeat(80)));
+    /// Cuts the conversation back to just before message `index`, keeping what was cut for
+    /// `/undo`.
+    fn truncate_messages(&mut self, index: usize) {
+        if index > self.messages.len() {
+            return;
+        }
+        let replaced = self.messages.split_off(index);
+        self.replaced_messages = Some((index, replaced));
+        self.messages_replaced();
+    }
+
+    /// Swaps the messages the last edit replaced back in for the ones that followed it, which
+    /// `/undo` then swaps back again.
+    pub(crate) fn undo_edit(&mut self) -> Option<String> {
+        if self.is_processing {
+            return Some("Wait for the response to finish before undoing an edit.".to_string());
         }
+        let Some((index, replaced)) = self.replaced_messages.take() else {
+            return Some("There is no edit to undo.".to_string());
+        };
+
+        let index = index.min(self.messages.len());
+        let newer = self.messages.split_off(index);
+        self.messages.extend(replaced);
+        self.replaced_messages = Some((index, newer));
+        self.messages_replaced();
 
-        fs::write(&filename, log_content)?;
-        Ok(filename)
+        match self.save_session() {
+            Ok(()) => {
+                self.push_notification(NotificationLevel::Info, "Restored the conversation");
+                None
+            }
+            Err(e) => Some(format!("Restored the conversation, but failed to save it: {}", e)),
+        }
     }
 
-    fn update_command_menu(&mut self) {
-        let input = self.input_state.text();
-        let cursor_position = self.input_state.cursor_position();
-        let input_before_cursor = &input[..cursor_position];
+    fn messages_replaced(&mut self) {
+        self.message_layout.clear();
+        self.refresh_search();
+        self.scroll_manager.enable_auto_scroll();
+        self.needs_redraw = true;
+    }
 
-        if let Some(last_word_start) = input_before_cursor.rfind(|c: char| c.is_whitespace()) {
-            let word = &input_before_cursor[last_word_start + 1..];
-            if word.starts_with('/') {
-                self.show_command_menu = true;
-                return;
+    fn copy_selection(&mut self) -> bool {
+        let Some(text) = self.input_state.selected_text() else {
+            return false;
+        };
+        match self.clipboard.copy(text) {
+            Ok(()) => true,
+            Err(e) => {
+                self.add_message(Message::notice(format!("Could not copy to clipboard: {}", e)));
+                false
             }
-        } else if input_before_cursor.starts_with('/') {
-            self.show_command_menu = true;
-            return;
         }
+    }
 
+    fn focus_chat(&mut self) {
+        self.input_focused = false;
         self.show_command_menu = false;
-        self.command_menu_selected = 0;
+        self.select_message(self.messages.len().checked_sub(1));
     }
 
-    fn get_filtered_commands(&self) -> Vec<(&'static str, &'static str)> {
-        let input = self.input_state.text();
-        let cursor_position = self.input_state.cursor_position();
-        let input_before_cursor = &input[..cursor_position];
+    fn focus_input(&mut self) {
+        self.input_focused = true;
+        self.selected_message = None;
+        self.message_copied = false;
+    }
 
-        let command_prefix =
-            if let Some(last_word_start) = input_before_cursor.rfind(|c: char| c.is_whitespace()) {
-                &input_before_cursor[last_word_start + 1..]
-            } else {
-                input_before_cursor
-            };
+    fn select_message(&mut self, index: Option<usize>) {
+        self.selected_message = index;
+        self.selection_moved = true;
+    }
 
-        if !command_prefix.starts_with('/') {
-            return Vec::new();
+    fn copy_selected_message(&mut self) {
+        let Some(message) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+            return;
+        };
+        match self.clipboard.copy(&message.content) {
+            Ok(()) => self.message_copied = true,
+            Err(e) => {
+                self.add_message(Message::notice(format!("Could not copy to clipboard: {}", e)))
+            }
         }
-
-        self.available_commands
-            .iter()
-            .filter(|(cmd, _)| cmd.starts_with(command_prefix))
-            .copied()
-            .collect()
     }
 
-    pub fn get_command_menu_state(&self) -> Option<(Vec<(&'static str, &'static str)>, usize)> {
-        if self.show_command_menu {
-            let filtered = self.get_filtered_commands();
-            if !filtered.is_empty() {
-                return Some((filtered, self.command_menu_selected));
+    /// Keys while the chat pane has focus. Returns `None` for keys that should get their usual
+    /// handling; typing a character hands focus back to the input and inserts it there.
+    fn handle_chat_pane_event(&mut self, key: KeyEvent) -> Option<bool> {
+        self.message_copied = false;
+        let last = self.messages.len().checked_sub(1);
+
+        match key.code {
+            KeyCode::Up => {
+                let index = self.selected_message.map_or(last, |i| Some(i.saturating_sub(1)));
+                self.select_message(index);
+            }
+            KeyCode::Down => {
+                let index =
+                    self.selected_message.map_or(last, |i| last.map(|last| (i + 1).min(last)));
+                self.select_message(index);
+            }
+            KeyCode::Home => self.select_message((!self.messages.is_empty()).then_some(0)),
+            KeyCode::End => {
+                self.select_message(last);
+                self.scroll_manager.scroll_to_bottom();
             }
+            KeyCode::Enter => self.copy_selected_message(),
+            KeyCode::Esc => self.focus_input(),
+            KeyCode::Char('T') => self.cycle_timestamp_display(),
+            KeyCode::Char(_)
+                if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.focus_input();
+                return None;
+            }
+            _ => return None,
         }
-        None
+        Some(true)
     }
 
-    fn expand_now_command(input: &str) -> String {
-        let now = chrono::Local::now();
-        let formatted = now.format("%Y-%m-%d %H:%M:%S").to_string();
-        input.replace("/now", &formatted)
+    /// Switches between absolute, relative and hidden timestamps for this run only.
+    fn cycle_timestamp_display(&mut self) {
+        self.timestamp_display = match self.timestamp_display {
+            TimestampDisplay::Absolute => TimestampDisplay::Relative,
+            TimestampDisplay::Relative => TimestampDisplay::Hidden,
+            TimestampDisplay::Hidden => TimestampDisplay::Absolute,
+        };
+        self.message_layout.clear();
     }
 
-    pub fn draw(&mut self, frame: &mut Frame) {
-        self.terminal_cursor.update();
-
-        match self.mode {
-            AppMode::Chat => {
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Min(1), Constraint::Length(3)])
-                    .split(frame.area());
+    fn refresh_search(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.refresh(&self.messages);
+        }
+    }
 
-                self.render_chat_area(frame, chunks[0]);
+    /// Keys while a conversation search is open. While the query is being typed it takes the
+    /// text; afterwards n/N and F3/Shift+F3 step through the matches. Returns `None` for keys
+    /// that should get their usual handling.
+    fn handle_search_event(&mut self, key: KeyEvent) -> Option<bool> {
+        let search = self.search.as_mut()?;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+        match key.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::PageUp | KeyCode::PageDown => return None,
+            KeyCode::Char('f') if ctrl => search.start_editing(),
+            KeyCode::Char(_) if ctrl || key.modifiers.contains(KeyModifiers::ALT) => return None,
+            KeyCode::Enter if search.is_editing() => search.finish_editing(),
+            KeyCode::Backspace if search.is_editing() => search.pop_char(&self.messages),
+            KeyCode::Char(c) if search.is_editing() => search.push_char(c, &self.messages),
+            KeyCode::Char('/') => search.start_editing(),
+            KeyCode::Char('n') | KeyCode::Enter => search.next(),
+            KeyCode::F(3) if !shift => search.next(),
+            KeyCode::Char('N') | KeyCode::F(3) => search.previous(),
+            _ => {}
+        }
+        Some(true)
+    }
 
-                let input_widget = InputWidget::new(
-                    self.input_state.text(),
-                    &self.theme,
-                    self.input_focused,
-                    self.is_processing,
-                    self.spinner_state,
-                    self.input_state.cursor_position(),
-                    self.input_state.selection_range(),
-                );
-                input_widget.render(frame, chunks[1], &self.terminal_cursor);
+    fn cut_selection(&mut self) {
+        if self.copy_selection() {
+            self.undo_manager.save(&self.input_state, true);
+            self.input_state.delete_char_before();
+            self.update_command_menu();
+        }
+    }
 
-                if let Some((commands, selected)) = self.get_command_menu_state() {
-                    self.render_command_menu(frame, chunks[1], &commands, selected);
-                }
+    fn paste(&mut self) {
+        match self.clipboard.paste() {
+            Ok(text) => {
+                self.undo_manager.save(&self.input_state, true);
+                // The input is a single line, and a newline would otherwise mean "send".
+                let text = text.lines().collect::<Vec<_>>().join(" ");
+                self.input_state.insert_str(&text);
+                self.update_command_menu();
+                self.hide_help();
             }
-            AppMode::Config => {
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Min(1), Constraint::Length(3)])
-                    .split(frame.area());
+            Err(e) => {
+                self.add_message(Message::notice(format!("Could not paste from clipboard: {}", e)))
+            }
+        }
+    }
 
-                self.render_chat_area(frame, chunks[0]);
+    fn undo(&mut self) {
+        if let Some(state) = self.undo_manager.undo(&self.input_state) {
+            self.input_state = state;
+            self.update_command_menu();
+        }
+    }
 
-                let input_widget = InputWidget::new(
-                    self.input_state.text(),
-                    &self.theme,
-                    false,
-                    self.is_processing,
-                    self.spinner_state,
-                    self.input_state.cursor_position(),
-                    None,
-                );
-                input_widget.render(frame, chunks[1], &self.terminal_cursor);
+    fn redo(&mut self) {
+        if let Some(state) = self.undo_manager.redo(&self.input_state) {
+            self.input_state = state;
+            self.update_command_menu();
+        }
+    }
 
-                if let Some(editor) = &mut self.config_editor {
-                    editor.render(frame, frame.area(), &self.theme, &self.terminal_cursor);
-                }
+    fn move_word(&mut self, backward: bool, with_selection: bool) {
+        self.terminal_cursor.on_activity();
+        if backward {
+            self.input_state.move_cursor_word_left(with_selection);
+        } else {
+            self.input_state.move_cursor_word_right(with_selection);
+        }
+        self.update_command_menu();
+    }
 
-                if self.config_saved {
-                    self.render_save_notification(frame, frame.area());
+    fn apply_vim_action(&mut self, action: VimAction) {
+        self.terminal_cursor.on_activity();
+        match action {
+            VimAction::Move(motion) | VimAction::Insert(Some(motion)) => self.move_to(motion),
+            VimAction::Delete(motion) => {
+                self.undo_manager.save(&self.input_state, true);
+                match motion {
+                    Motion::Left => self.input_state.delete_char_before(),
+                    Motion::Right => self.input_state.delete_char_after(),
+                    Motion::WordForward => self.input_state.delete_to_next_word_start(),
+                    Motion::WordBackward => self.input_state.delete_word_before(),
+                    Motion::LineStart => self.input_state.delete_to_start(),
+                    Motion::LineEnd => self.input_state.delete_to_end(),
                 }
             }
+            VimAction::DeleteLine => {
+                self.undo_manager.save(&self.input_state, true);
+                self.input_state.clear();
+            }
+            VimAction::Undo => self.undo(),
+            VimAction::Redo => self.redo(),
+            VimAction::ScrollUp => self.scroll_manager.scroll_up(1),
+            VimAction::ScrollDown => self.scroll_manager.scroll_down(1),
+            VimAction::ScrollTop => self.scroll_manager.scroll_to_top(),
+            VimAction::ScrollBottom => self.scroll_manager.scroll_to_bottom(),
+            VimAction::Insert(None) | VimAction::Nothing => {}
         }
+        self.update_command_menu();
+    }
 
-        let _ = self.terminal_cursor.apply();
+    fn move_to(&mut self, motion: Motion) {
+        match motion {
+            Motion::Left => self.input_state.move_cursor_left(false),
+            Motion::Right => self.input_state.move_cursor_right(false),
+            Motion::WordForward => self.input_state.move_cursor_next_word_start(false),
+            Motion::WordBackward => self.input_state.move_cursor_word_left(false),
+            Motion::LineStart => self.input_state.move_cursor_home(false),
+            Motion::LineEnd => self.input_state.move_cursor_end(false),
+        }
     }
 
-    fn render_save_notification(&self, frame: &mut Frame, area: Rect) {
-        use ratatui::widgets::Clear;
+    /// Deletes a word, always as its own undo step.
+    fn delete_word(&mut self, backward: bool) {
+        self.terminal_cursor.on_activity();
+        self.undo_manager.save(&self.input_state, true);
+        if backward {
+            self.input_state.delete_word_before();
+        } else {
+            self.input_state.delete_word_after();
+        }
+        self.update_command_menu();
+    }
 
-        let width = 40;
-        let height = 5;
-        let notification_area = Rect {
-            x: (area.width.saturating_sub(width)) / 2,
-            y: (area.height.saturating_sub(height)) / 2,
-            width,
-            height,
-        };
+    fn recall_previous_input(&mut self) {
+        if let Some(entry) = self.input_history.previous(self.input_state.text()) {
+            let entry = entry.to_string();
+            self.recall_input(entry);
+        }
+    }
 
-        frame.render_widget(Clear, notification_area);
+    fn recall_next_input(&mut self) {
+        if let Some(entry) = self.input_history.next() {
+            self.recall_input(entry);
+        }
+    }
 
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .border_style(self.theme.success)
-            .title(Span::styled(" Success ", self.theme.success));
+    pub fn save_config_from_editor(&mut self) -> Result<()> {
+        if let Some(editor) = &mut self.config_editor {
+            editor.mark_saved();
+            self.config = editor.config.clone();
+            self.config
+                .save()
+                .map_err(|e| UiError::IoError(std::io::Error::other(e.to_string())))?;
+            self.config_saved = true;
+            self.agent_rebuild_requested = true;
+            self.apply_config();
+        }
+        Ok(())
+    }
 
-        let inner = block.inner(notification_area);
-        frame.render_widget(block, notification_area);
+    /// Adopts a config that was edited outside Onyx. Display settings take effect at once, and
+    /// the agent is only rebuilt if something it uses changed.
+    pub fn reload_config(&mut self, config: Config) {
+        if self.config.differs_beyond_display(&config) {
+            self.agent_rebuild_requested = true;
+        }
+        self.config = config;
+        self.apply_config();
+        self.push_notification(NotificationLevel::Info, "Config reloaded from disk".to_string());
+    }
 
-        let message = Paragraph::new(Line::from(vec![
-            Span::styled("✓ ", self.theme.success),
-            Span::raw("Configuration saved!"),
-        ]))
-        .alignment(Alignment::Center);
+    /// Brings the cursor, input history and theme in line with a changed config.
+    fn apply_config(&mut self) {
+        self.terminal_cursor =
+            TerminalCursor::new(self.config.cursor_style, self.config.cursor_blink_interval);
+        self.terminal_cursor.set_paused(!self.focused);
+        self.input_history.set_limit(self.config.input_history_limit as usize);
+        self.timestamp_display = self.config.timestamp_display;
+        if let Err(e) = self.apply_theme() {
+            self.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not load the theme file: {}", e),
+            );
+        }
+    }
 
-        frame.render_widget(message, inner);
+    pub fn get_config(&self) -> &Config {
+        &self.config
     }
 
-    fn render_command_menu(
-        &self,
-        frame: &mut Frame,
-        input_area: Rect,
-        commands: &[(&str, &str)],
-        selected: usize,
-    ) {
+    pub fn take_agent_rebuild_request(&mut self) -> bool {
+        std::mem::take(&mut self.agent_rebuild_requested)
+    }
+
+    pub fn take_models_request(&mut self) -> bool {
+        std::mem::take(&mut self.models_requested)
+    }
+
+    pub fn take_pull_request(&mut self) -> Option<String> {
+        self.pull_requested.take()
+    }
+
+    pub fn take_doctor_request(&mut self) -> bool {
+        std::mem::take(&mut self.doctor_requested)
+    }
+
+    /// A connection test asked for from the config editor: its id and the config to try.
+    pub fn take_connection_test_request(&mut self) -> Option<(u64, Config)> {
+        self.config_editor.as_mut()?.take_connection_test_request()
+    }
+
+    /// Shows the outcome of a connection test in the config editor, if it is still open.
+    pub fn finish_connection_test(&mut self, id: u64, result: std::result::Result<String, String>) {
+        if let Some(editor) = &mut self.config_editor {
+            editor.finish_connection_test(id, result);
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn request_confirmation(
+        &mut self,
+        command: impl Into<String>,
+        working_dir: impl Into<String>,
+    ) {
+        self.confirmation =
+            Some(Confirmation { command: command.into(), working_dir: working_dir.into() });
+        self.needs_redraw = true;
+    }
+
+    pub fn take_confirmation_response(&mut self) -> Option<bool> {
+        self.confirmation_response.take()
+    }
+
+    fn answer_confirmation(&mut self, approved: bool) {
+        self.confirmation = None;
+        self.confirmation_response = Some(approved);
+    }
+
+    /// Clears the chat, as Ctrl+L does, asking first if there is a conversation to lose.
+    pub(crate) fn request_clear_chat(&mut self) {
+        let has_conversation = self.messages.iter().any(|msg| msg.kind != MessageKind::Notice);
+        if self.config.confirm_destructive_actions && has_conversation {
+            self.pending_action = Some(PendingAction::ClearChat);
+        } else {
+            self.run_pending_action(PendingAction::ClearChat);
+        }
+    }
+
+    /// Quits, asking first while a response is streaming or messages haven't been saved yet.
+    pub(crate) fn request_quit(&mut self) {
+        if self.config.confirm_destructive_actions
+            && (self.is_processing || self.unsaved_messages > 0)
+        {
+            self.pending_action = Some(PendingAction::Quit);
+        } else {
+            self.quit();
+        }
+    }
+
+    fn run_pending_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::ClearChat => {
+                if let Err(e) = self.clear_chat() {
+                    self.add_message(Message::notice(format!(
+                        "The last conversation wasn't saved: {}",
+                        e
+                    )));
+                }
+            }
+            PendingAction::Quit => self.quit(),
+            PendingAction::SendDuplicate => {
+                self.duplicate_confirmed = true;
+                self.submit = true;
+            }
+            PendingAction::OpenLink(url) => {
+                if let Err(e) = links::open_url(&url) {
+                    self.push_notification(
+                        NotificationLevel::Error,
+                        format!("Could not open {}: {}", url, e),
+                    );
+                }
+            }
+        }
+    }
+
+    fn last_user_message(&self) -> Option<&Message> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|msg| msg.kind == MessageKind::Chat && matches!(msg.role, Role::User))
+    }
+
+    /// The links in the latest reply, as `/links` numbers them.
+    fn latest_links(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|msg| msg.kind == MessageKind::Chat && matches!(msg.role, Role::Assistant))
+            .map(|msg| find_links(&msg.content).iter().map(|link| link.url.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// `/links`: lists the links in the latest reply.
+    pub(crate) fn list_links(&self) -> String {
+        let links = self.latest_links();
+        if links.is_empty() {
+            return "No links in the last reply".to_string();
+        }
+
+        let mut list = "Links in the last reply:".to_string();
+        for (number, url) in links.iter().enumerate() {
+            list.push_str(&format!("\n  {}. {}", number + 1, url));
+        }
+        list.push_str("\n\nOpen one with /open <number>");
+        list
+    }
+
+    /// `/open`: opens link `argument` of the latest reply in the browser, once confirmed.
+    pub(crate) fn open_link(&mut self, argument: &str) -> Option<String> {
+        if argument.is_empty() {
+            return Some("Usage: /open <number> — /links lists them".to_string());
+        }
+        let Some(url) = argument
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| self.latest_links().into_iter().nth(number.checked_sub(1)?))
+        else {
+            return Some(format!("No link #{} — use /links to list them", argument));
+        };
+
+        self.pending_action = Some(PendingAction::OpenLink(url));
+        None
+    }
+
+    /// Draws the links in view as OSC 8 hyperlinks over `buffer`, the frame just drawn, if the
+    /// terminal supports them.
+    pub fn write_hyperlinks(&self, buffer: &Buffer) {
+        if self.hyperlinks
+            && !self.screen_links.is_empty()
+            && let Err(e) = links::write_hyperlinks(&self.screen_links, buffer, self.theme.link)
+        {
+            tracing::warn!("Could not write hyperlinks: {}", e);
+        }
+    }
+
+    pub fn take_attachments(&mut self) -> Vec<Attachment> {
+        std::mem::take(&mut self.attachments)
+    }
+
+    pub fn add_message(&mut self, message: Message) {
+        let finished = message.kind != MessageKind::Notice && !message.is_streaming;
+        self.messages.push(message);
+        self.scroll_manager.enable_auto_scroll();
+        self.refresh_search();
+        self.needs_redraw = true;
+        if finished {
+            self.note_unsaved_message();
+        }
+    }
+
+    fn note_unsaved_message(&mut self) {
+        self.unsaved_messages += 1;
+        if self.unsaved_messages >= AUTOSAVE_INTERVAL
+            && let Err(e) = self.save_session()
+        {
+            self.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not autosave the conversation: {}", e),
+            );
+        }
+    }
+
+    /// Shows `text` as a toast for a few seconds, for warnings and errors from background work
+    /// that aren't part of the conversation. `/notifications` lists the recent ones.
+    pub fn push_notification(&mut self, level: NotificationLevel, text: impl Into<String>) {
+        let text = text.into();
+        match level {
+            NotificationLevel::Info => tracing::info!("{}", text),
+            NotificationLevel::Warning => tracing::warn!("{}", text),
+            NotificationLevel::Error => tracing::error!("{}", text),
+        }
+        self.notifications.push(level, text);
+        self.needs_redraw = true;
+    }
+
+    pub(crate) fn show_log_tail(&self) -> String {
+        let Some(path) = &self.log_file else {
+            return "Logging is not set up.".to_string();
+        };
+        let lines = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                return format!(
+                    "Could not read {}: {}\nSet Debug Log Level in /config to start logging.",
+                    path.display(),
+                    e
+                );
+            }
+        };
+
+        let lines: Vec<&str> = lines.lines().collect();
+        let tail = &lines[lines.len().saturating_sub(DEBUG_LOG_LINES)..];
+        format!("Last {} lines of {}:\n{}", tail.len(), path.display(), tail.join("\n"))
+    }
+
+    /// Lists the recent notifications in the chat, which also clears the toasts away.
+    pub(crate) fn list_notifications(&mut self) -> String {
+        self.notifications.dismiss_all();
+        if self.notifications.recent().next().is_none() {
+            return "No notifications yet.".to_string();
+        }
+
+        let mut listing = String::from("Recent notifications:");
+        for notification in self.notifications.recent() {
+            listing.push_str(&format!(
+                "\n  {} [{}] {}",
+                notification.time.format("%H:%M:%S"),
+                notification.level.label(),
+                notification.text
+            ));
+        }
+        listing
+    }
+
+    /// Writes the conversation to its session file. Notices are left out, and a response that
+    /// is still streaming is saved as far as it got.
+    pub fn save_session(&mut self) -> ConfigResult<()> {
+        self.unsaved_messages = 0;
+
+        let messages: Vec<Message> = self
+            .messages
+            .iter()
+            .filter(|msg| msg.kind != MessageKind::Notice)
+            .cloned()
+            .map(|mut msg| {
+                msg.finish_streaming();
+                msg
+            })
+            .collect();
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        Session { id: self.session_id.clone(), title: self.session_title.clone(), messages }.save()
+    }
+
+    /// The conversation to title once its first response has finished, along with the id of
+    /// the session the title belongs to.
+    pub fn take_title_request(&mut self) -> Option<(String, Vec<Message>)> {
+        std::mem::take(&mut self.title_request_pending)
+            .then(|| (self.session_id.clone(), self.history()))
+    }
+
+    /// Stores a generated title, unless the session has since been switched or renamed.
+    pub fn apply_generated_title(&mut self, session_id: &str, title: String) {
+        if session_id != self.session_id || self.session_title.is_some() || title.is_empty() {
+            return;
+        }
+        self.session_title = Some(title);
+        self.needs_redraw = true;
+        if let Err(e) = self.save_session() {
+            self.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not save the conversation title: {}", e),
+            );
+        }
+    }
+
+    /// Finished chat messages, as sent to the model for context.
+    pub fn history(&self) -> Vec<Message> {
+        self.messages.iter().filter(|msg| Self::in_history(msg)).cloned().collect()
+    }
+
+    fn in_history(msg: &Message) -> bool {
+        !matches!(msg.role, Role::System)
+            && !matches!(msg.kind, MessageKind::Notice | MessageKind::Error)
+            && !msg.summarized
+            && !msg.is_streaming
+            && !msg.content.is_empty()
+    }
+
+    /// Folds the oldest `replaced_messages` of the history into `summary`, which is shown just
+    /// before the user message that triggered it.
+    pub fn apply_history_summary(&mut self, replaced_messages: usize, summary: String) {
+        for msg in
+            self.messages.iter_mut().filter(|msg| Self::in_history(msg)).take(replaced_messages)
+        {
+            msg.summarized = true;
+        }
+
+        let position = self
+            .messages
+            .iter()
+            .rposition(|msg| matches!(msg.role, Role::User))
+            .unwrap_or(self.messages.len());
+        self.messages.insert(position, Message::summary(summary));
+        self.message_layout.clear();
+        self.refresh_search();
+        self.needs_redraw = true;
+    }
+
+    pub fn update_last_message<F>(&mut self, update_fn: F)
+    where
+        F: FnOnce(&mut Message),
+    {
+        if let Some(last_msg) = self.messages.last_mut() {
+            update_fn(last_msg);
+            self.message_layout.invalidate(self.messages.len() - 1);
+            self.scroll_manager.content_changed();
+            self.refresh_search();
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn get_last_message_mut(&mut self) -> Option<&mut Message> {
+        self.needs_redraw = true;
+        self.message_layout.invalidate(self.messages.len().saturating_sub(1));
+        self.messages.last_mut()
+    }
+
+    pub fn take_input(&mut self) -> Option<String> {
+        if !self.submit {
+            return None;
+        }
+        self.submit = false;
+        let confirmed = std::mem::take(&mut self.duplicate_confirmed);
+        // Only blank lines or spaces would reach the provider as an empty message.
+        let text = self.input_state.text().trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        if self.config.confirm_duplicate_send
+            && !confirmed
+            && self.editing_message.is_none()
+            && !text.starts_with('/')
+            && self.last_user_message().is_some_and(|msg| msg.content == text)
+        {
+            self.pending_action = Some(PendingAction::SendDuplicate);
+            return None;
+        }
+
+        let input = self.input_state.take_text().trim().to_string();
+        if let Some(index) = self.editing_message.take()
+            && !input.starts_with('/')
+        {
+            self.truncate_messages(index);
+        }
+        if self.config.input_history_skip_commands && input.starts_with('/') {
+            self.input_history.stop_browsing();
+        } else {
+            // Losing history shouldn't get in the way of sending the message.
+            if let Err(e) = self.input_history.push(&input) {
+                self.push_notification(
+                    NotificationLevel::Warning,
+                    format!("Could not save input history: {}", e),
+                );
+            }
+        }
+
+        self.show_command_menu = false;
+        self.command_menu_selected = 0;
+        self.undo_manager.clear();
+        self.vim.reset();
+
+        Some(input)
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    pub fn set_processing(&mut self, processing: bool) {
+        if processing && !self.is_processing {
+            self.processing_started = Instant::now();
+            self.spinner_state = 0;
+        }
+        self.is_processing = processing;
+        self.retry_attempt = None;
+        self.context_dropped = 0;
+        self.needs_redraw = true;
+    }
+
+    /// Discards the partial response of a failed attempt before the agent retries it.
+    pub fn begin_retry(&mut self, attempt: u32) {
+        self.retry_attempt = Some(attempt);
+        self.update_last_message(|msg| msg.reset_content());
+    }
+
+    pub fn request_cancel(&mut self) {
+        if self.is_processing {
+            self.cancel_requested = true;
+        }
+    }
+
+    pub fn is_cancel_requested(&self) -> bool {
+        self.cancel_requested
+    }
+
+    pub fn finish_streaming(&mut self) {
+        let cancelled = std::mem::take(&mut self.cancel_requested);
+        self.update_last_message(|msg| {
+            if cancelled {
+                msg.append_content(" (cancelled)");
+            }
+            msg.finish_streaming();
+        });
+        self.confirmation = None;
+        self.set_processing(false);
+        self.note_unsaved_message();
+
+        // A task like /pull finishes the same way as a reply, but leaves nothing to title.
+        let replied = self.messages.last().is_some_and(|msg| msg.kind == MessageKind::Chat);
+        if replied && self.session_title.is_none() && !self.title_requested {
+            self.title_requested = true;
+            self.title_request_pending = true;
+        }
+    }
+
+    /// Ends the response with `error`, shown as a message of its own. A reply that got
+    /// nothing before failing is dropped rather than left empty.
+    pub fn fail_streaming(&mut self, error: impl std::fmt::Display) {
+        self.finish_streaming();
+        if self.messages.last().is_some_and(|msg| {
+            matches!(msg.role, Role::Assistant)
+                && msg.kind == MessageKind::Chat
+                && msg.content.is_empty()
+                && msg.thinking.is_none()
+                && msg.tool_calls.is_empty()
+        }) {
+            self.messages.pop();
+            self.message_layout.invalidate(self.messages.len());
+        }
+        self.add_message(Message::error(error.to_string()));
+    }
+
+    pub fn note_context_truncated(&mut self, dropped_messages: usize) {
+        self.context_dropped = dropped_messages;
+        self.needs_redraw = true;
+    }
+
+    fn processing_status(&self) -> Option<String> {
+        if self.confirmation.is_some() {
+            return Some("Waiting for approval".to_string());
+        }
+        if let Some(attempt) = self.retry_attempt {
+            return Some(format!("Retrying ({}/{})", attempt, self.config.max_retries));
+        }
+        match self.context_dropped {
+            0 => None,
+            1 => Some("Processing (1 older message trimmed)".to_string()),
+            n => Some(format!("Processing ({} older messages trimmed)", n)),
+        }
+    }
+
+    /// Where the debug log is written, for `/debug` to show.
+    pub fn set_log_file(&mut self, path: PathBuf) {
+        self.log_file = Some(path);
+    }
+
+    /// Whether a chat agent could be built from the current config, shown in the status bar.
+    pub fn set_agent_ready(&mut self, ready: bool) {
+        self.agent_ready = ready;
+        self.needs_redraw = true;
+    }
+
+    /// The field that has to be set before the agent can be built, which `/config` opens on.
+    pub fn set_setup_field(&mut self, field_id: Option<String>) {
+        self.setup_field = field_id;
+    }
+
+    /// Whether the screen is out of date, either because the state changed or because an
+    /// animation moved on. The event loop only draws when this is true, so an idle app doesn't
+    /// repaint the whole terminal on every wake-up.
+    pub fn needs_redraw(&mut self) -> bool {
+        self.tick();
+        self.needs_redraw || self.terminal_cursor.needs_apply()
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.terminal_cursor.set_paused(!focused);
+    }
+
+    /// How long until the screen next changes by itself: the spinner moving on while
+    /// processing, the cursor blinking or a toast expiring. `None` when nothing is due. Out of
+    /// focus, frames come no more often than every [`UNFOCUSED_FRAME`].
+    pub fn time_until_next_frame(&self) -> Option<Duration> {
+        let testing = self.config_editor.as_ref().is_some_and(ConfigEditor::is_testing_connection);
+        let spinner = (self.is_processing || testing).then(|| {
+            let elapsed = self.processing_started.elapsed().as_nanos();
+            SPINNER_FRAME - Duration::from_nanos((elapsed % SPINNER_FRAME.as_nanos()) as u64)
+        });
+        let timestamps = (self.timestamp_display == TimestampDisplay::Relative)
+            .then(|| RELATIVE_TIME_REFRESH.saturating_sub(self.timestamps_refreshed.elapsed()));
+        [
+            spinner,
+            self.terminal_cursor.time_until_next_blink(),
+            self.notifications.time_until_expiry(),
+            timestamps,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|delay| if self.focused { delay } else { delay.max(UNFOCUSED_FRAME) })
+    }
+
+    /// Brings animations up to date with the clock, so they move at the same pace however often
+    /// input or stream events wake the loop.
+    fn tick(&mut self) {
+        self.terminal_cursor.update();
+        if self.notifications.expire() {
+            self.needs_redraw = true;
+        }
+        if self.config_editor.as_ref().is_some_and(ConfigEditor::is_testing_connection) {
+            self.needs_redraw = true;
+        }
+        if self.timestamp_display == TimestampDisplay::Relative
+            && self.timestamps_refreshed.elapsed() >= RELATIVE_TIME_REFRESH
+        {
+            self.timestamps_refreshed = Instant::now();
+            self.needs_redraw = true;
+        }
+        if self.is_processing {
+            let spinner_state = (self.processing_started.elapsed().as_millis()
+                / SPINNER_FRAME.as_millis()) as usize;
+            if spinner_state != self.spinner_state {
+                self.spinner_state = spinner_state;
+                self.needs_redraw = true;
+            }
+        }
+    }
+
+    /// Saves the current conversation and starts a new session, so clearing never overwrites
+    /// what was said before.
+    pub fn clear_chat(&mut self) -> ConfigResult<()> {
+        let saved = self.save_session();
+        self.switch_session(Session::new());
+        saved
+    }
+
+    /// Saves the current conversation and replaces it with session `id`, which later autosaves
+    /// then write back to.
+    fn load_session(&mut self, id: &str) -> ConfigResult<()> {
+        let session = Session::load(id)?;
+        self.save_session()?;
+
+        self.switch_session(session);
+        self.show_help = self.messages.is_empty();
+        self.scroll_manager.enable_auto_scroll();
+        Ok(())
+    }
+
+    fn switch_session(&mut self, session: Session) {
+        self.messages = session.messages;
+        self.message_layout.clear();
+        self.search = None;
+        self.session_id = session.id;
+        self.session_title = session.title;
+        self.title_requested = false;
+        self.title_request_pending = false;
+        self.unsaved_messages = 0;
+        self.editing_message = None;
+        self.replaced_messages = None;
+        self.scroll_manager.reset();
+        if !self.input_focused {
+            self.focus_chat();
+        }
+    }
+
+    pub(crate) fn rename_session(&mut self, title: &str) -> String {
+        if title.is_empty() {
+            return match &self.session_title {
+                Some(title) => format!("Current title: {}\nUsage: /rename <title>", title),
+                None => "Usage: /rename <title>".to_string(),
+            };
+        }
+
+        self.session_title = Some(title.to_string());
+        match self.save_session() {
+            Ok(()) => format!("Renamed conversation to: {}", title),
+            Err(e) => format!("Renamed conversation, but failed to save it: {}", e),
+        }
+    }
+
+    /// Writes the conversation log to `target`, which may be a file or a directory. Without a
+    /// target the configured log directory is used, or else the current directory.
+    pub fn save_conversation_log(&self, target: &str) -> Result<String> {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        let filename = format!("onyx-conversation-{}.log", timestamp);
+        let path = match (target, self.config.log_directory()) {
+            ("", None) => PathBuf::from(&filename),
+            ("", Some(dir)) => self.config.resolve_path(dir)?.join(&filename),
+            (target, _) => {
+                let path = self.config.resolve_path(target)?;
+                if path.is_dir() || target.ends_with(['/', '\\']) {
+                    path.join(&filename)
+                } else {
+                    path
+                }
+            }
+        };
+        let write_error = |path: &std::path::Path, source| UiError::Write {
+            path: path.display().to_string(),
+            source,
+        };
+
+        let mut log_content = String::new();
+        log_content.push_str("Onyx Conversation Log\n");
+        log_content
+            .push_str(&format!("Generated: {}\n", self.config.format_timestamp(SystemTime::now())));
+        log_content.push_str(&format!(
+            "Model: {} ({})\n",
+            self.config.get_active_provider().model,
+            self.config.active_provider
+        ));
+        log_content.push_str(&format!("{}\n\n", "=".repeat(80)));
+
+        for msg in &self.messages {
+            let role = match msg.role {
+                onyx_core::Role::User => "USER",
+                onyx_core::Role::Assistant => "ASSISTANT",
+                onyx_core::Role::System => "SYSTEM",
+            };
+            let timestamp = self.config.format_timestamp(msg.timestamp);
+            log_content.push_str(&format!("[{}] {} at {}", role, role, timestamp));
+            if let Some(usage) = &msg.usage {
+                log_content.push_str(&format!(
+                    " ({} prompt / {} completion / {} total tokens)",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                ));
+            }
+            log_content.push('\n');
+            log_content.push_str(&format!("{}\n", "-".repeat(80)));
+            log_content.push_str(&msg.content);
+            log_content.push_str(&format!("\n\n{}\n\n", "=".repeat(80)));
+        }
+
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|source| write_error(parent, source))?;
+        }
+        fs::write(&path, log_content).map_err(|source| write_error(&path, source))?;
+        Ok(path.display().to_string())
+    }
+
+    /// Handles `/export [md|json|html] [path]`. A lone argument may be either the format or a
+    /// path, whose extension then picks the format.
+    pub(crate) fn export(&self, args: &Args) -> String {
+        let (format, path) = match args.words() {
+            [] => (ExportFormat::default(), None),
+            [argument] => match argument.parse() {
+                Ok(format) => (format, None),
+                Err(_) => {
+                    (ExportFormat::from_path(argument).unwrap_or_default(), Some(argument.clone()))
+                }
+            },
+            [format, path @ ..] => match format.parse() {
+                Ok(format) => (format, Some(path.join(" "))),
+                Err(e) => return e,
+            },
+        };
+
+        let path = path.unwrap_or_else(|| {
+            let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            format!("onyx-conversation-{}.{}", timestamp, format.extension())
+        });
+
+        let written =
+            export::render(format, &self.messages, self.session_title.as_deref(), &self.config)
+                .and_then(|content| std::fs::write(&path, content));
+
+        match written {
+            Ok(()) => format!("Conversation exported to: {}", path),
+            Err(e) => format!("Failed to export conversation: {}", e),
+        }
+    }
+
+    fn update_command_menu(&mut self) {
+        let shown = self.get_filtered_commands().len();
+        self.show_command_menu = shown > 0;
+        if self.command_menu_selected >= shown {
+            self.command_menu_selected = 0;
+        }
+    }
+
+    /// Puts the highlighted completion in place of the word under the cursor, followed by a
+    /// space if it is a command that takes an argument, or what it stands for if it is an
+    /// inline command like `/now`. Returns the completion, if there was one.
+    fn accept_completion(&mut self) -> Option<Completion> {
+        let filtered = self.get_filtered_commands();
+        let completion = filtered.into_iter().nth(self.command_menu_selected)?;
+        self.undo_manager.save(&self.input_state, true);
+
+        let cursor_position = self.input_state.cursor_position();
+        let input = self.input_state.text();
+        let cmd_start = input[..cursor_position]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(pos, c)| pos + c.len_utf8());
+        let followed_by_space = input[cursor_position..].starts_with(char::is_whitespace);
+
+        let text = commands::inline_text(&completion.text, &self.config)
+            .unwrap_or_else(|| completion.text.clone());
+        self.input_state.replace_range(cmd_start, cursor_position, &text);
+        if completion.argument.is_some() {
+            if followed_by_space {
+                self.input_state.move_cursor_right(false);
+            } else {
+                self.input_state.insert_char(' ');
+            }
+            // Offers the argument's values, for the commands that have a list of them.
+            self.update_command_menu();
+        } else {
+            self.show_command_menu = false;
+            self.command_menu_selected = 0;
+        }
+        Some(completion)
+    }
+
+    /// Completions for the word under the cursor: command names, or the value of a command
+    /// argument such as `/provider <name>`.
+    fn get_filtered_commands(&self) -> Vec<Completion> {
+        let input = self.input_state.text();
+        let cursor_position = self.input_state.cursor_position();
+        let input_before_cursor = &input[..cursor_position];
+
+        let command_prefix =
+            input_before_cursor.rsplit(char::is_whitespace).next().unwrap_or(input_before_cursor);
+
+        if !command_prefix.starts_with('/') {
+            return match input_before_cursor.split_once(' ') {
+                Some((command, argument)) if !argument.contains(char::is_whitespace) => {
+                    Self::argument_completions(command, argument)
+                }
+                _ => Vec::new(),
+            };
+        }
+
+        let built_in = commands::COMMANDS.iter().map(|command| Completion {
+            text: command.name.to_string(),
+            description: command.description.to_string(),
+            argument: command.argument,
+        });
+        let custom = self.config.custom_commands().filter(|(name, _)| !commands::is_built_in(name));
+        let custom = custom
+            .map(|(name, template)| Completion::new(name, commands::template_summary(template)));
+        built_in
+            .chain(custom)
+            .filter(|completion| completion.text.starts_with(command_prefix))
+            .collect()
+    }
+
+    fn argument_completions(command: &str, prefix: &str) -> Vec<Completion> {
+        match command {
+            "/provider" => Provider::iter()
+                .map(<&'static str>::from)
+                .filter(|name| name.to_lowercase().starts_with(&prefix.to_lowercase()))
+                .map(|name| Completion::new(name, "Switch to this provider"))
+                .collect(),
+            "/theme" => ThemeName::iter()
+                .map(<&'static str>::from)
+                .chain(["reload"])
+                .filter(|name| name.starts_with(&prefix.to_lowercase()))
+                .map(|name| match name {
+                    "reload" => Completion::new(name, "Reload the theme file"),
+                    _ => Completion::new(name, "Switch to this theme"),
+                })
+                .collect(),
+            "/attach" => Self::path_completions(prefix),
+            "/export" => [
+                (ExportFormat::Markdown, "Export as Markdown"),
+                (ExportFormat::Json, "Export as JSON"),
+                (ExportFormat::Html, "Export as a standalone HTML page"),
+            ]
+            .into_iter()
+            .map(|(format, description)| Completion::new(format.to_string(), description))
+            .filter(|completion| completion.text.starts_with(&prefix.to_lowercase()))
+            .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Entries of the directory named by `prefix` whose names continue it. Hidden files are
+    /// only offered once the name being typed starts with a dot.
+    fn path_completions(prefix: &str) -> Vec<Completion> {
+        let (dir, name_prefix) = match prefix.rfind('/') {
+            Some(pos) => prefix.split_at(pos + 1),
+            None => ("", prefix),
+        };
+        let Ok(entries) =
+            read_directory(std::path::Path::new(if dir.is_empty() { "." } else { dir }))
+        else {
+            return Vec::new();
+        };
+
+        entries
+            .into_iter()
+            .filter(|entry| entry.name.starts_with(name_prefix))
+            .filter(|entry| name_prefix.starts_with('.') || !entry.is_hidden())
+            .map(|entry| {
+                let suffix = if entry.is_dir { "/" } else { "" };
+                let description = if entry.is_dir { "Directory" } else { "File" };
+                Completion::new(format!("{}{}{}", dir, entry.name, suffix), description)
+            })
+            .collect()
+    }
+
+    fn get_command_menu_state(&self) -> Option<(Vec<Completion>, usize)> {
+        if self.show_command_menu {
+            let filtered = self.get_filtered_commands();
+            if !filtered.is_empty() {
+                return Some((filtered, self.command_menu_selected));
+            }
+        }
+        None
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        self.needs_redraw = false;
+
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            self.render_too_small(frame, area);
+            let _ = self.terminal_cursor.apply();
+            return;
+        }
+
+        match self.mode {
+            AppMode::Chat => {
+                // The input grows with its text, but always leaves the chat a few lines.
+                let max_lines = (self.config.input_max_lines as u16).min(area.height / 2);
+                let input_height =
+                    InputWidget::height(self.input_state.text(), area.width, max_lines);
+                let chips_height = if self.attachments.is_empty() { 0 } else { 1 };
+                let status_height = if self.config.show_status_bar { 1 } else { 0 };
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Min(1),
+                        Constraint::Length(chips_height),
+                        Constraint::Length(input_height),
+                        Constraint::Length(status_height),
+                    ])
+                    .split(area);
+
+                self.render_chat_area(frame, chunks[0]);
+                self.render_attachments(frame, chunks[1]);
+                if self.config.show_status_bar {
+                    self.render_status_bar(frame, chunks[3]);
+                }
+
+                let input_widget = InputWidget::new(
+                    self.input_state.text(),
+                    &self.theme,
+                    self.input_focused,
+                    self.is_processing,
+                    self.spinner_state,
+                    self.input_state.cursor_position(),
+                    self.input_state.selection_range(),
+                )
+                .status(self.processing_status())
+                .show_progress(!self.config.show_status_bar)
+                .editing(self.editing_message.is_some())
+                .vim_mode((self.config.input_mode == InputMode::Vim).then(|| self.vim.indicator()));
+                input_widget.render(frame, chunks[2], &self.terminal_cursor);
+
+                if let Some(search) = &self.reverse_search {
+                    search.render(frame, chunks[2], &self.theme);
+                } else if let Some((commands, selected)) = self.get_command_menu_state() {
+                    self.render_command_menu(frame, chunks[2], &commands, selected);
+                }
+            }
+            AppMode::Config
+            | AppMode::FilePicker
+            | AppMode::Sessions
+            | AppMode::HistorySearch
+            | AppMode::Help => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(3)])
+                    .split(frame.area());
+
+                self.render_chat_area(frame, chunks[0]);
+
+                let input_widget = InputWidget::new(
+                    self.input_state.text(),
+                    &self.theme,
+                    false,
+                    self.is_processing,
+                    self.spinner_state,
+                    self.input_state.cursor_position(),
+                    None,
+                )
+                .status(self.processing_status());
+                input_widget.render(frame, chunks[1], &self.terminal_cursor);
+
+                if let Some(editor) = &mut self.config_editor {
+                    editor.render(frame, frame.area(), &self.theme, &self.terminal_cursor);
+                }
+
+                if let Some(picker) = &mut self.file_picker {
+                    picker.render(frame, frame.area(), &self.theme);
+                }
+
+                if let Some(browser) = &mut self.session_browser {
+                    browser.render(frame, frame.area(), &self.theme, &self.config);
+                }
+
+                if let Some(search) = &mut self.history_search {
+                    search.render(frame, frame.area(), &self.theme);
+                }
+
+                if let Some(help) = &mut self.help {
+                    help.render(frame, frame.area(), &self.theme);
+                }
+
+                if self.confirming_config_close {
+                    self.render_unsaved_changes_prompt(frame, frame.area());
+                } else if self.config_saved {
+                    self.render_save_notification(frame, frame.area());
+                }
+            }
+        }
+
+        self.render_toasts(frame, frame.area());
+
+        if let Some(confirmation) = &self.confirmation {
+            self.render_confirmation(frame, frame.area(), confirmation);
+        } else if let Some(action) = &self.pending_action {
+            self.render_pending_action_prompt(frame, frame.area(), action);
+        }
+
+        let _ = self.terminal_cursor.apply();
+    }
+
+    fn render_confirmation(&self, frame: &mut Frame, area: Rect, confirmation: &Confirmation) {
+        use ratatui::widgets::{Clear, Wrap};
+
+        let dialog_area = centered(area, 70.min(area.width.saturating_sub(4)), 9);
+
+        frame.render_widget(Clear, dialog_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.error)
+            .title(Span::styled(" Run shell command? ", self.theme.error));
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let text = vec![
+            Line::from(Span::styled(confirmation.command.clone(), self.theme.assistant_message)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("in ", self.theme.help_text),
+                Span::styled(confirmation.working_dir.clone(), self.theme.help_text),
+            ]),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("[y] ", self.theme.success),
+                Span::styled("run ", self.theme.help_text),
+                Span::styled("• ", self.theme.border),
+                Span::styled("[n] ", self.theme.error),
+                Span::styled("deny", self.theme.help_text),
+            ]),
+        ];
+
+        frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }), inner);
+    }
+
+    fn render_too_small(&self, frame: &mut Frame, area: Rect) {
+        use ratatui::widgets::Wrap;
+
+        let message = vec![
+            Line::from(Span::styled("Terminal too small", self.theme.error)),
+            Line::from(Span::styled(
+                format!("need ≥ {}x{}, have {}x{}", MIN_WIDTH, MIN_HEIGHT, area.width, area.height),
+                self.theme.help_text,
+            )),
+        ];
+        let top = area.height.saturating_sub(message.len() as u16) / 2;
+        let message_area = Rect { y: area.y + top, height: area.height - top, ..area };
+
+        frame.render_widget(
+            Paragraph::new(message).alignment(Alignment::Center).wrap(Wrap { trim: true }),
+            message_area,
+        );
+    }
+
+    fn render_attachments(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = vec![Span::raw(" ")];
+        for attachment in &self.attachments {
+            spans.push(Span::styled(
+                format!("📎 {} ({})", attachment.file_name(), format_size(attachment.size())),
+                self.theme.success,
+            ));
+            spans.push(Span::raw("  "));
+        }
+
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    /// One line with what the app is doing, the provider and model in use, the session and its
+    /// token usage. When space runs out the session name goes first, then the usage.
+    fn render_status_bar(&self, frame: &mut Frame, area: Rect) {
+        let activity = if self.is_processing {
+            let status = self.processing_status().unwrap_or_else(|| "Processing".to_string());
+            (format!("{} {}…", spinner_char(self.spinner_state), status), self.theme.success)
+        } else if self.agent_ready {
+            ("● Ready".to_string(), self.theme.success)
+        } else {
+            ("○ Not configured".to_string(), self.theme.error)
+        };
+        let model = (
+            format!(
+                "{} · {}",
+                self.config.active_provider,
+                self.config.get_active_provider().model
+            ),
+            self.theme.title,
+        );
+        let session = self.session_title.clone().map(|title| (title, self.theme.help_text));
+        let usage = self.session_usage().map(|(prompt, completion)| {
+            let cost = self.session_cost().map_or_else(|| "n/a".to_string(), format_cost);
+            (format!("{} in / {} out · {}", prompt, completion, cost), self.theme.help_text)
+        });
+
+        let separator = " │ ";
+        let width = |parts: &[&(String, Style)]| {
+            parts.iter().map(|(text, _)| text.width()).sum::<usize>()
+                + separator.width() * parts.len().saturating_sub(1)
+                + 2
+        };
+        let mut parts: Vec<&(String, Style)> =
+            [Some(&activity), Some(&model), usage.as_ref(), session.as_ref()]
+                .into_iter()
+                .flatten()
+                .collect();
+        while parts.len() > 2 && width(&parts) > area.width as usize {
+            parts.pop();
+        }
+
+        let mut spans = vec![Span::raw(" ")];
+        for (i, (text, style)) in parts.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(separator, self.theme.border));
+            }
+            spans.push(Span::styled(text.clone(), *style));
+        }
+
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    /// Tokens used so far in this session, summed over the replies that reported usage.
+    fn session_usage(&self) -> Option<(u64, u64)> {
+        self.messages.iter().filter_map(|msg| msg.usage.as_ref()).fold(None, |total, usage| {
+            let (prompt, completion) = total.unwrap_or_default();
+            Some((prompt + usage.prompt_tokens, completion + usage.completion_tokens))
+        })
+    }
+
+    /// What the replies so far have cost, counting those whose model's price is known.
+    fn session_cost(&self) -> Option<f64> {
+        self.messages.iter().filter_map(|msg| msg.cost).reduce(|total, cost| total + cost)
+    }
+
+    /// Adds the reply that just finished to the lifetime stats in `stats.json`.
+    pub fn record_response(&mut self) {
+        let Some(message) = self.messages.last() else {
+            return;
+        };
+        // A pull finishes the same way, with its progress as the last message.
+        if message.kind != MessageKind::Chat || !matches!(message.role, Role::Assistant) {
+            return;
+        }
+        let provider = self.config.active_provider.to_string();
+        if let Err(e) = LifetimeStats::record(&provider, message) {
+            self.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not save usage stats: {}", e),
+            );
+        }
+    }
+
+    /// `/stats`: shows the usage report, or with `reset` starts the lifetime stats over.
+    pub(crate) fn stats(&mut self, argument: &str) -> String {
+        match argument {
+            "" => self.stats_report(),
+            "reset" => match LifetimeStats::reset() {
+                Ok(()) => "Usage stats reset".to_string(),
+                Err(e) => format!("Failed to reset usage stats: {}", e),
+            },
+            _ => "Usage: /stats [reset]".to_string(),
+        }
+    }
+
+    /// The `/stats` table: this session's messages, tokens, speed and cost beside the totals
+    /// across every session, then the replies each provider gave.
+    fn stats_report(&self) -> String {
+        let lifetime = match LifetimeStats::load() {
+            Ok(lifetime) => lifetime,
+            Err(e) => return format!("Failed to load usage stats: {}", e),
+        };
+        let session = UsageTotals::from_messages(&self.messages);
+
+        let chat = || self.messages.iter().filter(|msg| msg.kind == MessageKind::Chat);
+        let count = |role: fn(&Role) -> bool| {
+            chat()
+                .filter(|msg| role(&msg.role))
+                .fold((0, 0), |(n, chars), msg| (n + 1, chars + msg.content.chars().count()))
+        };
+        let (from_you, your_chars) = count(|role| matches!(role, Role::User));
+        let (_, reply_chars) = count(|role| matches!(role, Role::Assistant));
+
+        let seconds = |totals: &UsageTotals| {
+            totals.average_latency().map_or_else(|| "–".to_string(), |s| format!("{:.1}s", s))
+        };
+        let speed = |totals: &UsageTotals| {
+            totals.tokens_per_second().map_or_else(|| "–".to_string(), |t| format!("{:.1}", t))
+        };
+        let cost =
+            |totals: &UsageTotals| totals.cost.map_or_else(|| "n/a".to_string(), format_cost);
+        let all = &lifetime.totals;
+
+        let rows = [
+            ("Messages from you", from_you.to_string(), "–".to_string()),
+            ("Replies", session.responses.to_string(), all.responses.to_string()),
+            ("Characters (you)", your_chars.to_string(), "–".to_string()),
+            ("Characters (replies)", reply_chars.to_string(), "–".to_string()),
+            (
+                "Estimated tokens",
+                (your_chars + reply_chars).div_ceil(4).to_string(),
+                "–".to_string(),
+            ),
+            ("Tokens in", session.prompt_tokens.to_string(), all.prompt_tokens.to_string()),
+            (
+                "Tokens out",
+                session.completion_tokens.to_string(),
+                all.completion_tokens.to_string(),
+            ),
+            ("Average latency", seconds(&session), seconds(all)),
+            ("Tokens/sec", speed(&session), speed(all)),
+            ("Estimated cost", cost(&session), cost(all)),
+        ];
+
+        let mut report = format!("  {:<22}{:>14}{:>14}\n", "", "This session", "All time");
+        for (label, this_session, all_time) in rows {
+            report.push_str(&format!("  {:<22}{:>14}{:>14}\n", label, this_session, all_time));
+        }
+
+        let since = chrono::DateTime::<chrono::Local>::from(lifetime.since).format("%Y-%m-%d");
+        if all.requests.is_empty() {
+            report.push_str(&format!("\nNo replies recorded since {}", since));
+        } else {
+            report.push_str(&format!("\nReplies by provider since {}:\n", since));
+            for (provider, requests) in &all.requests {
+                report.push_str(&format!("  {:<22}{:>14}\n", provider, requests));
+            }
+        }
+
+        format!("Usage statistics:\n{}", report.trim_end())
+    }
+
+    fn render_save_notification(&self, frame: &mut Frame, area: Rect) {
+        use ratatui::widgets::Clear;
+
+        let notification_area = centered(area, 40, 5);
+
+        frame.render_widget(Clear, notification_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.success)
+            .title(Span::styled(" Success ", self.theme.success));
+
+        let inner = block.inner(notification_area);
+        frame.render_widget(block, notification_area);
+
+        let message = Paragraph::new(Line::from(vec![
+            Span::styled("✓ ", self.theme.success),
+            Span::raw("Configuration saved!"),
+        ]))
+        .alignment(Alignment::Center);
+
+        frame.render_widget(message, inner);
+    }
+
+    fn render_unsaved_changes_prompt(&self, frame: &mut Frame, area: Rect) {
+        use ratatui::widgets::Clear;
+
+        let prompt_area = centered(area, 40, 5);
+
+        frame.render_widget(Clear, prompt_area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.error)
+            .title(Span::styled(" Unsaved Changes ", self.theme.error));
+
+        let inner = block.inner(prompt_area);
+        frame.render_widget(block, prompt_area);
+
+        let message = Paragraph::new(vec![
+            Line::from("Save your changes before closing?"),
+            Line::from(""),
+            Line::from(Span::styled("[s] Save  [d] Discard  [c] Cancel", self.theme.help_text)),
+        ])
+        .alignment(Alignment::Center);
+
+        frame.render_widget(message, inner);
+    }
+
+    fn render_pending_action_prompt(&self, frame: &mut Frame, area: Rect, action: &PendingAction) {
+        use ratatui::widgets::Clear;
+
+        // Wide enough for most links.
+        let width = match action {
+            PendingAction::OpenLink(_) => 70.min(area.width.saturating_sub(4)),
+            _ => 44,
+        };
+        let prompt_area = centered(area, width, 6);
+
+        frame.render_widget(Clear, prompt_area);
+
+        let (title, question, detail) = match action {
+            PendingAction::ClearChat => {
+                (" Clear Chat ", "Start a new conversation?", "This one stays in /sessions.")
+            }
+            PendingAction::Quit if self.is_processing => {
+                (" Quit ", "Quit Onyx?", "The response so far will be kept.")
+            }
+            PendingAction::Quit => (" Quit ", "Quit Onyx?", "The conversation will be saved."),
+            PendingAction::OpenLink(url) => (" Open Link ", "Open in the browser?", url.as_str()),
+            PendingAction::SendDuplicate => {
+                (" Send Again ", "Send the same message again?", "It matches your last one.")
+            }
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.error)
+            .title(Span::styled(title, self.theme.error));
+
+        let inner = block.inner(prompt_area);
+        frame.render_widget(block, prompt_area);
+
+        let message = Paragraph::new(vec![
+            Line::from(question),
+            Line::from(Span::styled(detail, self.theme.help_text)),
+            Line::from(""),
+            Line::from(Span::styled("[y] Yes  [n] No", self.theme.help_text)),
+        ])
+        .alignment(Alignment::Center);
+
+        frame.render_widget(message, inner);
+    }
+
+    /// Stacks the current toasts in the top-right corner, newest at the top.
+    fn render_toasts(&self, frame: &mut Frame, area: Rect) {
+        use ratatui::widgets::Clear;
+
+        let width = 48.min(area.width.saturating_sub(2));
+        let mut y = area.y + 1;
+        for notification in self.notifications.toasts().collect::<Vec<_>>().into_iter().rev() {
+            let style = match notification.level {
+                NotificationLevel::Info => self.theme.success,
+                NotificationLevel::Warning => self.theme.title,
+                NotificationLevel::Error => self.theme.error,
+            };
+            let lines: Vec<Line> = wrap_text(&notification.text, width.saturating_sub(2) as usize)
+                .into_iter()
+                .map(Line::from)
+                .collect();
+            let height = (lines.len() as u16 + 2).min(area.bottom().saturating_sub(y));
+            if height < 3 {
+                break;
+            }
+
+            let toast_area = Rect { x: area.right().saturating_sub(width + 1), y, width, height };
+            frame.render_widget(Clear, toast_area);
+            frame.render_widget(
+                Paragraph::new(lines).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(style)
+                        .title(Span::styled(format!(" {} ", notification.level.label()), style)),
+                ),
+                toast_area,
+            );
+            y += height;
+        }
+    }
+
+    fn render_command_menu(
+        &self,
+        frame: &mut Frame,
+        input_area: Rect,
+        commands: &[Completion],
+        selected: usize,
+    ) {
         use crate::widgets::CommandMenuWidget;
 
-        let menu_height = (commands.len() as u16).min(5) + 2;
+        // The menu sits above the input, so it can't be taller than the space there.
+        let menu_height = ((commands.len() as u16).min(5) + 2).min(input_area.y);
         let menu_width = 50.min(input_area.width.saturating_sub(4));
+        if menu_height < 3 || menu_width == 0 {
+            return;
+        }
 
         let menu_area = Rect {
             x: input_area.x + 2,
-            y: input_area.y.saturating_sub(menu_height),
+            y: input_area.y - menu_height,
             width: menu_width,
             height: menu_height,
         };
@@ -368,43 +2111,184 @@ impl App {
     }
 
     fn render_chat_area(&mut self, frame: &mut Frame, area: Rect) {
-        let block = Block::default()
+        let border_style =
+            if self.input_focused { self.theme.border } else { self.theme.border_focused };
+        let mut title = vec![Span::styled(
+            format!(" {} ", self.session_title.as_deref().unwrap_or("Onyx Chat")),
+            self.theme.title,
+        )];
+        if let Some(search) = &self.search
+            && !search.query().is_empty()
+        {
+            title.push(Span::styled(
+                format!("[{}: {}] ", search.query(), search.position()),
+                self.theme.help_text,
+            ));
+        }
+        let mut block = Block::default()
             .borders(Borders::ALL)
-            .border_style(self.theme.border)
-            .title(Span::styled(" Onyx Chat ", self.theme.title))
+            .border_style(border_style)
+            .title(Line::from(title))
             .title_alignment(Alignment::Center);
+        if self.scroll_manager.has_new_content() {
+            block = block.title_bottom(
+                Line::from(Span::styled(" ↓ new content [End] ", self.theme.title)).right_aligned(),
+            );
+        }
+        if let Some(search) = &self.search {
+            let hint = if search.is_editing() {
+                Line::from(vec![
+                    Span::styled(" Search: ", self.theme.help_text),
+                    Span::styled(format!("{}█ ", search.query()), self.theme.input_active),
+                    Span::styled("[Enter] ", self.theme.success),
+                    Span::styled("done ", self.theme.help_text),
+                    Span::styled("• ", self.theme.border),
+                    Span::styled("[Esc] ", self.theme.success),
+                    Span::styled("close ", self.theme.help_text),
+                ])
+            } else {
+                Line::from(vec![
+                    Span::styled(" [n/N] ", self.theme.success),
+                    Span::styled("next/previous ", self.theme.help_text),
+                    Span::styled("• ", self.theme.border),
+                    Span::styled("[/] ", self.theme.success),
+                    Span::styled("edit ", self.theme.help_text),
+                    Span::styled("• ", self.theme.border),
+                    Span::styled("[Esc] ", self.theme.success),
+                    Span::styled("close ", self.theme.help_text),
+                ])
+            };
+            block = block.title_bottom(hint.left_aligned());
+        } else if !self.input_focused {
+            let hint = if self.message_copied {
+                Line::from(Span::styled(" ✓ Copied message ", self.theme.success))
+            } else {
+                Line::from(vec![
+                    Span::styled(" [↑/↓] ", self.theme.success),
+                    Span::styled("select ", self.theme.help_text),
+                    Span::styled("• ", self.theme.border),
+                    Span::styled("[Enter] ", self.theme.success),
+                    Span::styled("copy ", self.theme.help_text),
+                    Span::styled("• ", self.theme.border),
+                    Span::styled("[Esc] ", self.theme.success),
+                    Span::styled("back to input ", self.theme.help_text),
+                ])
+            };
+            block = block.title_bottom(hint.left_aligned());
+        }
 
-        let inner_area = block.inner(area);
+        let mut inner_area = block.inner(area);
+        // The hint bar stays put at the top while the messages scroll under it.
+        let hint_area = (self.config.show_hint_bar && inner_area.height > 1).then(|| {
+            let hint_area = Rect { height: 1, ..inner_area };
+            inner_area.y += 1;
+            inner_area.height -= 1;
+            hint_area
+        });
         let chat_width = inner_area.width.saturating_sub(2) as usize;
 
-        let mut lines = Vec::new();
-
-        if self.show_help {
-            lines.extend(HelpWidget::new(&self.theme).render());
-        }
-
-        for msg in &self.messages {
-            let message_widget = MessageWidget::new(
-                msg,
-                &self.theme,
-                chat_width,
-                &self.config.timestamp_format,
-                self.config.cursor_style,
-            );
-            lines.extend(message_widget.render());
-            lines.push(Line::from(""));
+        let help_lines =
+            if self.show_help { HelpWidget::new(&self.theme).render() } else { Vec::new() };
+
+        // Only messages that changed since the last frame are rendered again; the rest come
+        // from the layout cache.
+        let mut content_length = help_lines.len();
+        let mut selected_lines = None;
+        let mut current_match_line = None;
+        let current_match = self.search.as_ref().and_then(ConversationSearch::current_match);
+        let now = std::time::SystemTime::now();
+        for (i, msg) in self.messages.iter().enumerate() {
+            let current = current_match.filter(|m| m.message == i);
+            let key = RenderKey {
+                width: chat_width,
+                selected: self.selected_message == Some(i),
+                show_stats: self.config.show_message_stats,
+                age: (self.timestamp_display == TimestampDisplay::Relative)
+                    .then(|| relative_time(msg.timestamp, now)),
+                matches: self.search.as_ref().map(|s| s.ranges_in(i)).unwrap_or_default(),
+                current_match: current.map(|m| m.range.clone()),
+            };
+            if msg.is_streaming {
+                self.message_layout.parse_streaming(i, &msg.content);
+            }
+            let rendered = self.message_layout.get_or_render(i, key, |key, parser| {
+                MessageWidget::new(
+                    msg,
+                    &self.theme,
+                    key.width,
+                    &self.config.timestamp_format,
+                    self.config.cursor_style,
+                )
+                .timestamp_display(self.timestamp_display)
+                .names(self.config.user_name(), self.config.assistant_name())
+                .align_user_right(self.config.align_user_messages_right)
+                .selected(key.selected)
+                .show_stats(key.show_stats)
+                .matches(key.matches.clone(), key.current_match.clone())
+                .streaming_blocks(parser)
+                .render()
+            });
+
+            let start = content_length;
+            content_length += rendered.lines.len();
+            if self.selected_message == Some(i) {
+                selected_lines = Some((start, content_length.saturating_sub(1)));
+            }
+            if current.is_some() {
+                // Matches in lines that aren't shown as written, like code fences, fall back
+                // to the top of their message.
+                current_match_line = Some(start + rendered.match_line.unwrap_or_default());
+            }
+            content_length += 1;
         }
 
-        let content_length = lines.len();
         let viewport_height = inner_area.height as usize;
 
+        // Bring a newly selected message into view, its top taking priority over its bottom.
+        if std::mem::take(&mut self.selection_moved)
+            && let Some((first, last)) = selected_lines
+        {
+            self.scroll_manager.ensure_visible(last, viewport_height, content_length);
+            self.scroll_manager.ensure_visible(first, viewport_height, content_length);
+        }
+        if self.search.as_mut().is_some_and(ConversationSearch::take_moved)
+            && let Some(line) = current_match_line
+        {
+            self.scroll_manager.ensure_visible(line, viewport_height, content_length);
+        }
         self.scroll_manager.update(content_length, viewport_height);
 
+        // Only the lines in view are copied out of the cache.
+        let visible: Vec<Line> = help_lines
+            .iter()
+            .chain(self.message_layout.lines(self.messages.len()))
+            .skip(self.scroll_manager.position())
+            .take(viewport_height)
+            .cloned()
+            .collect();
+
+        if self.hyperlinks {
+            let first = self.scroll_manager.position();
+            let rows = first..first + viewport_height;
+            self.screen_links = self
+                .message_layout
+                .links(self.messages.len())
+                .map(|(line, link)| (line + help_lines.len(), link))
+                .filter(|(line, link)| rows.contains(line) && link.columns.start < chat_width)
+                .map(|(line, link)| ScreenLink {
+                    x: inner_area.x + link.columns.start as u16,
+                    y: inner_area.y + (line - first) as u16,
+                    width: (link.columns.end.min(chat_width) - link.columns.start) as u16,
+                    url: link.url.clone(),
+                })
+                .collect();
+        }
+
         frame.render_widget(block, area);
-        frame.render_widget(
-            Paragraph::new(lines).scroll((self.scroll_manager.position() as u16, 0)),
-            inner_area,
-        );
+        if let Some(hint_area) = hint_area {
+            frame.render_widget(self.hint_bar().centered(), hint_area);
+        }
+        frame.render_widget(Paragraph::new(visible), inner_area);
         frame.render_stateful_widget(
             Scrollbar::new(ScrollbarOrientation::VerticalRight)
                 .begin_symbol(Some("↑"))
@@ -414,57 +2298,292 @@ impl App {
         );
     }
 
-    pub fn handle_event(&mut self) -> Result<bool> {
-        let poll_duration = if self.is_processing {
-            std::time::Duration::from_millis(16)
-        } else {
-            std::time::Duration::from_millis(100)
-        };
+    /// The main keys, as the keymap has them: "[Enter] send • [/] commands • [F1] help".
+    fn hint_bar(&self) -> Line<'static> {
+        let mut spans = Vec::new();
+        for (key, label) in keymap::hints() {
+            if !spans.is_empty() {
+                spans.push(Span::styled("• ", self.theme.border));
+            }
+            spans.push(Span::styled(format!("[{}] ", key), self.theme.success));
+            spans.push(Span::styled(format!("{} ", label), self.theme.help_text));
+        }
+        Line::from(spans)
+    }
 
-        if event::poll(poll_duration)?
-            && let Event::Key(key) = event::read()?
-        {
+    pub fn handle_event(&mut self, event: Event) -> Result<bool> {
+        // Any event may change what is on screen, a resize included.
+        self.needs_redraw = true;
+
+        match event {
+            Event::FocusGained => {
+                self.set_focused(true);
+                return Ok(true);
+            }
+            Event::FocusLost => {
+                self.set_focused(false);
+                return Ok(true);
+            }
+            Event::Resize(..) => {
+                // Every message wraps differently at the new width. Scroll positions are
+                // brought back in range when the chat is next laid out.
+                self.message_layout.clear();
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        if let Event::Key(key) = event {
             if key.kind != KeyEventKind::Press {
                 return Ok(false);
             }
 
-            if self.mode == AppMode::Config {
-                return self.handle_config_event(key);
+            if self.confirmation.is_some() {
+                return Ok(self.handle_confirmation_event(key));
+            }
+
+            if let Some(action) = self.pending_action.clone() {
+                return Ok(self.handle_pending_action_event(key, action));
+            }
+
+            match self.mode {
+                AppMode::Config => return self.handle_config_event(key),
+                AppMode::FilePicker => return Ok(self.handle_file_picker_event(key)),
+                AppMode::Sessions => return Ok(self.handle_session_browser_event(key)),
+                AppMode::HistorySearch => return Ok(self.handle_history_search_event(key)),
+                AppMode::Help => return Ok(self.handle_help_event(key)),
+                AppMode::Chat => {}
+            }
+
+            if let Some(handled) = self.handle_search_event(key) {
+                return Ok(handled);
+            }
+
+            if let Some(handled) = self.handle_reverse_search_event(key) {
+                return Ok(handled);
+            }
+
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::F(1) => {
+                    self.open_help();
+                    return Ok(true);
+                }
+                KeyCode::Char('?')
+                    if self.input_focused && self.input_state.is_empty() && !ctrl =>
+                {
+                    self.open_help();
+                    return Ok(true);
+                }
+                KeyCode::F(6) => {
+                    if self.input_focused {
+                        self.focus_chat();
+                    } else {
+                        self.focus_input();
+                    }
+                    return Ok(true);
+                }
+                KeyCode::Up if ctrl => {
+                    self.focus_chat();
+                    return Ok(true);
+                }
+                KeyCode::Down if ctrl => {
+                    self.focus_input();
+                    return Ok(true);
+                }
+                _ if !self.input_focused => {
+                    if let Some(handled) = self.handle_chat_pane_event(key) {
+                        return Ok(handled);
+                    }
+                }
+                _ => {}
+            }
+
+            if self.config.input_mode == InputMode::Vim
+                && let Some(action) = self.vim.handle_key(key)
+            {
+                self.apply_vim_action(action);
+                return Ok(true);
             }
 
             match key.code {
                 KeyCode::Char('c')
                     if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
                 {
-                    self.should_quit = true;
+                    if self.input_state.selected_text().is_some() {
+                        self.copy_selection();
+                    } else if self.is_processing {
+                        self.request_cancel();
+                    } else {
+                        self.request_quit();
+                    }
+                    return Ok(true);
+                }
+                KeyCode::Esc if self.editing_message.is_some() && !self.is_processing => {
+                    self.cancel_edit();
+                    return Ok(true);
+                }
+                KeyCode::Esc => {
+                    self.request_cancel();
+                    return Ok(true);
+                }
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                    if let Some(notice) = self.edit_last_message() {
+                        self.add_message(Message::notice(notice));
+                    }
+                    return Ok(true);
+                }
+                KeyCode::Char('o')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.open_file_picker();
+                    return Ok(true);
+                }
+                KeyCode::Char('l')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.request_clear_chat();
+                    return Ok(true);
+                }
+                KeyCode::Char('a')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.input_state.select_all();
+                    return Ok(true);
+                }
+                KeyCode::Char('x')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.cut_selection();
+                    return Ok(true);
+                }
+                KeyCode::Char('v')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.terminal_cursor.on_activity();
+                    self.paste();
+                    return Ok(true);
+                }
+                KeyCode::Char('e')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.terminal_cursor.on_activity();
+                    let with_selection =
+                        key.modifiers.contains(crossterm::event::KeyModifiers::SHIFT);
+                    self.input_state.move_cursor_end(with_selection);
+                    self.update_command_menu();
+                    return Ok(true);
+                }
+                KeyCode::Char('u') | KeyCode::Char('k')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.terminal_cursor.on_activity();
+                    self.undo_manager.save(&self.input_state, true);
+                    if key.code == KeyCode::Char('u') {
+                        self.input_state.delete_to_start();
+                    } else {
+                        self.input_state.delete_to_end();
+                    }
+                    self.update_command_menu();
+                    return Ok(true);
+                }
+                KeyCode::Char('w')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.delete_word(true);
+                    return Ok(true);
+                }
+                KeyCode::Backspace
+                    if key.modifiers.intersects(
+                        crossterm::event::KeyModifiers::CONTROL
+                            | crossterm::event::KeyModifiers::ALT,
+                    ) =>
+                {
+                    self.delete_word(true);
+                    return Ok(true);
+                }
+                KeyCode::Delete
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.delete_word(false);
+                    return Ok(true);
+                }
+                KeyCode::Char('d')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::ALT) =>
+                {
+                    self.delete_word(false);
+                    return Ok(true);
+                }
+                KeyCode::Left | KeyCode::Right
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    let with_selection =
+                        key.modifiers.contains(crossterm::event::KeyModifiers::SHIFT);
+                    self.move_word(key.code == KeyCode::Left, with_selection);
                     return Ok(true);
                 }
-                KeyCode::Char('l')
+                KeyCode::Char('b') | KeyCode::Char('f')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::ALT) =>
+                {
+                    self.move_word(key.code == KeyCode::Char('b'), false);
+                    return Ok(true);
+                }
+                KeyCode::Char('p')
                     if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
                 {
-                    self.clear_chat();
+                    self.recall_previous_input();
                     return Ok(true);
                 }
-                KeyCode::Char('a')
+                KeyCode::Char('n')
                     if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
                 {
-                    self.input_state.select_all();
+                    self.recall_next_input();
+                    return Ok(true);
+                }
+                KeyCode::Char('h')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.open_history_search();
+                    return Ok(true);
+                }
+                KeyCode::Char('r')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.open_reverse_search();
+                    return Ok(true);
+                }
+                KeyCode::Char('f')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.search = Some(ConversationSearch::new("", &self.messages));
+                    return Ok(true);
+                }
+                KeyCode::Char('y')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    self.redo();
+                    return Ok(true);
+                }
+                // Terminals report Ctrl+Shift+Z either with the Shift flag or as a capital Z.
+                KeyCode::Char('z') | KeyCode::Char('Z')
+                    if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL)
+                        && (key.code == KeyCode::Char('Z')
+                            || key.modifiers.contains(crossterm::event::KeyModifiers::SHIFT)) =>
+                {
+                    self.redo();
                     return Ok(true);
                 }
                 KeyCode::Char('z')
                     if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
                 {
-                    if let Some(state) = self.undo_manager.undo() {
-                        self.input_state = state;
-                        self.update_command_menu();
-                    }
+                    self.undo();
                     return Ok(true);
                 }
                 KeyCode::Char('d')
                     if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) =>
                 {
                     if self.input_state.is_empty() {
-                        self.should_quit = true;
+                        self.request_quit();
                     } else {
                         self.undo_manager.save(&self.input_state, true);
                         self.input_state.clear();
@@ -479,6 +2598,8 @@ impl App {
                             self.command_menu_selected =
                                 self.command_menu_selected.saturating_sub(1);
                         }
+                    } else if !self.input_state.is_empty() || self.input_history.is_browsing() {
+                        self.recall_previous_input();
                     } else {
                         self.scroll_manager.scroll_up(1);
                     }
@@ -489,6 +2610,8 @@ impl App {
                         if !filtered.is_empty() && self.command_menu_selected < filtered.len() - 1 {
                             self.command_menu_selected += 1;
                         }
+                    } else if self.input_history.is_browsing() {
+                        self.recall_next_input();
                     } else {
                         self.scroll_manager.scroll_down(1);
                     }
@@ -499,6 +2622,17 @@ impl App {
                 KeyCode::PageDown => {
                     self.scroll_manager.scroll_page_down();
                 }
+                KeyCode::Home | KeyCode::End if !self.input_state.is_empty() => {
+                    self.terminal_cursor.on_activity();
+                    let with_selection =
+                        key.modifiers.contains(crossterm::event::KeyModifiers::SHIFT);
+                    if key.code == KeyCode::Home {
+                        self.input_state.move_cursor_home(with_selection);
+                    } else {
+                        self.input_state.move_cursor_end(with_selection);
+                    }
+                    self.update_command_menu();
+                }
                 KeyCode::Home => {
                     self.scroll_manager.scroll_to_top();
                 }
@@ -511,7 +2645,7 @@ impl App {
                     self.undo_manager.save(&self.input_state, is_word_boundary);
                     self.input_state.insert_char(c);
                     self.update_command_menu();
-                    self.show_help = false;
+                    self.hide_help();
                     return Ok(true);
                 }
                 KeyCode::Backspace => {
@@ -541,38 +2675,29 @@ impl App {
                     self.input_state.move_cursor_right(with_selection);
                     self.update_command_menu();
                 }
-                KeyCode::Tab => {
-                    if self.show_command_menu {
-                        let filtered = self.get_filtered_commands();
-                        if !filtered.is_empty() {
-                            self.undo_manager.save(&self.input_state, true);
-                            let selected_idx = self.command_menu_selected % filtered.len();
-                            let selected_command = filtered[selected_idx].0;
-
-                            let cursor_position = self.input_state.cursor_position();
-                            let input = self.input_state.text();
-                            let input_before_cursor = &input[..cursor_position];
-                            let cmd_start = if let Some(pos) =
-                                input_before_cursor.rfind(|c: char| c.is_whitespace())
-                            {
-                                pos + 1
-                            } else {
-                                0
-                            };
-
-                            self.input_state.replace_range(
-                                cmd_start,
-                                cursor_position,
-                                selected_command,
-                            );
-                            self.show_command_menu = false;
-                            self.command_menu_selected = 0;
-                        }
-                        return Ok(true);
+                KeyCode::Tab if self.show_command_menu => {
+                    self.accept_completion();
+                    return Ok(true);
+                }
+                // Enter completes like Tab, and sends a command right away if there is nothing
+                // more to type: it takes no argument, or was already typed out in full.
+                KeyCode::Enter if self.get_command_menu_state().is_some() => {
+                    let before = self.input_state.text().to_string();
+                    let accepted = self.accept_completion();
+                    let complete = accepted.is_some_and(|completion| {
+                        completion.text.starts_with('/')
+                            && completion.argument.is_none()
+                            && commands::inline_text(&completion.text, &self.config).is_none()
+                    });
+                    if complete || self.input_state.text() == before {
+                        self.show_command_menu = false;
+                        self.hide_help();
+                        self.submit = true;
                     }
+                    return Ok(true);
                 }
                 KeyCode::Enter => {
-                    self.show_help = false;
+                    self.hide_help();
                     self.submit = true;
                     return Ok(true);
                 }
@@ -580,46 +2705,391 @@ impl App {
             }
         }
 
-        self.tick_spinner();
         Ok(false)
     }
 
+    /// Replaces the input with `text`, selecting `stop` so that typing replaces it.
+    pub(crate) fn fill_input(&mut self, text: String, stop: Option<std::ops::Range<usize>>) {
+        self.undo_manager.save(&self.input_state, true);
+        self.input_state = TextInputState::with_text(text);
+        if let Some(stop) = stop {
+            self.input_state.select(stop.start, stop.end);
+        }
+        self.focus_input();
+    }
+
+    pub(crate) fn quit(&mut self) {
+        self.should_quit = true;
+    }
+
+    pub(crate) fn request_models(&mut self) {
+        self.models_requested = true;
+    }
+
+    /// Starts downloading `model`, or the configured one when empty, into Ollama. Its progress
+    /// shows in a message of its own, which [`Self::show_progress`] updates.
+    pub(crate) fn request_pull(&mut self, model: &str) -> Option<String> {
+        if self.config.active_provider != Provider::Ollama {
+            return Some("/pull only works with Ollama as the active provider.".to_string());
+        }
+        if self.is_processing {
+            return Some("Wait for the response to finish, or cancel it with Esc.".to_string());
+        }
+
+        let model = match model.trim() {
+            "" => self.config.get_active_provider().model.clone(),
+            model => model.to_string(),
+        };
+        self.add_message(Message {
+            is_streaming: true,
+            ..Message::notice(format!("Pulling {}…", model))
+        });
+        self.set_processing(true);
+        self.pull_requested = Some(model);
+        None
+    }
+
+    /// Replaces the last message's text with `status`, for a task reporting its progress.
+    pub fn show_progress(&mut self, status: String) {
+        self.update_last_message(|msg| msg.content = status);
+    }
+
+    pub(crate) fn request_doctor(&mut self) {
+        self.doctor_requested = true;
+    }
+
+    pub(crate) fn start_search(&mut self, query: &str) {
+        self.search = Some(ConversationSearch::new(query, &self.messages));
+    }
+
     pub fn handle_command(&mut self, cmd: &str) -> Option<String> {
-        match cmd {
-            "/config" => {
-                self.open_config_editor();
+        self.needs_redraw = true;
+        commands::dispatch(self, cmd)
+    }
+
+    pub(crate) fn switch_provider(&mut self, name: &str) -> String {
+        if name.is_empty() {
+            return format!(
+                "Current provider: {} (model: {})",
+                self.config.active_provider,
+                self.config.get_active_provider().model
+            );
+        }
+
+        let Some(provider) = Provider::iter().find(|p| p.to_string().eq_ignore_ascii_case(name))
+        else {
+            let available: Vec<String> = Provider::iter().map(|p| p.to_string()).collect();
+            return format!("Unknown provider '{}'. Available: {}", name, available.join(", "));
+        };
+
+        self.config.active_provider = provider;
+        if let Err(e) = self.config.save() {
+            return format!("Failed to save configuration: {}", e);
+        }
+        self.agent_rebuild_requested = true;
+
+        format!(
+            "Switched to {} (model: {})",
+            self.config.active_provider,
+            self.config.get_active_provider().model
+        )
+    }
+
+    pub(crate) fn switch_model(&mut self, name: &str) -> String {
+        if name.is_empty() {
+            return format!(
+                "Current model: {} ({})",
+                self.config.get_active_provider().model,
+                self.config.active_provider
+            );
+        }
+
+        self.config.get_active_provider_mut().model = name.to_string();
+        if let Err(e) = self.config.save() {
+            return format!("Failed to save configuration: {}", e);
+        }
+        self.agent_rebuild_requested = true;
+
+        format!("Switched to {} ({})", name, self.config.active_provider)
+    }
+
+    pub(crate) fn switch_theme(&mut self, name: &str) -> String {
+        let names = ThemeName::iter().map(<&'static str>::from).collect::<Vec<_>>().join(", ");
+        if name.is_empty() {
+            let file = self.config.theme_file().map(|file| format!(" with {}", file));
+            return format!(
+                "Current theme: {}{} (available: {})",
+                self.config.theme,
+                file.unwrap_or_default(),
+                names
+            );
+        }
+
+        if name == "reload" {
+            if self.config.theme_file().is_none() {
+                return "No theme file is configured; set one in /config".to_string();
+            }
+            return match self.apply_theme() {
+                Ok(()) => "Reloaded the theme file".to_string(),
+                Err(e) => format!("Could not load the theme file: {}", e),
+            };
+        }
+
+        let Ok(theme) = name.parse::<ThemeName>() else {
+            return format!("Unknown theme '{}'. Available: {}", name, names);
+        };
+        self.config.theme = theme;
+        let applied = self.apply_theme();
+        if let Err(e) = self.config.save() {
+            return format!("Switched to the {} theme, but it wasn't saved: {}", theme, e);
+        }
+
+        match applied {
+            Ok(()) => format!("Switched to the {} theme", theme),
+            Err(e) => format!("Switched to the {} theme, but the theme file failed: {}", theme, e),
+        }
+    }
+
+    pub(crate) fn attach(&mut self, path: &str) -> Option<String> {
+        if path.is_empty() {
+            self.open_file_picker();
+            return None;
+        }
+
+        match read_text_file(std::path::Path::new(path), self.config.attachment_max_bytes) {
+            Ok(contents) => {
+                self.attachments.push(Attachment { path: path.to_string(), contents });
                 None
             }
-            "/save" => match self.save_conversation_log() {
-                Ok(filename) => Some(format!("Conversation saved to: {}", filename)),
-                Err(e) => Some(format!("Failed to save conversation: {}", e)),
-            },
-            "/help" => Some(
-                "Commands:\n  \
-                    /config - Open configuration editor\n  \
-                    /save - Save conversation to log file\n  \
-                    /help - Show this help\n\n\
-                    Navigation:\n  \
-                    ↑/↓ - Scroll up/down\n  \
-                    PgUp/PgDn - Scroll page up/down\n  \
-                    Home/End - Jump to top/bottom\n\n\
-                    Actions:\n  \
-                    Ctrl+L - Clear chat\n  \
-                    Ctrl+C - Quit"
-                    .to_string(),
-            ),
-            _ => None,
+            Err(e) => Some(format!("Could not attach {}: {}", path, e)),
         }
     }
 
-    fn handle_config_event(&mut self, key: crossterm::event::KeyEvent) -> Result<bool> {
-        use crossterm::event::KeyModifiers;
+    pub(crate) fn detach(&mut self) -> String {
+        match std::mem::take(&mut self.attachments).len() {
+            0 => "No attachments to remove".to_string(),
+            1 => "Removed 1 attachment".to_string(),
+            n => format!("Removed {} attachments", n),
+        }
+    }
+
+    pub(crate) fn remember(&mut self, text: &str) -> String {
+        if text.is_empty() {
+            return "Usage: /remember <text>".to_string();
+        }
+
+        let mut memories = match Memories::load() {
+            Ok(memories) => memories,
+            Err(e) => return format!("Failed to load memories: {}", e),
+        };
+        memories.add(text);
+        if let Err(e) = memories.save() {
+            return format!("Failed to save memories: {}", e);
+        }
+        self.agent_rebuild_requested = true;
+
+        format!("Remembered: {}", text)
+    }
+
+    pub(crate) fn forget(&mut self, argument: &str) -> String {
+        let mut memories = match Memories::load() {
+            Ok(memories) => memories,
+            Err(e) => return format!("Failed to load memories: {}", e),
+        };
+
+        if argument.is_empty() {
+            return if memories.is_empty() {
+                "No memories yet. Add one with /remember <text>".to_string()
+            } else {
+                format!("Memories:\n{}\n\nRemove one with /forget <number>", memories.numbered())
+            };
+        }
+
+        let Some(memory) = argument.parse().ok().and_then(|number| memories.remove(number)) else {
+            return format!("No memory #{} — use /forget to list them", argument);
+        };
+        if let Err(e) = memories.save() {
+            return format!("Failed to save memories: {}", e);
+        }
+        self.agent_rebuild_requested = true;
+
+        format!("Forgot: {}", memory.text)
+    }
+
+    fn handle_file_picker_event(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(picker) = &mut self.file_picker else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Up => picker.move_up(),
+            KeyCode::Down => picker.move_down(),
+            KeyCode::Esc => {
+                if !picker.back() {
+                    self.close_file_picker();
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(path) = picker.enter() {
+                    match self.attach(&path) {
+                        Some(error) => {
+                            if let Some(picker) = &mut self.file_picker {
+                                picker.error = Some(error);
+                            }
+                        }
+                        None => self.close_file_picker(),
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_session_browser_event(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(browser) = &mut self.session_browser else {
+            return false;
+        };
+
+        if browser.is_confirming_delete() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    // Forget the open conversation too, or the next autosave would bring it back.
+                    if browser.delete_selected().is_some_and(|id| id == self.session_id) {
+                        self.switch_session(Session::new());
+                    }
+                }
+                _ => browser.cancel_delete(),
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Up => browser.move_up(),
+            KeyCode::Down => browser.move_down(),
+            KeyCode::Esc => self.close_session_browser(),
+            KeyCode::Char('d') => browser.request_delete(),
+            KeyCode::Enter => {
+                if let Some(id) = browser.selected_id().map(str::to_string) {
+                    match self.load_session(&id) {
+                        Ok(()) => self.close_session_browser(),
+                        Err(e) => {
+                            if let Some(browser) = &mut self.session_browser {
+                                browser.error = Some(format!("Cannot open session: {}", e));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_help_event(&mut self, key: KeyEvent) -> bool {
+        let Some(help) = &mut self.help else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Esc | KeyCode::F(1) | KeyCode::Char('q') => self.close_help(),
+            KeyCode::Up | KeyCode::Char('k') => help.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => help.scroll_down(1),
+            KeyCode::PageUp => help.page_up(),
+            KeyCode::PageDown => help.page_down(),
+            KeyCode::Home => help.scroll_to_top(),
+            KeyCode::End => help.scroll_to_bottom(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_history_search_event(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(search) = &mut self.history_search else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Up => search.move_up(),
+            KeyCode::Down => search.move_down(),
+            KeyCode::Esc => self.close_history_search(),
+            KeyCode::Backspace => search.pop_char(&self.input_history),
+            KeyCode::Enter => {
+                let selected = search.selected().map(str::to_string);
+                self.close_history_search();
+                if let Some(entry) = selected {
+                    self.recall_input(entry);
+                }
+            }
+            KeyCode::Char(c) => search.push_char(c, &self.input_history),
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_confirmation_event(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.answer_confirmation(true),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.answer_confirmation(false)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys while a clear, quit, link or repeated message waits to be confirmed. Other keys are
+    /// ignored, leaving the prompt open.
+    fn handle_pending_action_event(&mut self, key: KeyEvent, action: PendingAction) -> bool {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.pending_action = None;
+                self.run_pending_action(action);
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.pending_action = None,
+            _ => return false,
+        }
+        true
+    }
 
+    fn handle_config_event(&mut self, key: crossterm::event::KeyEvent) -> Result<bool> {
         let Some(editor) = &mut self.config_editor else {
             return Ok(false);
         };
 
-        if editor.editing {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if self.confirming_config_close {
+            match key.code {
+                KeyCode::Char('s') | KeyCode::Char('S') => {
+                    self.confirming_config_close = false;
+                    if editor.commit_pending_edit() {
+                        self.save_config_from_editor()?;
+                        self.close_config_editor();
+                    }
+                }
+                KeyCode::Char('d') | KeyCode::Char('D') => self.close_config_editor(),
+                KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Esc => {
+                    self.confirming_config_close = false
+                }
+                _ => return Ok(false),
+            }
+        } else if editor.confirming_reset {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => editor.answer_reset_all(true),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    editor.answer_reset_all(false)
+                }
+                _ => return Ok(false),
+            }
+        } else if key.code == KeyCode::Char('s') && ctrl {
+            if editor.commit_pending_edit() {
+                self.save_config_from_editor()?;
+            }
+        } else if key.code == KeyCode::Char('t') && ctrl {
+            editor.toggle_reveal();
+        } else if key.code == KeyCode::Char('k') && ctrl {
+            editor.request_connection_test();
+        } else if editor.editing {
             match key.code {
                 KeyCode::Enter => editor.save_current_field(),
                 KeyCode::Esc => editor.cancel_editing(),
@@ -647,24 +3117,47 @@ impl App {
                 KeyCode::Down if editor.show_enum_menu => editor.enum_menu_down(),
                 _ => return Ok(false),
             }
+        } else if editor.filtering {
+            match key.code {
+                KeyCode::Enter => editor.confirm_filter(),
+                KeyCode::Esc => editor.clear_filter(),
+                KeyCode::Char(c) => editor.edit_filter(|filter| filter.insert_char(c)),
+                KeyCode::Backspace => editor.edit_filter(TextInputState::delete_char_before),
+                KeyCode::Delete => editor.edit_filter(TextInputState::delete_char_after),
+                KeyCode::Left => editor.edit_filter(|filter| filter.move_cursor_left(false)),
+                KeyCode::Right => editor.edit_filter(|filter| filter.move_cursor_right(false)),
+                KeyCode::Up | KeyCode::BackTab => editor.prev_field(),
+                KeyCode::Down | KeyCode::Tab => editor.next_field(),
+                _ => return Ok(false),
+            }
+            self.terminal_cursor.on_activity();
         } else {
             match key.code {
+                KeyCode::Esc if editor.has_filter() => editor.clear_filter(),
+                KeyCode::Esc if editor.has_unsaved_changes() => self.confirming_config_close = true,
                 KeyCode::Esc => self.close_config_editor(),
-                KeyCode::Up if key.modifiers.contains(KeyModifiers::CONTROL) => editor.prev_field(),
-                KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    editor.next_field()
-                }
-                KeyCode::Up => editor.scroll_up(),
-                KeyCode::Down => editor.scroll_down(),
-                KeyCode::PageUp => editor.scroll_page_up(),
-                KeyCode::PageDown => editor.scroll_page_down(),
-                KeyCode::Home => editor.scroll_to_top(),
-                KeyCode::Tab => editor.next_field(),
-                KeyCode::BackTab => editor.prev_field(),
+                KeyCode::Char('/') => editor.start_filter(),
+                KeyCode::Left | KeyCode::Char('[') => editor.prev_section(),
+                KeyCode::Right | KeyCode::Char(']') => editor.next_section(),
+                KeyCode::Up | KeyCode::BackTab => editor.prev_field(),
+                KeyCode::Down | KeyCode::Tab => editor.next_field(),
+                KeyCode::PageUp => editor.page_up(),
+                KeyCode::PageDown => editor.page_down(),
+                KeyCode::Home => editor.select_first(),
+                KeyCode::End => editor.select_last(),
+                KeyCode::Enter | KeyCode::Char(' ') if editor.is_group_selected() => {
+                    editor.toggle_group()
+                }
                 KeyCode::Enter => editor.start_editing(),
-                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.save_config_from_editor()?
+                // Terminals report Ctrl+Shift+R either with the Shift flag or as a capital R.
+                KeyCode::Char('r') | KeyCode::Char('R')
+                    if ctrl
+                        && (key.code == KeyCode::Char('R')
+                            || key.modifiers.contains(KeyModifiers::SHIFT)) =>
+                {
+                    editor.request_reset_all()
                 }
+                KeyCode::Char('r') if ctrl => editor.reset_current_field(),
                 _ => return Ok(false),
             }
         }
@@ -678,3 +3171,295 @@ impl Default for App {
         Self::new(Config::default())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use onyx_core::CursorStyle;
+    use ratatui::{Terminal, backend::TestBackend, buffer::Buffer};
+    use std::sync::Once;
+
+    /// An app on `config`, with the config and data directory in a temporary directory so that
+    /// nothing a test does reaches the user's.
+    pub(crate) fn app_with(config: Config) -> App {
+        static DATA_DIR: Once = Once::new();
+        DATA_DIR.call_once(|| {
+            let dir = std::env::temp_dir().join(format!("onyx-tui-tests-{}", std::process::id()));
+            // SAFETY: set once, before any test builds an app and so before anything in this
+            // crate reads the variable.
+            unsafe { std::env::set_var(onyx_core::config::CONFIG_DIR_VAR, dir) };
+        });
+        App::new(config)
+    }
+
+    pub(crate) fn press(app: &mut App, code: KeyCode) {
+        press_with(app, code, KeyModifiers::NONE);
+    }
+
+    pub(crate) fn press_with(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+        app.handle_event(Event::Key(KeyEvent::new(code, modifiers))).unwrap();
+    }
+
+    pub(crate) fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    pub(crate) fn input(app: &App) -> &str {
+        app.input_state.text()
+    }
+
+    pub(crate) fn selected(app: &App) -> Option<&str> {
+        app.input_state.selected_text()
+    }
+
+    /// Draws `app` on a `width` by `height` screen.
+    pub(crate) fn draw(app: &mut App, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    pub(crate) fn screen_text(buffer: &Buffer) -> String {
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| (area.left()..area.right()).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn sent(app: &mut App, text: &str) -> Option<String> {
+        type_text(app, text);
+        press(app, KeyCode::Enter);
+        app.take_input()
+    }
+
+    fn app_with_last_message(confirm_duplicate_send: bool, text: &str) -> App {
+        let mut app = app_with(Config { confirm_duplicate_send, ..Config::default() });
+        app.add_message(Message::user(text));
+        app
+    }
+
+    #[test]
+    fn nothing_is_taken_before_enter() {
+        let mut app = app_with(Config::default());
+        type_text(&mut app, "hello");
+        assert_eq!(app.take_input(), None);
+        assert_eq!(input(&app), "hello");
+    }
+
+    #[test]
+    fn takes_the_input_trimmed() {
+        let mut app = app_with(Config::default());
+        assert_eq!(sent(&mut app, "  hello there  "), Some("hello there".to_string()));
+        assert_eq!(input(&app), "");
+        assert_eq!(app.take_input(), None, "each Enter sends once");
+    }
+
+    #[test]
+    fn refuses_blank_input() {
+        let mut app = app_with(Config::default());
+        assert_eq!(sent(&mut app, "   "), None);
+        assert_eq!(input(&app), "   ");
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.take_input(), None);
+        type_text(&mut app, "x");
+        assert_eq!(app.take_input(), None, "the refused Enter doesn't send later input");
+    }
+
+    #[test]
+    fn sends_a_repeated_message_without_the_guard() {
+        let mut app = app_with_last_message(false, "hello");
+        assert_eq!(sent(&mut app, "hello"), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn the_guard_asks_before_sending_the_last_message_again() {
+        let mut app = app_with_last_message(true, "hello");
+        assert_eq!(sent(&mut app, " hello "), None);
+        assert_eq!(app.pending_action, Some(PendingAction::SendDuplicate));
+
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.take_input(), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn declining_a_repeated_message_keeps_it_in_the_input() {
+        let mut app = app_with_last_message(true, "hello");
+        assert_eq!(sent(&mut app, "hello"), None);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.take_input(), None);
+        assert_eq!(input(&app), "hello");
+
+        // It asks again on the next Enter.
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.take_input(), None);
+        assert_eq!(app.pending_action, Some(PendingAction::SendDuplicate));
+    }
+
+    #[test]
+    fn the_guard_passes_other_messages_and_commands() {
+        let mut app = app_with_last_message(true, "/help");
+        assert_eq!(sent(&mut app, "hello there"), Some("hello there".to_string()));
+        assert_eq!(sent(&mut app, "/help"), Some("/help".to_string()));
+    }
+
+    /// The sizes around the smallest the layout supports, down to a single cell.
+    const SMALL_SIZES: [(u16, u16); 8] = [
+        (1, 1),
+        (1, 24),
+        (80, 1),
+        (2, 2),
+        (MIN_WIDTH - 1, MIN_HEIGHT),
+        (MIN_WIDTH, MIN_HEIGHT - 1),
+        (MIN_WIDTH, MIN_HEIGHT),
+        (MIN_WIDTH, 2),
+    ];
+
+    #[test]
+    fn tiny_terminals_show_the_too_small_screen() {
+        let mut app = app_with(Config::default());
+        for (width, height) in SMALL_SIZES {
+            let screen = screen_text(&draw(&mut app, width, height));
+            let too_small = width < MIN_WIDTH || height < MIN_HEIGHT;
+            // Narrower than the message, only some of it shows.
+            if !too_small || width >= 20 {
+                let shown = screen.contains("Terminal too small");
+                assert_eq!(shown, too_small, "{}x{}:\n{}", width, height, screen);
+            }
+        }
+        let screen = screen_text(&draw(&mut app, MIN_WIDTH - 1, MIN_HEIGHT));
+        assert!(screen.contains("need ≥ 40x10, have 39x10"), "{}", screen);
+    }
+
+    #[test]
+    fn every_view_draws_at_the_smallest_sizes() {
+        let mut menu = app_with(Config::default());
+        type_text(&mut menu, "/");
+        let mut help = app_with(Config::default());
+        help.open_help();
+        let mut editor = app_with(Config::default());
+        editor.open_config_editor();
+        let mut prompt = app_with(Config::default());
+        prompt.add_message(Message::user("hello"));
+        prompt.request_clear_chat();
+
+        let sizes = SMALL_SIZES.into_iter().chain([(MIN_WIDTH, 30), (200, MIN_HEIGHT)]);
+        for (width, height) in sizes {
+            for app in [&mut menu, &mut help, &mut editor, &mut prompt] {
+                draw(app, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn the_command_menu_fits_above_the_input() {
+        let mut app = app_with(Config::default());
+        type_text(&mut app, "/");
+        let screen = screen_text(&draw(&mut app, MIN_WIDTH, MIN_HEIGHT));
+        assert!(screen.contains("/help"), "{}", screen);
+    }
+
+    fn app_with_cursor(cursor_style: CursorStyle) -> App {
+        app_with(Config { cursor_style, cursor_blink_interval: 100, ..Config::default() })
+    }
+
+    #[test]
+    fn focused_frames_follow_the_cursor_blink() {
+        let app = app_with_cursor(CursorStyle::LineBlinking);
+        assert!(app.is_focused());
+        let delay = app.time_until_next_frame().unwrap();
+        assert!(delay <= Duration::from_millis(100), "{:?}", delay);
+    }
+
+    #[test]
+    fn unfocused_the_cursor_stops_blinking() {
+        let mut app = app_with_cursor(CursorStyle::LineBlinking);
+        app.handle_event(Event::FocusLost).unwrap();
+        assert!(!app.is_focused());
+        assert_eq!(app.time_until_next_frame(), None);
+
+        app.handle_event(Event::FocusGained).unwrap();
+        assert!(app.time_until_next_frame().unwrap() <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn unfocused_the_spinner_slows_down() {
+        let mut app = app_with_cursor(CursorStyle::Line);
+        assert_eq!(app.time_until_next_frame(), None, "nothing moves while idle");
+
+        app.set_processing(true);
+        assert!(app.time_until_next_frame().unwrap() <= SPINNER_FRAME);
+
+        app.handle_event(Event::FocusLost).unwrap();
+        assert_eq!(app.time_until_next_frame(), Some(UNFOCUSED_FRAME));
+
+        app.handle_event(Event::FocusGained).unwrap();
+        assert!(app.time_until_next_frame().unwrap() <= SPINNER_FRAME);
+    }
+
+    /// Runs `iterations` turns of the event loop with nothing happening, drawing only when the
+    /// app asks to as the real loop does. Returns how many frames were drawn.
+    fn idle_draws(app: &mut App, iterations: usize) -> usize {
+        let mut draws = 0;
+        for _ in 0..iterations {
+            if app.needs_redraw() {
+                draw(app, 80, 24);
+                draws += 1;
+            }
+        }
+        draws
+    }
+
+    #[test]
+    fn an_idle_app_draws_nothing() {
+        let mut app = app_with_cursor(CursorStyle::Line);
+        app.add_message(Message::user("hello"));
+        assert_eq!(idle_draws(&mut app, 1), 1, "the first frame is drawn");
+        assert_eq!(idle_draws(&mut app, 1000), 0);
+    }
+
+    #[test]
+    fn events_draw_one_frame_each() {
+        let mut app = app_with_cursor(CursorStyle::Line);
+        idle_draws(&mut app, 1);
+
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(idle_draws(&mut app, 100), 1);
+        app.handle_event(Event::Resize(100, 30)).unwrap();
+        assert_eq!(idle_draws(&mut app, 100), 1);
+        app.add_message(Message::assistant("hi"));
+        assert_eq!(idle_draws(&mut app, 100), 1);
+    }
+
+    #[test]
+    fn a_blinking_cursor_draws_only_when_it_blinks() {
+        let mut app = app_with(Config {
+            cursor_style: CursorStyle::LineBlinking,
+            cursor_blink_interval: 50,
+            ..Config::default()
+        });
+        idle_draws(&mut app, 1);
+        assert_eq!(idle_draws(&mut app, 100), 0);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(idle_draws(&mut app, 100), 1);
+    }
+
+    #[test]
+    fn history_never_includes_system_messages() {
+        let mut app = app_with(Config::default());
+        app.add_message(Message::system("Welcome to Onyx"));
+        app.add_message(Message::user("hello"));
+        app.add_message(Message::notice("Saved"));
+        app.add_message(Message::assistant("hi"));
+        app.add_message(Message::error("Rate limited"));
+        app.add_message(Message::system("Usage statistics: ..."));
+
+        let history = app.history();
+        let roles: Vec<&Role> = history.iter().map(|message| &message.role).collect();
+        assert!(matches!(roles.as_slice(), [Role::User, Role::Assistant]), "{:?}", roles);
+    }
+}
@@ -0,0 +1,296 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Where a motion moves the cursor to, or an operator acts up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    /// `w`: the start of the next word.
+    WordForward,
+    /// `b`: the start of the current or previous word.
+    WordBackward,
+    LineStart,
+    LineEnd,
+}
+
+/// What a key does in vim mode, for the app to carry out on the input and the chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimAction {
+    Move(Motion),
+    /// `x`, and `d` followed by a motion.
+    Delete(Motion),
+    /// `dd`: the input is a single line, so this clears it.
+    DeleteLine,
+    /// Back to insert mode after moving the cursor, as `i`, `a`, `I`, `A` and `o` do.
+    Insert(Option<Motion>),
+    Undo,
+    Redo,
+    ScrollUp,
+    ScrollDown,
+    ScrollTop,
+    ScrollBottom,
+    /// The first key of a two-key command, or a key normal mode doesn't use.
+    Nothing,
+}
+
+/// Vim's normal and insert modes for the input, when the `input_mode` setting is `vim`. Each
+/// key goes through [`Self::handle_key`] first; what it doesn't take gets the usual handling.
+#[derive(Debug, Default)]
+pub struct VimState {
+    normal: bool,
+    /// The first key of `dd`, `dw` or `gg`, waiting for the second.
+    pending: Option<char>,
+}
+
+impl VimState {
+    /// The letter shown in the input's title: `N` in normal mode, `I` in insert mode.
+    pub fn indicator(&self) -> char {
+        if self.normal { 'N' } else { 'I' }
+    }
+
+    /// Back to insert mode, as after sending a message.
+    pub fn reset(&mut self) {
+        self.normal = false;
+        self.pending = None;
+    }
+
+    /// The action for `key`, or `None` to leave it to the usual handling: everything in
+    /// insert mode but Esc, and in normal mode Enter, arrows and keys with Ctrl or Alt.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<VimAction> {
+        if !self.normal {
+            if key.code == KeyCode::Esc {
+                self.normal = true;
+                // Like vim, leaving insert mode steps back onto the last character typed.
+                return Some(VimAction::Move(Motion::Left));
+            }
+            return None;
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('r') {
+            self.pending = None;
+            return Some(VimAction::Redo);
+        }
+        let KeyCode::Char(c) = key.code else {
+            self.pending = None;
+            return None;
+        };
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            self.pending = None;
+            return None;
+        }
+
+        let action = match (self.pending.take(), c) {
+            (Some('d'), 'd') => VimAction::DeleteLine,
+            (Some('d'), c) => match Self::motion(c) {
+                Some(motion) => VimAction::Delete(motion),
+                None => VimAction::Nothing,
+            },
+            (Some('g'), 'g') => VimAction::ScrollTop,
+            (Some(_), _) => VimAction::Nothing,
+            (None, 'd' | 'g') => {
+                self.pending = Some(c);
+                VimAction::Nothing
+            }
+            (None, 'x') => VimAction::Delete(Motion::Right),
+            (None, 'i') => VimAction::Insert(None),
+            (None, 'a') => VimAction::Insert(Some(Motion::Right)),
+            (None, 'I') => VimAction::Insert(Some(Motion::LineStart)),
+            // The input has one line, so opening a line below means carrying on at its end.
+            (None, 'A' | 'o') => VimAction::Insert(Some(Motion::LineEnd)),
+            (None, 'u') => VimAction::Undo,
+            (None, 'j') => VimAction::ScrollDown,
+            (None, 'k') => VimAction::ScrollUp,
+            (None, 'G') => VimAction::ScrollBottom,
+            (None, c) => Self::motion(c).map_or(VimAction::Nothing, VimAction::Move),
+        };
+
+        if matches!(action, VimAction::Insert(_)) {
+            self.normal = false;
+        }
+        Some(action)
+    }
+
+    fn motion(c: char) -> Option<Motion> {
+        match c {
+            'h' => Some(Motion::Left),
+            'l' => Some(Motion::Right),
+            'w' => Some(Motion::WordForward),
+            'b' => Some(Motion::WordBackward),
+            '0' => Some(Motion::LineStart),
+            '$' => Some(Motion::LineEnd),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::tests::{app_with, input, press, press_with, type_text};
+    use onyx_core::{Config, InputMode};
+
+    fn keys(vim: &mut VimState, keys: &str) -> Vec<Option<VimAction>> {
+        keys.chars().map(|c| vim.handle_key(KeyEvent::from(KeyCode::Char(c)))).collect()
+    }
+
+    fn normal() -> VimState {
+        let mut vim = VimState::default();
+        vim.handle_key(KeyEvent::from(KeyCode::Esc));
+        vim
+    }
+
+    #[test]
+    fn insert_mode_leaves_keys_but_esc_alone() {
+        let mut vim = VimState::default();
+        assert_eq!(keys(&mut vim, "dwx"), [None, None, None]);
+        assert_eq!(vim.indicator(), 'I');
+        assert_eq!(
+            vim.handle_key(KeyEvent::from(KeyCode::Esc)),
+            Some(VimAction::Move(Motion::Left))
+        );
+        assert_eq!(vim.indicator(), 'N');
+    }
+
+    #[test]
+    fn motions() {
+        let mut vim = normal();
+        assert_eq!(
+            keys(&mut vim, "hlwb0$"),
+            [Motion::Left, Motion::Right, Motion::WordForward, Motion::WordBackward]
+                .into_iter()
+                .chain([Motion::LineStart, Motion::LineEnd])
+                .map(|motion| Some(VimAction::Move(motion)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn delete_takes_a_motion() {
+        let mut vim = normal();
+        assert_eq!(
+            keys(&mut vim, "dw"),
+            [Some(VimAction::Nothing), Some(VimAction::Delete(Motion::WordForward))]
+        );
+        assert_eq!(
+            keys(&mut vim, "d$"),
+            [Some(VimAction::Nothing), Some(VimAction::Delete(Motion::LineEnd))]
+        );
+        assert_eq!(keys(&mut vim, "dd"), [Some(VimAction::Nothing), Some(VimAction::DeleteLine)]);
+        assert_eq!(keys(&mut vim, "x"), [Some(VimAction::Delete(Motion::Right))]);
+    }
+
+    #[test]
+    fn an_unknown_second_key_cancels_the_first() {
+        let mut vim = normal();
+        assert_eq!(
+            keys(&mut vim, "dzw"),
+            [
+                Some(VimAction::Nothing),
+                Some(VimAction::Nothing),
+                Some(VimAction::Move(Motion::WordForward))
+            ]
+        );
+        assert_eq!(keys(&mut vim, "gx"), [Some(VimAction::Nothing), Some(VimAction::Nothing)]);
+        assert_eq!(vim.handle_key(KeyEvent::from(KeyCode::Char('d'))), Some(VimAction::Nothing));
+        assert_eq!(vim.handle_key(KeyEvent::from(KeyCode::Left)), None);
+        assert_eq!(keys(&mut vim, "w"), [Some(VimAction::Move(Motion::WordForward))]);
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut vim = normal();
+        assert_eq!(
+            keys(&mut vim, "jkG"),
+            [Some(VimAction::ScrollDown), Some(VimAction::ScrollUp), Some(VimAction::ScrollBottom)]
+        );
+        assert_eq!(keys(&mut vim, "gg"), [Some(VimAction::Nothing), Some(VimAction::ScrollTop)]);
+    }
+
+    #[test]
+    fn insert_commands_leave_normal_mode() {
+        for (key, motion) in [
+            ('i', None),
+            ('a', Some(Motion::Right)),
+            ('I', Some(Motion::LineStart)),
+            ('A', Some(Motion::LineEnd)),
+            ('o', Some(Motion::LineEnd)),
+        ] {
+            let mut vim = normal();
+            assert_eq!(keys(&mut vim, &key.to_string()), [Some(VimAction::Insert(motion))]);
+            assert_eq!(vim.indicator(), 'I');
+        }
+    }
+
+    #[test]
+    fn ctrl_keys_pass_through_except_redo() {
+        let mut vim = normal();
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert_eq!(vim.handle_key(ctrl('c')), None);
+        assert_eq!(vim.handle_key(ctrl('r')), Some(VimAction::Redo));
+        assert_eq!(keys(&mut vim, "u"), [Some(VimAction::Undo)]);
+    }
+
+    fn vim_app(text: &str) -> crate::App {
+        let mut app = app_with(Config { input_mode: InputMode::Vim, ..Config::default() });
+        type_text(&mut app, text);
+        press(&mut app, KeyCode::Esc);
+        app
+    }
+
+    #[test]
+    fn delete_word_forward_from_line_start() {
+        let mut app = vim_app("hello big world");
+        type_text(&mut app, "0dw");
+        assert_eq!(input(&app), "big world");
+        type_text(&mut app, "wdw");
+        assert_eq!(input(&app), "big ");
+    }
+
+    #[test]
+    fn delete_to_either_end() {
+        let mut app = vim_app("hello big world");
+        type_text(&mut app, "0wd$");
+        assert_eq!(input(&app), "hello ");
+
+        let mut app = vim_app("hello big world");
+        type_text(&mut app, "bd0");
+        assert_eq!(input(&app), "world");
+    }
+
+    #[test]
+    fn x_deletes_under_the_cursor_and_undoes_one_at_a_time() {
+        let mut app = vim_app("abc");
+        type_text(&mut app, "0xx");
+        assert_eq!(input(&app), "c");
+        type_text(&mut app, "u");
+        assert_eq!(input(&app), "bc");
+        type_text(&mut app, "u");
+        assert_eq!(input(&app), "abc");
+        press_with(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(input(&app), "bc");
+    }
+
+    #[test]
+    fn dd_clears_and_u_brings_it_back() {
+        let mut app = vim_app("hello world");
+        type_text(&mut app, "dd");
+        assert_eq!(input(&app), "");
+        type_text(&mut app, "u");
+        assert_eq!(input(&app), "hello world");
+    }
+
+    #[test]
+    fn inserting_after_moving() {
+        let mut app = vim_app("bc");
+        type_text(&mut app, "Ia");
+        assert_eq!(input(&app), "abc");
+
+        press(&mut app, KeyCode::Esc);
+        type_text(&mut app, "Ad");
+        assert_eq!(input(&app), "abcd");
+
+        press(&mut app, KeyCode::Esc);
+        type_text(&mut app, "0ax");
+        assert_eq!(input(&app), "axbcd");
+    }
+}
@@ -3,13 +3,23 @@ use ratatui::{
     layout::Rect,
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Paragraph},
 };
-use std::time::SystemTime;
+use std::ops::Range;
+use std::time::{Duration, SystemTime};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use crate::cursor::{CursorPosition, InlineCursor};
+use crate::cursor::{CursorPosition, InlineCursor, cursor_row, wrap_rows};
+use crate::layout::{LinkArea, RenderedMessage};
 use crate::theme::Theme;
-use onyx_core::{CursorStyle, Message, Role};
+use onyx_core::fs::format_size;
+use onyx_core::markup::{self, BlockParser, find_links, split_blocks};
+use onyx_core::{CursorStyle, Message, MessageKind, Role, TimestampDisplay};
+
+/// The columns of a message that aren't its text: the "│ " on the left, and as much again
+/// kept clear on the right.
+const FRAME_WIDTH: usize = 4;
 
 pub struct MessageWidget<'a> {
     message: &'a Message,
@@ -17,6 +27,15 @@ pub struct MessageWidget<'a> {
     width: usize,
     timestamp_format: &'a str,
     cursor_style: CursorStyle,
+    timestamp_display: TimestampDisplay,
+    user_name: &'a str,
+    assistant_name: &'a str,
+    align_right: bool,
+    selected: bool,
+    show_stats: bool,
+    matches: Vec<Range<usize>>,
+    current_match: Option<Range<usize>>,
+    streaming_blocks: Option<&'a BlockParser>,
 }
 
 impl<'a> MessageWidget<'a> {
@@ -27,39 +46,97 @@ impl<'a> MessageWidget<'a> {
         timestamp_format: &'a str,
         cursor_style: CursorStyle,
     ) -> Self {
-        Self { message, theme, width, timestamp_format, cursor_style }
+        Self {
+            message,
+            theme,
+            width,
+            timestamp_format,
+            cursor_style,
+            timestamp_display: TimestampDisplay::default(),
+            user_name: "You",
+            assistant_name: "Onyx",
+            align_right: false,
+            selected: false,
+            show_stats: false,
+            matches: Vec::new(),
+            current_match: None,
+            streaming_blocks: None,
+        }
+    }
+
+    pub fn timestamp_display(mut self, timestamp_display: TimestampDisplay) -> Self {
+        self.timestamp_display = timestamp_display;
+        self
+    }
+
+    pub fn names(mut self, user_name: &'a str, assistant_name: &'a str) -> Self {
+        self.user_name = user_name;
+        self.assistant_name = assistant_name;
+        self
+    }
+
+    /// Puts a user message's box against the right edge, at most 70% of the width wide.
+    pub fn align_user_right(mut self, align_right: bool) -> Self {
+        self.align_right = align_right;
+        self
+    }
+
+    pub fn show_stats(mut self, show_stats: bool) -> Self {
+        self.show_stats = show_stats;
+        self
+    }
+
+    /// Highlights these byte ranges of the message content, `current` most prominently.
+    pub fn matches(mut self, matches: Vec<Range<usize>>, current: Option<Range<usize>>) -> Self {
+        self.matches = matches;
+        self.current_match = current;
+        self
+    }
+
+    /// The blocks parsed so far while the message streams, rather than parsing it whole.
+    pub fn streaming_blocks(mut self, parser: Option<&'a BlockParser>) -> Self {
+        self.streaming_blocks = parser;
+        self
     }
 
-    pub fn render(&self) -> Vec<Line<'a>> {
-        let (prefix, style) = match self.message.role {
-            Role::User => ("You", self.theme.user_message),
-            Role::Assistant => ("Onyx", self.theme.assistant_message),
+    /// Draws the message's frame in the focused border style.
+    pub fn selected(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// Renders the message along with the index of the line showing the current match, if it
+    /// is shown at all, and where its links are.
+    pub fn render(&self) -> RenderedMessage {
+        let (prefix, style) = match (self.message.kind, &self.message.role) {
+            (MessageKind::Error, _) => ("⚠ Error", self.theme.error),
+            (_, Role::User) => (self.user_name, self.theme.user_message),
+            (_, Role::Assistant) => (self.assistant_name, self.theme.assistant_message),
+            (_, Role::System) => ("System", self.theme.system_message),
         };
+        let align_right = self.align_right && matches!(self.message.role, Role::User);
+        let width = if align_right { self.width * 7 / 10 } else { self.width };
 
+        let border = if self.selected { self.theme.border_focused } else { self.theme.border };
         let mut lines = Vec::new();
 
-        let timestamp = self.format_timestamp(self.message.timestamp);
-        let mut title_spans = vec![
-            Span::styled("┌─ ", self.theme.border),
-            Span::styled(prefix, style),
-            Span::styled(" ", self.theme.border),
-            Span::styled(timestamp, self.theme.help_text),
-        ];
+        let content_width = width.saturating_sub(FRAME_WIDTH);
+        // The header keeps to the same columns as the "│ " lines under it.
+        lines.push(self.header(prefix, style, border, content_width + 2));
 
-        if self.message.is_streaming {
-            title_spans.push(Span::styled(" ", self.theme.border));
-            title_spans.push(Span::styled("⠿", self.theme.success.add_modifier(Modifier::BOLD)));
-            title_spans.push(Span::styled(" streaming", self.theme.help_text));
+        for attachment in &self.message.attachments {
+            lines.push(Line::from(vec![
+                Span::styled("│ ", border),
+                Span::styled(
+                    format!("📎 {} ({})", attachment.path, format_size(attachment.size())),
+                    self.theme.help_text,
+                ),
+            ]));
         }
 
-        title_spans.push(Span::styled(" ─", self.theme.border));
-        lines.push(Line::from(title_spans));
-
-        let content_width = self.width.saturating_sub(4);
-
         if let Some(thinking) = &self.message.thinking {
             lines.push(Line::from(vec![
-                Span::styled("│ ", self.theme.border),
+                Span::styled("│ ", border),
                 Span::styled("💭 Thinking...", self.theme.help_text.add_modifier(Modifier::ITALIC)),
             ]));
 
@@ -68,56 +145,263 @@ impl<'a> MessageWidget<'a> {
 
             for line in wrapped_thinking {
                 lines.push(Line::from(vec![
-                    Span::styled("│   ", self.theme.border),
+                    Span::styled("│   ", border),
                     Span::styled(line, thinking_style),
                 ]));
             }
 
-            lines.push(Line::from(vec![Span::styled("│", self.theme.border)]));
+            lines.push(Line::from(vec![Span::styled("│", border)]));
         }
 
+        if !self.message.tool_calls.is_empty() {
+            let tool_style = self.theme.help_text.add_modifier(Modifier::ITALIC);
+
+            for call in &self.message.tool_calls {
+                let outcome = match &call.result {
+                    Some(result) => format!(" → {}", result),
+                    None => " …".to_string(),
+                };
+                let text = format!("🔧 {}({}){}", call.name, call.arguments, outcome);
+
+                for line in wrap_text(&text, content_width) {
+                    lines.push(Line::from(vec![
+                        Span::styled("│ ", border),
+                        Span::styled(line, tool_style),
+                    ]));
+                }
+            }
+
+            lines.push(Line::from(vec![Span::styled("│", border)]));
+        }
+
+        let mut match_line = None;
+        let mut links = Vec::new();
         if !self.message.content.is_empty() || self.message.is_streaming {
-            let wrapped_lines = wrap_text(&self.message.content, content_width);
+            let content = self.message.content.as_str();
+            let text_style = style.remove_modifier(Modifier::BOLD);
+            let fence_style = self.theme.help_text.add_modifier(Modifier::DIM);
+            let code_style = self.theme.system_message;
+
+            let match_style = Style::default().add_modifier(Modifier::REVERSED);
+            let current_style = self.theme.border_focused.add_modifier(Modifier::REVERSED);
+            let mut highlights: Vec<(Range<usize>, Style)> = self
+                .matches
+                .iter()
+                .map(|range| {
+                    let is_current = self.current_match.as_ref() == Some(range);
+                    (range.clone(), if is_current { current_style } else { match_style })
+                })
+                .collect();
+            // Links show in their own style, except where a search match shows over them.
+            let shown_links: Vec<_> = find_links(content)
+                .into_iter()
+                .filter(|link| {
+                    !self
+                        .matches
+                        .iter()
+                        .any(|m| m.start < link.range.end && link.range.start < m.end)
+                })
+                .collect();
+            highlights.extend(shown_links.iter().map(|link| (link.range.clone(), self.theme.link)));
+            let shows_current = |line: &MappedLine| {
+                self.current_match.as_ref().is_some_and(|current| {
+                    line.pieces
+                        .iter()
+                        .any(|(_, source)| source.start < current.end && current.start < source.end)
+                })
+            };
 
-            if wrapped_lines.is_empty() && self.message.is_streaming {
+            // Code keeps its layout: lines are shown verbatim and cut off rather than wrapped.
+            let mut content_lines: Vec<Vec<Span<'static>>> = Vec::new();
+            let mut add_line = |line: MappedLine, style: Style| {
+                if match_line.is_none() && shows_current(&line) {
+                    match_line = Some(content_lines.len());
+                }
+                for link in &shown_links {
+                    if let Some(columns) = line.columns(&link.range) {
+                        let line = content_lines.len();
+                        links.push(LinkArea { line, columns, url: link.url.to_string() });
+                    }
+                }
+                content_lines.push(line.spans(style, &highlights));
+            };
+            let blocks = match self.streaming_blocks {
+                Some(parser) if self.message.is_streaming => parser.blocks(content),
+                _ => split_blocks(content),
+            };
+            for block in blocks {
+                match block {
+                    markup::Block::Text(text) => {
+                        for line in wrap_mapped(text, offset_in(content, text), content_width) {
+                            add_line(line, text_style);
+                        }
+                    }
+                    markup::Block::Code { language, code, closed } => {
+                        add_line(MappedLine::indented(&format!("```{}", language)), fence_style);
+                        for line in code.split('\n') {
+                            add_line(
+                                code_line(line, offset_in(content, line), content_width),
+                                code_style,
+                            );
+                        }
+                        if closed {
+                            add_line(MappedLine::indented("```"), fence_style);
+                        }
+                    }
+                }
+            }
+            // Content lines follow everything rendered so far.
+            match_line = match_line.map(|line| line + lines.len());
+            // Past the "│ " border, too.
+            for link in &mut links {
+                link.line += lines.len();
+                link.columns = link.columns.start + 2..link.columns.end + 2;
+            }
+
+            let line_count = content_lines.len();
+            if content_lines.is_empty() && self.message.is_streaming {
                 let inline_cursor = InlineCursor::new(self.cursor_style);
                 lines.push(Line::from(vec![
-                    Span::styled("│ ", self.theme.border),
+                    Span::styled("│ ", border),
                     inline_cursor.render_char(style),
                 ]));
-            } else {
-                for (idx, line) in wrapped_lines.iter().enumerate() {
-                    let mut line_spans = vec![Span::styled("│ ", self.theme.border)];
-
-                    if idx == wrapped_lines.len() - 1 && self.message.is_streaming {
-                        line_spans.push(Span::styled(
-                            line.clone(),
-                            style.remove_modifier(Modifier::BOLD),
-                        ));
-
-                        let inline_cursor = InlineCursor::new(self.cursor_style);
-                        line_spans.push(inline_cursor.render_char(style));
-                    } else {
-                        line_spans.push(Span::styled(
-                            line.clone(),
-                            style.remove_modifier(Modifier::BOLD),
-                        ));
-                    }
+            }
+
+            for (idx, content) in content_lines.into_iter().enumerate() {
+                let mut line_spans = vec![Span::styled("│ ", border)];
+                line_spans.extend(content);
 
-                    lines.push(Line::from(line_spans));
+                if idx == line_count - 1 && self.message.is_streaming {
+                    let inline_cursor = InlineCursor::new(self.cursor_style);
+                    line_spans.push(inline_cursor.render_char(style));
                 }
+
+                lines.push(Line::from(line_spans));
             }
         }
 
-        lines.push(Line::from(Span::styled("└─", self.theme.border)));
+        if self.show_stats
+            && let Some(stats) = &self.message.stats
+        {
+            lines.push(Line::from(vec![
+                Span::styled("│ ", border),
+                Span::styled(stats.to_string(), self.theme.help_text.add_modifier(Modifier::DIM)),
+            ]));
+        }
+
+        lines.push(Line::from(Span::styled("└─", border)));
+
+        if align_right {
+            // The widest line ends at the right edge, and the rest line up with it.
+            let widest = lines.iter().map(Line::width).max().unwrap_or_default();
+            let indent = " ".repeat(self.width.saturating_sub(widest));
+            for line in &mut lines {
+                line.spans.insert(0, Span::raw(indent.clone()));
+            }
+            for link in &mut links {
+                link.columns = link.columns.start + indent.len()..link.columns.end + indent.len();
+            }
+        }
+
+        RenderedMessage { lines, match_line, links }
+    }
+
+    /// The "┌─ You 2025-01-02 13:45:12 ─" line, with less in it the narrower `width` is: the
+    /// timestamp loses its seconds, then its date, then goes with "streaming" shortened to the
+    /// spinner alone, and last of all the token counts go.
+    fn header(&self, prefix: &str, style: Style, border: Style, width: usize) -> Line<'static> {
+        let timestamps: Vec<String> = match self.timestamp_display {
+            TimestampDisplay::Absolute => {
+                let without_seconds =
+                    self.timestamp_format.replace("%T", "%H:%M").replace(":%S", "");
+                let mut timestamps: Vec<String> =
+                    [self.timestamp_format, without_seconds.as_str(), "%H:%M"]
+                        .iter()
+                        .map(|format| self.format_timestamp(self.message.timestamp, format))
+                        .collect();
+                timestamps.dedup();
+                timestamps
+            }
+            TimestampDisplay::Relative => {
+                vec![relative_time(self.message.timestamp, SystemTime::now())]
+            }
+            TimestampDisplay::Hidden => Vec::new(),
+        };
+
+        // From the most detailed header to the least, until one fits.
+        let mut headers = timestamps
+            .iter()
+            .map(|timestamp| (Some(timestamp.as_str()), true, true))
+            .chain([(None, true, true), (None, false, true), (None, false, false)])
+            .map(|(timestamp, spell_out_streaming, show_usage)| {
+                self.header_spans(prefix, style, border, timestamp, spell_out_streaming, show_usage)
+            });
+        let mut header = headers.next().unwrap_or_default();
+        while header.width() > width
+            && let Some(shorter) = headers.next()
+        {
+            header = shorter;
+        }
+        header
+    }
+
+    fn header_spans(
+        &self,
+        prefix: &str,
+        style: Style,
+        border: Style,
+        timestamp: Option<&str>,
+        spell_out_streaming: bool,
+        show_usage: bool,
+    ) -> Line<'static> {
+        let mut title_spans =
+            vec![Span::styled("┌─ ", border), Span::styled(prefix.to_string(), style)];
+        if let Some(timestamp) = timestamp {
+            title_spans.push(Span::styled(" ", border));
+            title_spans.push(Span::styled(timestamp.to_string(), self.theme.help_text));
+        }
+
+        if self.message.kind == MessageKind::Summary {
+            title_spans.push(Span::styled(
+                " (summary of earlier conversation)",
+                self.theme.help_text.add_modifier(Modifier::ITALIC),
+            ));
+        }
+
+        if self.message.is_streaming {
+            title_spans.push(Span::styled(" ", border));
+            title_spans.push(Span::styled("⠿", self.theme.success.add_modifier(Modifier::BOLD)));
+            if spell_out_streaming {
+                title_spans.push(Span::styled(" streaming", self.theme.help_text));
+            }
+        }
+
+        if show_usage && let Some(usage) = &self.message.usage {
+            title_spans.push(Span::styled(
+                format!(" ({} in / {} out)", usage.prompt_tokens, usage.completion_tokens),
+                self.theme.help_text.add_modifier(Modifier::DIM),
+            ));
+        }
 
-        lines
+        title_spans.push(Span::styled(" ─", border));
+        Line::from(title_spans)
     }
 
-    fn format_timestamp(&self, timestamp: SystemTime) -> String {
+    fn format_timestamp(&self, timestamp: SystemTime, format: &str) -> String {
         use chrono::{DateTime, Utc};
         let datetime: DateTime<Utc> = timestamp.into();
-        datetime.format(self.timestamp_format).to_string()
+        datetime.format(format).to_string()
+    }
+}
+
+/// How long before `now` the `timestamp` was, as "just now", "4m ago", "2h ago" or "3d ago".
+pub fn relative_time(timestamp: SystemTime, now: SystemTime) -> String {
+    let seconds = now.duration_since(timestamp).unwrap_or(Duration::ZERO).as_secs();
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
     }
 }
 
@@ -126,6 +410,10 @@ pub struct InputWidget<'a> {
     theme: &'a Theme,
     focused: bool,
     is_processing: bool,
+    status: Option<String>,
+    show_progress: bool,
+    editing: bool,
+    vim_mode: Option<char>,
     spinner_state: usize,
     cursor_position: usize,
     selection_range: Option<(usize, usize)>,
@@ -146,15 +434,38 @@ impl<'a> InputWidget<'a> {
             theme,
             focused,
             is_processing,
+            status: None,
+            show_progress: true,
+            editing: false,
+            vim_mode: None,
             spinner_state,
             cursor_position,
             selection_range,
         }
     }
 
-    fn get_spinner_char(&self) -> &'static str {
-        const SPINNER_CHARS: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-        SPINNER_CHARS[self.spinner_state % SPINNER_CHARS.len()]
+    /// Replaces the "Processing..." label in the footer while a response is in flight.
+    pub fn status(mut self, status: Option<String>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Leaves the spinner and status out of the footer, for when the status bar shows them.
+    pub fn show_progress(mut self, show_progress: bool) -> Self {
+        self.show_progress = show_progress;
+        self
+    }
+
+    /// Marks the input as holding an earlier message being edited, which sending resends.
+    pub fn editing(mut self, editing: bool) -> Self {
+        self.editing = editing;
+        self
+    }
+
+    /// Shows the vim mode's letter after the title, in vim input mode.
+    pub fn vim_mode(mut self, indicator: Option<char>) -> Self {
+        self.vim_mode = indicator;
+        self
     }
 
     fn render_input_with_cursor(&self, base_style: Style) -> Vec<Span<'static>> {
@@ -184,12 +495,33 @@ impl<'a> InputWidget<'a> {
         spans
     }
 
+    /// The height of an input box holding `input` at `width`: enough for the text as it wraps,
+    /// between one line and `max_lines`, plus the borders.
+    pub fn height(input: &str, width: u16, max_lines: u16) -> u16 {
+        let lines = wrap_rows(input, width.saturating_sub(2) as usize).len() as u16;
+        lines.clamp(1, max_lines.max(1)) + 2
+    }
+
+    /// How many rows the text is scrolled by in `area`, so that the cursor's row stays in view.
+    fn scroll(&self, area: Rect) -> u16 {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        let rows = wrap_rows(self.input, inner.width as usize);
+        let row = cursor_row(&rows, self.cursor_position) as u16;
+        row.saturating_sub(inner.height.saturating_sub(1))
+    }
+
     pub fn get_cursor_position(&self, area: Rect) -> Option<(u16, u16)> {
         if !self.focused {
             return None;
         }
 
-        let pos = CursorPosition::calculate(self.input, self.cursor_position, area, true)?;
+        let pos = CursorPosition::calculate(
+            self.input,
+            self.cursor_position,
+            area,
+            true,
+            self.scroll(area),
+        )?;
         Some((pos.x, pos.y))
     }
 
@@ -241,16 +573,40 @@ impl<'a> InputWidget<'a> {
 
         let border_style = if self.focused { self.theme.border_focused } else { self.theme.border };
 
-        let title = Line::from(Span::styled(" Input ", self.theme.title));
+        let title = if self.editing { " Editing message " } else { " Input " };
+        let mut title = Line::from(Span::styled(title, self.theme.title));
+        if let Some(indicator) = self.vim_mode {
+            title.push_span(Span::styled(
+                format!("{} ", indicator),
+                self.theme.success.add_modifier(Modifier::BOLD),
+            ));
+        }
 
-        let bottom_title = if self.is_processing {
+        let bottom_title = if self.is_processing && !self.show_progress {
+            Line::from(vec![
+                Span::styled(" [Esc] ", self.theme.success),
+                Span::styled("cancel ", self.theme.help_text),
+            ])
+        } else if self.is_processing {
+            let status = format!(" {}... ", self.status.as_deref().unwrap_or("Processing"));
             Line::from(vec![
                 Span::styled(" ", self.theme.help_text),
                 Span::styled(
-                    self.get_spinner_char(),
+                    spinner_char(self.spinner_state),
                     self.theme.success.add_modifier(Modifier::BOLD),
                 ),
-                Span::styled(" Processing... ", self.theme.help_text),
+                Span::styled(status, self.theme.help_text),
+                Span::styled("• ", self.theme.border),
+                Span::styled("[Esc] ", self.theme.success),
+                Span::styled("cancel ", self.theme.help_text),
+            ])
+        } else if self.editing {
+            Line::from(vec![
+                Span::styled(" [Enter] ", self.theme.success),
+                Span::styled("resend ", self.theme.help_text),
+                Span::styled("• ", self.theme.border),
+                Span::styled("[Esc] ", self.theme.success),
+                Span::styled("cancel edit ", self.theme.help_text),
             ])
         } else {
             Line::from(vec![
@@ -276,13 +632,13 @@ impl<'a> InputWidget<'a> {
             .title_bottom(bottom_title);
 
         let input_text = if self.input.is_empty() && !self.focused {
-            vec![Span::styled("Type your message here...", self.theme.help_text)]
+            vec![Line::from(Span::styled("Type your message here...", self.theme.help_text))]
         } else {
-            self.render_input_with_cursor(style)
+            let rows = wrap_rows(self.input, area.width.saturating_sub(2) as usize);
+            split_rows(&self.render_input_with_cursor(style), &rows)
         };
 
-        let paragraph =
-            Paragraph::new(Line::from(input_text)).block(block).wrap(Wrap { trim: false });
+        let paragraph = Paragraph::new(input_text).block(block).scroll((self.scroll(area), 0));
 
         frame.render_widget(paragraph, area);
 
@@ -295,6 +651,45 @@ impl<'a> InputWidget<'a> {
     }
 }
 
+/// Cuts `spans`, which hold the whole input, into one line per row of `rows`.
+fn split_rows(spans: &[Span<'static>], rows: &[Range<usize>]) -> Vec<Line<'static>> {
+    rows.iter()
+        .map(|row| {
+            let mut line = Vec::new();
+            let mut offset = 0;
+            for span in spans {
+                let end = offset + span.content.len();
+                let (from, to) = (row.start.max(offset), row.end.min(end));
+                if from < to {
+                    line.push(Span::styled(
+                        span.content[from - offset..to - offset].to_string(),
+                        span.style,
+                    ));
+                }
+                offset = end;
+            }
+            Line::from(line)
+        })
+        .collect()
+}
+
+/// A `width` by `height` dialog in the middle of `area`, shrunk to fit when `area` is smaller.
+pub fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+pub fn spinner_char(state: usize) -> &'static str {
+    const SPINNER_CHARS: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    SPINNER_CHARS[state % SPINNER_CHARS.len()]
+}
+
 pub struct HelpWidget<'a> {
     theme: &'a Theme,
 }
@@ -304,7 +699,7 @@ impl<'a> HelpWidget<'a> {
         Self { theme }
     }
 
-    pub fn render(&self) -> Vec<Line<'a>> {
+    pub fn render(&self) -> Vec<Line<'static>> {
         vec![
             Line::from(vec![Span::styled(
                 "Welcome to Onyx! ",
@@ -322,11 +717,14 @@ impl<'a> HelpWidget<'a> {
                 Span::styled("/config", self.theme.success),
                 Span::styled(" • ", self.theme.help_text),
                 Span::styled("/help", self.theme.success),
+                Span::styled(" • ", self.theme.help_text),
+                Span::styled("[F1]", self.theme.success),
+                Span::styled(" all keys and commands", self.theme.help_text),
             ]),
             Line::from(vec![
                 Span::styled("Navigation: ", self.theme.help_text.add_modifier(Modifier::BOLD)),
                 Span::styled("↑↓", self.theme.success),
-                Span::styled(" scroll • ", self.theme.help_text),
+                Span::styled(" scroll (recall while typing) • ", self.theme.help_text),
                 Span::styled("PgUp/PgDn", self.theme.success),
                 Span::styled(" page • ", self.theme.help_text),
                 Span::styled("Home/End", self.theme.success),
@@ -337,14 +735,29 @@ impl<'a> HelpWidget<'a> {
     }
 }
 
+/// An entry of the command menu: a command, or a value for a command's argument.
+pub struct Completion {
+    /// What replaces the word under the cursor.
+    pub text: String,
+    pub description: String,
+    /// The argument a command takes, shown after it, such as `[name]`.
+    pub argument: Option<&'static str>,
+}
+
+impl Completion {
+    pub fn new(text: impl Into<String>, description: impl Into<String>) -> Self {
+        Self { text: text.into(), description: description.into(), argument: None }
+    }
+}
+
 pub struct CommandMenuWidget<'a> {
-    commands: &'a [(&'a str, &'a str)],
+    commands: &'a [Completion],
     selected: usize,
     theme: &'a Theme,
 }
 
 impl<'a> CommandMenuWidget<'a> {
-    pub fn new(commands: &'a [(&'a str, &'a str)], selected: usize, theme: &'a Theme) -> Self {
+    pub fn new(commands: &'a [Completion], selected: usize, theme: &'a Theme) -> Self {
         Self { commands, selected, theme }
     }
 
@@ -358,23 +771,27 @@ impl<'a> CommandMenuWidget<'a> {
         frame.render_widget(block, area);
 
         let mut lines = Vec::new();
-        for (idx, (cmd, desc)) in self.commands.iter().enumerate() {
-            let line = if idx == self.selected {
-                Line::from(vec![
-                    Span::styled(" ▶ ", self.theme.success.add_modifier(Modifier::BOLD)),
-                    Span::styled(*cmd, self.theme.success.add_modifier(Modifier::BOLD)),
-                    Span::styled(" - ", self.theme.help_text),
-                    Span::styled(*desc, self.theme.help_text.add_modifier(Modifier::ITALIC)),
-                ])
+        for (idx, completion) in self.commands.iter().enumerate() {
+            let (marker, text_style, description_style) = if idx == self.selected {
+                (
+                    " ▶ ",
+                    self.theme.success.add_modifier(Modifier::BOLD),
+                    self.theme.help_text.add_modifier(Modifier::ITALIC),
+                )
             } else {
-                Line::from(vec![
-                    Span::styled("   ", self.theme.help_text),
-                    Span::styled(*cmd, self.theme.success),
-                    Span::styled(" - ", self.theme.help_text),
-                    Span::styled(*desc, self.theme.help_text),
-                ])
+                ("   ", self.theme.success, self.theme.help_text)
             };
-            lines.push(line);
+
+            let mut spans = vec![
+                Span::styled(marker, text_style),
+                Span::styled(completion.text.clone(), text_style),
+            ];
+            if let Some(argument) = completion.argument {
+                spans.push(Span::styled(format!(" {}", argument), self.theme.help_text));
+            }
+            spans.push(Span::styled(" - ", self.theme.help_text));
+            spans.push(Span::styled(completion.description.clone(), description_style));
+            lines.push(Line::from(spans));
         }
 
         let paragraph = Paragraph::new(lines);
@@ -382,70 +799,207 @@ impl<'a> CommandMenuWidget<'a> {
     }
 }
 
-fn wrap_text(text: &str, width: usize) -> Vec<String> {
+/// A rendered line that remembers which bytes of the source text each part shows, so that
+/// ranges of the source (such as search matches) can be found again after wrapping.
+#[derive(Default)]
+struct MappedLine {
+    text: String,
+    pieces: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl MappedLine {
+    fn indented(indent: &str) -> Self {
+        Self { text: indent.to_string(), pieces: Vec::new() }
+    }
+
+    /// Appends `text`, which shows `source` of the original.
+    fn push(&mut self, text: &str, source: Range<usize>) {
+        let start = self.text.len();
+        self.text.push_str(text);
+
+        // Runs of text copied verbatim merge, so most lines end up with a handful of pieces.
+        if let Some((last_text, last_source)) = self.pieces.last_mut()
+            && last_text.end == start
+            && last_source.end == source.start
+            && last_text.len() == last_source.len()
+            && text.len() == source.len()
+        {
+            last_text.end = self.text.len();
+            last_source.end = source.end;
+        } else {
+            self.pieces.push((start..self.text.len(), source));
+        }
+    }
+
+    /// The bytes of each piece of the line that show part of `range` of the source.
+    fn parts<'b>(&'b self, range: &'b Range<usize>) -> impl Iterator<Item = Range<usize>> + 'b {
+        self.pieces.iter().filter_map(move |(text, source)| {
+            if range.start >= source.end || range.end <= source.start {
+                return None;
+            }
+            // Pieces that were rewritten, like expanded tabs, are highlighted as a whole.
+            Some(if text.len() == source.len() {
+                let start = text.start + range.start.saturating_sub(source.start);
+                let end = text.start + (range.end.min(source.end) - source.start);
+                start..end
+            } else {
+                text.clone()
+            })
+        })
+    }
+
+    /// The columns that show `range` of the source, if the line shows any of it.
+    fn columns(&self, range: &Range<usize>) -> Option<Range<usize>> {
+        let start = self.parts(range).map(|part| part.start).min()?;
+        let end = self.parts(range).map(|part| part.end).max()?;
+        Some(self.text[..start].width()..self.text[..end].width())
+    }
+
+    /// Splits the line into spans, giving the parts that show a highlighted source range that
+    /// range's style.
+    fn spans(self, style: Style, highlights: &[(Range<usize>, Style)]) -> Vec<Span<'static>> {
+        let mut marked: Vec<(Range<usize>, Style)> = Vec::new();
+        for (range, highlight) in highlights {
+            marked.extend(self.parts(range).map(|part| (part, *highlight)));
+        }
+
+        if marked.is_empty() {
+            return vec![Span::styled(self.text, style)];
+        }
+        marked.sort_by_key(|(range, _)| range.start);
+
+        let mut spans = Vec::new();
+        let mut position = 0;
+        for (range, highlight) in marked {
+            let start = range.start.max(position);
+            if start >= range.end {
+                continue;
+            }
+            if start > position {
+                spans.push(Span::styled(self.text[position..start].to_string(), style));
+            }
+            spans.push(Span::styled(self.text[start..range.end].to_string(), highlight));
+            position = range.end;
+        }
+        if position < self.text.len() {
+            spans.push(Span::styled(self.text[position..].to_string(), style));
+        }
+        spans
+    }
+}
+
+/// The byte offset of `part` within `text`, which it must be a slice of.
+fn offset_in(text: &str, part: &str) -> usize {
+    part.as_ptr() as usize - text.as_ptr() as usize
+}
+
+/// Wraps `text` to `width` columns. Each line keeps its leading indentation, which its
+/// continuation lines repeat unless it would take up most of the width.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    wrap_mapped(text, 0, width).into_iter().map(|line| line.text).collect()
+}
+
+/// Like [`wrap_text`], with source positions counted from `offset`.
+fn wrap_mapped(text: &str, offset: usize, width: usize) -> Vec<MappedLine> {
     if width == 0 {
-        return vec![text.to_string()];
+        let mut line = MappedLine::default();
+        line.push(text, offset..offset + text.len());
+        return vec![line];
     }
 
     let mut result = Vec::new();
 
     for paragraph in text.split('\n') {
-        if paragraph.is_empty() {
-            result.push(String::new());
+        let body = paragraph.trim_start();
+        if body.is_empty() {
+            result.push(MappedLine::default());
             continue;
         }
 
-        let mut current_line = String::new();
-        let mut current_width = 0;
+        let mut indent = paragraph[..paragraph.len() - body.len()].replace('\t', "    ");
+        if indent.width() * 2 > width {
+            indent.clear();
+        }
+        let indent_width = indent.width();
+
+        let mut current_line = MappedLine::indented(&indent);
+        let mut current_width = indent_width;
+        let mut previous_end = None;
 
-        for word in paragraph.split_whitespace() {
-            let word_len = word.len();
+        for word in body.split_whitespace() {
+            let word_start = offset + offset_in(text, word);
+            let word_width = word.width();
 
-            if current_width + word_len + 1 > width && !current_line.is_empty() {
-                result.push(current_line.clone());
-                current_line.clear();
-                current_width = 0;
+            if current_width > indent_width && current_width + word_width + 1 > width {
+                result.push(std::mem::replace(&mut current_line, MappedLine::indented(&indent)));
+                current_width = indent_width;
             }
 
-            if !current_line.is_empty() {
-                current_line.push(' ');
+            if current_width > indent_width {
+                current_line.push(" ", previous_end.unwrap_or(word_start)..word_start);
                 current_width += 1;
             }
+            previous_end = Some(word_start + word.len());
 
-            if word_len > width {
-                for chunk in word.as_bytes().chunks(width) {
-                    let chunk_str = std::str::from_utf8(chunk).unwrap_or("");
-                    if !current_line.is_empty() {
-                        result.push(current_line.clone());
-                        current_line.clear();
-                        current_width = 0;
-                    }
-                    result.push(chunk_str.to_string());
+            if current_width + word_width <= width {
+                current_line.push(word, word_start..word_start + word.len());
+                current_width += word_width;
+                continue;
+            }
+
+            // Words wider than a line, such as unspaced CJK text, break between graphemes.
+            for (index, grapheme) in word.grapheme_indices(true) {
+                let grapheme_width = grapheme.width();
+                if current_width + grapheme_width > width && current_width > indent_width {
+                    result
+                        .push(std::mem::replace(&mut current_line, MappedLine::indented(&indent)));
+                    current_width = indent_width;
                 }
-            } else {
-                current_line.push_str(word);
-                current_width += word_len;
+                let start = word_start + index;
+                current_line.push(grapheme, start..start + grapheme.len());
+                current_width += grapheme_width;
             }
         }
 
-        if !current_line.is_empty() {
-            result.push(current_line);
-        }
+        result.push(current_line);
     }
 
     if result.is_empty() {
-        result.push(String::new());
+        result.push(MappedLine::default());
     }
 
     result
 }
 
+/// Lays out a line of code verbatim, with tabs expanded, cut down to `width` columns with an
+/// ellipsis marking the cut.
+fn code_line(line: &str, offset: usize, width: usize) -> MappedLine {
+    let fits = line.width() + line.matches('\t').count() * 3 <= width;
+    let mut mapped = MappedLine::default();
+    let mut mapped_width = 0;
+
+    for (index, grapheme) in line.grapheme_indices(true) {
+        let shown = if grapheme == "\t" { "    " } else { grapheme };
+        let shown_width = shown.width();
+        if !fits && mapped_width + shown_width + 1 > width {
+            mapped.text.push('…');
+            break;
+        }
+        let start = offset + index;
+        mapped.push(shown, start..start + grapheme.len());
+        mapped_width += shown_width;
+    }
+    mapped
+}
+
 pub struct ConfigFieldWidget<'a> {
     label: String,
     value: String,
     is_selected: bool,
     is_editing: bool,
     cursor_position: usize,
+    is_default: bool,
+    indent: usize,
     theme: &'a Theme,
 }
 
@@ -458,7 +1012,29 @@ impl<'a> ConfigFieldWidget<'a> {
         cursor_position: usize,
         theme: &'a Theme,
     ) -> Self {
-        Self { label, value, is_selected, is_editing, cursor_position, theme }
+        Self {
+            label,
+            value,
+            is_selected,
+            is_editing,
+            cursor_position,
+            is_default: false,
+            indent: 0,
+            theme,
+        }
+    }
+
+    /// Shifts the label right by `indent` columns, keeping the values lined up with unindented
+    /// fields.
+    pub fn indented(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Marks the value as the default, so customized fields stand out.
+    pub fn at_default(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
     }
 
     pub fn render(&self) -> Line<'static> {
@@ -478,13 +1054,23 @@ impl<'a> ConfigFieldWidget<'a> {
 
         let prefix = if self.is_selected { "▶ " } else { "  " };
         let label_width = 22;
-        let formatted_label = format!("{}{:<width$}", prefix, self.label, width = label_width);
-
-        Line::from(vec![
+        let formatted_label = format!(
+            "{}{}{:<width$}",
+            " ".repeat(self.indent),
+            prefix,
+            self.label,
+            width = label_width - self.indent.min(label_width)
+        );
+
+        let mut spans = vec![
             Span::styled(formatted_label, label_style),
             Span::raw(" : "),
             Span::styled(self.value.clone(), value_style),
-        ])
+        ];
+        if self.is_default && !self.is_editing {
+            spans.push(Span::styled(" (default)", self.theme.help_text));
+        }
+        Line::from(spans)
     }
 
     pub fn get_cursor_position(&self, area: Rect, line_y: u16) -> Option<(u16, u16)> {
@@ -496,10 +1082,111 @@ impl<'a> ConfigFieldWidget<'a> {
         const LABEL_WIDTH: usize = 22;
         const SEPARATOR_WIDTH: usize = 3;
 
-        let cursor_x =
-            area.x + (PREFIX_WIDTH + LABEL_WIDTH + SEPARATOR_WIDTH + self.cursor_position) as u16;
+        let value_width = self.value.get(..self.cursor_position).map_or(0, |text| text.width());
+        let cursor_x = area.x + (PREFIX_WIDTH + LABEL_WIDTH + SEPARATOR_WIDTH + value_width) as u16;
         let cursor_y = line_y;
 
         Some((cursor_x, cursor_y))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use onyx_core::Usage;
+
+    /// 2025-01-02 13:45:12 UTC.
+    const TIMESTAMP: u64 = 1_735_825_512;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    fn header(message: &Message, width: usize) -> String {
+        let theme = Theme::default();
+        let widget =
+            MessageWidget::new(message, &theme, width, "%Y-%m-%d %H:%M:%S", CursorStyle::default());
+        text(&widget.render().lines[0])
+    }
+
+    fn streaming_reply() -> Message {
+        let mut message = Message::assistant_streaming();
+        message.timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(TIMESTAMP);
+        message.usage =
+            Some(Usage { prompt_tokens: 1200, completion_tokens: 350, total_tokens: 1550 });
+        message
+    }
+
+    #[test]
+    fn the_header_drops_detail_as_the_width_shrinks() {
+        let message = streaming_reply();
+        let headers: Vec<String> = [120, 80, 60, 40].map(|width| header(&message, width)).into();
+        assert_eq!(
+            headers,
+            [
+                "┌─ Onyx 2025-01-02 13:45:12 ⠿ streaming (1200 in / 350 out) ─",
+                "┌─ Onyx 2025-01-02 13:45:12 ⠿ streaming (1200 in / 350 out) ─",
+                "┌─ Onyx 2025-01-02 13:45 ⠿ streaming (1200 in / 350 out) ─",
+                "┌─ Onyx ⠿ (1200 in / 350 out) ─",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_header_keeps_within_the_body_columns() {
+        let mut message = streaming_reply();
+        message.usage = None;
+        assert_eq!(header(&message, 40), "┌─ Onyx 2025-01-02 13:45 ⠿ streaming ─");
+        assert_eq!(header(&message, 30), "┌─ Onyx 13:45 ⠿ streaming ─");
+        assert_eq!(header(&message, 20), "┌─ Onyx ⠿ ─");
+
+        for width in [120, 80, 60, 40, 30, 20] {
+            let rendered = header(&streaming_reply(), width);
+            assert!(rendered.width() <= width - FRAME_WIDTH + 2, "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn words_wider_than_the_line_break_between_graphemes() {
+        assert_eq!(wrap_text("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_text("hi abcdefghij", 4), ["hi", "abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn wide_characters_count_two_columns() {
+        assert_eq!(wrap_text("😀😀😀", 4), ["😀😀", "😀"]);
+        assert_eq!(wrap_text("日本語テキスト", 5), ["日本", "語テ", "キス", "ト"]);
+        assert_eq!(wrap_text("ok 日本 語", 7), ["ok 日本", "語"]);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_letter() {
+        let e = "e\u{301}";
+        assert_eq!(wrap_text(&e.repeat(3), 2), [e.repeat(2), e.to_string()]);
+        assert_eq!(wrap_text(&format!("caf{e} caf{e}"), 4), [format!("caf{e}"), format!("caf{e}")]);
+    }
+
+    #[test]
+    fn no_line_is_wider_than_the_width() {
+        let text = "mixed 日本語テキスト with 😀😀😀 and cafe\u{301}s, averyveryverylongword";
+        for width in 2..20 {
+            for line in wrap_text(text, width) {
+                assert!(line.width() <= width, "{line:?} is wider than {width}");
+            }
+        }
+    }
+
+    #[test]
+    fn centered_stays_inside_a_single_column_or_row() {
+        assert_eq!(centered(Rect::new(3, 4, 1, 10), 40, 5), Rect::new(3, 6, 1, 5));
+        assert_eq!(centered(Rect::new(3, 4, 80, 1), 40, 5), Rect::new(23, 4, 40, 1));
+        assert_eq!(centered(Rect::new(3, 4, 1, 1), 40, 5), Rect::new(3, 4, 1, 1));
+        assert_eq!(centered(Rect::new(3, 4, 0, 0), 40, 5), Rect::new(3, 4, 0, 0));
+    }
+
+    #[test]
+    fn centered_keeps_a_smaller_box_whole() {
+        assert_eq!(centered(Rect::new(0, 0, 80, 24), 40, 5), Rect::new(20, 9, 40, 5));
+        assert_eq!(centered(Rect::new(10, 2, 41, 6), 40, 5), Rect::new(10, 2, 40, 5));
+    }
+}
@@ -0,0 +1,271 @@
+use clap::{Parser, Subcommand};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use onyx_agent::diagnose;
+use onyx_core::{
+    Config, ConfigResult, ConfigSchema, FieldDescriptor, FieldType, FieldValue, Provider, ThemeName,
+};
+use strum::IntoEnumIterator;
+
+/// Exit code for a command line that couldn't be parsed.
+const USAGE_ERROR: i32 = 1;
+
+/// What was asked for on the command line. The options can go before or after a subcommand.
+#[derive(Parser)]
+#[command(
+    name = "onyx",
+    about = "Onyx - AI Chat Terminal Application",
+    version = concat!(env!("CARGO_PKG_VERSION"), " (", env!("ONYX_GIT_HASH"), ")"),
+    after_help = EXAMPLES
+)]
+pub struct Args {
+    /// Use this config file (.toml or .json)
+    #[arg(short, long = "config", value_name = "PATH", global = true)]
+    pub config_path: Option<PathBuf>,
+    /// Write the debug log here (default logs/onyx.log in the data directory)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub log_file: Option<PathBuf>,
+    /// Use this provider for this run
+    #[arg(short, long, value_name = "NAME", value_parser = parse_provider, global = true)]
+    pub provider: Option<Provider>,
+    /// Use this model for this run
+    #[arg(short, long, value_name = "NAME", global = true)]
+    pub model: Option<String>,
+    /// Use this theme for this run
+    #[arg(long, value_name = "NAME", value_parser = parse_theme, global = true)]
+    pub theme: Option<ThemeName>,
+    /// Show API keys in `config get` and `config list`
+    #[arg(long, global = true)]
+    pub show_secrets: bool,
+    /// A subcommand to run instead of starting the chat.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Read and change the config from the shell
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Check the config and the connection to the provider
+    Doctor,
+}
+
+/// The `onyx config ...` subcommands, which read and change the config from the shell.
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print a config field
+    Get { field: String },
+    /// Change a config field
+    Set { field: String, value: String },
+    /// Put a config field back to its default
+    Reset { field: String },
+    /// Print every config field
+    List,
+    /// Print where the config file is
+    Path,
+}
+
+const EXAMPLES: &str = "\
+Examples:
+  onyx                              # Use config.toml, or else config.json, in ~/.config/onyx
+  onyx --config /path/to/config.toml
+  onyx --provider ollama --model llama3.2
+  onyx config set theme monokai";
+
+impl Args {
+    /// Parses the process arguments, exiting with a message on stderr if they don't make sense
+    /// and after printing `--help` or `--version`.
+    pub fn parse() -> Self {
+        <Self as Parser>::try_parse().unwrap_or_else(|error| {
+            let _ = error.print();
+            process::exit(if error.use_stderr() { USAGE_ERROR } else { 0 });
+        })
+    }
+
+    /// The config file to use: the `--config` one if given, otherwise the default.
+    pub fn config_file(&self) -> ConfigResult<PathBuf> {
+        match &self.config_path {
+            Some(path) => Ok(path.clone()),
+            None => Config::config_path(),
+        }
+    }
+
+    /// Applies `--provider`, `--model` and `--theme` to the loaded config. They only last for
+    /// this run: nothing is written back to the config file here.
+    pub fn apply_overrides(&self, config: &mut Config) {
+        if let Some(provider) = &self.provider {
+            config.active_provider = provider.clone();
+        }
+        if let Some(model) = &self.model {
+            config.get_active_provider_mut().model = model.clone();
+        }
+        if let Some(theme) = self.theme {
+            config.theme = theme;
+        }
+    }
+}
+
+/// Runs an `onyx config` subcommand, printing its output to stdout.
+pub fn run_config_command(command: &ConfigCommand, args: &Args) -> Result<(), String> {
+    let config_path = args.config_file().map_err(|e| e.to_string())?;
+    if let ConfigCommand::Path = command {
+        println!("{}", config_path.display());
+        return Ok(());
+    }
+
+    let mut config = Config::load_from(Some(config_path.clone())).map_err(|e| e.to_string())?;
+    match command {
+        ConfigCommand::Get { field } => {
+            let field = find_field(field)?;
+            println!("{}", field_value(&config, &field, args.show_secrets)?);
+        }
+        ConfigCommand::Set { field, value } => {
+            let field = find_field(field)?;
+            config
+                .set_field(&field.id, parse_field_value(&field, value)?)
+                .map_err(|e| e.to_string())?;
+            config.save().map_err(|e| e.to_string())?;
+        }
+        ConfigCommand::Reset { field } => {
+            let field = find_field(field)?;
+            config.reset_field(&field.id).map_err(|e| e.to_string())?;
+            config.save().map_err(|e| e.to_string())?;
+            println!("{} = {}", field.id, field_value(&config, &field, args.show_secrets)?);
+        }
+        ConfigCommand::List => {
+            // Written without println!, which panics once a reader such as `head` goes away.
+            let mut out = io::stdout().lock();
+            for field in Config::fields().iter().filter(|field| !field.is_group) {
+                let value = field_value(&config, field, args.show_secrets)?;
+                if writeln!(out, "{} = {}", field.id, value).is_err() {
+                    break;
+                }
+            }
+        }
+        ConfigCommand::Path => {}
+    }
+    Ok(())
+}
+
+/// Runs `onyx doctor`, printing a line per check. Returns whether every hard requirement passed.
+pub async fn run_doctor(args: &Args) -> Result<bool, String> {
+    let config_path = args.config_file().map_err(|e| e.to_string())?;
+    // The file is only read here: unlike at startup, a missing or broken one is left alone and
+    // reported rather than replaced with the defaults.
+    let mut config = Config::read_from(&config_path).unwrap_or_default();
+    args.apply_overrides(&mut config);
+
+    let diagnosis = diagnose(&config_path, &config).await;
+    print!("{}", diagnosis);
+    Ok(diagnosis.is_healthy())
+}
+
+fn find_field(id: &str) -> Result<FieldDescriptor, String> {
+    Config::fields()
+        .into_iter()
+        .find(|field| field.id == id && !field.is_group)
+        .ok_or_else(|| format!("Unknown field '{}'. Run `onyx config list` to see them all", id))
+}
+
+fn field_value(
+    config: &Config,
+    field: &FieldDescriptor,
+    show_secrets: bool,
+) -> Result<String, String> {
+    let value = field.get_value(config).map_err(|e| e.to_string())?;
+    Ok(field.display_string(&value, show_secrets))
+}
+
+/// Parses `value` the way the config editor does, except that an enum value must be one of
+/// the listed ones rather than quietly falling back to the default.
+fn parse_field_value(field: &FieldDescriptor, value: &str) -> Result<FieldValue, String> {
+    if field.field_type == FieldType::Enum {
+        return field
+            .enum_values
+            .iter()
+            .find(|valid| valid.eq_ignore_ascii_case(value.trim()))
+            .map(|valid| FieldValue::Enum(valid.clone()))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid value '{}' for {}. Valid values: {}",
+                    value,
+                    field.id,
+                    field.enum_values.join(", ")
+                )
+            });
+    }
+    FieldValue::from_string(value.to_string(), field)
+        .map_err(|e| format!("Invalid value '{}' for {}: {}", value, field.id, e))
+}
+
+fn parse_provider(name: &str) -> Result<Provider, String> {
+    Provider::iter().find(|provider| provider.to_string().eq_ignore_ascii_case(name)).ok_or_else(
+        || {
+            let valid: Vec<String> =
+                Provider::iter().map(|provider| provider.to_string()).collect();
+            format!("Unknown provider '{}'. Valid values: {}", name, valid.join(", "))
+        },
+    )
+}
+
+fn parse_theme(name: &str) -> Result<ThemeName, String> {
+    name.parse().map_err(|_| {
+        let valid: Vec<&str> = ThemeName::iter().map(<&'static str>::from).collect();
+        format!("Unknown theme '{}'. Valid values: {}", name, valid.join(", "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("onyx").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn definition_is_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn overrides() {
+        let args = parse(&["--provider=ollama", "-m", "llama3.2", "--theme", "monokai"]).unwrap();
+        assert_eq!(args.provider, Some(Provider::Ollama));
+        assert_eq!(args.model.as_deref(), Some("llama3.2"));
+        assert_eq!(args.theme, Some(ThemeName::Monokai));
+        assert!(args.command.is_none());
+    }
+
+    #[test]
+    fn options_can_follow_a_subcommand() {
+        let args = parse(&["config", "get", "theme", "--show-secrets", "-c", "a.toml"]).unwrap();
+        assert!(args.show_secrets);
+        assert_eq!(args.config_path, Some(PathBuf::from("a.toml")));
+        assert!(matches!(
+            args.command,
+            Some(Command::Config(ConfigCommand::Get { field })) if field == "theme"
+        ));
+    }
+
+    #[test]
+    fn an_unknown_provider_lists_the_valid_ones() {
+        let message = parse(&["--provider", "nope"]).err().unwrap().to_string();
+        assert!(message.contains("Unknown provider 'nope'"), "{}", message);
+        for provider in Provider::iter() {
+            assert!(message.contains(&provider.to_string()), "{}", message);
+        }
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["config", "get"]).is_err());
+        assert!(parse(&["config", "set", "theme"]).is_err());
+        assert!(parse(&["--theme", "nope"]).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Logging to a file through `tracing-subscriber`, one line per event, with API keys redacted
+//! from every line. The file is rolled over once it grows past [`MAX_LOG_BYTES`];
+//! `tracing-appender` only rolls files over by time, so the writer here does it instead.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::FormatTime;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Registry, reload};
+
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const MIN_SECRET_LEN: usize = 6;
+
+static LOG_FILE: OnceLock<LogFile> = OnceLock::new();
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Starts logging to `path` at `level`. Later calls change nothing, since the subscriber can
+/// only be installed once.
+pub fn init(path: PathBuf, level: LevelFilter) {
+    if LOG_FILE.set(LogFile::new(path)).is_err() {
+        return;
+    }
+
+    let (filter, handle) = reload::Layer::new(level);
+    let _ = LEVEL.set(handle);
+    let subscriber = tracing_subscriber::registry().with(filter).with(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_timer(LocalTime)
+            .with_writer(|| LineWriter { buffer: Vec::new() }),
+    );
+    let _ = tracing::subscriber::set_global_default(subscriber);
+}
+
+pub fn set_level(level: LevelFilter) {
+    if let Some(handle) = LEVEL.get() {
+        let _ = handle.modify(|filter| *filter = level);
+    }
+}
+
+/// Sets the strings to keep out of the log, replacing the previous ones. Very short ones are
+/// left alone, as they are placeholders for servers without keys rather than secrets, and
+/// redacting them would mangle every line.
+pub fn set_secrets<'a>(secrets: impl IntoIterator<Item = &'a str>) {
+    if let Some(log_file) = LOG_FILE.get() {
+        *log_file.secrets.write().unwrap_or_else(|e| e.into_inner()) = secrets
+            .into_iter()
+            .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+            .map(str::to_string)
+            .collect();
+    }
+}
+
+/// Timestamps in local time, as the rest of the app shows them.
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+        write!(w, "{}", chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"))
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    secrets: RwLock<Vec<String>>,
+    file: Mutex<Option<File>>,
+}
+
+impl LogFile {
+    fn new(path: PathBuf) -> Self {
+        Self { path, secrets: RwLock::new(Vec::new()), file: Mutex::new(None) }
+    }
+
+    fn write_lines(&self, lines: &[u8]) {
+        let mut lines = String::from_utf8_lossy(lines).into_owned();
+        for secret in self.secrets.read().unwrap_or_else(|e| e.into_inner()).iter() {
+            lines = lines.replace(secret.as_str(), "[redacted]");
+        }
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // Logging must never get in the way of the app, so write failures are dropped.
+        let _ = self.roll_over(&mut file, lines.len() as u64);
+        if let Some(file) = file.as_mut() {
+            let _ = file.write_all(lines.as_bytes());
+        }
+    }
+
+    /// Opens the log file if it isn't open yet, first moving it to `<name>.1` if `incoming`
+    /// more bytes would take it past the size limit.
+    fn roll_over(&self, file: &mut Option<File>, incoming: u64) -> io::Result<()> {
+        let size = fs::metadata(&self.path).map(|metadata| metadata.len()).unwrap_or(0);
+        if size > 0 && size + incoming > MAX_LOG_BYTES {
+            *file = None;
+            let mut rolled = self.path.clone().into_os_string();
+            rolled.push(".1");
+            fs::rename(&self.path, rolled)?;
+        }
+
+        if file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            *file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        }
+        Ok(())
+    }
+}
+
+/// Collects one event's output and hands it to the log file whole once the event is written,
+/// so that a secret is never split between two writes and missed.
+struct LineWriter {
+    buffer: Vec<u8>,
+}
+
+impl Write for LineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        if let Some(log_file) = LOG_FILE.get()
+            && !self.buffer.is_empty()
+        {
+            log_file.write_lines(&self.buffer);
+        }
+    }
+}
@@ -1,107 +1,287 @@
+mod cli;
+mod logging;
+mod watcher;
+
+use crossterm::event::{DisableFocusChange, EnableFocusChange, EventStream};
 use eyre::Result;
-use std::path::PathBuf;
+use futures::StreamExt;
+use notify::RecommendedWatcher;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::level_filters::LevelFilter;
+
+use onyx_agent::{
+    AgentError, ChatAgent, ModelInfo, ShellApproval, StreamEvent, check_ollama, diagnose,
+    list_models, pull_model, reply_cost, test_connection,
+};
+use onyx_core::{Config, ConfigError, ConfigSchema, Message, ResponseTimer};
+use onyx_tui::{App, NotificationLevel};
 
-use onyx_agent::{ChatAgent, StreamEvent};
-use onyx_core::{Config, ConfigSchema, Message};
-use onyx_tui::App;
+use crate::cli::{Args, Command};
 
 enum AppEvent {
     StreamChunk(StreamEvent),
+    /// A finished background request to show in the chat, like `/models`.
+    Notice(String),
+    TitleGenerated {
+        session_id: String,
+        title: String,
+    },
+    Notify(NotificationLevel, String),
+    /// The config file was changed on disk.
+    ConfigChanged,
+    /// The outcome of a connection test started from the config editor.
+    ConnectionTested(u64, Result<String, String>),
+}
+
+/// Explains how to finish setting up the active provider, for errors fixable from /config.
+fn setup_hint(error: &AgentError) -> Option<String> {
+    match error {
+        AgentError::ConfigError(ConfigError::MissingApiKey(provider, _)) => Some(format!(
+            "No API key found for {}.\n\
+            Type /config to open the configuration editor and set up your API keys.",
+            provider
+        )),
+        AgentError::ConfigError(ConfigError::MissingUrl(provider, _)) => Some(format!(
+            "No server URL configured for {}.\n\
+            Type /config to open the configuration editor and set the provider URL.",
+            provider
+        )),
+        AgentError::ConfigError(error @ ConfigError::UnsetVariable(..)) => Some(format!(
+            "{}.\n\
+            Set it before starting Onyx, or type /config to enter the API key directly.",
+            error
+        )),
+        AgentError::ConfigError(error @ ConfigError::InvalidValue(..)) => Some(format!(
+            "{}.\n\
+            Type /config to open the configuration editor and correct it.",
+            error
+        )),
+        _ => None,
+    }
 }
 
-fn parse_args() -> Option<PathBuf> {
-    let args: Vec<String> = std::env::args().skip(1).collect();
+/// The field `/config` should open on to fix `error`.
+fn setup_field(config: &Config, error: &AgentError) -> Option<String> {
+    let suffix = match error {
+        AgentError::ConfigError(ConfigError::MissingApiKey(..)) => "api_key",
+        AgentError::ConfigError(ConfigError::MissingUrl(..)) => "url",
+        _ => return None,
+    };
+    Some(format!("{}_{}", config.active_provider.field_prefix(), suffix))
+}
 
-    if args.is_empty() {
-        return None;
+fn format_models(config: &Config, models: &[ModelInfo]) -> String {
+    if models.is_empty() {
+        return format!("{} reported no available models.", config.active_provider);
     }
 
-    match args[0].as_str() {
-        "-c" | "--config" => {
-            if args.len() < 2 {
-                eprintln!("Error: --config requires a path argument");
-                std::process::exit(1);
-            }
-            Some(PathBuf::from(&args[1]))
-        }
-        "-h" | "--help" => {
-            println!("Onyx - AI Chat Terminal Application");
-            println!();
-            println!("USAGE:");
-            println!("    onyx [OPTIONS]");
-            println!();
-            println!("OPTIONS:");
-            println!("    -c, --config <PATH>    Specify custom config file path");
-            println!("    -h, --help             Print this help message");
-            println!();
-            println!("EXAMPLES:");
-            println!(
-                "    onyx                              # Use default config (~/.onyx/config.json)"
-            );
-            println!("    onyx --config /path/to/config.json");
-            std::process::exit(0);
-        }
-        _ => {
-            eprintln!("Error: Unknown argument '{}'", args[0]);
-            eprintln!("Use --help for usage information");
-            std::process::exit(1);
+    let mut listing = format!("Models available from {}:\n", config.active_provider);
+    for model in models {
+        match &model.details {
+            Some(details) => listing.push_str(&format!("  {}  ({})\n", model.id, details)),
+            None => listing.push_str(&format!("  {}\n", model.id)),
+        }
+    }
+    listing.push_str("\nSwitch with /model <name>.");
+    listing
+}
+
+fn apply_log_settings(config: &Config) {
+    logging::set_level(config.log_level.filter());
+    logging::set_secrets(config.secrets());
+}
+
+/// Starts or stops watching the config file at `path` for changes, as the config asks.
+fn update_config_watch(
+    watcher: &mut Option<RecommendedWatcher>,
+    app: &mut App,
+    path: &Path,
+    tx: &mpsc::UnboundedSender<AppEvent>,
+) {
+    let wanted = app.get_config().watch_config_file;
+    if wanted == watcher.is_some() {
+        return;
+    }
+
+    *watcher = None;
+    if wanted {
+        let tx = tx.clone();
+        match watcher::watch(path, move || {
+            let _ = tx.send(AppEvent::ConfigChanged);
+        }) {
+            Ok(started) => *watcher = Some(started),
+            Err(e) => app.push_notification(
+                NotificationLevel::Warning,
+                format!("Could not watch the config file for changes: {}", e),
+            ),
         }
     }
 }
 
+/// Puts the terminal back as it was, and stops it reporting focus changes.
+fn restore_terminal() {
+    let _ = crossterm::execute!(std::io::stdout(), DisableFocusChange);
+    ratatui::restore();
+}
+
+/// Waits for the app's next animation frame, or forever if nothing is animating.
+async fn next_frame(delay: Option<Duration>) {
+    match delay {
+        Some(delay) => tokio::time::sleep(delay).await,
+        None => std::future::pending().await,
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let custom_config_path = parse_args();
-    let config = Config::load_from(custom_config_path)?;
+    let args = Args::parse();
+    if let Some(command) = &args.command {
+        let result = match command {
+            Command::Config(command) => cli::run_config_command(command, &args).map(|()| true),
+            Command::Doctor => cli::run_doctor(&args).await,
+        };
+        match result {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(message) => {
+                eprintln!("Error: {}", message);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let log_file = match args.log_file.clone() {
+        Some(path) => path,
+        None => Config::data_dir()?.join("logs").join("onyx.log"),
+    };
+    // Until the config says otherwise, only problems loading it are worth logging.
+    logging::init(log_file.clone(), LevelFilter::WARN);
+
+    let config_path = args.config_file()?;
+    let mut config = Config::load_from(Some(config_path.clone()))?;
+    args.apply_overrides(&mut config);
+    apply_log_settings(&config);
 
     let mut terminal = ratatui::init();
+    // Lets the app slow down while the terminal is in the background. Terminals that can't
+    // report focus just never send the events.
+    let _ = crossterm::execute!(std::io::stdout(), EnableFocusChange);
     let mut app = App::new(config.clone());
+    app.set_log_file(log_file);
 
-    let agent = match ChatAgent::new(&config).await {
+    let mut agent = match ChatAgent::new(&config).await {
         Ok(agent) => Some(Arc::new(agent)),
         Err(e) => {
-            let provider_config = config.get_active_provider();
-            let needs_api_key = provider_config.api_key.is_none()
-                || provider_config.api_key.as_ref().unwrap().is_empty();
-
-            if needs_api_key {
-                app.add_message(Message::assistant(
-                    "Welcome to Onyx!\n\n\
-                    No API key found for the active provider.\n\
-                    Type /config to open the configuration editor and set up your API keys.\n\n\
-                    You can still use commands like /help and /config."
-                        .to_string(),
-                ));
-                None
-            } else {
-                ratatui::restore();
-                return Err(e.into());
+            app.set_setup_field(setup_field(&config, &e));
+            match setup_hint(&e) {
+                Some(hint) => {
+                    app.add_message(Message::notice(format!(
+                        "Welcome to Onyx!\n\n{}\n\n\
+                        You can still use commands like /help and /config.",
+                        hint
+                    )));
+                    None
+                }
+                None => {
+                    restore_terminal();
+                    return Err(e.into());
+                }
             }
         }
     };
 
+    app.set_agent_ready(agent.is_some());
+
     let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut cancel_token: Option<CancellationToken> = None;
+    let mut pending_approval: Option<ShellApproval> = None;
+    let mut response_timer: Option<ResponseTimer> = None;
+    let mut terminal_events = EventStream::new();
+    let mut config_watcher = None;
+    update_config_watch(&mut config_watcher, &mut app, &config_path, &tx);
+
+    if agent.is_some() {
+        let config = config.clone();
+        let tx_clone = tx.clone();
+        tokio::spawn(async move {
+            if let Some(problem) = check_ollama(&config).await {
+                let _ = tx_clone.send(AppEvent::Notice(problem));
+            }
+        });
+    }
 
     loop {
-        terminal.draw(|frame| {
-            app.draw(frame);
-        })?;
+        if app.needs_redraw() {
+            let completed = terminal.draw(|frame| {
+                app.draw(frame);
+            })?;
+            app.write_hyperlinks(completed.buffer);
+        }
 
-        app.handle_event()?;
+        // Sleep until there is input, something from a background task, or an animation frame
+        // to draw, whichever comes first.
+        let mut received = None;
+        tokio::select! {
+            event = terminal_events.next() => match event {
+                Some(event) => {
+                    app.handle_event(event?)?;
+                }
+                None => break,
+            },
+            Some(event) = rx.recv() => received = Some(event),
+            _ = next_frame(app.time_until_next_frame()) => {}
+        }
 
         if app.should_quit() {
             break;
         }
 
+        if app.take_agent_rebuild_request() {
+            apply_log_settings(app.get_config());
+            agent = match ChatAgent::new(app.get_config()).await {
+                Ok(agent) => Some(Arc::new(agent)),
+                Err(e) => {
+                    let field = setup_field(app.get_config(), &e);
+                    app.set_setup_field(field);
+                    match setup_hint(&e) {
+                        Some(hint) => app.add_message(Message::notice(hint)),
+                        None => app.push_notification(
+                            NotificationLevel::Error,
+                            format!("Failed to rebuild agent: {}", e),
+                        ),
+                    }
+                    None
+                }
+            };
+            app.set_agent_ready(agent.is_some());
+            update_config_watch(&mut config_watcher, &mut app, &config_path, &tx);
+        }
+
+        if let Some(approved) = app.take_confirmation_response()
+            && let Some(approval) = pending_approval.take()
+        {
+            approval.respond(approved);
+        }
+
+        if app.is_cancel_requested()
+            && let Some(token) = cancel_token.take()
+        {
+            token.cancel();
+        }
+
         if let Some(input) = app.take_input() {
             if input.starts_with('/') {
                 if let Some(cmd_response) = app.handle_command(&input) {
-                    app.add_message(Message::assistant(cmd_response));
+                    app.add_message(Message::notice(cmd_response));
                 }
             } else {
-                let user_msg = Message::user(input.clone());
+                let mut user_msg = Message::user(input.clone());
+                user_msg.attachments = app.take_attachments();
+                let history = app.history();
                 app.add_message(user_msg.clone());
 
                 if let Some(ref agent) = agent {
@@ -109,33 +289,59 @@ async fn main() -> Result<()> {
 
                     let streaming_msg = Message::assistant_streaming();
                     app.add_message(streaming_msg);
+                    response_timer = Some(ResponseTimer::start());
 
                     let agent_arc = Arc::clone(agent);
                     let tx_clone = tx.clone();
+                    let token = CancellationToken::new();
+                    cancel_token = Some(token.clone());
 
                     tokio::spawn(async move {
                         let (stream_tx, mut stream_rx) = mpsc::unbounded_channel();
 
-                        let agent_handle = {
-                            let agent_arc = Arc::clone(&agent_arc);
-                            let stream_tx = stream_tx.clone();
-                            tokio::spawn(async move {
-                                if let Err(e) = agent_arc.send_stream(user_msg, stream_tx).await {
-                                    eprintln!("Stream error: {}", e);
-                                }
-                            })
-                        };
+                        let agent_handle = tokio::spawn(async move {
+                            let error_tx = stream_tx.clone();
+                            if let Err(e) =
+                                agent_arc.send_stream(user_msg, history, stream_tx).await
+                            {
+                                let _ =
+                                    error_tx.send(StreamEvent::Error(agent_arc.describe_error(&e)));
+                            }
+                        });
 
-                        while let Some(event) = stream_rx.recv().await {
-                            if tx_clone.send(AppEvent::StreamChunk(event)).is_err() {
-                                break;
+                        loop {
+                            tokio::select! {
+                                _ = token.cancelled() => {
+                                    agent_handle.abort();
+                                    let _ = tx_clone.send(AppEvent::StreamChunk(StreamEvent::Done(None)));
+                                    break;
+                                }
+                                event = stream_rx.recv() => {
+                                    let Some(event) = event else {
+                                        // The agent hangs up after its last event, unless it
+                                        // panicked first.
+                                        if let Err(e) = agent_handle.await
+                                            && e.is_panic()
+                                        {
+                                            let _ = tx_clone.send(AppEvent::Notify(
+                                                NotificationLevel::Error,
+                                                "The agent task crashed".to_string(),
+                                            ));
+                                            let _ = tx_clone.send(AppEvent::StreamChunk(
+                                                StreamEvent::Error("the response was interrupted".to_string()),
+                                            ));
+                                        }
+                                        break;
+                                    };
+                                    if tx_clone.send(AppEvent::StreamChunk(event)).is_err() {
+                                        break;
+                                    }
+                                }
                             }
                         }
-
-                        let _ = agent_handle.await;
                     });
                 } else {
-                    app.add_message(Message::assistant(
+                    app.add_message(Message::notice(
                         "Please configure your API key first. Type /config to open the configuration editor."
                             .to_string(),
                     ));
@@ -143,7 +349,140 @@ async fn main() -> Result<()> {
             }
         }
 
-        while let Ok(AppEvent::StreamChunk(chunk)) = rx.try_recv() {
+        if app.take_models_request() {
+            let config = app.get_config().clone();
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                let listing = match list_models(&config).await {
+                    Ok(models) => format_models(&config, &models),
+                    Err(e) => setup_hint(&e)
+                        .unwrap_or_else(|| format!("Failed to list models: {}", e.user_message())),
+                };
+                let _ = tx_clone.send(AppEvent::Notice(listing));
+            });
+        }
+
+        if let Some(model) = app.take_pull_request() {
+            let config = app.get_config().clone();
+            let tx_clone = tx.clone();
+            let token = CancellationToken::new();
+            cancel_token = Some(token.clone());
+
+            tokio::spawn(async move {
+                let (stream_tx, mut stream_rx) = mpsc::unbounded_channel();
+                let pull_handle = tokio::spawn(async move {
+                    let event = match pull_model(&config, &model, &stream_tx).await {
+                        Ok(()) => StreamEvent::Done(None),
+                        Err(e) => StreamEvent::Error(e.user_message()),
+                    };
+                    let _ = stream_tx.send(event);
+                });
+
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => {
+                            pull_handle.abort();
+                            let _ = tx_clone.send(AppEvent::StreamChunk(StreamEvent::Done(None)));
+                            break;
+                        }
+                        event = stream_rx.recv() => {
+                            let Some(event) = event else { break };
+                            if tx_clone.send(AppEvent::StreamChunk(event)).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+
+        if app.take_doctor_request() {
+            let config = app.get_config().clone();
+            let config_path = config_path.clone();
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                let diagnosis = diagnose(&config_path, &config).await;
+                let _ =
+                    tx_clone.send(AppEvent::Notice(diagnosis.to_string().trim_end().to_string()));
+            });
+        }
+
+        if let Some((id, config)) = app.take_connection_test_request() {
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                let result = test_connection(&config).await;
+                let _ = tx_clone.send(AppEvent::ConnectionTested(id, result));
+            });
+        }
+
+        if let Some((session_id, history)) = app.take_title_request()
+            && let Some(ref agent) = agent
+        {
+            let agent_arc = Arc::clone(agent);
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                let event = match agent_arc.generate_title(&history).await {
+                    Ok(title) => AppEvent::TitleGenerated { session_id, title },
+                    Err(e) => AppEvent::Notify(
+                        NotificationLevel::Warning,
+                        format!("Could not generate a title: {}", e.user_message()),
+                    ),
+                };
+                let _ = tx_clone.send(event);
+            });
+        }
+
+        // Apply everything that has arrived so far before drawing again, so a burst of chunks
+        // costs one frame.
+        let pending = std::iter::from_fn(|| rx.try_recv().ok());
+        for event in received.into_iter().chain(pending) {
+            let chunk = match event {
+                AppEvent::StreamChunk(chunk) => chunk,
+                AppEvent::Notice(listing) => {
+                    app.add_message(Message::notice(listing));
+                    continue;
+                }
+                AppEvent::TitleGenerated { session_id, title } => {
+                    app.apply_generated_title(&session_id, title);
+                    continue;
+                }
+                AppEvent::Notify(level, text) => {
+                    app.push_notification(level, text);
+                    continue;
+                }
+                AppEvent::ConnectionTested(id, result) => {
+                    app.finish_connection_test(id, result);
+                    continue;
+                }
+                AppEvent::ConfigChanged => {
+                    // Read without the recovery load_from does, so a half-finished edit is
+                    // reported instead of overwritten.
+                    match Config::read_from(&config_path) {
+                        Ok(mut reloaded) => {
+                            args.apply_overrides(&mut reloaded);
+                            // Onyx's own saves come back here too, and change nothing.
+                            if !reloaded.same_settings(app.get_config()) {
+                                tracing::info!("config changed on disk");
+                                app.reload_config(reloaded);
+                            }
+                        }
+                        Err(e) => app.push_notification(
+                            NotificationLevel::Warning,
+                            format!("Ignored the edited config file: {}", e),
+                        ),
+                    }
+                    continue;
+                }
+            };
+
+            match &chunk {
+                StreamEvent::ContentChunk(_) | StreamEvent::ThinkingChunk(_) => {
+                    tracing::trace!(event = ?chunk, "stream event")
+                }
+                StreamEvent::Error(error) => tracing::error!(%error, "stream error"),
+                _ => tracing::debug!(event = ?chunk, "stream event"),
+            }
+
             match chunk {
                 StreamEvent::ThinkingStart => {}
                 StreamEvent::ThinkingChunk(text) => {
@@ -151,23 +490,60 @@ async fn main() -> Result<()> {
                 }
                 StreamEvent::ThinkingEnd => {}
                 StreamEvent::ContentChunk(text) => {
+                    if let Some(timer) = &mut response_timer {
+                        timer.record_chunk(&text);
+                    }
                     app.update_last_message(|msg| msg.append_content(text));
                 }
-                StreamEvent::Done => {
-                    app.update_last_message(|msg| msg.finish_streaming());
-                    app.set_processing(false);
+                StreamEvent::ToolCallStart { name, arguments } => {
+                    app.update_last_message(|msg| msg.start_tool_call(name, arguments));
                 }
-                StreamEvent::Error(err) => {
+                StreamEvent::ToolCallResult { name, summary } => {
+                    app.update_last_message(|msg| msg.finish_tool_call(&name, summary));
+                }
+                StreamEvent::ShellApprovalRequested(approval) => {
+                    app.request_confirmation(
+                        approval.command.clone(),
+                        approval.working_dir.display().to_string(),
+                    );
+                    pending_approval = Some(approval);
+                }
+                StreamEvent::Retrying { attempt, .. } => {
+                    app.begin_retry(attempt);
+                }
+                StreamEvent::ContextTruncated { dropped_messages } => {
+                    app.note_context_truncated(dropped_messages);
+                }
+                StreamEvent::HistorySummarized { replaced_messages, summary } => {
+                    app.apply_history_summary(replaced_messages, summary);
+                }
+                StreamEvent::Progress(status) => app.show_progress(status),
+                StreamEvent::Done(usage) => {
+                    cancel_token = None;
+                    pending_approval = None;
+                    let stats = response_timer.take().map(|timer| timer.finish(usage.as_ref()));
+                    let cost = usage.as_ref().and_then(|usage| reply_cost(app.get_config(), usage));
                     app.update_last_message(|msg| {
-                        msg.append_content(format!("\n\nError: {}", err));
-                        msg.finish_streaming();
+                        msg.usage = usage;
+                        msg.stats = stats;
+                        msg.cost = cost;
                     });
-                    app.set_processing(false);
+                    app.record_response();
+                    app.finish_streaming();
+                }
+                StreamEvent::Error(err) => {
+                    cancel_token = None;
+                    response_timer = None;
+                    pending_approval = None;
+                    app.fail_streaming(err);
                 }
             }
         }
     }
 
-    ratatui::restore();
+    restore_terminal();
+    if let Err(e) = app.save_session() {
+        eprintln!("Failed to save session: {}", e);
+    }
     Ok(())
 }
@@ -0,0 +1,43 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the file has to stay untouched before a change is reported, so that an editor's
+/// burst of writes is read once, after it is done.
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// Calls `on_change` after the file at `path` changes, until the returned watcher is dropped.
+/// Its directory is watched rather than the file itself, since editors and Onyx's own saves
+/// replace the file by renaming a new one over it.
+pub fn watch(
+    path: &Path,
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let file_name = path.file_name().map(ToOwned::to_owned);
+    let (changes_tx, changes_rx) = mpsc::channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+            && event.paths.iter().any(|changed| changed.file_name() == file_name.as_deref())
+        {
+            let _ = changes_tx.send(());
+        }
+    })?;
+
+    let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    watcher.watch(directory.unwrap_or(Path::new(".")), RecursiveMode::NonRecursive)?;
+
+    // Ends once the watcher, and with it the sender, is dropped.
+    thread::spawn(move || {
+        while changes_rx.recv().is_ok() {
+            while changes_rx.recv_timeout(SETTLE_TIME).is_ok() {}
+            on_change();
+        }
+    });
+
+    Ok(watcher)
+}